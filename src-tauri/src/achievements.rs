@@ -0,0 +1,404 @@
+// Badges players unlock with their runs, e.g. a first clean run. Each
+// achievement is a rule over the saved runs; the built-in catalog can be
+// extended or overridden with achievements from the config store. Unlocks
+// are kept in the scores file.
+
+use std::collections::HashSet;
+use std::sync::Mutex;
+use tauri_plugin_store::StoreExt;
+
+use crate::players;
+use crate::scores::{Run, ScoreBook, ScoreFile};
+use crate::STORE_FILE;
+
+// Config store key of the achievements added to the built-in ones.
+const CUSTOM_ACHIEVEMENTS_KEY: &str = "achievements.custom";
+
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Achievement {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub rule: Rule,
+}
+
+// What unlocks an achievement.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(
+    tag = "kind",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum Rule {
+    // A single ranked run, not part of a relay, meeting every condition
+    // set.
+    Run {
+        #[serde(default)]
+        mode: Option<String>,
+        // No hits.
+        #[serde(default)]
+        clean: bool,
+        // Final score of a timed run at most this.
+        #[serde(default)]
+        max_time_ms: Option<u64>,
+        // Every laser of the run's config broken at least once.
+        #[serde(default)]
+        every_sensor_hit: bool,
+    },
+    // Runs saved by the player, practice runs aside.
+    TotalRuns {
+        count: usize,
+        #[serde(default)]
+        mode: Option<String>,
+    },
+    // Clean runs in a row.
+    Streak {
+        runs: u32,
+    },
+}
+
+// An achievement a player unlocked, and the run that did it.
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Unlock {
+    pub achievement_id: String,
+    pub player_id: u64,
+    pub run_id: u64,
+    pub unlocked_ms: u64,
+}
+
+// Payload of achievement-unlocked.
+#[derive(Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AchievementUnlocked {
+    pub achievement_id: String,
+    pub name: String,
+    pub player: String,
+    pub player_id: u64,
+    pub run_id: u64,
+}
+
+// Returned by get_achievements.
+#[derive(Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnlockedAchievement {
+    #[serde(flatten)]
+    pub achievement: Achievement,
+    pub run_id: u64,
+    pub unlocked_ms: u64,
+}
+
+fn built_in() -> Vec<Achievement> {
+    let run_rule = |clean, max_time_ms, every_sensor_hit| Rule::Run {
+        mode: None,
+        clean,
+        max_time_ms,
+        every_sensor_hit,
+    };
+    vec![
+        Achievement {
+            id: "first-clean-run".to_string(),
+            name: "First clean run".to_string(),
+            description: "Finish a run without breaking a beam".to_string(),
+            rule: run_rule(true, None, false),
+        },
+        Achievement {
+            id: "sub-30-seconds".to_string(),
+            name: "Sub-30 seconds".to_string(),
+            description: "Finish a run in under 30 seconds".to_string(),
+            rule: run_rule(false, Some(29_999), false),
+        },
+        Achievement {
+            id: "every-laser".to_string(),
+            name: "Hit every laser".to_string(),
+            description: "Break every laser in one run".to_string(),
+            rule: run_rule(false, None, true),
+        },
+        Achievement {
+            id: "clean-streak-5".to_string(),
+            name: "Five in a row".to_string(),
+            description: "Finish five clean runs in a row".to_string(),
+            rule: Rule::Streak { runs: 5 },
+        },
+        Achievement {
+            id: "runs-100".to_string(),
+            name: "100 runs".to_string(),
+            description: "Play 100 runs".to_string(),
+            rule: Rule::TotalRuns {
+                count: 100,
+                mode: None,
+            },
+        },
+    ]
+}
+
+// The built-in achievements, with custom ones replacing those of the same
+// id or coming after them.
+pub fn catalog(app_handle: &tauri::AppHandle) -> Vec<Achievement> {
+    let custom: Vec<Achievement> = app_handle
+        .store(STORE_FILE)
+        .ok()
+        .and_then(|store| store.get(CUSTOM_ACHIEVEMENTS_KEY))
+        .and_then(|value| serde_json::from_value(value).ok())
+        .unwrap_or_default();
+    let mut catalog = built_in();
+    for achievement in custom {
+        match catalog.iter_mut().find(|a| a.id == achievement.id) {
+            Some(existing) => *existing = achievement,
+            None => catalog.push(achievement),
+        }
+    }
+    catalog
+}
+
+// Whether a run's config lists lasers and the run broke all of them.
+fn every_sensor_hit(run: &Run) -> bool {
+    let ids = |key: &str| -> HashSet<u64> {
+        run.config[key]
+            .as_array()
+            .map(|ids| ids.iter().filter_map(|id| id.as_u64()).collect())
+            .unwrap_or_default()
+    };
+    let fatal = ids("fatalSensors");
+    let lasers: HashSet<u64> = ids("sensors").difference(&fatal).copied().collect();
+    let hit: HashSet<u64> = run
+        .timeline
+        .iter()
+        .flatten()
+        .filter(|hit| hit.counts())
+        .map(|hit| hit.sensor as u64)
+        .collect();
+    !lasers.is_empty() && lasers.is_subset(&hit)
+}
+
+// Whether a run unlocks a rule, counting the player's runs up to it.
+fn earned(rule: &Rule, file: &ScoreFile, run: &Run) -> bool {
+    match rule {
+        Rule::Run {
+            mode,
+            clean,
+            max_time_ms,
+            every_sensor_hit: all_hit,
+        } => {
+            run.ranks()
+                && run.relay_id.is_none()
+                && mode.as_ref().is_none_or(|mode| run.mode == *mode)
+                && (!clean || run.hits == 0)
+                && max_time_ms.is_none_or(|max| run.is_timed() && run.final_score() <= max)
+                && (!all_hit || every_sensor_hit(run))
+        }
+        Rule::TotalRuns { count, mode } => {
+            file.runs
+                .iter()
+                .filter(|other| {
+                    other.player_id == run.player_id
+                        && !other.practice
+                        && (other.timestamp_ms, other.id) <= (run.timestamp_ms, run.id)
+                        && mode.as_ref().is_none_or(|mode| other.mode == *mode)
+                })
+                .count()
+                >= *count
+        }
+        Rule::Streak { runs } => run.streak >= *runs,
+    }
+}
+
+// Unlocks the achievements a saved run earns its player and returns them.
+pub fn evaluate(file: &mut ScoreFile, run: &Run, catalog: &[Achievement]) -> Vec<Unlock> {
+    let Some(player_id) = run.player_id else {
+        return Vec::new();
+    };
+    let unlocks: Vec<Unlock> = catalog
+        .iter()
+        .filter(|achievement| {
+            !file.unlocks.iter().any(|unlock| {
+                unlock.player_id == player_id && unlock.achievement_id == achievement.id
+            })
+        })
+        .filter(|achievement| earned(&achievement.rule, file, run))
+        .map(|achievement| Unlock {
+            achievement_id: achievement.id.clone(),
+            player_id,
+            run_id: run.id,
+            unlocked_ms: run.timestamp_ms,
+        })
+        .collect();
+    file.unlocks.extend(unlocks.iter().cloned());
+    unlocks
+}
+
+// The event payloads of unlocks.
+pub fn announcements(
+    file: &ScoreFile,
+    unlocks: &[Unlock],
+    catalog: &[Achievement],
+) -> Vec<AchievementUnlocked> {
+    unlocks
+        .iter()
+        .filter_map(|unlock| {
+            let achievement = catalog.iter().find(|a| a.id == unlock.achievement_id)?;
+            let player = file.players.iter().find(|p| p.id == unlock.player_id)?;
+            Some(AchievementUnlocked {
+                achievement_id: unlock.achievement_id.clone(),
+                name: achievement.name.clone(),
+                player: player.name.clone(),
+                player_id: player.id,
+                run_id: unlock.run_id,
+            })
+        })
+        .collect()
+}
+
+// Goes through all runs in the order they were saved and unlocks what they
+// earned; returns the number of new unlocks.
+fn backfill(file: &mut ScoreFile, catalog: &[Achievement]) -> usize {
+    let mut runs = file.runs.to_vec();
+    runs.sort_by_key(|run| (run.timestamp_ms, run.id));
+    runs.iter()
+        .map(|run| evaluate(file, run, catalog).len())
+        .sum()
+}
+
+// Command to list the achievements that can be unlocked.
+#[tauri::command]
+pub fn get_achievement_catalog(app_handle: tauri::AppHandle) -> Vec<Achievement> {
+    catalog(&app_handle)
+}
+
+// Command to replace the custom achievements. Unlocks stay, even of
+// achievements no longer in the catalog.
+#[tauri::command]
+pub fn set_custom_achievements(
+    achievements: Vec<Achievement>,
+    app_handle: tauri::AppHandle,
+) -> Result<(), String> {
+    let mut ids = HashSet::new();
+    for achievement in &achievements {
+        if achievement.id.trim().is_empty() || achievement.name.trim().is_empty() {
+            return Err("achievements need an id and a name".to_string());
+        }
+        if !ids.insert(&achievement.id) {
+            return Err(format!(
+                "the achievement {} is listed twice",
+                achievement.id
+            ));
+        }
+    }
+    let store = app_handle.store(STORE_FILE).map_err(|e| e.to_string())?;
+    store.set(
+        CUSTOM_ACHIEVEMENTS_KEY,
+        serde_json::to_value(achievements).map_err(|e| e.to_string())?,
+    );
+    Ok(())
+}
+
+// Command to list the achievements a player unlocked, earliest first.
+#[tauri::command]
+pub fn get_achievements(
+    player: String,
+    app_handle: tauri::AppHandle,
+    scores: tauri::State<Mutex<ScoreBook>>,
+) -> Result<Vec<UnlockedAchievement>, String> {
+    let catalog = catalog(&app_handle);
+    let book = scores.lock().map_err(|e| e.to_string())?;
+    let file = book.file();
+    let id = players::find(file, &player)
+        .ok_or_else(|| format!("no player named {}", player))?
+        .id;
+    let mut unlocked: Vec<UnlockedAchievement> = file
+        .unlocks
+        .iter()
+        .filter(|unlock| unlock.player_id == id)
+        .filter_map(|unlock| {
+            Some(UnlockedAchievement {
+                achievement: catalog
+                    .iter()
+                    .find(|a| a.id == unlock.achievement_id)?
+                    .clone(),
+                run_id: unlock.run_id,
+                unlocked_ms: unlock.unlocked_ms,
+            })
+        })
+        .collect();
+    unlocked.sort_by_key(|unlocked| unlocked.unlocked_ms);
+    Ok(unlocked)
+}
+
+// Admin command to unlock what the saved runs earned, e.g. after adding
+// achievements; no events are sent. Returns the number of new unlocks.
+#[tauri::command]
+pub fn backfill_achievements(
+    app_handle: tauri::AppHandle,
+    scores: tauri::State<Mutex<ScoreBook>>,
+) -> Result<usize, String> {
+    let catalog = catalog(&app_handle);
+    let mut book = scores.lock().map_err(|e| e.to_string())?;
+    book.update(|file| Ok(backfill(file, &catalog)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(id: u64, hits: u32, elapsed_ms: u64, timeline: serde_json::Value) -> Run {
+        serde_json::from_value(serde_json::json!({
+            "id": id, "player": "a", "mode": "timeAttack", "elapsedMs": elapsed_ms,
+            "hits": hits, "penaltyMs": 0, "timestampMs": id, "playerId": 1,
+            "config": { "sensors": [0, 1, 2], "fatalSensors": [2] },
+            "timeline": timeline
+        }))
+        .unwrap()
+    }
+
+    fn hit(sensor: usize) -> serde_json::Value {
+        serde_json::json!({ "sensor": sensor, "name": "", "elapsedMs": 0, "count": 1 })
+    }
+
+    #[test]
+    fn unlocks_each_achievement_once() {
+        let catalog = built_in();
+        let mut file = ScoreFile::new();
+        file.runs = vec![
+            run(1, 0, 40_000, serde_json::json!([])),
+            run(2, 2, 25_000, serde_json::json!([hit(0), hit(1)])),
+        ]
+        .into();
+        let first = file.runs[0].clone();
+        let ids = |unlocks: Vec<Unlock>| -> Vec<String> {
+            unlocks.into_iter().map(|u| u.achievement_id).collect()
+        };
+        assert_eq!(
+            ids(evaluate(&mut file, &first, &catalog)),
+            ["first-clean-run"]
+        );
+        assert!(evaluate(&mut file, &first, &catalog).is_empty());
+        let second = file.runs[1].clone();
+        assert_eq!(
+            ids(evaluate(&mut file, &second, &catalog)),
+            ["sub-30-seconds", "every-laser"]
+        );
+    }
+
+    #[test]
+    fn backfills_in_save_order() {
+        let catalog = vec![Achievement {
+            id: "two-runs".to_string(),
+            name: "Two runs".to_string(),
+            description: String::new(),
+            rule: Rule::TotalRuns {
+                count: 2,
+                mode: None,
+            },
+        }];
+        let mut file = ScoreFile::new();
+        file.runs = vec![
+            run(3, 1, 50_000, serde_json::json!([])),
+            run(2, 1, 50_000, serde_json::json!([])),
+        ]
+        .into();
+        assert_eq!(backfill(&mut file, &catalog), 1);
+        assert_eq!(file.unlocks[0].run_id, 3);
+        assert_eq!(backfill(&mut file, &catalog), 0);
+    }
+}
@@ -0,0 +1,458 @@
+// Derives beam-break thresholds from the values the sensors report. The
+// one-shot calibration only looks at unbroken beams; the guided calibration
+// also has the operator block each beam in turn.
+
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use tauri::{Emitter, Manager};
+
+use crate::sensors::{SensorNames, SensorThresholds};
+use crate::{SensorData, SerialManager};
+
+// How recent the sensor data must be for calibration to start.
+const MAX_DATA_AGE_MS: u128 = 1000;
+// How often the sensor data is sampled.
+const SAMPLE_INTERVAL_MS: u64 = 5;
+// Thresholds are placed this many standard deviations below the mean.
+const THRESHOLD_SIGMAS: f64 = 4.0;
+// Minimum distance of a threshold below the mean, for sensors without noise.
+const MIN_THRESHOLD_MARGIN: f64 = 20.0;
+// Sensors whose deviation exceeds this fraction of their mean are flagged as noisy.
+const NOISY_DEVIATION_RATIO: f64 = 0.05;
+// Error a calibration ends with when it was cancelled.
+const CANCELLED: &str = "calibration cancelled";
+// How long the guided calibration records the unbroken beams.
+const UNBROKEN_PHASE_MS: u64 = 2000;
+// How long a blocked beam is recorded once it was detected.
+const BLOCKED_CAPTURE_MS: u64 = 1000;
+// How long the operator has to block each beam.
+const BLOCK_TIMEOUT_MS: u64 = 30000;
+// A beam counts as blocked when it drops by at least this fraction of its mean.
+const BLOCKED_DROP_RATIO: f64 = 0.3;
+
+// Calibration result of one sensor.
+#[derive(Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SensorBaseline {
+    pub sensor: usize,
+    pub name: String,
+    pub mean: f64,
+    pub std_dev: f64,
+    pub threshold: u32,
+    // The sensor fluctuates a lot and probably needs attention.
+    pub noisy: bool,
+}
+
+// Result of calibrate_baseline.
+#[derive(Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Calibration {
+    pub samples: usize,
+    pub sensors: Vec<SensorBaseline>,
+}
+
+// Fails unless sensor data arrived within the last second.
+fn require_fresh_data(sensor_data: &Mutex<SensorData>) -> Result<(), String> {
+    let sensor_data = sensor_data.lock().map_err(|e| e.to_string())?;
+    match sensor_data.updated_at {
+        Some(updated_at) if updated_at.elapsed().as_millis() <= MAX_DATA_AGE_MS => Ok(()),
+        _ => Err("no sensor data received in the last second".to_string()),
+    }
+}
+
+// Hands out each update of the sensor values once.
+struct Sampler<'a> {
+    sensor_data: &'a Mutex<SensorData>,
+    last_update: Option<std::time::Instant>,
+}
+
+impl<'a> Sampler<'a> {
+    fn new(sensor_data: &'a Mutex<SensorData>) -> Self {
+        Self {
+            sensor_data,
+            last_update: None,
+        }
+    }
+
+    // The current values if they changed since the last call.
+    fn next(&mut self) -> Result<Option<Vec<u32>>, String> {
+        let sensor_data = self.sensor_data.lock().map_err(|e| e.to_string())?;
+        if sensor_data.updated_at == self.last_update || sensor_data.frame.values.is_empty() {
+            return Ok(None);
+        }
+        self.last_update = sensor_data.updated_at;
+        Ok(Some(sensor_data.frame.values.clone()))
+    }
+}
+
+// Waits for the next sample, failing if a cancel signal arrives.
+fn wait_for_sample(stop_rx: &Receiver<()>) -> Result<(), String> {
+    match stop_rx.recv_timeout(std::time::Duration::from_millis(SAMPLE_INTERVAL_MS)) {
+        Err(RecvTimeoutError::Timeout) => Ok(()),
+        _ => Err(CANCELLED.to_string()),
+    }
+}
+
+// Collects every update of the sensor values for the given duration. Updates
+// with a different number of sensors than the first are skipped.
+fn collect_samples(
+    sensor_data: &Mutex<SensorData>,
+    duration_ms: u64,
+    stop_rx: &Receiver<()>,
+) -> Result<Vec<Vec<u32>>, String> {
+    let deadline = std::time::Instant::now() + std::time::Duration::from_millis(duration_ms);
+    let mut sampler = Sampler::new(sensor_data);
+    let mut samples: Vec<Vec<u32>> = Vec::new();
+    while std::time::Instant::now() < deadline {
+        if let Some(values) = sampler.next()? {
+            if samples
+                .first()
+                .is_none_or(|first| first.len() == values.len())
+            {
+                samples.push(values);
+            }
+        }
+        wait_for_sample(stop_rx)?;
+    }
+    if samples.is_empty() {
+        return Err("no sensor data received during calibration".to_string());
+    }
+    Ok(samples)
+}
+
+// Mean and standard deviation of one sensor over the samples.
+fn mean_and_deviation(samples: &[Vec<u32>], sensor: usize) -> (f64, f64) {
+    let count = samples.len() as f64;
+    let mean = samples.iter().map(|s| s[sensor] as f64).sum::<f64>() / count;
+    let variance = samples
+        .iter()
+        .map(|s| (s[sensor] as f64 - mean).powi(2))
+        .sum::<f64>()
+        / count;
+    (mean, variance.sqrt())
+}
+
+// Computes mean, deviation and threshold of every sensor from the samples.
+fn compute_baselines(samples: &[Vec<u32>], names: &SensorNames) -> Vec<SensorBaseline> {
+    let sensor_count = samples.first().map_or(0, |first| first.len());
+    (0..sensor_count)
+        .map(|sensor| {
+            let (mean, std_dev) = mean_and_deviation(samples, sensor);
+            let margin = (std_dev * THRESHOLD_SIGMAS).max(MIN_THRESHOLD_MARGIN);
+            SensorBaseline {
+                sensor,
+                name: names.resolve(sensor),
+                mean,
+                std_dev,
+                threshold: (mean - margin).max(1.0) as u32,
+                noisy: std_dev > mean * NOISY_DEVIATION_RATIO,
+            }
+        })
+        .collect()
+}
+
+// Command to sample the unbroken beams for `duration_ms` and derive new
+// thresholds from their mean and noise. The thresholds are saved and used
+// right away. Runs off the main thread since it blocks for the duration.
+#[tauri::command(async)]
+pub fn calibrate_baseline(
+    duration_ms: u64,
+    app_handle: tauri::AppHandle,
+    state: tauri::State<Arc<Mutex<SerialManager>>>,
+    sensor_data: tauri::State<Arc<Mutex<SensorData>>>,
+) -> Result<Calibration, String> {
+    if duration_ms == 0 {
+        return Err("duration must be greater than 0".to_string());
+    }
+    require_fresh_data(&sensor_data)?;
+    // Nothing cancels a one-shot calibration.
+    let (_stop_tx, stop_rx) = channel();
+    let samples = collect_samples(&sensor_data, duration_ms, &stop_rx)?;
+
+    let manager = state.lock().map_err(|e| e.to_string())?;
+    let sensors = compute_baselines(&samples, &manager.live.sensor_names);
+    let thresholds = sensors.iter().map(|sensor| sensor.threshold).collect();
+    manager.live.thresholds.save(&app_handle, thresholds)?;
+    Ok(Calibration {
+        samples: samples.len(),
+        sensors,
+    })
+}
+
+// Payload for the calibration-progress event.
+#[derive(Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CalibrationProgress {
+    // "unbroken", "waiting", "capturing", "captured" or "wrongSensor".
+    step: &'static str,
+    sensor: Option<usize>,
+    message: String,
+}
+
+// Unbroken and blocked ranges of one sensor, part of calibration-complete.
+#[derive(Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SensorRange {
+    sensor: usize,
+    name: String,
+    unbroken_min: u32,
+    unbroken_max: u32,
+    broken_min: u32,
+    broken_max: u32,
+    // Midway between the lowest unbroken and the highest broken value.
+    threshold: u32,
+}
+
+// Where the guided calibration currently is.
+enum Step {
+    // Waiting for the operator to block the sensor.
+    Waiting(usize),
+    // Recording the blocked sensor since the given time.
+    Capturing(usize, std::time::Instant),
+    // Waiting for the captured sensor to be unblocked again.
+    Restoring(usize),
+}
+
+// Runs the guided calibration on the background thread.
+struct GuidedRun {
+    app_handle: tauri::AppHandle,
+    names: Arc<SensorNames>,
+}
+
+impl GuidedRun {
+    fn progress(&self, step: &'static str, sensor: Option<usize>, message: String) {
+        let _ = self.app_handle.emit(
+            "calibration-progress",
+            CalibrationProgress {
+                step,
+                sensor,
+                message,
+            },
+        );
+    }
+
+    fn run(&self, stop_rx: &Receiver<()>) -> Result<Vec<SensorRange>, String> {
+        let sensor_data = self.app_handle.state::<Arc<Mutex<SensorData>>>();
+        require_fresh_data(&sensor_data)?;
+
+        // Phase one: record the unbroken beams.
+        self.progress(
+            "unbroken",
+            None,
+            "recording unbroken beams, keep the maze clear".to_string(),
+        );
+        let unbroken = collect_samples(&sensor_data, UNBROKEN_PHASE_MS, stop_rx)?;
+        let sensor_count = unbroken[0].len();
+        // Values below these levels mean the beam is blocked.
+        let blocked_levels: Vec<f64> = (0..sensor_count)
+            .map(|sensor| {
+                let (mean, std_dev) = mean_and_deviation(&unbroken, sensor);
+                mean - (std_dev * THRESHOLD_SIGMAS).max(mean * BLOCKED_DROP_RATIO)
+            })
+            .collect();
+
+        // Phase two: have the operator block each beam in turn.
+        let mut blocked: Vec<Vec<u32>> = vec![Vec::new(); sensor_count];
+        let mut sampler = Sampler::new(&sensor_data);
+        let mut step = Step::Waiting(0);
+        let mut step_started = std::time::Instant::now();
+        let mut wrong_sensor = None;
+        self.announce_waiting(0);
+        loop {
+            wait_for_sample(stop_rx)?;
+            let Some(values) = sampler.next()? else {
+                continue;
+            };
+            if values.len() != sensor_count {
+                return Err("the number of sensors changed during calibration".to_string());
+            }
+            let is_blocked = |sensor: usize| (values[sensor] as f64) < blocked_levels[sensor];
+
+            step = match step {
+                Step::Waiting(sensor) if is_blocked(sensor) => {
+                    self.progress(
+                        "capturing",
+                        Some(sensor),
+                        format!("{} blocked, hold still", self.names.resolve(sensor)),
+                    );
+                    Step::Capturing(sensor, std::time::Instant::now())
+                }
+                Step::Waiting(sensor) => {
+                    // Point out a different beam being blocked, once.
+                    let other =
+                        (0..sensor_count).find(|&other| other != sensor && is_blocked(other));
+                    if other.is_some() && other != wrong_sensor {
+                        self.progress(
+                            "wrongSensor",
+                            other,
+                            format!(
+                                "{} is blocked, but {} is expected",
+                                self.names.resolve(other.unwrap_or_default()),
+                                self.names.resolve(sensor)
+                            ),
+                        );
+                    }
+                    wrong_sensor = other;
+                    if step_started.elapsed().as_millis() as u64 >= BLOCK_TIMEOUT_MS {
+                        return Err(format!(
+                            "{} was not blocked in time",
+                            self.names.resolve(sensor)
+                        ));
+                    }
+                    Step::Waiting(sensor)
+                }
+                Step::Capturing(sensor, since) => {
+                    if is_blocked(sensor) {
+                        blocked[sensor].push(values[sensor]);
+                    }
+                    if since.elapsed().as_millis() as u64 >= BLOCKED_CAPTURE_MS {
+                        if blocked[sensor].is_empty() {
+                            // The beam was only blocked for a moment; try again.
+                            self.announce_waiting(sensor);
+                            step_started = std::time::Instant::now();
+                            Step::Waiting(sensor)
+                        } else {
+                            self.progress(
+                                "captured",
+                                Some(sensor),
+                                format!("{} captured", self.names.resolve(sensor)),
+                            );
+                            Step::Restoring(sensor)
+                        }
+                    } else {
+                        Step::Capturing(sensor, since)
+                    }
+                }
+                Step::Restoring(sensor) if !is_blocked(sensor) => {
+                    let next = sensor + 1;
+                    if next == sensor_count {
+                        break;
+                    }
+                    self.announce_waiting(next);
+                    step_started = std::time::Instant::now();
+                    wrong_sensor = None;
+                    Step::Waiting(next)
+                }
+                Step::Restoring(sensor) => Step::Restoring(sensor),
+            };
+        }
+
+        Ok((0..sensor_count)
+            .map(|sensor| {
+                let unbroken_values = unbroken.iter().map(|s| s[sensor]);
+                let unbroken_min = unbroken_values.clone().min().unwrap_or_default();
+                let unbroken_max = unbroken_values.max().unwrap_or_default();
+                let broken_min = blocked[sensor].iter().copied().min().unwrap_or_default();
+                let broken_max = blocked[sensor].iter().copied().max().unwrap_or_default();
+                SensorRange {
+                    sensor,
+                    name: self.names.resolve(sensor),
+                    unbroken_min,
+                    unbroken_max,
+                    broken_min,
+                    broken_max,
+                    threshold: ((unbroken_min as u64 + broken_max as u64) / 2) as u32,
+                }
+            })
+            .collect())
+    }
+
+    fn announce_waiting(&self, sensor: usize) {
+        self.progress(
+            "waiting",
+            Some(sensor),
+            format!("waiting for {} to be blocked", self.names.resolve(sensor)),
+        );
+    }
+}
+
+// Holds the thread of a running guided calibration.
+pub struct GuidedCalibration {
+    thread: Option<JoinHandle<()>>,
+    stop_sender: Option<Sender<()>>,
+}
+
+impl GuidedCalibration {
+    pub fn new() -> Self {
+        Self {
+            thread: None,
+            stop_sender: None,
+        }
+    }
+
+    fn start(
+        &mut self,
+        app_handle: tauri::AppHandle,
+        names: Arc<SensorNames>,
+        thresholds: Arc<SensorThresholds>,
+    ) -> Result<(), String> {
+        if self.thread.as_ref().is_some_and(|t| !t.is_finished()) {
+            return Err("calibration is already running".to_string());
+        }
+
+        let (stop_tx, stop_rx) = channel();
+        let handle = thread::spawn(move || {
+            let run = GuidedRun { app_handle, names };
+            // Thresholds are only replaced when every sensor was captured.
+            let result = run.run(&stop_rx).and_then(|sensors| {
+                let values = sensors.iter().map(|sensor| sensor.threshold).collect();
+                thresholds.save(&run.app_handle, values)?;
+                Ok(sensors)
+            });
+            match result {
+                Ok(sensors) => {
+                    let _ = run.app_handle.emit("calibration-complete", sensors);
+                }
+                // A cancelled calibration was asked for, so it isn't reported.
+                Err(e) if e == CANCELLED => {}
+                Err(e) => {
+                    let _ = run.app_handle.emit("calibration-failed", e);
+                }
+            }
+        });
+
+        self.thread = Some(handle);
+        self.stop_sender = Some(stop_tx);
+        Ok(())
+    }
+
+    fn cancel(&mut self) {
+        if let Some(sender) = self.stop_sender.take() {
+            let _ = sender.send(());
+        }
+        if let Some(handle) = self.thread.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+// Command to start the guided calibration. Progress is reported with
+// calibration-progress events, the result with calibration-complete or
+// calibration-failed.
+#[tauri::command]
+pub fn start_calibration(
+    app_handle: tauri::AppHandle,
+    state: tauri::State<Arc<Mutex<SerialManager>>>,
+    calibration: tauri::State<Mutex<GuidedCalibration>>,
+) -> Result<(), String> {
+    let (names, thresholds) = {
+        let manager = state.lock().map_err(|e| e.to_string())?;
+        (
+            Arc::clone(&manager.live.sensor_names),
+            Arc::clone(&manager.live.thresholds),
+        )
+    };
+    let mut calibration = calibration.lock().map_err(|e| e.to_string())?;
+    calibration.start(app_handle, names, thresholds)
+}
+
+// Command to abort the guided calibration, keeping the previous thresholds.
+#[tauri::command]
+pub fn cancel_calibration(
+    calibration: tauri::State<Mutex<GuidedCalibration>>,
+) -> Result<(), String> {
+    let mut calibration = calibration.lock().map_err(|e| e.to_string())?;
+    calibration.cancel();
+    Ok(())
+}
@@ -0,0 +1,3836 @@
+// Authoritative game state. Hits and the finish are recorded from the
+// laser-broken and buzzer events as they are emitted, timed by a monotonic
+// clock, so a reloading or lagging webview can't lose or delay them. The UI
+// renders game-state-changed and game-hit and fetches the result at the end.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Instant;
+use tauri::{Emitter, Listener, Manager};
+use tauri_plugin_store::StoreExt;
+
+use crate::hit_stats::{self, SensorHits};
+use crate::players;
+use crate::scores::ScoreBook;
+use crate::STORE_FILE;
+
+// Time the UI blinks a hit laser before its reactivation starts.
+const HIT_BLINK_MS: u64 = 900;
+// Default rate of game-tick events.
+const DEFAULT_TICK_RATE_HZ: u32 = 10;
+// How often the ticker checks whether a paused game was resumed.
+const PAUSE_POLL_MS: u64 = 100;
+// Highest urgency of the overtime sound.
+const MAX_OVERTIME_LEVEL: u32 = 10;
+// Longest countdown a game can start with.
+const MAX_COUNTDOWN_SECONDS: f64 = 60.0;
+// Default time after the start in which beam breaks don't count.
+const DEFAULT_GRACE_PERIOD_SECONDS: f64 = 1.5;
+// Default time an armed game waits for its first beam break.
+const DEFAULT_ARM_TIMEOUT_SECONDS: f64 = 300.0;
+// Default time after a relay leg in which the buzzer is ignored.
+const DEFAULT_CHANGEOVER_SECONDS: f64 = 2.0;
+// Default game time between pace-delta events.
+const DEFAULT_PACE_INTERVAL_SECONDS: f64 = 1.0;
+// Default time a head-to-head lane has to finish after the other one did.
+const DEFAULT_LANE_TIMEOUT_SECONDS: f64 = 30.0;
+// Config store key of the saved penalty tables, by maze profile.
+const PENALTY_TABLES_KEY: &str = "penaltyTables";
+
+// Source of the game time in milliseconds. Only differences matter, so any
+// monotonic origin works; tests drive it by hand.
+pub trait Clock: Send + Sync {
+    fn now_ms(&self) -> u64;
+}
+
+// Milliseconds since the clock was created.
+pub struct MonotonicClock {
+    origin: Instant,
+}
+
+impl MonotonicClock {
+    pub fn new() -> Self {
+        Self {
+            origin: Instant::now(),
+        }
+    }
+}
+
+impl Clock for MonotonicClock {
+    fn now_ms(&self) -> u64 {
+        self.origin.elapsed().as_millis() as u64
+    }
+}
+
+// The game time, measured on the one monotonic clock: it runs from the start
+// of the game, stands still while paused and stops at the end. Beam breaks
+// and buzzer presses are stamped with its time when the manager gets them,
+// which is while the serial thread emits them, so hits, splits and the
+// finish can't fall outside the time the game actually ran.
+pub struct GameClock {
+    clock: Arc<dyn Clock>,
+    // Clock times the game started, which may still lie ahead, and ended.
+    started_at: u64,
+    ended_at: Option<u64>,
+    // Clock times of the pauses so far, and the start of the current one.
+    pauses: Vec<(u64, u64)>,
+    paused_at: Option<u64>,
+}
+
+impl GameClock {
+    pub fn new(clock: Arc<dyn Clock>) -> Self {
+        Self {
+            clock,
+            started_at: 0,
+            ended_at: None,
+            pauses: Vec::new(),
+            paused_at: None,
+        }
+    }
+
+    pub fn now(&self) -> u64 {
+        self.clock.now_ms()
+    }
+
+    // Starts the game time over at clock time `at`.
+    fn start(&mut self, at: u64) {
+        self.started_at = at;
+        self.ended_at = None;
+        self.pauses.clear();
+        self.paused_at = None;
+    }
+
+    fn pause(&mut self) {
+        if self.ended_at.is_none() && self.paused_at.is_none() {
+            self.paused_at = Some(self.now());
+        }
+    }
+
+    // Ends the current pause, returning its clock times.
+    fn resume(&mut self) -> Option<(u64, u64)> {
+        let pause = (self.paused_at.take()?, self.now());
+        self.pauses.push(pause);
+        Some(pause)
+    }
+
+    // Stops the game time as of clock time `at`; a game stopped while paused
+    // stopped when the pause began. Returns the pause this ended, if any.
+    fn stop(&mut self, at: u64) -> Option<(u64, u64)> {
+        let at = self.paused_at.map_or(at, |paused_at| at.min(paused_at));
+        let pause = self.resume();
+        let at = self
+            .pauses
+            .iter()
+            .find(|&&(from, to)| from < at && at < to)
+            .map_or(at, |&(from, _)| from);
+        self.ended_at = Some(at.max(self.started_at));
+        pause
+    }
+
+    // Game time at clock time `at`; instants before the start, during a
+    // pause or after the end map to the game time of then.
+    pub fn game_ms(&self, at: u64) -> u64 {
+        let at = self.ended_at.map_or(at, |ended_at| at.min(ended_at));
+        let at = self.paused_at.map_or(at, |paused_at| at.min(paused_at));
+        let paused: u64 = self
+            .pauses
+            .iter()
+            .map(|&(from, to)| to.min(at).saturating_sub(from.max(self.started_at)))
+            .sum();
+        at.saturating_sub(self.started_at).saturating_sub(paused)
+    }
+
+    pub fn elapsed_ms(&self) -> u64 {
+        self.game_ms(self.now())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GamePhase {
+    Idle,
+    // Waiting for the beam break that starts the game.
+    Armed,
+    Countdown,
+    Running,
+    Paused,
+    Finished,
+    Aborted,
+}
+
+// What a game is about. Each mode decides which hits end it, whether the
+// buzzer finishes it and what its score is; lower scores are better, except
+// in reverse mode.
+#[derive(Clone, Debug, Default, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(
+    tag = "kind",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum GameMode {
+    // Run to the buzzer as fast as possible; using up the allowed touches
+    // ends the game. Scored by time.
+    #[default]
+    TimeAttack,
+    // Run to the buzzer; the hit that takes the last life ends the game at
+    // once. Scored by time.
+    LimitedLives {
+        lives: u32,
+    },
+    // Stay in the maze for a fixed time; the buzzer does nothing. Scored by
+    // hits.
+    Stealth {
+        window_seconds: f64,
+    },
+    // Break as many different beams as possible before the time runs out;
+    // only the first break of each beam counts. Scored by beams broken, plus
+    // a point per second left if all of them were. Higher is better.
+    Reverse {
+        time_limit_seconds: f64,
+    },
+}
+
+impl GameMode {
+    // Name the mode is saved under with a run.
+    pub fn name(&self) -> &'static str {
+        match self {
+            GameMode::TimeAttack => "timeAttack",
+            GameMode::LimitedLives { .. } => "limitedLives",
+            GameMode::Stealth { .. } => "stealth",
+            GameMode::Reverse { .. } => "reverse",
+        }
+    }
+
+    // Hits that end the game and why; 0 means unlimited.
+    fn hit_limit(&self, max_allowed_touches: u32) -> (u32, GameOverReason) {
+        match self {
+            GameMode::TimeAttack => (max_allowed_touches, GameOverReason::TouchLimit),
+            GameMode::LimitedLives { lives } => (*lives, GameOverReason::OutOfLives),
+            GameMode::Stealth { .. } | GameMode::Reverse { .. } => (0, GameOverReason::TouchLimit),
+        }
+    }
+
+    // Game time after which the game ends by itself.
+    fn window_ms(&self) -> Option<u64> {
+        match self {
+            GameMode::Stealth { window_seconds } => Some(seconds_to_ms(*window_seconds)),
+            GameMode::Reverse { time_limit_seconds } => Some(seconds_to_ms(*time_limit_seconds)),
+            _ => None,
+        }
+    }
+
+    // Whether hits are beams to collect rather than mistakes.
+    fn collects_beams(&self) -> bool {
+        matches!(self, GameMode::Reverse { .. })
+    }
+
+    // `time_ms` includes the penalties; `remaining_ms` is the time left if
+    // every beam was collected.
+    fn score(&self, time_ms: u64, hits: u32, remaining_ms: Option<u64>) -> u64 {
+        match self {
+            GameMode::Stealth { .. } => u64::from(hits),
+            GameMode::Reverse { .. } => u64::from(hits) + remaining_ms.unwrap_or(0) / 1000,
+            _ => time_ms,
+        }
+    }
+}
+
+// Time added per hit, by sensor, e.g. less for an easy waist-high beam than
+// for an ankle beam.
+#[derive(Clone, Debug, Default, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PenaltyTable {
+    // Penalty of sensors not listed.
+    pub default_seconds: f64,
+    pub sensors: BTreeMap<usize, f64>,
+}
+
+impl PenaltyTable {
+    fn penalty_ms(&self, sensor: usize) -> u64 {
+        seconds_to_ms(
+            self.sensors
+                .get(&sensor)
+                .copied()
+                .unwrap_or(self.default_seconds),
+        )
+    }
+}
+
+// Why a game ended in a game over.
+#[derive(Clone, Copy, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum GameOverReason {
+    // The allowed touches of a time attack game were used up.
+    TouchLimit,
+    // The last life of a limited lives game was lost.
+    OutOfLives,
+    // The hit limit set with start_game was reached.
+    MaxHits,
+    // A fatal beam was broken.
+    Tripwire,
+    // The time limit was reached, and the game ends at it.
+    TimeExpired,
+}
+
+// A sensor that marks a split time rather than counting as a hit, e.g. a
+// gate halfway through the maze.
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Checkpoint {
+    pub sensor: usize,
+    pub name: String,
+    // Fire when the beam is restored rather than when it breaks.
+    #[serde(default)]
+    pub on_restore: bool,
+}
+
+// What ends a game successfully.
+#[derive(Clone, Copy, Debug, Default, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(
+    tag = "kind",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum FinishCondition {
+    // Any buzzer press.
+    #[default]
+    Buzzer,
+    // The break, or the restore, of the finish beam.
+    Beam {
+        sensor: usize,
+        #[serde(default)]
+        on_restore: bool,
+    },
+    // A buzzer press once the finish beam fired; earlier presses are
+    // ignored, e.g. from players leaning over from the start area.
+    BeamThenBuzzer {
+        sensor: usize,
+        #[serde(default)]
+        on_restore: bool,
+    },
+}
+
+impl FinishCondition {
+    fn beam(&self) -> Option<(usize, bool)> {
+        match *self {
+            Self::Buzzer => None,
+            Self::Beam { sensor, on_restore } | Self::BeamThenBuzzer { sensor, on_restore } => {
+                Some((sensor, on_restore))
+            }
+        }
+    }
+}
+
+// Payload of premature-finish-attempt: a buzzer press the finish condition
+// didn't allow.
+#[derive(Clone, Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrematureFinish {
+    pub elapsed_ms: u64,
+    pub finish: FinishCondition,
+}
+
+// Players taking turns in one game: each buzzer press ends a leg and the
+// next player sets off, while the game time runs on.
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RelayConfig {
+    // Players in running order; they take turns until all legs are run.
+    pub players: Vec<String>,
+    // Legs of the relay; 0 means one per player.
+    pub legs: u32,
+    // Time after each leg in which buzzer presses are ignored, so a single
+    // press can't end two legs.
+    pub changeover_seconds: f64,
+}
+
+impl Default for RelayConfig {
+    fn default() -> Self {
+        Self {
+            players: Vec::new(),
+            legs: 0,
+            changeover_seconds: DEFAULT_CHANGEOVER_SECONDS,
+        }
+    }
+}
+
+impl RelayConfig {
+    fn leg_count(&self) -> usize {
+        match self.legs {
+            0 => self.players.len(),
+            legs => legs as usize,
+        }
+    }
+
+    // Player of the leg with index `leg`, from 0.
+    fn player(&self, leg: usize) -> Option<&String> {
+        self.players.get(leg % self.players.len().max(1))
+    }
+}
+
+// One finished relay leg.
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RelayLeg {
+    // From 1.
+    pub leg: usize,
+    pub player: String,
+    // Time of the leg alone, and the game time it ended at.
+    pub elapsed_ms: u64,
+    pub split_ms: u64,
+    pub hits: u32,
+    pub penalty_ms: u64,
+}
+
+// The beams of a head-to-head lane.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(
+    tag = "kind",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum LaneSensors {
+    // Sensors `first` to `last` of all sensors, e.g. half of one device.
+    Range { first: usize, last: usize },
+    // All sensors of one connection, e.g. a second device.
+    Connection { connection_id: String },
+}
+
+impl LaneSensors {
+    fn contains(&self, connection_id: &str, sensor: usize) -> bool {
+        match self {
+            LaneSensors::Range { first, last } => (*first..=*last).contains(&sensor),
+            LaneSensors::Connection { connection_id: id } => id == connection_id,
+        }
+    }
+}
+
+// What finishes a head-to-head lane.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(
+    tag = "kind",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum FinishTrigger {
+    // A buzzer press, of the given connection if set.
+    Buzzer {
+        #[serde(default)]
+        connection_id: Option<String>,
+    },
+    // A beam break of the sensor at the end of the lane.
+    Sensor {
+        sensor: usize,
+    },
+}
+
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LaneConfig {
+    // Tags the lane's events, e.g. "left".
+    pub id: String,
+    pub player: Option<String>,
+    pub sensors: LaneSensors,
+    pub finish: FinishTrigger,
+}
+
+// Two players racing at once in mirrored lanes, under the same rules.
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct HeadToHeadConfig {
+    pub lanes: Vec<LaneConfig>,
+    // Time the other lane has to finish once one lane finished; it is
+    // aborted after that.
+    pub finish_timeout_seconds: f64,
+}
+
+impl Default for HeadToHeadConfig {
+    fn default() -> Self {
+        Self {
+            lanes: Vec::new(),
+            finish_timeout_seconds: DEFAULT_LANE_TIMEOUT_SECONDS,
+        }
+    }
+}
+
+// Rules of one game, mirroring the frontend's game settings.
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct GameConfig {
+    pub mode: GameMode,
+    // Sensors that count as lasers; empty means all of them.
+    pub sensors: Vec<usize>,
+    // Tripwire sensors whose beam break ends the game at once, whatever the
+    // hits so far. Their breaks carry no penalty and aren't counted as hits.
+    pub fatal_sensors: Vec<usize>,
+    // Hits that end a time attack game; 0 means unlimited.
+    pub max_allowed_touches: u32,
+    // Whether a hit laser counts again after the reactivation time.
+    pub reactivate_lasers: bool,
+    pub reactivation_time_seconds: f64,
+    pub countdown_seconds: f64,
+    pub countdown_audio: CountdownAudio,
+    // game-tick events per second while the game runs.
+    pub tick_rate_hz: u32,
+    // Hits after which any game is over and failed, e.g. 3 for "three
+    // strikes".
+    pub max_hits: Option<u32>,
+    // Whether beam breaks during a pause are recorded as paused-period hits.
+    pub count_paused_hits: bool,
+    // Game time after the start in which hits are recorded but don't count,
+    // for players still leaving the start gate.
+    pub grace_period_seconds: f64,
+    // Checkpoints in the order they are passed.
+    pub checkpoints: Vec<Checkpoint>,
+    // Player of the game, whose best run the splits are compared with.
+    pub player: Option<String>,
+    // Warm-up game that never reaches the leaderboards.
+    pub practice: bool,
+    pub penalties: PenaltyTable,
+    // Maze profile whose saved penalty table replaces `penalties`.
+    pub penalty_profile: Option<String>,
+    // Sensor whose beam break starts an armed game; None means any.
+    pub start_gate_sensor: Option<usize>,
+    // Time an armed game waits for its start before disarming; 0 means
+    // forever.
+    pub arm_timeout_seconds: f64,
+    pub relay: Option<RelayConfig>,
+    // Game time between pace-delta events against the player's best run;
+    // 0 means only at checkpoints.
+    pub pace_interval_seconds: f64,
+    pub head_to_head: Option<HeadToHeadConfig>,
+    pub finish: FinishCondition,
+    // Longest a run may take; 0 means no limit.
+    pub time_limit_seconds: f64,
+    pub on_time_limit: TimeLimitAction,
+}
+
+// What happens when a game reaches its time limit.
+#[derive(Clone, Copy, Debug, Default, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TimeLimitAction {
+    // The game ends as failed, did not finish.
+    #[default]
+    End,
+    // The clock keeps running, but the run is flagged as overtime.
+    Overtime,
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        Self {
+            mode: GameMode::default(),
+            sensors: Vec::new(),
+            fatal_sensors: Vec::new(),
+            max_allowed_touches: 3,
+            reactivate_lasers: false,
+            reactivation_time_seconds: 5.0,
+            countdown_seconds: 3.0,
+            countdown_audio: CountdownAudio::default(),
+            tick_rate_hz: DEFAULT_TICK_RATE_HZ,
+            max_hits: None,
+            count_paused_hits: false,
+            grace_period_seconds: DEFAULT_GRACE_PERIOD_SECONDS,
+            checkpoints: Vec::new(),
+            player: None,
+            practice: false,
+            penalties: PenaltyTable::default(),
+            penalty_profile: None,
+            start_gate_sensor: None,
+            arm_timeout_seconds: DEFAULT_ARM_TIMEOUT_SECONDS,
+            relay: None,
+            pace_interval_seconds: DEFAULT_PACE_INTERVAL_SECONDS,
+            head_to_head: None,
+            finish: FinishCondition::Buzzer,
+            time_limit_seconds: 0.0,
+            on_time_limit: TimeLimitAction::End,
+        }
+    }
+}
+
+fn seconds_to_ms(seconds: f64) -> u64 {
+    (seconds.max(0.0) * 1000.0).round() as u64
+}
+
+// One recorded beam break, also the payload of game-hit.
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Hit {
+    pub sensor: usize,
+    pub name: String,
+    // Game time of the hit.
+    pub elapsed_ms: u64,
+    // Hits so far, this one included unless it is a grace hit.
+    pub count: u32,
+    // Hit within the grace period; it doesn't count.
+    #[serde(default)]
+    pub grace: bool,
+    // Break of a tripwire, which ended the game.
+    #[serde(default)]
+    pub fatal: bool,
+    // Penalty of this hit, and the game time with all penalties so far.
+    #[serde(default)]
+    pub penalty_ms: u64,
+    #[serde(default)]
+    pub penalized_ms: u64,
+    // Reason an operator voided the hit, e.g. a spectator leaning in; it
+    // stays in the timeline but no longer counts.
+    #[serde(default)]
+    pub voided: Option<String>,
+}
+
+impl Hit {
+    // Counts towards the hits, unlike grace hits, tripwires and voided hits.
+    pub fn counts(&self) -> bool {
+        !self.grace && !self.fatal && self.voided.is_none()
+    }
+}
+
+// Voids the hit at `index` of a timeline and renumbers the hits after it.
+// Returns whether it was the last hit that counted or broke a tripwire,
+// i.e. the one that ended a game over.
+pub fn void_hit(timeline: &mut [Hit], index: usize, reason: &str) -> Result<bool, String> {
+    let hit = timeline
+        .get_mut(index)
+        .ok_or_else(|| format!("no hit {} in the timeline", index))?;
+    if hit.voided.is_some() {
+        return Err(format!("hit {} is already voided", index));
+    }
+    if hit.grace {
+        return Err("grace hits don't count anyway".to_string());
+    }
+    hit.voided = Some(reason.to_string());
+    let last = !timeline[index + 1..]
+        .iter()
+        .any(|hit| hit.voided.is_none() && !hit.grace);
+    let (mut count, mut penalty_ms) = (0, 0);
+    for hit in timeline.iter_mut() {
+        if hit.counts() {
+            count += 1;
+            penalty_ms += hit.penalty_ms;
+        }
+        hit.count = count;
+        hit.penalized_ms = hit.elapsed_ms + penalty_ms;
+    }
+    Ok(last)
+}
+
+// Split time of a checkpoint, also the payload of checkpoint-reached.
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Split {
+    pub name: String,
+    pub sensor: usize,
+    // Game time the checkpoint was reached; None if it was missed.
+    pub elapsed_ms: Option<u64>,
+    // Difference to the split of the player's best run, if it had one.
+    pub delta_ms: Option<i64>,
+    pub missed: bool,
+}
+
+// One pause of a game.
+#[derive(Clone, Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Pause {
+    // Game time the pause began; 0 for pauses during the countdown.
+    pub at_ms: u64,
+    pub duration_ms: u64,
+    pub during_countdown: bool,
+}
+
+// Payload of disabled-sensors-warning, emitted when a game starts or is
+// armed with sensors left out of hit detection.
+#[derive(Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DisabledSensorsWarning {
+    sensors: Vec<usize>,
+}
+
+// Payload of countdown-tick, emitted as each countdown second begins and,
+// with 0 seconds, the moment the game starts running.
+#[derive(Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CountdownTick {
+    seconds: u64,
+    remaining_ms: u64,
+}
+
+// How the countdown sounds: one clip from its first tick, a click per second
+// and the start sound on "go", or not at all.
+#[derive(Clone, Copy, Debug, Default, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CountdownAudio {
+    #[default]
+    Clip,
+    PerTick,
+    Off,
+}
+
+impl CountdownAudio {
+    // The sound of the countdown tick with `seconds` left, 0 being "go".
+    fn cue(self, seconds: u64, first: bool) -> Option<SoundEffect> {
+        match self {
+            CountdownAudio::Clip if first && seconds > 0 => Some(SoundEffect::Countdown),
+            CountdownAudio::PerTick if seconds > 0 => Some(SoundEffect::Click),
+            CountdownAudio::PerTick => Some(SoundEffect::GameStart),
+            _ => None,
+        }
+    }
+}
+
+// Sound effects of the frontend's audio manager, by their names there.
+#[derive(Clone, Copy, Debug, PartialEq, serde::Serialize)]
+enum SoundEffect {
+    Countdown,
+    Click,
+    GameStart,
+    Overtime,
+}
+
+// Payload of play-sound, for the audio manager to play the effect at once,
+// and of stop-sounds, to cut the sounds of the game short.
+#[derive(Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PlaySound {
+    game_id: u64,
+    effect: Option<SoundEffect>,
+    // How urgent the effect sounds, rising through overtime; 0 for others.
+    level: u32,
+}
+
+// Payload of time-expired, emitted when a game reaches its time limit.
+#[derive(Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimeExpired {
+    pub game_id: u64,
+    pub limit_ms: u64,
+    // Whether the game plays on in overtime rather than ending.
+    pub overtime: bool,
+}
+
+// Payload of game-tick.
+#[derive(Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GameTick {
+    game_id: u64,
+    elapsed_ms: u64,
+}
+
+// Snapshot returned by get_game_state and emitted as game-state-changed.
+#[derive(Clone, Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GameState {
+    // Counts up with every started game.
+    pub game_id: u64,
+    pub phase: GamePhase,
+    pub elapsed_ms: u64,
+    pub hits: u32,
+    // Penalties so far, to add to the game time.
+    pub penalty_ms: u64,
+    pub countdown_remaining_ms: u64,
+    // Hits left before the game is over, if the mode limits them.
+    pub lives_left: Option<u32>,
+    // Game time left, if the mode has a fixed length.
+    pub remaining_ms: Option<u64>,
+    pub practice: bool,
+    // Past the time limit, with the clock still running.
+    pub overtime: bool,
+    // The lanes of a head-to-head game.
+    pub lanes: Vec<LaneState>,
+}
+
+// What an entry of the spectator feed is about.
+#[derive(Clone, Copy, Debug, PartialEq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FeedKind {
+    Start,
+    Hit,
+    Checkpoint,
+    Pause,
+    Resume,
+    Finish,
+    GameOver,
+    Abort,
+}
+
+// Payload of game-event: one entry of the spectator feed, e.g. "0:23.4 -
+// Ankle Trap - 3rd hit". Every kind has the same fields; those it has no
+// use for are unset.
+#[derive(Clone, Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeedEvent {
+    pub game_id: u64,
+    pub kind: FeedKind,
+    // Game time of the event.
+    pub elapsed_ms: u64,
+    // Hits and penalties so far.
+    pub hits: u32,
+    pub penalty_ms: u64,
+    // Sensor of a hit or checkpoint.
+    pub sensor: Option<usize>,
+    pub name: Option<String>,
+    // Penalty of a hit, and whether it fell in the grace period.
+    pub hit_penalty_ms: u64,
+    pub grace: bool,
+    pub game_over_reason: Option<GameOverReason>,
+    pub abort_reason: Option<String>,
+}
+
+impl FeedEvent {
+    pub fn new(kind: FeedKind, state: &GameState) -> Self {
+        Self {
+            game_id: state.game_id,
+            kind,
+            elapsed_ms: state.elapsed_ms,
+            hits: state.hits,
+            penalty_ms: state.penalty_ms,
+            sensor: None,
+            name: None,
+            hit_penalty_ms: 0,
+            grace: false,
+            game_over_reason: None,
+            abort_reason: None,
+        }
+    }
+
+    fn hit(hit: &Hit, state: &GameState) -> Self {
+        Self {
+            elapsed_ms: hit.elapsed_ms,
+            hits: hit.count,
+            penalty_ms: hit.penalized_ms.saturating_sub(hit.elapsed_ms),
+            sensor: Some(hit.sensor),
+            name: Some(hit.name.clone()),
+            hit_penalty_ms: hit.penalty_ms,
+            grace: hit.grace,
+            ..Self::new(FeedKind::Hit, state)
+        }
+    }
+
+    // None for a missed checkpoint.
+    fn checkpoint(split: &Split, state: &GameState) -> Option<Self> {
+        Some(Self {
+            elapsed_ms: split.elapsed_ms?,
+            sensor: Some(split.sensor),
+            name: Some(split.name.clone()),
+            ..Self::new(FeedKind::Checkpoint, state)
+        })
+    }
+}
+
+#[derive(Clone, Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LaneState {
+    pub lane: String,
+    pub player: Option<String>,
+    #[serde(flatten)]
+    pub state: GameState,
+}
+
+// Payload of game-hit in a head-to-head game.
+#[derive(Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct LaneHit {
+    lane: String,
+    #[serde(flatten)]
+    hit: Hit,
+}
+
+// Outcome of one head-to-head lane, also the payload of lane-finished.
+#[derive(Clone, Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LaneResult {
+    pub lane: String,
+    pub player: Option<String>,
+    // Aborted for not finishing in time after the other lane.
+    pub timed_out: bool,
+    #[serde(flatten)]
+    pub result: GameResult,
+}
+
+#[derive(Clone, Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HeadToHeadResult {
+    pub lanes: Vec<LaneResult>,
+    // Lane with the better score of those that finished successfully; None
+    // on a draw or when neither did.
+    pub winner: Option<String>,
+}
+
+// What a timed game is paced against: the best run of its player, with its
+// split times by checkpoint name, and the record of its mode.
+#[derive(Clone, Debug, Default)]
+pub struct Pace {
+    pub splits: HashMap<String, u64>,
+    // Game time of the best run, and its final score.
+    pub finish_ms: Option<u64>,
+    pub best_ms: Option<u64>,
+    pub record_ms: Option<u64>,
+}
+
+// Payload of pace-delta: how far the game is behind the player's best run,
+// negative when ahead.
+#[derive(Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PaceDelta {
+    // Checkpoint just reached; None for the periodic estimate.
+    checkpoint: Option<String>,
+    elapsed_ms: u64,
+    delta_ms: i64,
+}
+
+// Payload of new-personal-best and new-record.
+#[derive(Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct NewBest {
+    player: Option<String>,
+    mode: String,
+    score: u64,
+    previous: u64,
+}
+
+// Payload of relay-leg-complete.
+#[derive(Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct LegComplete {
+    #[serde(flatten)]
+    leg: RelayLeg,
+    total_legs: usize,
+    // Player of the next leg; None after the last one.
+    next_player: Option<String>,
+}
+
+// Payload of beam-collected, emitted for each new beam of a reverse game.
+#[derive(Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BeamCollected {
+    sensor: usize,
+    name: String,
+    collected: u32,
+    // Beams to collect; None when the game plays all sensors.
+    total: Option<usize>,
+}
+
+// Payload of game-over.
+#[derive(Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GameOver {
+    reason: GameOverReason,
+    // The tripwire that ended the game, if one did.
+    sensor: Option<usize>,
+    name: Option<String>,
+    #[serde(flatten)]
+    state: GameState,
+}
+
+// Outcome of a finished or aborted game.
+#[derive(Clone, Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GameResult {
+    // Name of the mode, as saved with the run.
+    pub mode: String,
+    // True when the buzzer or the end of the time ended the game, false on
+    // game over or abort.
+    pub success: bool,
+    pub aborted: bool,
+    // Ended in a game over, for the reason given.
+    pub failed: bool,
+    pub game_over_reason: Option<GameOverReason>,
+    // Why the game was aborted, if a reason was given.
+    pub abort_reason: Option<String>,
+    pub practice: bool,
+    pub elapsed_ms: u64,
+    // Hits that count; grace hits are only in the timeline.
+    pub hits: u32,
+    // Penalties of the hits, and the game time including them.
+    pub penalty_ms: u64,
+    pub penalized_ms: u64,
+    // Score by the rules of the mode; lower is better, except in reverse
+    // mode.
+    pub score: u64,
+    pub timeline: Vec<Hit>,
+    // Hits per sensor, most first.
+    pub hits_by_sensor: Vec<SensorHits>,
+    // One split per checkpoint, in checkpoint order.
+    pub splits: Vec<Split>,
+    pub pauses: Vec<Pause>,
+    // Beam breaks while paused, if the config counts them.
+    pub paused_hits: Vec<Hit>,
+    // Finished legs of a relay game, in order.
+    pub legs: Vec<RelayLeg>,
+    // Score difference to the player's best run and to the record of the
+    // mode, negative when better; None without one to compare with.
+    pub personal_best_delta_ms: Option<i64>,
+    pub record_delta_ms: Option<i64>,
+    pub head_to_head: Option<HeadToHeadResult>,
+    // Game time without sensor data; over half the run marks a saved run
+    // as suspect.
+    pub silent_ms: u64,
+    // Sensors disabled at any time during the game, whose beams didn't
+    // count; the maze was shorter than usual.
+    pub disabled_sensors: Vec<usize>,
+    // Finished past the time limit; such runs rank below those that didn't.
+    pub overtime: bool,
+    pub config: GameConfig,
+}
+
+// A head-to-head lane, played as a game of its own on the same clock.
+struct Lane {
+    config: LaneConfig,
+    game: GameManager,
+    timed_out: bool,
+    // Whether lane-finished was emitted.
+    reported: bool,
+}
+
+impl Lane {
+    fn result(&self) -> Option<LaneResult> {
+        Some(LaneResult {
+            lane: self.config.id.clone(),
+            player: self.config.player.clone(),
+            timed_out: self.timed_out,
+            result: self.game.result()?,
+        })
+    }
+}
+
+pub struct GameManager {
+    clock: GameClock,
+    game_id: u64,
+    phase: GamePhase,
+    config: GameConfig,
+    countdown_ends_at: u64,
+    // Clock time an armed game disarms, if it does.
+    armed_until: Option<u64>,
+    success: bool,
+    game_over: Option<GameOverReason>,
+    abort_reason: Option<String>,
+    timeline: Vec<Hit>,
+    splits: Vec<Split>,
+    // Index of the first checkpoint not yet passed.
+    next_checkpoint: usize,
+    pace: Pace,
+    // Lasers that were hit, with the game time they count again; None if
+    // never.
+    inactive: HashMap<usize, Option<u64>>,
+    // The phase the current pause interrupted.
+    paused_from: GamePhase,
+    pauses: Vec<Pause>,
+    paused_hits: Vec<Hit>,
+    legs: Vec<RelayLeg>,
+    // Game time the current relay leg began.
+    leg_started_ms: u64,
+    lanes: Vec<Lane>,
+    // Game time the sensor data stopped, while it is missing, and the time
+    // it was missing before.
+    silent_since: Option<u64>,
+    silent_ms: u64,
+    // Game time the finish beam fired, letting the buzzer finish a
+    // two-stage game.
+    finish_beam_ms: Option<u64>,
+    // Sensors left out of hit detection, and those that were at any time
+    // during the current game.
+    disabled: BTreeSet<usize>,
+    disabled_in_game: BTreeSet<usize>,
+    // Set once the time limit is reached, with whether time-expired was
+    // emitted.
+    overtime: bool,
+    time_expired: Option<TimeExpired>,
+    expiry_reported: bool,
+    // Whether the end of the game went into the feed.
+    end_fed: bool,
+    // Whether the game was armed rather than started, and whether its
+    // result was saved or discarded.
+    armed: bool,
+    result_settled: bool,
+}
+
+impl GameManager {
+    pub fn new(clock: Arc<dyn Clock>) -> Self {
+        Self {
+            clock: GameClock::new(clock),
+            game_id: 0,
+            phase: GamePhase::Idle,
+            config: GameConfig::default(),
+            countdown_ends_at: 0,
+            armed_until: None,
+            success: false,
+            game_over: None,
+            abort_reason: None,
+            timeline: Vec::new(),
+            splits: Vec::new(),
+            next_checkpoint: 0,
+            pace: Pace::default(),
+            inactive: HashMap::new(),
+            paused_from: GamePhase::Idle,
+            pauses: Vec::new(),
+            paused_hits: Vec::new(),
+            legs: Vec::new(),
+            leg_started_ms: 0,
+            lanes: Vec::new(),
+            silent_since: None,
+            silent_ms: 0,
+            finish_beam_ms: None,
+            disabled: BTreeSet::new(),
+            disabled_in_game: BTreeSet::new(),
+            overtime: false,
+            time_expired: None,
+            expiry_reported: false,
+            end_fed: false,
+            armed: false,
+            result_settled: false,
+        }
+    }
+
+    fn in_progress(&self) -> bool {
+        matches!(
+            self.phase,
+            GamePhase::Armed | GamePhase::Countdown | GamePhase::Running | GamePhase::Paused
+        )
+    }
+
+    // Starts the countdown of a new game.
+    pub fn start(&mut self, config: GameConfig) -> Result<(), String> {
+        let now = self.clock.now();
+        self.reset(config)?;
+        self.countdown_ends_at = now + seconds_to_ms(self.config.countdown_seconds);
+        self.clock.start(self.countdown_ends_at);
+        self.phase = GamePhase::Countdown;
+        self.armed = false;
+        if let Some(head_to_head) = self.config.head_to_head.clone() {
+            for lane in head_to_head.lanes {
+                let mut game = GameManager::new(Arc::clone(&self.clock.clock));
+                game.disabled = self.disabled.clone();
+                game.start(lane_config(&self.config, &lane))?;
+                game.countdown_ends_at = self.countdown_ends_at;
+                game.clock.start(self.countdown_ends_at);
+                self.lanes.push(Lane {
+                    config: lane,
+                    game,
+                    timed_out: false,
+                    reported: false,
+                });
+            }
+        }
+        self.advance();
+        Ok(())
+    }
+
+    // Arms a new game that starts, without a countdown, on the next beam
+    // break of the start gate.
+    pub fn arm(&mut self, config: GameConfig) -> Result<(), String> {
+        if config.head_to_head.is_some() {
+            return Err("head-to-head games start with a countdown".to_string());
+        }
+        let now = self.clock.now();
+        self.reset(config)?;
+        let timeout_ms = seconds_to_ms(self.config.arm_timeout_seconds);
+        self.armed_until = (timeout_ms > 0).then(|| now + timeout_ms);
+        self.phase = GamePhase::Armed;
+        self.armed = true;
+        Ok(())
+    }
+
+    // Clears the last game for a new one.
+    fn reset(&mut self, config: GameConfig) -> Result<(), String> {
+        if self.in_progress() {
+            return Err("a game is already in progress".to_string());
+        }
+        if let Some(relay) = &config.relay {
+            if relay.players.iter().all(|player| player.trim().is_empty()) {
+                return Err("a relay needs players".to_string());
+            }
+            if config.mode.window_ms().is_some() {
+                return Err("relay legs end with the buzzer, which this mode ignores".to_string());
+            }
+        }
+        if let Some(head_to_head) = &config.head_to_head {
+            let [first, second] = head_to_head.lanes.as_slice() else {
+                return Err("a head-to-head game needs two lanes".to_string());
+            };
+            if first.id.trim().is_empty() || first.id == second.id {
+                return Err("the lanes need different ids".to_string());
+            }
+            if config.relay.is_some() {
+                return Err("a relay can't be played head-to-head".to_string());
+            }
+        }
+        if config.finish != FinishCondition::Buzzer {
+            if config.relay.is_some() || config.head_to_head.is_some() {
+                return Err("relays and head-to-head games finish with the buzzer".to_string());
+            }
+            if config.mode.window_ms().is_some() {
+                return Err("this mode ends by time, not with a finish beam".to_string());
+            }
+        }
+        if config.time_limit_seconds < 0.0 {
+            return Err("the time limit can't be negative".to_string());
+        }
+        if config.time_limit_seconds > 0.0 {
+            if config.mode.window_ms().is_some() {
+                return Err("this mode already ends by time".to_string());
+            }
+            if config.head_to_head.is_some() {
+                return Err("head-to-head lanes have a finish timeout instead".to_string());
+            }
+        }
+        if !(0.0..=MAX_COUNTDOWN_SECONDS).contains(&config.countdown_seconds) {
+            return Err(format!(
+                "the countdown must be between 0 and {} seconds",
+                MAX_COUNTDOWN_SECONDS
+            ));
+        }
+        self.game_id += 1;
+        self.armed_until = None;
+        self.clock.start(0);
+        self.success = false;
+        self.game_over = None;
+        self.abort_reason = None;
+        self.timeline.clear();
+        self.inactive.clear();
+        self.pauses.clear();
+        self.paused_hits.clear();
+        self.legs.clear();
+        self.leg_started_ms = 0;
+        self.lanes.clear();
+        self.silent_since = None;
+        self.silent_ms = 0;
+        self.finish_beam_ms = None;
+        self.disabled_in_game = self.disabled.clone();
+        self.overtime = false;
+        self.time_expired = None;
+        self.expiry_reported = false;
+        self.end_fed = false;
+        self.result_settled = false;
+        // Checkpoints count as missed until they are reached.
+        self.splits = config
+            .checkpoints
+            .iter()
+            .map(|checkpoint| Split {
+                name: checkpoint.name.clone(),
+                sensor: checkpoint.sensor,
+                elapsed_ms: None,
+                delta_ms: None,
+                missed: true,
+            })
+            .collect();
+        self.next_checkpoint = 0;
+        self.pace = Pace::default();
+        self.config = config;
+        Ok(())
+    }
+
+    // Sets the best run and record to compare the game with.
+    pub fn compare_with(&mut self, pace: Pace) {
+        self.pace = pace;
+    }
+
+    // How far the game is behind the best run now. Between checkpoints the
+    // game keeps the delta of the last one, unless the best run has passed
+    // the next one (or finished) longer ago than that. None without a best
+    // run to compare with.
+    fn pace_delta(&self) -> Option<PaceDelta> {
+        let elapsed_ms = self.elapsed_ms() as i64;
+        let (mut delta_ms, mut next_ms) = (0, None);
+        for split in &self.splits {
+            let Some(&best) = self.pace.splits.get(&split.name) else {
+                continue;
+            };
+            match split.elapsed_ms {
+                Some(reached) => delta_ms = reached as i64 - best as i64,
+                None if next_ms.is_none() => next_ms = Some(best),
+                None => {}
+            }
+        }
+        let next_ms = next_ms.or(self.pace.finish_ms)?;
+        Some(PaceDelta {
+            checkpoint: None,
+            elapsed_ms: elapsed_ms as u64,
+            delta_ms: delta_ms.max(elapsed_ms - next_ms as i64),
+        })
+    }
+
+    // The new-record or new-personal-best event a finished game earns, if
+    // any; practice games earn none.
+    fn new_best(&self) -> Option<(&'static str, NewBest)> {
+        let result = self.result().filter(|result| !result.practice)?;
+        let (event, previous) = match (result.record_delta_ms, result.personal_best_delta_ms) {
+            (Some(delta), _) if delta < 0 => ("new-record", self.pace.record_ms?),
+            (_, Some(delta)) if delta < 0 => ("new-personal-best", self.pace.best_ms?),
+            _ => return None,
+        };
+        Some((
+            event,
+            NewBest {
+                player: self.config.player.clone(),
+                mode: result.mode,
+                score: result.score,
+                previous,
+            },
+        ))
+    }
+
+    // Records the split of the next checkpoint on `sensor` that fires on a
+    // break (or a restore). Checkpoints fire once each and in order, so
+    // reaching one marks those skipped before it as missed.
+    pub fn record_checkpoint(&mut self, sensor: usize, restored: bool) -> Option<Split> {
+        self.advance();
+        if self.phase != GamePhase::Running {
+            return None;
+        }
+        let index = (self.next_checkpoint..self.config.checkpoints.len()).find(|&i| {
+            let checkpoint = &self.config.checkpoints[i];
+            checkpoint.sensor == sensor && checkpoint.on_restore == restored
+        })?;
+        let elapsed_ms = self.clock.game_ms(self.clock.now());
+        let split = &mut self.splits[index];
+        split.elapsed_ms = Some(elapsed_ms);
+        split.delta_ms = self
+            .pace
+            .splits
+            .get(&split.name)
+            .map(|&best| elapsed_ms as i64 - best as i64);
+        split.missed = false;
+        self.next_checkpoint = index + 1;
+        Some(split.clone())
+    }
+
+    fn is_checkpoint(&self, sensor: usize) -> bool {
+        self.config
+            .checkpoints
+            .iter()
+            .any(|checkpoint| checkpoint.sensor == sensor)
+    }
+
+    // Starts an armed game if the beam break of `sensor` is its start gate.
+    // The game time begins with the break. Returns whether the game
+    // started; the triggering break is no hit.
+    pub fn trigger_start(&mut self, sensor: usize) -> bool {
+        self.advance();
+        if self.phase != GamePhase::Armed
+            || self
+                .config
+                .start_gate_sensor
+                .is_some_and(|gate| gate != sensor)
+        {
+            return false;
+        }
+        self.countdown_ends_at = self.clock.now();
+        self.clock.start(self.countdown_ends_at);
+        self.armed_until = None;
+        self.phase = GamePhase::Running;
+        true
+    }
+
+    // Seconds left in the countdown, rounded up, with the time until the next
+    // of them begins.
+    fn countdown_second(&self) -> (u64, u64) {
+        let remaining = self.countdown_ends_at.saturating_sub(self.clock.now());
+        let seconds = remaining.div_ceil(1000);
+        (seconds, remaining - seconds.saturating_sub(1) * 1000)
+    }
+
+    // Moves from the countdown to the running game once the countdown is
+    // over, ends a game whose time is up and disarms an armed game that
+    // waited too long. Returns whether the phase changed.
+    pub fn advance(&mut self) -> bool {
+        let now = self.clock.now();
+        let mut changed = false;
+        if self.phase == GamePhase::Armed && self.armed_until.is_some_and(|until| now >= until) {
+            self.phase = GamePhase::Idle;
+            changed = true;
+        }
+        if self.phase == GamePhase::Countdown && now >= self.countdown_ends_at {
+            self.phase = GamePhase::Running;
+            changed = true;
+        }
+        if let Some(window_ms) = self.config.mode.window_ms() {
+            let elapsed_ms = self.clock.elapsed_ms();
+            if self.phase == GamePhase::Running && self.lanes.is_empty() && elapsed_ms >= window_ms
+            {
+                // The time ran out between two ticks.
+                self.clock.stop(now - (elapsed_ms - window_ms));
+                self.success = true;
+                self.phase = GamePhase::Finished;
+                changed = true;
+            }
+        }
+        if let Some(limit_ms) = self.time_limit_ms() {
+            let elapsed_ms = self.clock.elapsed_ms();
+            if self.phase == GamePhase::Running
+                && self.time_expired.is_none()
+                && elapsed_ms >= limit_ms
+            {
+                let overtime = self.config.on_time_limit == TimeLimitAction::Overtime;
+                self.time_expired = Some(TimeExpired {
+                    game_id: self.game_id,
+                    limit_ms,
+                    overtime,
+                });
+                if overtime {
+                    self.overtime = true;
+                } else {
+                    self.clock.stop(now - (elapsed_ms - limit_ms));
+                    self.game_over = Some(GameOverReason::TimeExpired);
+                    self.success = false;
+                    self.phase = GamePhase::Finished;
+                    changed = true;
+                }
+            }
+        }
+        if self.phase == GamePhase::Running && !self.lanes.is_empty() {
+            changed |= self.advance_lanes(now);
+        }
+        changed
+    }
+
+    fn time_limit_ms(&self) -> Option<u64> {
+        Some(seconds_to_ms(self.config.time_limit_seconds)).filter(|&limit_ms| limit_ms > 0)
+    }
+
+    // The time-expired payload, once the time limit was reached and until
+    // it is taken.
+    fn take_time_expired(&mut self) -> Option<TimeExpired> {
+        if self.expiry_reported {
+            return None;
+        }
+        let expired = self.time_expired.clone()?;
+        self.expiry_reported = true;
+        Some(expired)
+    }
+
+    // Seconds into overtime, counting from 0, while the game runs past its
+    // time limit.
+    fn overtime_second(&self) -> Option<u64> {
+        let limit_ms = self.time_limit_ms().filter(|_| self.overtime)?;
+        (self.phase == GamePhase::Running)
+            .then(|| self.clock.elapsed_ms().saturating_sub(limit_ms) / 1000)
+    }
+
+    // Advances the lanes of a head-to-head game, aborts a lane that took too
+    // long after the other one finished and ends the game once no lane is
+    // left running.
+    fn advance_lanes(&mut self, now: u64) -> bool {
+        for lane in &mut self.lanes {
+            lane.game.advance();
+        }
+        let timeout_ms = self.config.head_to_head.as_ref().map_or(0, |head_to_head| {
+            seconds_to_ms(head_to_head.finish_timeout_seconds)
+        });
+        let first_finish = self
+            .lanes
+            .iter()
+            .filter(|lane| lane.game.phase == GamePhase::Finished && lane.game.success)
+            .filter_map(|lane| lane.game.clock.ended_at)
+            .min();
+        if first_finish.is_some_and(|ended_at| now >= ended_at + timeout_ms) {
+            for lane in self.lanes.iter_mut().filter(|lane| lane.game.in_progress()) {
+                let _ = lane.game.abort(Some("timeout".to_string()));
+                lane.timed_out = true;
+            }
+        }
+        if self.lanes.iter().any(|lane| lane.game.in_progress()) {
+            return false;
+        }
+        let success = self.winner().is_some();
+        self.end(GamePhase::Finished, success);
+        true
+    }
+
+    // The lane with the best score of those that finished successfully; a
+    // tie is a draw.
+    fn winner(&self) -> Option<String> {
+        let mut scores: Vec<(u64, &String)> = self
+            .lanes
+            .iter()
+            .filter(|lane| lane.game.phase == GamePhase::Finished && lane.game.success)
+            .filter_map(|lane| Some((lane.game.result()?.score, &lane.config.id)))
+            .collect();
+        scores.sort_by_key(|&(score, _)| score);
+        if self.config.mode.collects_beams() {
+            scores.reverse();
+        }
+        match scores.as_slice() {
+            [(best, id), rest @ ..] if rest.first().is_none_or(|(next, _)| next != best) => {
+                Some((*id).clone())
+            }
+            _ => None,
+        }
+    }
+
+    pub fn is_head_to_head(&self) -> bool {
+        !self.lanes.is_empty()
+    }
+
+    // Passes a beam break to its lane: the lane's finish sensor finishes it,
+    // its other beams are hits.
+    fn record_lane_break(
+        &mut self,
+        connection_id: &str,
+        sensor: usize,
+        name: &str,
+    ) -> Option<LaneHit> {
+        self.advance();
+        if self.phase != GamePhase::Running {
+            return None;
+        }
+        let finish = FinishTrigger::Sensor { sensor };
+        if let Some(lane) = self
+            .lanes
+            .iter_mut()
+            .find(|lane| lane.config.finish == finish)
+        {
+            lane.game.finish();
+            self.advance();
+            return None;
+        }
+        let lane = self
+            .lanes
+            .iter_mut()
+            .find(|lane| lane.config.sensors.contains(connection_id, sensor))?;
+        let hit = lane.game.record_hit(sensor, name)?;
+        let lane = lane.config.id.clone();
+        self.advance();
+        Some(LaneHit { lane, hit })
+    }
+
+    // Finishes the running lane a buzzer press of `connection_id` belongs to.
+    fn finish_lane(&mut self, connection_id: Option<&str>) -> bool {
+        self.advance();
+        let Some(lane) = self.lanes.iter_mut().find(|lane| {
+            lane.game.phase == GamePhase::Running
+                && matches!(&lane.config.finish, FinishTrigger::Buzzer { connection_id: id }
+                    if id.is_none() || id.as_deref() == connection_id)
+        }) else {
+            return false;
+        };
+        let finished = lane.game.finish();
+        self.advance();
+        finished
+    }
+
+    // Results of the lanes that ended since the last call, for
+    // lane-finished.
+    fn finished_lanes(&mut self) -> Vec<LaneResult> {
+        self.lanes
+            .iter_mut()
+            .filter(|lane| !lane.reported && !lane.game.in_progress())
+            .filter_map(|lane| {
+                lane.reported = true;
+                lane.result()
+            })
+            .collect()
+    }
+
+    fn end(&mut self, phase: GamePhase, success: bool) {
+        self.end_at(phase, success, self.clock.now());
+    }
+
+    // Ends the game as of clock time `at`, when the event ending it
+    // happened.
+    fn end_at(&mut self, phase: GamePhase, success: bool, at: u64) {
+        if let Some(pause) = self.clock.stop(at) {
+            self.record_pause(pause);
+        }
+        self.success = success;
+        self.phase = phase;
+    }
+
+    // Aborts the game in progress; an armed game is just disarmed.
+    pub fn abort(&mut self, reason: Option<String>) -> Result<(), String> {
+        if !self.in_progress() {
+            return Err("no game is in progress".to_string());
+        }
+        if self.phase == GamePhase::Armed {
+            self.phase = GamePhase::Idle;
+            return Ok(());
+        }
+        for lane in self.lanes.iter_mut().filter(|lane| lane.game.in_progress()) {
+            let _ = lane.game.abort(reason.clone());
+        }
+        self.abort_reason = reason;
+        self.end(GamePhase::Aborted, false);
+        Ok(())
+    }
+
+    // Freezes the clock of the game in progress.
+    pub fn pause(&mut self) -> Result<(), String> {
+        self.advance();
+        if !matches!(self.phase, GamePhase::Countdown | GamePhase::Running) {
+            return Err("no game is running".to_string());
+        }
+        self.clock.pause();
+        self.paused_from = self.phase;
+        self.phase = GamePhase::Paused;
+        // Lanes that already ended stay as they are.
+        for lane in &mut self.lanes {
+            let _ = lane.game.pause();
+        }
+        Ok(())
+    }
+
+    fn record_pause(&mut self, (from, to): (u64, u64)) {
+        let during_countdown = self.paused_from == GamePhase::Countdown;
+        self.pauses.push(Pause {
+            at_ms: if during_countdown {
+                0
+            } else {
+                self.clock.game_ms(from)
+            },
+            duration_ms: to - from,
+            during_countdown,
+        });
+    }
+
+    // Continues a paused game. A pause during the countdown restarts it;
+    // otherwise the game time and reactivations carry on where they stopped.
+    pub fn resume(&mut self) -> Result<(), String> {
+        if self.phase != GamePhase::Paused {
+            return Err("the game isn't paused".to_string());
+        }
+        if let Some(pause) = self.clock.resume() {
+            self.record_pause(pause);
+        }
+        if self.paused_from == GamePhase::Countdown {
+            self.countdown_ends_at =
+                self.clock.now() + seconds_to_ms(self.config.countdown_seconds);
+            self.clock.start(self.countdown_ends_at);
+        }
+        self.phase = self.paused_from;
+        for lane in &mut self.lanes {
+            let _ = lane.game.resume();
+        }
+        self.advance();
+        Ok(())
+    }
+
+    // Ends the current leg of a running relay game, as the buzzer does, and
+    // starts the next one. Returns None for other games and for presses
+    // within the changeover time after the last leg.
+    fn complete_leg(&mut self) -> Option<LegComplete> {
+        self.advance();
+        let relay = self.config.relay.as_ref()?;
+        if self.phase != GamePhase::Running || self.legs.len() >= relay.leg_count() {
+            return None;
+        }
+        let elapsed_ms = self.elapsed_ms();
+        let changeover_ms = seconds_to_ms(relay.changeover_seconds);
+        if !self.legs.is_empty() && elapsed_ms < self.leg_started_ms + changeover_ms {
+            return None;
+        }
+        let hits_before: u32 = self.legs.iter().map(|leg| leg.hits).sum();
+        let penalty_before: u64 = self.legs.iter().map(|leg| leg.penalty_ms).sum();
+        let leg = RelayLeg {
+            leg: self.legs.len() + 1,
+            player: relay.player(self.legs.len()).cloned().unwrap_or_default(),
+            elapsed_ms: elapsed_ms - self.leg_started_ms,
+            split_ms: elapsed_ms,
+            hits: self.hits() - hits_before,
+            penalty_ms: self.penalty_ms() - penalty_before,
+        };
+        let total_legs = relay.leg_count();
+        let next_player = (leg.leg < total_legs)
+            .then(|| relay.player(leg.leg).cloned())
+            .flatten();
+        self.legs.push(leg.clone());
+        self.leg_started_ms = elapsed_ms;
+        Some(LegComplete {
+            leg,
+            total_legs,
+            next_player,
+        })
+    }
+
+    // Finishes a running game successfully, as the buzzer does, unless the
+    // mode has a fixed length, relay legs are left or the finish condition
+    // doesn't allow it yet. Returns whether a game was finished.
+    pub fn finish(&mut self) -> bool {
+        self.advance();
+        if self.phase != GamePhase::Running
+            || self.config.mode.window_ms().is_some()
+            || self.is_head_to_head()
+            || self
+                .config
+                .relay
+                .as_ref()
+                .is_some_and(|relay| self.legs.len() < relay.leg_count())
+            || !self.buzzer_finishes()
+        {
+            return false;
+        }
+        self.end_at(GamePhase::Finished, true, self.clock.now());
+        true
+    }
+
+    // Whether the finish condition lets the buzzer end the game now.
+    fn buzzer_finishes(&self) -> bool {
+        match self.config.finish {
+            FinishCondition::Buzzer => true,
+            FinishCondition::Beam { .. } => false,
+            FinishCondition::BeamThenBuzzer { .. } => self.finish_beam_ms.is_some(),
+        }
+    }
+
+    // A buzzer press of a running game that its finish condition doesn't
+    // allow yet.
+    pub fn premature_finish(&mut self) -> Option<PrematureFinish> {
+        self.advance();
+        (self.phase == GamePhase::Running && !self.buzzer_finishes()).then(|| PrematureFinish {
+            elapsed_ms: self.elapsed_ms(),
+            finish: self.config.finish,
+        })
+    }
+
+    // Handles a break (or restore) of `sensor` if it is the
+    // finish beam of the running game: the game finishes then, or with a
+    // two-stage finish the buzzer may finish it from now on. Returns whether
+    // it was the finish beam.
+    pub fn record_finish_beam(&mut self, sensor: usize, restored: bool) -> bool {
+        self.advance();
+        if self.phase != GamePhase::Running || self.config.finish.beam() != Some((sensor, restored))
+        {
+            return false;
+        }
+        let at = self.clock.now();
+        if let FinishCondition::Beam { .. } = self.config.finish {
+            self.end_at(GamePhase::Finished, true, at);
+        } else if self.finish_beam_ms.is_none() {
+            self.finish_beam_ms = Some(self.clock.game_ms(at));
+        }
+        true
+    }
+
+    // Records a beam break of a laser in play, ending the game once the
+    // allowed touches are used up, a tripwire is broken, or in reverse mode
+    // once every beam is collected. The hit is stamped with the game clock
+    // when it arrives.
+    pub fn record_hit(&mut self, sensor: usize, name: &str) -> Option<Hit> {
+        self.advance();
+        if self.disabled.contains(&sensor) {
+            return None;
+        }
+        let fatal = self.config.fatal_sensors.contains(&sensor);
+        if !(fatal || self.config.sensors.is_empty() || self.config.sensors.contains(&sensor))
+            || self.is_checkpoint(sensor)
+            || self
+                .config
+                .finish
+                .beam()
+                .is_some_and(|(beam, _)| beam == sensor)
+        {
+            return None;
+        }
+        if self.phase == GamePhase::Paused && self.config.count_paused_hits {
+            self.paused_hits.push(Hit {
+                sensor,
+                name: name.to_string(),
+                elapsed_ms: self.elapsed_ms(),
+                count: self.paused_hits.len() as u32 + 1,
+                grace: false,
+                fatal: false,
+                penalty_ms: 0,
+                penalized_ms: self.elapsed_ms() + self.penalty_ms(),
+                voided: None,
+            });
+        }
+        if self.phase != GamePhase::Running {
+            return None;
+        }
+        let at = self.clock.now();
+        let elapsed_ms = self.clock.game_ms(at);
+        if let Some(&until) = self.inactive.get(&sensor) {
+            if until.is_none_or(|until| elapsed_ms < until) {
+                return None;
+            }
+        }
+        // The grace period is game time, so pauses extend it. Grace hits
+        // leave the laser active. Collected beams stay collected, but
+        // tripwires are still spared at the start.
+        let collecting = self.config.mode.collects_beams();
+        if (fatal || !collecting) && elapsed_ms < seconds_to_ms(self.config.grace_period_seconds) {
+            let hit = Hit {
+                sensor,
+                name: name.to_string(),
+                elapsed_ms,
+                count: self.hits(),
+                grace: true,
+                fatal: false,
+                penalty_ms: 0,
+                penalized_ms: elapsed_ms + self.penalty_ms(),
+                voided: None,
+            };
+            self.timeline.push(hit.clone());
+            return Some(hit);
+        }
+        if fatal {
+            let hit = Hit {
+                sensor,
+                name: name.to_string(),
+                elapsed_ms,
+                count: self.hits(),
+                grace: false,
+                fatal: true,
+                penalty_ms: 0,
+                penalized_ms: elapsed_ms + self.penalty_ms(),
+                voided: None,
+            };
+            self.timeline.push(hit.clone());
+            self.game_over = Some(GameOverReason::Tripwire);
+            self.end_at(GamePhase::Finished, false, at);
+            return Some(hit);
+        }
+        let reactivates_at = (self.config.reactivate_lasers && !collecting).then(|| {
+            elapsed_ms + HIT_BLINK_MS + seconds_to_ms(self.config.reactivation_time_seconds)
+        });
+        self.inactive.insert(sensor, reactivates_at);
+
+        let penalty_ms = if collecting {
+            0
+        } else {
+            self.config.penalties.penalty_ms(sensor)
+        };
+        let hit = Hit {
+            sensor,
+            name: name.to_string(),
+            elapsed_ms,
+            count: self.hits() + 1,
+            grace: false,
+            fatal: false,
+            penalty_ms,
+            penalized_ms: elapsed_ms + self.penalty_ms() + penalty_ms,
+            voided: None,
+        };
+        self.timeline.push(hit.clone());
+        // Once over, the game takes no more hits, so a burst of breaks can't
+        // push the count past the limit.
+        if let Some((max, reason)) = self.hit_limit() {
+            if hit.count >= max {
+                self.game_over = Some(reason);
+                self.end_at(GamePhase::Finished, false, at);
+            }
+        }
+        if collecting && self.all_collected() {
+            self.end_at(GamePhase::Finished, true, at);
+        }
+        Some(hit)
+    }
+
+    // Whether every beam of a reverse game was broken; never when the game
+    // plays all sensors, as their number isn't known.
+    fn all_collected(&self) -> bool {
+        let mut beams = self
+            .config
+            .sensors
+            .iter()
+            .filter(|&&sensor| !self.is_checkpoint(sensor))
+            .peekable();
+        beams.peek().is_some() && beams.all(|sensor| self.inactive.contains_key(sensor))
+    }
+
+    // The beam-collected payload of a hit in a reverse game.
+    fn collected(&self, hit: &Hit) -> Option<BeamCollected> {
+        self.config.mode.collects_beams().then(|| BeamCollected {
+            sensor: hit.sensor,
+            name: hit.name.clone(),
+            collected: hit.count,
+            total: (!self.config.sensors.is_empty()).then(|| {
+                self.config
+                    .sensors
+                    .iter()
+                    .filter(|&&sensor| !self.is_checkpoint(sensor))
+                    .count()
+            }),
+        })
+    }
+
+    // The lowest hit limit of the game, with the reason reaching it gives.
+    fn hit_limit(&self) -> Option<(u32, GameOverReason)> {
+        let mode_limit = self.config.mode.hit_limit(self.config.max_allowed_touches);
+        let max_hits = self
+            .config
+            .max_hits
+            .map(|max| (max, GameOverReason::MaxHits));
+        [Some(mode_limit), max_hits]
+            .into_iter()
+            .flatten()
+            .filter(|&(max, _)| max > 0)
+            .min_by_key(|&(max, _)| max)
+    }
+
+    // Why the last game ended in a game over, if it did.
+    pub fn game_over_reason(&self) -> Option<GameOverReason> {
+        self.game_over
+    }
+
+    // Penalties of all hits so far.
+    fn penalty_ms(&self) -> u64 {
+        self.timeline
+            .iter()
+            .filter(|hit| hit.voided.is_none())
+            .map(|hit| hit.penalty_ms)
+            .sum()
+    }
+
+    // Hits that count, leaving out grace hits, tripwires and voided hits.
+    fn hits(&self) -> u32 {
+        self.timeline.iter().filter(|hit| hit.counts()).count() as u32
+    }
+
+    // Notes that no sensor data arrived for `silent_for_ms`, e.g. with the
+    // controller unplugged.
+    pub fn sensors_silent(&mut self, silent_for_ms: u64) {
+        if matches!(self.phase, GamePhase::Running | GamePhase::Paused)
+            && self.silent_since.is_none()
+        {
+            self.silent_since = Some(self.elapsed_ms().saturating_sub(silent_for_ms));
+        }
+    }
+
+    pub fn sensors_resumed(&mut self) {
+        if let Some(since) = self.silent_since.take() {
+            self.silent_ms += self.elapsed_ms().saturating_sub(since);
+        }
+    }
+
+    // The result of game `game_id`, which has to be the last game and to
+    // have ended; of the lane of `player` in a head-to-head game.
+    pub fn result_of(&self, game_id: u64, player: &str) -> Result<GameResult, String> {
+        if game_id != self.game_id {
+            return Err(format!("game {} isn't the last game", game_id));
+        }
+        let result = self
+            .result()
+            .ok_or_else(|| format!("game {} hasn't ended", game_id))?;
+        if result.aborted {
+            return Err(format!("game {} was aborted", game_id));
+        }
+        let Some(head_to_head) = result.head_to_head else {
+            return Ok(result);
+        };
+        head_to_head
+            .lanes
+            .into_iter()
+            .find(|lane| {
+                lane.player
+                    .as_deref()
+                    .is_some_and(|name| players::name_key(name) == players::name_key(player))
+            })
+            .map(|lane| lane.result)
+            .ok_or_else(|| format!("{} didn't play in game {}", player, game_id))
+    }
+
+    // Marks the result of game `game_id` as saved or discarded. Returns
+    // false unless it is the last game and over.
+    pub fn settle_result(&mut self, game_id: u64) -> bool {
+        let over = matches!(self.phase, GamePhase::Finished | GamePhase::Aborted);
+        if game_id != self.game_id || !over {
+            return false;
+        }
+        self.result_settled = true;
+        true
+    }
+
+    // The feed entry of the end of the game, once it has ended; None after
+    // the first call.
+    pub fn take_end_feed(&mut self) -> Option<FeedEvent> {
+        let kind = match (self.phase, self.game_over) {
+            (GamePhase::Aborted, _) => FeedKind::Abort,
+            (GamePhase::Finished, Some(_)) => FeedKind::GameOver,
+            (GamePhase::Finished, None) => FeedKind::Finish,
+            _ => return None,
+        };
+        if self.end_fed {
+            return None;
+        }
+        self.end_fed = true;
+        Some(FeedEvent {
+            game_over_reason: self.game_over,
+            abort_reason: self.abort_reason.clone(),
+            ..FeedEvent::new(kind, &self.state())
+        })
+    }
+
+    // Voids hit `index` of the current game, e.g. one a spectator caused.
+    // Returns the voided hit, and whether the game ended in a game over
+    // because of it; such a game stays over.
+    pub fn void_hit(&mut self, index: usize, reason: &str) -> Result<(Hit, bool), String> {
+        if !matches!(
+            self.phase,
+            GamePhase::Running | GamePhase::Paused | GamePhase::Finished
+        ) {
+            return Err("there is no game to remove a hit from".to_string());
+        }
+        if self.result_settled {
+            return Err("the game is saved; remove the hit from its run".to_string());
+        }
+        if self.config.mode.collects_beams() {
+            return Err("collected beams can't be removed".to_string());
+        }
+        if !self.lanes.is_empty() {
+            return Err("hits of a head-to-head game can't be removed".to_string());
+        }
+        let last = void_hit(&mut self.timeline, index, reason)?;
+        let ended_game = last
+            && self.phase == GamePhase::Finished
+            && self
+                .game_over
+                .is_some_and(|reason| reason != GameOverReason::TimeExpired);
+        Ok((self.timeline[index].clone(), ended_game))
+    }
+
+    // The config of the last game and whether it was armed, to play it
+    // again. A finished game's result has to be saved or discarded first.
+    pub fn rematch(&self) -> Result<(GameConfig, bool), String> {
+        match self.phase {
+            GamePhase::Finished if !self.result_settled => {
+                Err("save or discard the result of the last game first".to_string())
+            }
+            GamePhase::Finished | GamePhase::Aborted => Ok((self.config.clone(), self.armed)),
+            GamePhase::Idle => Err("there is no game to play again".to_string()),
+            _ => Err("a game is already in progress".to_string()),
+        }
+    }
+
+    // Leaves sensors out of hit detection from now on, also in the game in
+    // progress and its lanes.
+    pub fn set_disabled_sensors(&mut self, sensors: &[usize]) {
+        self.disabled = sensors.iter().copied().collect();
+        if !matches!(
+            self.phase,
+            GamePhase::Idle | GamePhase::Finished | GamePhase::Aborted
+        ) {
+            self.disabled_in_game.extend(sensors);
+        }
+        for lane in &mut self.lanes {
+            lane.game.set_disabled_sensors(sensors);
+        }
+    }
+
+    pub fn disabled_sensors(&self) -> Vec<usize> {
+        self.disabled.iter().copied().collect()
+    }
+
+    // Game time without sensor data so far.
+    fn silent_ms(&self) -> u64 {
+        self.silent_ms
+            + self
+                .silent_since
+                .map_or(0, |since| self.elapsed_ms().saturating_sub(since))
+    }
+
+    pub fn elapsed_ms(&self) -> u64 {
+        match self.phase {
+            GamePhase::Running | GamePhase::Paused | GamePhase::Finished | GamePhase::Aborted => {
+                self.clock.elapsed_ms()
+            }
+            GamePhase::Idle | GamePhase::Armed | GamePhase::Countdown => 0,
+        }
+    }
+
+    pub fn state(&self) -> GameState {
+        let countdown_remaining_ms = match self.phase {
+            GamePhase::Countdown => self.countdown_ends_at.saturating_sub(self.clock.now()),
+            _ => 0,
+        };
+        let hits = self.hits();
+        GameState {
+            game_id: self.game_id,
+            phase: self.phase,
+            elapsed_ms: self.elapsed_ms(),
+            hits,
+            penalty_ms: self.penalty_ms(),
+            countdown_remaining_ms,
+            lives_left: self.hit_limit().map(|(max, _)| max.saturating_sub(hits)),
+            remaining_ms: self
+                .config
+                .mode
+                .window_ms()
+                .or(self.time_limit_ms())
+                .map(|window_ms| window_ms.saturating_sub(self.elapsed_ms())),
+            practice: self.config.practice,
+            overtime: self.overtime,
+            lanes: self
+                .lanes
+                .iter()
+                .map(|lane| LaneState {
+                    lane: lane.config.id.clone(),
+                    player: lane.config.player.clone(),
+                    state: lane.game.state(),
+                })
+                .collect(),
+        }
+    }
+
+    // The result of the last game, once it is over.
+    pub fn result(&self) -> Option<GameResult> {
+        if !matches!(self.phase, GamePhase::Finished | GamePhase::Aborted) {
+            return None;
+        }
+        let elapsed_ms = self.elapsed_ms();
+        let hits = self.hits();
+        let penalty_ms = self.penalty_ms();
+        let score = self.config.mode.score(
+            elapsed_ms + penalty_ms,
+            hits,
+            self.all_collected().then(|| {
+                self.config
+                    .mode
+                    .window_ms()
+                    .unwrap_or(0)
+                    .saturating_sub(elapsed_ms)
+            }),
+        );
+        let delta = |previous: Option<u64>| {
+            previous
+                .filter(|_| self.success)
+                .map(|previous| score as i64 - previous as i64)
+        };
+        Some(GameResult {
+            mode: self.config.mode.name().to_string(),
+            success: self.success,
+            aborted: self.phase == GamePhase::Aborted,
+            failed: self.game_over.is_some(),
+            game_over_reason: self.game_over,
+            abort_reason: self.abort_reason.clone(),
+            practice: self.config.practice,
+            elapsed_ms,
+            hits,
+            penalty_ms,
+            penalized_ms: elapsed_ms + penalty_ms,
+            score,
+            timeline: self.timeline.clone(),
+            hits_by_sensor: hit_stats::count_by_sensor(&self.timeline),
+            splits: self.splits.clone(),
+            pauses: self.pauses.clone(),
+            paused_hits: self.paused_hits.clone(),
+            legs: self.legs.clone(),
+            personal_best_delta_ms: delta(self.pace.best_ms),
+            record_delta_ms: delta(self.pace.record_ms),
+            head_to_head: self.is_head_to_head().then(|| HeadToHeadResult {
+                lanes: self.lanes.iter().filter_map(Lane::result).collect(),
+                winner: self.winner(),
+            }),
+            silent_ms: self.silent_ms(),
+            disabled_sensors: self.disabled_in_game.iter().copied().collect(),
+            overtime: self.overtime,
+            config: self.config.clone(),
+        })
+    }
+}
+
+// The config of a head-to-head lane: the game's rules, limited to its
+// sensors.
+fn lane_config(config: &GameConfig, lane: &LaneConfig) -> GameConfig {
+    let mut lane_config = config.clone();
+    lane_config.head_to_head = None;
+    lane_config.player = lane.player.clone();
+    if let LaneSensors::Range { first, last } = lane.sensors {
+        lane_config.sensors = (first..=last).collect();
+    }
+    lane_config
+}
+
+// Emits the state, and the end of the game into the feed once it is over.
+pub fn emit_state(app_handle: &tauri::AppHandle, state: GameState) {
+    let ended = matches!(state.phase, GamePhase::Finished | GamePhase::Aborted)
+        .then(|| {
+            let game = app_handle.state::<Arc<Mutex<GameManager>>>();
+            let mut game = game.lock().ok()?;
+            game.take_end_feed()
+        })
+        .flatten();
+    let _ = app_handle.emit("game-state-changed", state);
+    if let Some(ended) = ended {
+        emit_feed(app_handle, ended);
+    }
+}
+
+fn emit_feed(app_handle: &tauri::AppHandle, event: FeedEvent) {
+    let _ = app_handle.emit("game-event", event);
+}
+
+// Emits what the finish beam did: a finished game and a new best, if set.
+// A two-stage finish only changes what the buzzer does.
+fn emit_finish(
+    app_handle: &tauri::AppHandle,
+    best: Option<(&'static str, NewBest)>,
+    state: GameState,
+) {
+    if state.phase != GamePhase::Finished {
+        return;
+    }
+    if let Some((event, best)) = best {
+        let _ = app_handle.emit(event, best);
+    }
+    emit_state(app_handle, state);
+}
+
+// Emits what a beam break or buzzer press did to a head-to-head game.
+fn emit_lane_events(
+    app_handle: &tauri::AppHandle,
+    hit: Option<LaneHit>,
+    finished: Vec<LaneResult>,
+    state: GameState,
+) {
+    let changed = hit.is_some() || !finished.is_empty();
+    if let Some(hit) = hit {
+        let _ = app_handle.emit("game-hit", hit);
+    }
+    for result in finished {
+        let _ = app_handle.emit("lane-finished", result);
+    }
+    if changed {
+        emit_state(app_handle, state);
+    }
+}
+
+// What the ticker emits after one wakeup.
+enum TickEvent {
+    Countdown(CountdownTick),
+    Sound(PlaySound),
+    TimeExpired(TimeExpired, GameState),
+    Started(GameState),
+    Tick(GameTick),
+    Pace(PaceDelta),
+    LaneFinished(Box<LaneResult>),
+    Ended(GameState),
+}
+
+// Drives game `game_id`: emits countdown-tick for every countdown second
+// with its play-sound, the final tick and game-started once it runs (unless
+// `started`, as for armed games, whose start is announced by the beam break)
+// and then game-tick at the configured rate, with pace-delta at the pace
+// interval, except while paused. Stops as soon as the game ends or another
+// one starts.
+fn spawn_ticker(
+    app_handle: tauri::AppHandle,
+    game: Arc<Mutex<GameManager>>,
+    game_id: u64,
+    started: bool,
+) {
+    thread::spawn(move || {
+        let mut ticker = Ticker::new(game_id, started);
+        loop {
+            let (events, wait_ms) = {
+                let Ok(mut game) = game.lock() else {
+                    return;
+                };
+                ticker.wake(&mut game)
+            };
+            for event in events {
+                match event {
+                    TickEvent::Countdown(tick) => {
+                        let _ = app_handle.emit("countdown-tick", tick);
+                    }
+                    TickEvent::Sound(sound) => {
+                        let _ = app_handle.emit("play-sound", sound);
+                    }
+                    TickEvent::Started(state) => {
+                        let _ = app_handle.emit("game-started", state.clone());
+                        emit_feed(&app_handle, FeedEvent::new(FeedKind::Start, &state));
+                        emit_state(&app_handle, state);
+                    }
+                    TickEvent::Tick(tick) => {
+                        let _ = app_handle.emit("game-tick", tick);
+                    }
+                    TickEvent::Pace(delta) => {
+                        let _ = app_handle.emit("pace-delta", delta);
+                    }
+                    TickEvent::LaneFinished(result) => {
+                        let _ = app_handle.emit("lane-finished", result);
+                    }
+                    TickEvent::TimeExpired(expired, state) => {
+                        emit_time_expired(&app_handle, expired, state);
+                    }
+                    TickEvent::Ended(state) => emit_state(&app_handle, state),
+                }
+            }
+            let Some(wait_ms) = wait_ms else {
+                return;
+            };
+            thread::sleep(std::time::Duration::from_millis(wait_ms));
+        }
+    });
+}
+
+// The ticker of one game between wakeups.
+struct Ticker {
+    game_id: u64,
+    // Whether game-started was emitted.
+    started: bool,
+    last_second: Option<u64>,
+    last_overtime_second: Option<u64>,
+    next_pace_ms: u64,
+}
+
+impl Ticker {
+    fn new(game_id: u64, started: bool) -> Self {
+        Self {
+            game_id,
+            started,
+            last_second: None,
+            last_overtime_second: None,
+            next_pace_ms: 0,
+        }
+    }
+
+    // What to emit after one wakeup, and how long to sleep until the next;
+    // no time once the game has ended or another one started.
+    fn wake(&mut self, game: &mut GameManager) -> (Vec<TickEvent>, Option<u64>) {
+        if game.game_id != self.game_id {
+            return (Vec::new(), None);
+        }
+        if !game.in_progress() {
+            // Another event may have run into the time limit.
+            let events = game
+                .take_time_expired()
+                .map(|expired| TickEvent::TimeExpired(expired, game.state()))
+                .into_iter()
+                .collect();
+            return (events, None);
+        }
+        game.advance();
+        let mut events: Vec<TickEvent> = game
+            .finished_lanes()
+            .into_iter()
+            .map(|result| TickEvent::LaneFinished(Box::new(result)))
+            .collect();
+        if let Some(expired) = game.take_time_expired() {
+            events.push(TickEvent::TimeExpired(expired, game.state()));
+        }
+        let wait_ms = if !game.in_progress() {
+            // The time of the game is up, or the armed game timed out.
+            events.push(TickEvent::Ended(game.state()));
+            0
+        } else if matches!(game.phase, GamePhase::Armed | GamePhase::Paused) {
+            // The countdown starts over after a pause.
+            self.last_second = None;
+            PAUSE_POLL_MS
+        } else if game.phase == GamePhase::Countdown {
+            let (seconds, wait_ms) = game.countdown_second();
+            if self.last_second != Some(seconds) {
+                let first = self.last_second.is_none();
+                self.last_second = Some(seconds);
+                events.push(TickEvent::Countdown(CountdownTick {
+                    seconds,
+                    remaining_ms: game.state().countdown_remaining_ms,
+                }));
+                let effect = game.config.countdown_audio.cue(seconds, first);
+                if effect.is_some() {
+                    events.push(TickEvent::Sound(PlaySound {
+                        game_id: self.game_id,
+                        effect,
+                        level: 0,
+                    }));
+                }
+            }
+            wait_ms
+        } else {
+            // The countdown just ended, and with it the final tick.
+            if self.last_second.take().is_some() {
+                events.push(TickEvent::Countdown(CountdownTick {
+                    seconds: 0,
+                    remaining_ms: 0,
+                }));
+                let effect = game.config.countdown_audio.cue(0, false);
+                if effect.is_some() {
+                    events.push(TickEvent::Sound(PlaySound {
+                        game_id: self.game_id,
+                        effect,
+                        level: 0,
+                    }));
+                }
+            }
+            if !self.started {
+                self.started = true;
+                events.push(TickEvent::Started(game.state()));
+            }
+            let elapsed_ms = game.elapsed_ms();
+            events.push(TickEvent::Tick(GameTick {
+                game_id: self.game_id,
+                elapsed_ms,
+            }));
+            // Overtime beeps every second, more urgently each time.
+            if let Some(second) = game.overtime_second() {
+                if self.last_overtime_second != Some(second) {
+                    self.last_overtime_second = Some(second);
+                    events.push(TickEvent::Sound(PlaySound {
+                        game_id: self.game_id,
+                        effect: Some(SoundEffect::Overtime),
+                        level: (second as u32 + 1).min(MAX_OVERTIME_LEVEL),
+                    }));
+                }
+            }
+            let interval_ms = seconds_to_ms(game.config.pace_interval_seconds);
+            if interval_ms > 0 && elapsed_ms >= self.next_pace_ms {
+                self.next_pace_ms = (elapsed_ms / interval_ms + 1) * interval_ms;
+                events.extend(game.pace_delta().map(TickEvent::Pace));
+            }
+            1000 / u64::from(game.config.tick_rate_hz.max(1))
+        };
+        (events, Some(wait_ms))
+    }
+}
+
+// Part of the laser-broken and laser-restored payload the game needs.
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BrokenBeam {
+    #[serde(default)]
+    connection_id: String,
+    sensor: usize,
+    name: String,
+}
+
+// Part of the serial-stalled payload the game needs.
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Stall {
+    seconds_since_data: f64,
+}
+
+// Part of the buzzer payload the game needs.
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ButtonPress {
+    connection_id: Option<String>,
+}
+
+// Feeds laser-broken and buzzer events into the managed game.
+pub fn subscribe(app_handle: &tauri::AppHandle) {
+    let handle = app_handle.clone();
+    app_handle.listen_any("laser-broken", move |event| {
+        let Ok(beam) = serde_json::from_str::<BrokenBeam>(event.payload()) else {
+            return;
+        };
+        let game = handle.state::<Arc<Mutex<GameManager>>>();
+        let lane_events = match game.lock() {
+            Ok(mut game) if game.is_head_to_head() => Some((
+                game.record_lane_break(&beam.connection_id, beam.sensor, &beam.name),
+                game.finished_lanes(),
+                game.state(),
+            )),
+            Ok(_) => None,
+            Err(_) => return,
+        };
+        if let Some((hit, finished, state)) = lane_events {
+            emit_lane_events(&handle, hit, finished, state);
+            return;
+        }
+        let (started, finish_beam, split, hit, collected, state, reason) = match game.lock() {
+            Ok(mut game) => {
+                let started = game.trigger_start(beam.sensor);
+                let finish_beam = !started && game.record_finish_beam(beam.sensor, false);
+                let split = if started || finish_beam {
+                    None
+                } else {
+                    game.record_checkpoint(beam.sensor, false)
+                };
+                let hit = if started || finish_beam || split.is_some() {
+                    None
+                } else {
+                    game.record_hit(beam.sensor, &beam.name)
+                };
+                let collected = hit.as_ref().and_then(|hit| game.collected(hit));
+                let finish_beam = finish_beam.then(|| game.new_best());
+                (
+                    started,
+                    finish_beam,
+                    split,
+                    hit,
+                    collected,
+                    game.state(),
+                    game.game_over_reason(),
+                )
+            }
+            Err(_) => return,
+        };
+        if let Some(best) = finish_beam {
+            emit_finish(&handle, best, state);
+        } else if let Some(split) = split {
+            if let (Some(elapsed_ms), Some(delta_ms)) = (split.elapsed_ms, split.delta_ms) {
+                let _ = handle.emit(
+                    "pace-delta",
+                    PaceDelta {
+                        checkpoint: Some(split.name.clone()),
+                        elapsed_ms,
+                        delta_ms,
+                    },
+                );
+            }
+            if let Some(feed) = FeedEvent::checkpoint(&split, &state) {
+                emit_feed(&handle, feed);
+            }
+            let _ = handle.emit("checkpoint-reached", split);
+        } else if started {
+            let _ = handle.emit("game-started", state.clone());
+            emit_feed(&handle, FeedEvent::new(FeedKind::Start, &state));
+            emit_state(&handle, state);
+        } else if let Some(hit) = hit {
+            let _ = handle.emit("game-hit", hit.clone());
+            emit_feed(&handle, FeedEvent::hit(&hit, &state));
+            if let Some(collected) = collected {
+                let _ = handle.emit("beam-collected", collected);
+            }
+            if let (GamePhase::Finished, Some(reason)) = (state.phase, reason) {
+                let _ = handle.emit(
+                    "game-over",
+                    GameOver {
+                        reason,
+                        sensor: hit.fatal.then_some(hit.sensor),
+                        name: hit.fatal.then(|| hit.name.clone()),
+                        state: state.clone(),
+                    },
+                );
+            }
+            emit_state(&handle, state);
+        }
+    });
+
+    let handle = app_handle.clone();
+    app_handle.listen_any("laser-restored", move |event| {
+        let Ok(beam) = serde_json::from_str::<BrokenBeam>(event.payload()) else {
+            return;
+        };
+        let game = handle.state::<Arc<Mutex<GameManager>>>();
+        let (finish_beam, split, state) = match game.lock() {
+            Ok(mut game) => {
+                if game.record_finish_beam(beam.sensor, true) {
+                    (Some(game.new_best()), None, game.state())
+                } else {
+                    let split = game.record_checkpoint(beam.sensor, true);
+                    (None, split, game.state())
+                }
+            }
+            Err(_) => return,
+        };
+        if let Some(best) = finish_beam {
+            emit_finish(&handle, best, state);
+        } else if let Some(split) = split {
+            if let (Some(elapsed_ms), Some(delta_ms)) = (split.elapsed_ms, split.delta_ms) {
+                let _ = handle.emit(
+                    "pace-delta",
+                    PaceDelta {
+                        checkpoint: Some(split.name.clone()),
+                        elapsed_ms,
+                        delta_ms,
+                    },
+                );
+            }
+            if let Some(feed) = FeedEvent::checkpoint(&split, &state) {
+                emit_feed(&handle, feed);
+            }
+            let _ = handle.emit("checkpoint-reached", split);
+        }
+    });
+
+    let handle = app_handle.clone();
+    app_handle.listen_any("serial-stalled", move |event| {
+        let Ok(stall) = serde_json::from_str::<Stall>(event.payload()) else {
+            return;
+        };
+        if let Ok(mut game) = handle.state::<Arc<Mutex<GameManager>>>().lock() {
+            game.sensors_silent((stall.seconds_since_data * 1000.0) as u64);
+        }
+    });
+
+    let handle = app_handle.clone();
+    app_handle.listen_any("serial-resumed", move |_| {
+        if let Ok(mut game) = handle.state::<Arc<Mutex<GameManager>>>().lock() {
+            game.sensors_resumed();
+        }
+    });
+
+    let handle = app_handle.clone();
+    app_handle.listen_any("buzzer", move |event| {
+        let press = serde_json::from_str::<ButtonPress>(event.payload()).ok();
+        let game = handle.state::<Arc<Mutex<GameManager>>>();
+        let lane_events = match game.lock() {
+            Ok(mut game) if game.is_head_to_head() => {
+                let connection_id = press.and_then(|press| press.connection_id);
+                game.finish_lane(connection_id.as_deref());
+                Some((game.finished_lanes(), game.state()))
+            }
+            Ok(_) => None,
+            Err(_) => return,
+        };
+        if let Some((finished, state)) = lane_events {
+            emit_lane_events(&handle, None, finished, state);
+            return;
+        }
+        let (leg, premature, best, state) = match game.lock() {
+            Ok(mut game) => {
+                let leg = game.complete_leg();
+                let premature = game.premature_finish();
+                let finished = game.finish();
+                let best = finished.then(|| game.new_best()).flatten();
+                let state = (leg.is_some() || finished).then(|| game.state());
+                (leg, premature, best, state)
+            }
+            Err(_) => return,
+        };
+        if let Some(leg) = leg {
+            let _ = handle.emit("relay-leg-complete", leg);
+        }
+        if let Some(premature) = premature {
+            let _ = handle.emit("premature-finish-attempt", premature);
+        }
+        if let Some((event, best)) = best {
+            let _ = handle.emit(event, best);
+        }
+        if let Some(state) = state {
+            emit_state(&handle, state);
+        }
+    });
+}
+
+// What a game is paced against; modes with a fixed length have no pace.
+fn pace(config: &GameConfig, scores: &Mutex<ScoreBook>) -> Result<Pace, String> {
+    if config.mode.window_ms().is_some() || config.relay.is_some() || config.head_to_head.is_some()
+    {
+        return Ok(Pace::default());
+    }
+    let book = scores.lock().map_err(|e| e.to_string())?;
+    Ok(book
+        .file()
+        .pace(config.player.as_deref(), config.mode.name()))
+}
+
+fn penalty_tables(app_handle: &tauri::AppHandle) -> BTreeMap<String, PenaltyTable> {
+    app_handle
+        .store(STORE_FILE)
+        .ok()
+        .and_then(|store| store.get(PENALTY_TABLES_KEY))
+        .and_then(|value| serde_json::from_value(value).ok())
+        .unwrap_or_default()
+}
+
+// Replaces the penalties of a config naming a maze profile with its table.
+fn load_penalty_profile(
+    app_handle: &tauri::AppHandle,
+    config: &mut GameConfig,
+) -> Result<(), String> {
+    if let Some(profile) = &config.penalty_profile {
+        config.penalties = penalty_tables(app_handle)
+            .remove(profile)
+            .ok_or_else(|| format!("no penalty table for the maze profile {}", profile))?;
+    }
+    Ok(())
+}
+
+// Command to save the penalty table of a maze profile.
+#[tauri::command]
+pub fn save_penalty_table(
+    profile: String,
+    table: PenaltyTable,
+    app_handle: tauri::AppHandle,
+) -> Result<(), String> {
+    let profile = profile.trim().to_string();
+    if profile.is_empty() {
+        return Err("the maze profile needs a name".to_string());
+    }
+    if std::iter::once(&table.default_seconds)
+        .chain(table.sensors.values())
+        .any(|seconds| !(0.0..=3600.0).contains(seconds))
+    {
+        return Err("penalties must be between 0 and 3600 seconds".to_string());
+    }
+    let mut tables = penalty_tables(&app_handle);
+    tables.insert(profile, table);
+    let store = app_handle.store(STORE_FILE).map_err(|e| e.to_string())?;
+    store.set(
+        PENALTY_TABLES_KEY,
+        serde_json::to_value(tables).map_err(|e| e.to_string())?,
+    );
+    Ok(())
+}
+
+// Command to fetch the saved penalty tables by maze profile.
+#[tauri::command]
+pub fn get_penalty_tables(app_handle: tauri::AppHandle) -> BTreeMap<String, PenaltyTable> {
+    penalty_tables(&app_handle)
+}
+
+// Emits time-expired and, for a game that ended at its time limit, game-over.
+fn emit_time_expired(app_handle: &tauri::AppHandle, expired: TimeExpired, state: GameState) {
+    let overtime = expired.overtime;
+    let _ = app_handle.emit("time-expired", expired);
+    if !overtime {
+        let _ = app_handle.emit(
+            "game-over",
+            GameOver {
+                reason: GameOverReason::TimeExpired,
+                sensor: None,
+                name: None,
+                state,
+            },
+        );
+    }
+}
+
+// Cuts the countdown sounds of a game short.
+fn stop_sounds(app_handle: &tauri::AppHandle, game_id: u64) {
+    let _ = app_handle.emit(
+        "stop-sounds",
+        PlaySound {
+            game_id,
+            effect: None,
+            level: 0,
+        },
+    );
+}
+
+fn warn_disabled_sensors(app_handle: &tauri::AppHandle, sensors: Vec<usize>) {
+    if !sensors.is_empty() {
+        let _ = app_handle.emit(
+            "disabled-sensors-warning",
+            DisabledSensorsWarning { sensors },
+        );
+    }
+}
+
+// Command to start a new game with its countdown; `config` defaults to the
+// standard rules. `max_hits` ends the game as failed once reached;
+// `practice` plays a warm-up game; `relay` makes players take turns;
+// `head_to_head` races two lanes at once.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub fn start_game(
+    config: Option<GameConfig>,
+    max_hits: Option<u32>,
+    practice: Option<bool>,
+    relay: Option<RelayConfig>,
+    head_to_head: Option<HeadToHeadConfig>,
+    app_handle: tauri::AppHandle,
+    game: tauri::State<Arc<Mutex<GameManager>>>,
+    scores: tauri::State<Mutex<ScoreBook>>,
+) -> Result<GameState, String> {
+    let mut config = config.unwrap_or_default();
+    if max_hits.is_some() {
+        config.max_hits = max_hits;
+    }
+    if let Some(practice) = practice {
+        config.practice = practice;
+    }
+    if relay.is_some() {
+        config.relay = relay;
+    }
+    if head_to_head.is_some() {
+        config.head_to_head = head_to_head;
+    }
+    launch(config, false, app_handle, &game, &scores)
+}
+
+// Starts the countdown of a game with `config`, or arms it.
+fn launch(
+    mut config: GameConfig,
+    armed: bool,
+    app_handle: tauri::AppHandle,
+    game: &Arc<Mutex<GameManager>>,
+    scores: &Mutex<ScoreBook>,
+) -> Result<GameState, String> {
+    load_penalty_profile(&app_handle, &mut config)?;
+    let pace = pace(&config, scores)?;
+    let (state, disabled) = {
+        let mut game = game.lock().map_err(|e| e.to_string())?;
+        if armed {
+            game.arm(config)?;
+        } else {
+            game.start(config)?;
+        }
+        game.compare_with(pace);
+        (game.state(), game.disabled_sensors())
+    };
+    warn_disabled_sensors(&app_handle, disabled);
+    if armed {
+        let _ = app_handle.emit("game-armed", state.clone());
+    }
+    emit_state(&app_handle, state.clone());
+    spawn_ticker(app_handle, Arc::clone(game), state.game_id, armed);
+    Ok(state)
+}
+
+// Command to arm a game for self-service: it starts on the next beam break
+// of the start gate, and is disarmed by abort_game or after the timeout.
+#[tauri::command]
+pub fn arm_game(
+    config: Option<GameConfig>,
+    app_handle: tauri::AppHandle,
+    game: tauri::State<Arc<Mutex<GameManager>>>,
+    scores: tauri::State<Mutex<ScoreBook>>,
+) -> Result<GameState, String> {
+    launch(config.unwrap_or_default(), true, app_handle, &game, &scores)
+}
+
+// Command to play the last game again with the same settings, armed again
+// if it was armed. Returns the id of the new game.
+#[tauri::command]
+pub fn rematch(
+    app_handle: tauri::AppHandle,
+    game: tauri::State<Arc<Mutex<GameManager>>>,
+    scores: tauri::State<Mutex<ScoreBook>>,
+) -> Result<u64, String> {
+    let (config, armed) = game.lock().map_err(|e| e.to_string())?.rematch()?;
+    Ok(launch(config, armed, app_handle, &game, &scores)?.game_id)
+}
+
+// Command to play the last game again with the same settings for the next
+// player.
+#[tauri::command]
+pub fn rematch_with_player(
+    name: String,
+    app_handle: tauri::AppHandle,
+    game: tauri::State<Arc<Mutex<GameManager>>>,
+    scores: tauri::State<Mutex<ScoreBook>>,
+) -> Result<u64, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("the player needs a name".to_string());
+    }
+    let (mut config, armed) = game.lock().map_err(|e| e.to_string())?.rematch()?;
+    if config.relay.is_some() || config.head_to_head.is_some() {
+        return Err("relays and head-to-head games have more than one player".to_string());
+    }
+    config.player = Some(name.to_string());
+    Ok(launch(config, armed, app_handle, &game, &scores)?.game_id)
+}
+
+// Command to drop the result of the last game without saving it, which
+// allows a rematch.
+#[tauri::command]
+pub fn discard_result(game: tauri::State<Arc<Mutex<GameManager>>>) -> Result<(), String> {
+    let mut game = game.lock().map_err(|e| e.to_string())?;
+    let game_id = game.game_id;
+    if game.settle_result(game_id) {
+        Ok(())
+    } else {
+        Err("no game result to discard".to_string())
+    }
+}
+
+// Command to abort the game in progress, e.g. with the reason "player left".
+#[tauri::command]
+pub fn abort_game(
+    reason: Option<String>,
+    app_handle: tauri::AppHandle,
+    game: tauri::State<Arc<Mutex<GameManager>>>,
+) -> Result<GameState, String> {
+    let reason = reason
+        .map(|reason| reason.trim().to_string())
+        .filter(|reason| !reason.is_empty());
+    let (state, counting_down) = {
+        let mut game = game.lock().map_err(|e| e.to_string())?;
+        let counting_down = game.phase == GamePhase::Countdown;
+        game.abort(reason)?;
+        (game.state(), counting_down)
+    };
+    if counting_down {
+        stop_sounds(&app_handle, state.game_id);
+    }
+    emit_state(&app_handle, state.clone());
+    Ok(state)
+}
+
+// Command to pause the running game or its countdown.
+#[tauri::command]
+pub fn pause_game(
+    app_handle: tauri::AppHandle,
+    game: tauri::State<Arc<Mutex<GameManager>>>,
+) -> Result<GameState, String> {
+    let (state, counting_down) = {
+        let mut game = game.lock().map_err(|e| e.to_string())?;
+        game.pause()?;
+        (game.state(), game.paused_from == GamePhase::Countdown)
+    };
+    // The countdown and its sound start over on resume.
+    if counting_down {
+        stop_sounds(&app_handle, state.game_id);
+    }
+    let _ = app_handle.emit("game-paused", state.clone());
+    emit_feed(&app_handle, FeedEvent::new(FeedKind::Pause, &state));
+    emit_state(&app_handle, state.clone());
+    Ok(state)
+}
+
+// Command to resume a paused game.
+#[tauri::command]
+pub fn resume_game(
+    app_handle: tauri::AppHandle,
+    game: tauri::State<Arc<Mutex<GameManager>>>,
+) -> Result<GameState, String> {
+    let state = {
+        let mut game = game.lock().map_err(|e| e.to_string())?;
+        game.resume()?;
+        game.state()
+    };
+    let _ = app_handle.emit("game-resumed", state.clone());
+    emit_feed(&app_handle, FeedEvent::new(FeedKind::Resume, &state));
+    emit_state(&app_handle, state.clone());
+    Ok(state)
+}
+
+// Command to fetch the current game state, e.g. after a webview reload.
+#[tauri::command]
+pub fn get_game_state(game: tauri::State<Arc<Mutex<GameManager>>>) -> Result<GameState, String> {
+    let game = game.lock().map_err(|e| e.to_string())?;
+    Ok(game.state())
+}
+
+// Command to fetch the result of the last game; null while none has ended.
+#[tauri::command]
+pub fn get_game_result(
+    game: tauri::State<Arc<Mutex<GameManager>>>,
+) -> Result<Option<GameResult>, String> {
+    let game = game.lock().map_err(|e| e.to_string())?;
+    Ok(game.result())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    // Clock that only moves when told to.
+    struct ManualClock(AtomicU64);
+
+    impl ManualClock {
+        fn advance(&self, ms: u64) {
+            self.0.fetch_add(ms, Ordering::Relaxed);
+        }
+    }
+
+    impl Clock for ManualClock {
+        fn now_ms(&self) -> u64 {
+            self.0.load(Ordering::Relaxed)
+        }
+    }
+
+    fn game() -> (Arc<ManualClock>, GameManager) {
+        let clock = Arc::new(ManualClock(AtomicU64::new(1000)));
+        let game = GameManager::new(clock.clone());
+        (clock, game)
+    }
+
+    fn config(max_allowed_touches: u32, reactivate_lasers: bool) -> GameConfig {
+        GameConfig {
+            max_allowed_touches,
+            reactivate_lasers,
+            reactivation_time_seconds: 2.0,
+            countdown_seconds: 3.0,
+            grace_period_seconds: 0.0,
+            ..GameConfig::default()
+        }
+    }
+
+    // The countdown seconds and game ticks of one ticker wakeup, and
+    // whether it started the game.
+    fn wake(
+        ticker: &mut Ticker,
+        game: &mut GameManager,
+    ) -> (Vec<u64>, Vec<u64>, bool, Option<u64>) {
+        let (events, wait_ms) = ticker.wake(game);
+        let mut seconds = Vec::new();
+        let mut ticks = Vec::new();
+        let mut started = false;
+        for event in events {
+            match event {
+                TickEvent::Countdown(tick) => seconds.push(tick.seconds),
+                TickEvent::Tick(tick) => ticks.push(tick.elapsed_ms),
+                TickEvent::Started(_) => started = true,
+                _ => {}
+            }
+        }
+        (seconds, ticks, started, wait_ms)
+    }
+
+    #[test]
+    fn ticker_counts_down_then_ticks_until_the_finish() {
+        let (clock, mut game) = game();
+        game.start(config(0, false)).unwrap();
+        let mut ticker = Ticker::new(game.game_id, false);
+        assert_eq!(
+            wake(&mut ticker, &mut game),
+            (vec![3], vec![], false, Some(1000))
+        );
+        clock.advance(1000);
+        assert_eq!(wake(&mut ticker, &mut game).0, [2]);
+        clock.advance(1000);
+        assert_eq!(wake(&mut ticker, &mut game).0, [1]);
+        // A reconnecting webview resyncs from the state.
+        assert_eq!(game.state().countdown_remaining_ms, 1000);
+        clock.advance(1000);
+        let tick_ms = 1000 / u64::from(DEFAULT_TICK_RATE_HZ);
+        assert_eq!(
+            wake(&mut ticker, &mut game),
+            (vec![0], vec![0], true, Some(tick_ms))
+        );
+        clock.advance(250);
+        assert_eq!(wake(&mut ticker, &mut game).1, [250]);
+        assert_eq!(game.state().phase, GamePhase::Running);
+        assert_eq!(game.state().elapsed_ms, 250);
+
+        clock.advance(50);
+        assert!(game.finish());
+        assert_eq!(wake(&mut ticker, &mut game), (vec![], vec![], false, None));
+        assert_eq!(game.state().elapsed_ms, 300);
+    }
+
+    #[test]
+    fn saved_results_come_from_the_last_ended_game() {
+        let (clock, mut game) = game();
+        game.start(config(0, false)).unwrap();
+        let game_id = game.game_id;
+        clock.advance(3000);
+        assert!(game.result_of(game_id, "Ann").is_err());
+        clock.advance(1000);
+        game.record_hit(0, "a").unwrap();
+        clock.advance(500);
+        assert!(game.finish());
+        let result = game.result_of(game_id, "Ann").unwrap();
+        assert_eq!((result.elapsed_ms, result.hits), (1500, 1));
+        assert!(game.result_of(game_id + 1, "Ann").is_err());
+
+        game.start(config(0, false)).unwrap();
+        game.abort(None).unwrap();
+        assert!(game.result_of(game_id + 1, "Ann").is_err());
+    }
+
+    #[test]
+    fn ticker_stops_on_abort_and_for_a_newer_game() {
+        let (clock, mut game) = game();
+        game.start(config(0, false)).unwrap();
+        let mut ticker = Ticker::new(game.game_id, false);
+        clock.advance(3500);
+        assert_eq!(wake(&mut ticker, &mut game).1, [500]);
+        game.abort(None).unwrap();
+        assert_eq!(wake(&mut ticker, &mut game).3, None);
+
+        game.start(config(0, false)).unwrap();
+        let mut old = Ticker::new(game.game_id - 1, true);
+        assert_eq!(wake(&mut old, &mut game).3, None);
+        let mut ticker = Ticker::new(game.game_id, false);
+        assert_eq!(wake(&mut ticker, &mut game).0, [3]);
+    }
+
+    #[test]
+    fn counts_time_from_the_end_of_the_countdown() {
+        let (clock, mut game) = game();
+        game.start(config(0, false)).unwrap();
+        assert_eq!(game.state().phase, GamePhase::Countdown);
+        clock.advance(1000);
+        assert!(!game.advance());
+        assert_eq!(game.state().countdown_remaining_ms, 2000);
+        assert_eq!(game.countdown_second(), (2, 1000));
+        clock.advance(300);
+        assert_eq!(game.countdown_second(), (2, 700));
+        clock.advance(700);
+        assert_eq!(game.countdown_second(), (1, 1000));
+        // Hits during the countdown don't count.
+        assert!(game.record_hit(0, "a").is_none());
+
+        clock.advance(1500);
+        assert!(game.advance());
+        assert_eq!(game.state().phase, GamePhase::Running);
+        assert_eq!(game.elapsed_ms(), 500);
+        assert!(game.start(config(0, false)).is_err());
+    }
+
+    #[test]
+    fn cues_the_countdown_sounds() {
+        let ticks = |audio: CountdownAudio| {
+            [(3, true), (2, false), (1, false), (0, false)]
+                .map(|(seconds, first)| audio.cue(seconds, first))
+        };
+        assert_eq!(
+            ticks(CountdownAudio::Clip),
+            [Some(SoundEffect::Countdown), None, None, None]
+        );
+        assert_eq!(
+            ticks(CountdownAudio::PerTick),
+            [
+                Some(SoundEffect::Click),
+                Some(SoundEffect::Click),
+                Some(SoundEffect::Click),
+                Some(SoundEffect::GameStart)
+            ]
+        );
+        assert_eq!(ticks(CountdownAudio::Off), [None; 4]);
+    }
+
+    #[test]
+    fn time_limit_ends_the_game_or_goes_into_overtime() {
+        let (clock, mut game) = game();
+        let limited = |on_time_limit| GameConfig {
+            time_limit_seconds: 90.0,
+            on_time_limit,
+            ..config(0, false)
+        };
+        game.start(limited(TimeLimitAction::End)).unwrap();
+        clock.advance(3000);
+        clock.advance(90_250);
+        assert!(game.advance());
+        let result = game.result().unwrap();
+        assert_eq!(result.elapsed_ms, 90_000);
+        assert_eq!(result.game_over_reason, Some(GameOverReason::TimeExpired));
+        assert!(!game.take_time_expired().unwrap().overtime);
+        assert!(game.take_time_expired().is_none());
+
+        game.start(limited(TimeLimitAction::Overtime)).unwrap();
+        clock.advance(3000);
+        assert!(game.advance());
+        clock.advance(92_500);
+        assert!(!game.advance());
+        assert!(game.state().overtime);
+        assert_eq!(game.overtime_second(), Some(2));
+        assert!(game.take_time_expired().unwrap().overtime);
+        assert!(game.finish());
+        let result = game.result().unwrap();
+        assert!(result.success && result.overtime);
+        assert_eq!(result.elapsed_ms, 92_500);
+    }
+
+    #[test]
+    fn rematch_needs_the_result_settled() {
+        let (clock, mut game) = game();
+        assert!(game.rematch().is_err());
+        game.start(GameConfig {
+            player: Some("Ann".to_string()),
+            ..config(2, false)
+        })
+        .unwrap();
+        clock.advance(4000);
+        assert!(game.rematch().is_err());
+        assert!(game.finish());
+        assert!(game.rematch().is_err());
+        assert!(!game.settle_result(game.game_id + 1));
+        assert!(game.settle_result(game.game_id));
+        let (config, armed) = game.rematch().unwrap();
+        assert_eq!(config.player.as_deref(), Some("Ann"));
+        assert!(!armed);
+        game.start(config).unwrap();
+        assert!(game.rematch().is_err());
+        // An aborted game has no result to keep.
+        game.abort(None).unwrap();
+        assert!(game.rematch().is_ok());
+    }
+
+    #[test]
+    fn feeds_hits_and_the_end_once() {
+        let (clock, mut game) = game();
+        game.start(config(2, false)).unwrap();
+        clock.advance(4000);
+        assert!(game.take_end_feed().is_none());
+        let hit = game.record_hit(0, "Ankle Trap").unwrap();
+        let feed = FeedEvent::hit(&hit, &game.state());
+        assert_eq!(feed.kind, FeedKind::Hit);
+        assert_eq!((feed.hits, feed.name.as_deref()), (1, Some("Ankle Trap")));
+        clock.advance(1000);
+        game.record_hit(1, "Neck Line").unwrap();
+        let end = game.take_end_feed().unwrap();
+        assert_eq!(end.kind, FeedKind::GameOver);
+        assert_eq!(end.game_over_reason, Some(GameOverReason::TouchLimit));
+        assert!(game.take_end_feed().is_none());
+    }
+
+    #[test]
+    fn ignores_disabled_sensors() {
+        let (clock, mut game) = game();
+        game.set_disabled_sensors(&[1]);
+        game.start(config(0, false)).unwrap();
+        clock.advance(4000);
+        assert!(game.record_hit(1, "dead").is_none());
+        assert!(game.record_hit(2, "b").is_some());
+        // Disabling takes effect mid-run and is noted in the result.
+        game.set_disabled_sensors(&[3]);
+        assert!(game.record_hit(3, "c").is_none());
+        assert!(game.record_hit(1, "dead").is_some());
+        assert!(game.finish());
+        let result = game.result().unwrap();
+        assert_eq!(result.hits, 2);
+        assert_eq!(result.disabled_sensors, [1, 3]);
+    }
+
+    #[test]
+    fn finish_beam_gates_the_buzzer() {
+        let (clock, mut game) = game();
+        let two_stage = GameConfig {
+            finish: FinishCondition::BeamThenBuzzer {
+                sensor: 7,
+                on_restore: false,
+            },
+            ..config(0, false)
+        };
+        game.start(two_stage).unwrap();
+        clock.advance(3000);
+        clock.advance(800);
+        assert!(game.premature_finish().is_some());
+        assert!(!game.finish());
+        assert!(!game.record_finish_beam(7, true));
+        assert!(game.record_finish_beam(7, false));
+        assert!(game.record_hit(7, "finish").is_none());
+        assert!(game.premature_finish().is_none());
+        clock.advance(400);
+        assert!(game.finish());
+        assert_eq!(game.result().unwrap().elapsed_ms, 1200);
+
+        let beam = GameConfig {
+            finish: FinishCondition::Beam {
+                sensor: 7,
+                on_restore: true,
+            },
+            ..config(0, false)
+        };
+        game.start(beam).unwrap();
+        clock.advance(5000);
+        assert!(!game.finish());
+        assert!(game.record_finish_beam(7, true));
+        assert_eq!(game.result().unwrap().elapsed_ms, 2000);
+    }
+
+    #[test]
+    fn buzzer_finishes_with_the_hit_timeline() {
+        let (clock, mut game) = game();
+        game.start(config(0, false)).unwrap();
+        clock.advance(3000);
+        clock.advance(1200);
+        assert_eq!(game.record_hit(1, "b").unwrap().elapsed_ms, 1200);
+        clock.advance(800);
+        assert!(game.result().is_none());
+        assert!(game.finish());
+        assert!(!game.finish());
+
+        clock.advance(5000);
+        let result = game.result().unwrap();
+        assert!(result.success);
+        assert_eq!(result.elapsed_ms, 2000);
+        assert_eq!(result.hits, 1);
+        assert_eq!(result.timeline[0].sensor, 1);
+    }
+
+    #[test]
+    fn game_over_after_the_allowed_touches() {
+        let (clock, mut game) = game();
+        game.start(config(2, false)).unwrap();
+        clock.advance(3000);
+        assert!(game.record_hit(0, "a").is_some());
+        // A laser that was hit stays off without reactivation.
+        clock.advance(60_000);
+        assert!(game.record_hit(0, "a").is_none());
+        assert_eq!(game.record_hit(1, "b").unwrap().count, 2);
+        assert_eq!(game.state().phase, GamePhase::Finished);
+        assert!(!game.result().unwrap().success);
+        assert!(game.record_hit(2, "c").is_none());
+        assert_eq!(
+            game.result().unwrap().game_over_reason,
+            Some(GameOverReason::TouchLimit)
+        );
+    }
+
+    #[test]
+    fn max_hits_fail_the_game_without_overcounting() {
+        let (clock, mut game) = game();
+        game.start(GameConfig {
+            max_hits: Some(3),
+            ..config(5, false)
+        })
+        .unwrap();
+        clock.advance(3000);
+        assert_eq!(game.state().lives_left, Some(3));
+        // Five breaks in one frame: the third ends the game.
+        let counted = (0..5)
+            .filter_map(|sensor| game.record_hit(sensor, "x"))
+            .count();
+        assert_eq!(counted, 3);
+        let result = game.result().unwrap();
+        assert!(result.failed && !result.success);
+        assert_eq!(result.game_over_reason, Some(GameOverReason::MaxHits));
+        assert_eq!(result.hits, 3);
+    }
+
+    #[test]
+    fn reactivated_lasers_count_again() {
+        let (clock, mut game) = game();
+        game.start(config(0, true)).unwrap();
+        clock.advance(3000);
+        assert!(game.record_hit(0, "a").is_some());
+        clock.advance(HIT_BLINK_MS + 1999);
+        assert!(game.record_hit(0, "a").is_none());
+        clock.advance(1);
+        assert!(game.record_hit(0, "a").is_some());
+    }
+
+    #[test]
+    fn only_configured_sensors_count() {
+        let (clock, mut game) = game();
+        game.start(GameConfig {
+            sensors: vec![2],
+            ..config(0, false)
+        })
+        .unwrap();
+        clock.advance(3000);
+        assert!(game.record_hit(0, "a").is_none());
+        assert!(game.record_hit(2, "c").is_some());
+    }
+
+    #[test]
+    fn game_clock_skips_the_pauses() {
+        let clock = Arc::new(ManualClock(AtomicU64::new(0)));
+        let mut game_clock = GameClock::new(clock.clone());
+        game_clock.start(1000);
+        clock.advance(3000);
+        game_clock.pause();
+        clock.advance(4000);
+        assert_eq!(game_clock.resume(), Some((3000, 7000)));
+        clock.advance(1000);
+        assert_eq!(game_clock.stop(9000), None);
+        clock.advance(1000);
+        // Before the start, during the pause, after the end.
+        let times = [500, 2000, 5000, 7500, 8000, 10_000].map(|at| game_clock.game_ms(at));
+        assert_eq!(times, [0, 1000, 2000, 2500, 3000, 4000]);
+    }
+
+    #[test]
+    fn events_stay_within_the_running_time() {
+        let (clock, mut game) = game();
+        game.start(config(0, false)).unwrap();
+        // Breaks during the countdown aren't hits.
+        clock.advance(1000);
+        assert!(game.record_hit(0, "a").is_none());
+        clock.advance(2000);
+        clock.advance(1000);
+        assert_eq!(game.record_hit(0, "a").unwrap().elapsed_ms, 1000);
+        game.pause().unwrap();
+        clock.advance(5000);
+        assert!(game.record_hit(1, "b").is_none());
+        assert!(!game.finish());
+        game.resume().unwrap();
+        clock.advance(200);
+        assert_eq!(game.record_hit(2, "c").unwrap().elapsed_ms, 1200);
+        clock.advance(300);
+        assert!(game.finish());
+        clock.advance(1000);
+        assert!(game.record_hit(3, "d").is_none());
+        let result = game.result().unwrap();
+        assert_eq!(result.elapsed_ms, 1500);
+        let times: Vec<u64> = result.timeline.iter().map(|hit| hit.elapsed_ms).collect();
+        assert_eq!(times, [1000, 1200]);
+    }
+
+    #[test]
+    fn pauses_stop_the_game_time() {
+        let (clock, mut game) = game();
+        game.start(GameConfig {
+            count_paused_hits: true,
+            ..config(0, true)
+        })
+        .unwrap();
+        clock.advance(3000);
+        clock.advance(1000);
+        assert!(game.record_hit(0, "a").is_some());
+        game.pause().unwrap();
+        assert!(game.pause().is_err());
+        clock.advance(10_000);
+        assert_eq!(game.elapsed_ms(), 1000);
+        assert!(game.record_hit(1, "b").is_none());
+        assert!(!game.finish());
+
+        game.resume().unwrap();
+        clock.advance(500);
+        assert_eq!(game.elapsed_ms(), 1500);
+        // The reactivation time doesn't run during the pause either.
+        clock.advance(HIT_BLINK_MS + 1500);
+        assert!(game.record_hit(0, "a").is_some());
+        assert!(game.finish());
+
+        let result = game.result().unwrap();
+        assert_eq!(result.pauses.len(), 1);
+        assert_eq!(result.pauses[0].at_ms, 1000);
+        assert_eq!(result.pauses[0].duration_ms, 10_000);
+        assert_eq!(result.paused_hits.len(), 1);
+        assert_eq!(result.paused_hits[0].elapsed_ms, 1000);
+        assert_eq!(result.hits, 2);
+    }
+
+    #[test]
+    fn pausing_the_countdown_restarts_it() {
+        let (clock, mut game) = game();
+        game.start(config(0, false)).unwrap();
+        clock.advance(2500);
+        game.pause().unwrap();
+        clock.advance(5000);
+        game.resume().unwrap();
+        assert_eq!(game.state().phase, GamePhase::Countdown);
+        assert_eq!(game.state().countdown_remaining_ms, 3000);
+        clock.advance(3000);
+        assert!(game.advance());
+        assert!(game.result().is_none());
+        assert!(game.resume().is_err());
+    }
+
+    #[test]
+    fn limited_lives_end_the_game_on_the_last_hit() {
+        let (clock, mut game) = game();
+        game.start(GameConfig {
+            mode: GameMode::LimitedLives { lives: 2 },
+            ..config(1, false)
+        })
+        .unwrap();
+        clock.advance(3000);
+        assert!(game.record_hit(0, "a").is_some());
+        assert_eq!(game.state().lives_left, Some(1));
+        clock.advance(700);
+        assert!(game.record_hit(1, "b").is_some());
+        let result = game.result().unwrap();
+        assert!(!result.success);
+        assert_eq!(result.mode, "limitedLives");
+        assert_eq!(result.score, 700);
+    }
+
+    #[test]
+    fn stealth_scores_hits_within_the_window() {
+        let (clock, mut game) = game();
+        game.start(GameConfig {
+            mode: GameMode::Stealth {
+                window_seconds: 10.0,
+            },
+            ..config(1, true)
+        })
+        .unwrap();
+        clock.advance(3000);
+        assert!(game.record_hit(0, "a").is_some());
+        assert!(game.record_hit(1, "b").is_some());
+        assert!(!game.finish());
+        assert_eq!(game.state().remaining_ms, Some(10_000));
+        clock.advance(10_250);
+        assert!(game.record_hit(2, "c").is_none());
+        let result = game.result().unwrap();
+        assert!(result.success);
+        assert_eq!(result.elapsed_ms, 10_000);
+        assert_eq!(result.score, 2);
+    }
+
+    #[test]
+    fn grace_hits_are_recorded_but_dont_count() {
+        let (clock, mut game) = game();
+        game.start(GameConfig {
+            mode: GameMode::LimitedLives { lives: 1 },
+            grace_period_seconds: 1.5,
+            ..config(0, false)
+        })
+        .unwrap();
+        clock.advance(3000);
+        clock.advance(1000);
+        let hit = game.record_hit(0, "a").unwrap();
+        assert!(hit.grace);
+        assert_eq!(hit.count, 0);
+        assert_eq!(game.state().lives_left, Some(1));
+        // The pause doesn't eat into the grace period, and the laser stays
+        // active.
+        game.pause().unwrap();
+        clock.advance(5000);
+        game.resume().unwrap();
+        clock.advance(400);
+        assert!(game.record_hit(0, "a").unwrap().grace);
+        clock.advance(100);
+        assert!(!game.record_hit(0, "a").unwrap().grace);
+
+        let result = game.result().unwrap();
+        assert_eq!(result.hits, 1);
+        assert_eq!(result.timeline.len(), 3);
+        assert_eq!(result.elapsed_ms, 1500);
+    }
+
+    #[test]
+    fn armed_games_start_on_the_start_gate() {
+        let (clock, mut game) = game();
+        game.arm(GameConfig {
+            start_gate_sensor: Some(0),
+            ..config(0, false)
+        })
+        .unwrap();
+        assert!(game.start(config(0, false)).is_err());
+        clock.advance(30_000);
+        assert!(!game.trigger_start(1));
+        assert!(game.record_hit(1, "b").is_none());
+        assert!(game.trigger_start(0));
+        assert!(!game.trigger_start(0));
+        assert_eq!(game.state().phase, GamePhase::Running);
+        assert_eq!(game.elapsed_ms(), 0);
+        clock.advance(1000);
+        assert!(game.finish());
+        let result = game.result().unwrap();
+        assert_eq!(result.elapsed_ms, 1000);
+        assert_eq!(result.hits, 0);
+    }
+
+    #[test]
+    fn armed_games_disarm() {
+        let (clock, mut game) = game();
+        game.arm(GameConfig {
+            arm_timeout_seconds: 60.0,
+            ..config(0, false)
+        })
+        .unwrap();
+        clock.advance(59_999);
+        assert!(!game.advance());
+        clock.advance(1);
+        assert!(game.advance());
+        assert_eq!(game.state().phase, GamePhase::Idle);
+        assert!(!game.trigger_start(0));
+
+        game.arm(config(0, false)).unwrap();
+        game.abort(None).unwrap();
+        assert_eq!(game.state().phase, GamePhase::Idle);
+        assert!(game.result().is_none());
+    }
+
+    #[test]
+    fn checkpoints_record_splits_in_order() {
+        let (clock, mut game) = game();
+        let checkpoint = |sensor, name: &str| Checkpoint {
+            sensor,
+            name: name.to_string(),
+            on_restore: false,
+        };
+        game.start(GameConfig {
+            checkpoints: vec![
+                checkpoint(5, "first"),
+                checkpoint(6, "middle"),
+                checkpoint(7, "last"),
+            ],
+            ..config(0, false)
+        })
+        .unwrap();
+        game.compare_with(Pace {
+            splits: HashMap::from([("middle".to_string(), 2500)]),
+            ..Pace::default()
+        });
+        clock.advance(3000);
+        clock.advance(2000);
+        // Reaching the middle skips the first checkpoint, which can't fire
+        // any more; checkpoint sensors never count as hits.
+        let split = game.record_checkpoint(6, false).unwrap();
+        assert_eq!(split.elapsed_ms, Some(2000));
+        assert_eq!(split.delta_ms, Some(-500));
+        assert!(game.record_checkpoint(5, false).is_none());
+        assert!(game.record_checkpoint(6, false).is_none());
+        assert!(game.record_checkpoint(7, true).is_none());
+        assert!(game.record_hit(5, "first").is_none());
+        assert!(game.finish());
+
+        let splits = game.result().unwrap().splits;
+        let missed: Vec<bool> = splits.iter().map(|split| split.missed).collect();
+        assert_eq!(missed, [true, false, true]);
+    }
+
+    #[test]
+    fn paces_games_against_the_best_run() {
+        let (clock, mut game) = game();
+        game.start(GameConfig {
+            checkpoints: vec![Checkpoint {
+                sensor: 5,
+                name: "middle".to_string(),
+                on_restore: false,
+            }],
+            player: Some("Ann".to_string()),
+            ..config(0, false)
+        })
+        .unwrap();
+        assert!(game.pace_delta().is_none());
+        game.compare_with(Pace {
+            splits: HashMap::from([("middle".to_string(), 4000)]),
+            finish_ms: Some(10_000),
+            best_ms: Some(10_000),
+            record_ms: Some(9000),
+        });
+        clock.advance(3000);
+        clock.advance(3000);
+        game.advance();
+        assert_eq!(game.pace_delta().unwrap().delta_ms, 0);
+        clock.advance(2000);
+        assert_eq!(game.pace_delta().unwrap().delta_ms, 1000);
+        game.record_checkpoint(5, false).unwrap();
+        clock.advance(1000);
+        assert_eq!(game.pace_delta().unwrap().delta_ms, 1000);
+        clock.advance(3500);
+        assert!(game.finish());
+
+        let result = game.result().unwrap();
+        assert_eq!(result.personal_best_delta_ms, Some(-500));
+        assert_eq!(result.record_delta_ms, Some(500));
+        let (event, best) = game.new_best().unwrap();
+        assert_eq!((event, best.previous), ("new-personal-best", 10_000));
+    }
+
+    #[test]
+    fn tripwires_end_the_game_after_the_grace_period() {
+        let (clock, mut game) = game();
+        game.start(GameConfig {
+            sensors: vec![1, 2],
+            fatal_sensors: vec![9],
+            grace_period_seconds: 1.0,
+            penalties: PenaltyTable {
+                default_seconds: 2.0,
+                ..PenaltyTable::default()
+            },
+            ..config(3, false)
+        })
+        .unwrap();
+        clock.advance(3000);
+        assert!(game.record_hit(9, "tripwire").unwrap().grace);
+        clock.advance(1000);
+        game.record_hit(1, "left").unwrap();
+        let hit = game.record_hit(9, "tripwire").unwrap();
+        assert!(hit.fatal);
+        assert_eq!((hit.count, hit.penalty_ms), (1, 0));
+        assert!(game.record_hit(2, "right").is_none());
+
+        let result = game.result().unwrap();
+        assert!(result.failed && !result.success);
+        assert_eq!(result.game_over_reason, Some(GameOverReason::Tripwire));
+        assert_eq!((result.hits, result.penalty_ms), (1, 2000));
+    }
+
+    #[test]
+    fn hits_add_the_penalty_of_their_sensor() {
+        let (clock, mut game) = game();
+        game.start(GameConfig {
+            penalties: PenaltyTable {
+                default_seconds: 2.0,
+                sensors: BTreeMap::from([(7, 5.0)]),
+            },
+            grace_period_seconds: 1.0,
+            ..config(0, false)
+        })
+        .unwrap();
+        clock.advance(3000);
+        assert_eq!(game.record_hit(7, "ankle").unwrap().penalty_ms, 0);
+        clock.advance(1000);
+        let hit = game.record_hit(7, "ankle").unwrap();
+        assert_eq!((hit.penalty_ms, hit.penalized_ms), (5000, 6000));
+        clock.advance(1000);
+        let hit = game.record_hit(1, "waist").unwrap();
+        assert_eq!((hit.penalty_ms, hit.penalized_ms), (2000, 9000));
+        assert!(game.finish());
+        let result = game.result().unwrap();
+        assert_eq!(result.penalty_ms, 7000);
+        assert_eq!(result.score, 9000);
+    }
+
+    #[test]
+    fn reverse_games_collect_each_beam_once() {
+        let (clock, mut game) = game();
+        game.start(GameConfig {
+            mode: GameMode::Reverse {
+                time_limit_seconds: 60.0,
+            },
+            sensors: vec![1, 2, 3],
+            reactivate_lasers: true,
+            grace_period_seconds: 1.5,
+            ..config(1, false)
+        })
+        .unwrap();
+        clock.advance(3000);
+        let hit = game.record_hit(1, "left").unwrap();
+        assert_eq!((hit.count, hit.grace, hit.penalty_ms), (1, false, 0));
+        assert_eq!(game.collected(&hit).unwrap().total, Some(3));
+        clock.advance(10_000);
+        assert!(game.record_hit(1, "left").is_none());
+        game.record_hit(2, "middle").unwrap();
+        assert_eq!(game.state().phase, GamePhase::Running);
+        assert!(!game.finish());
+        clock.advance(10_400);
+        game.record_hit(3, "right").unwrap();
+
+        // All three beams with 39.6 seconds left.
+        let result = game.result().unwrap();
+        assert!(result.success && !result.failed);
+        assert_eq!(result.mode, "reverse");
+        assert_eq!((result.hits, result.score), (3, 42));
+    }
+
+    #[test]
+    fn reverse_games_end_with_the_time_limit() {
+        let (clock, mut game) = game();
+        game.start(GameConfig {
+            mode: GameMode::Reverse {
+                time_limit_seconds: 30.0,
+            },
+            ..config(0, false)
+        })
+        .unwrap();
+        clock.advance(3000);
+        game.record_hit(4, "top").unwrap();
+        game.record_hit(5, "bottom").unwrap();
+        clock.advance(30_000);
+        assert!(game.advance());
+        let result = game.result().unwrap();
+        assert_eq!((result.elapsed_ms, result.score), (30_000, 2));
+    }
+
+    #[test]
+    fn relay_legs_end_with_the_buzzer() {
+        let (clock, mut game) = game();
+        let relay = RelayConfig {
+            players: vec!["Ann".to_string(), "Ben".to_string()],
+            legs: 3,
+            changeover_seconds: 2.0,
+        };
+        assert!(game
+            .start(GameConfig {
+                relay: Some(relay.clone()),
+                mode: GameMode::Stealth {
+                    window_seconds: 30.0
+                },
+                ..config(0, false)
+            })
+            .is_err());
+        game.start(GameConfig {
+            relay: Some(relay),
+            ..config(0, false)
+        })
+        .unwrap();
+        clock.advance(3000);
+        clock.advance(10_000);
+        game.record_hit(1, "left").unwrap();
+        let first = game.complete_leg().unwrap();
+        assert_eq!((first.leg.player.as_str(), first.leg.hits), ("Ann", 1));
+        assert_eq!(first.next_player.as_deref(), Some("Ben"));
+        assert!(!game.finish());
+
+        // The press that ended the first leg bounces.
+        clock.advance(1999);
+        assert!(game.complete_leg().is_none());
+        clock.advance(8001);
+        let second = game.complete_leg().unwrap().leg;
+        assert_eq!(
+            (second.elapsed_ms, second.split_ms, second.hits),
+            (10_000, 20_000, 0)
+        );
+        assert!(!game.finish());
+        clock.advance(5000);
+        let third = game.complete_leg().unwrap();
+        assert_eq!(third.leg.player, "Ann");
+        assert_eq!(third.next_player, None);
+        assert!(game.finish());
+
+        let result = game.result().unwrap();
+        assert_eq!(result.elapsed_ms, 25_000);
+        assert_eq!(result.legs.len(), 3);
+    }
+
+    #[test]
+    fn head_to_head_lanes_race_to_a_winner() {
+        let (clock, mut game) = game();
+        let lane = |id: &str, sensors, finish| LaneConfig {
+            id: id.to_string(),
+            player: Some(id.to_uppercase()),
+            sensors,
+            finish,
+        };
+        game.start(GameConfig {
+            head_to_head: Some(HeadToHeadConfig {
+                lanes: vec![
+                    lane(
+                        "left",
+                        LaneSensors::Range { first: 0, last: 4 },
+                        FinishTrigger::Sensor { sensor: 5 },
+                    ),
+                    lane(
+                        "right",
+                        LaneSensors::Connection {
+                            connection_id: "COM4".to_string(),
+                        },
+                        FinishTrigger::Buzzer {
+                            connection_id: Some("COM4".to_string()),
+                        },
+                    ),
+                ],
+                finish_timeout_seconds: 10.0,
+            }),
+            ..config(2, false)
+        })
+        .unwrap();
+        clock.advance(3000);
+        clock.advance(5000);
+        let hit = game.record_lane_break("COM3", 2, "left 3").unwrap();
+        assert_eq!((hit.lane.as_str(), hit.hit.count), ("left", 1));
+        assert!(game.record_lane_break("COM3", 9, "other").is_none());
+        assert!(!game.finish());
+        assert!(!game.finish_lane(Some("COM3")));
+        assert!(game.finish_lane(Some("COM4")));
+        assert_eq!(game.finished_lanes()[0].lane, "right");
+        assert_eq!(game.state().phase, GamePhase::Running);
+
+        // The left lane misses the timeout.
+        clock.advance(10_000);
+        assert!(game.advance());
+        let finished = game.finished_lanes();
+        assert!(finished[0].timed_out && finished[0].result.aborted);
+        let result = game.result().unwrap();
+        let head_to_head = result.head_to_head.unwrap();
+        assert_eq!(head_to_head.winner.as_deref(), Some("right"));
+        assert_eq!(head_to_head.lanes[1].result.elapsed_ms, 5000);
+        assert!(result.success);
+    }
+
+    #[test]
+    fn head_to_head_draws_on_equal_scores() {
+        let (clock, mut game) = game();
+        let lane = |id: &str, first| LaneConfig {
+            id: id.to_string(),
+            player: None,
+            sensors: LaneSensors::Range {
+                first,
+                last: first + 3,
+            },
+            finish: FinishTrigger::Sensor { sensor: first + 3 },
+        };
+        game.start(GameConfig {
+            head_to_head: Some(HeadToHeadConfig {
+                lanes: vec![lane("a", 0), lane("b", 4)],
+                ..HeadToHeadConfig::default()
+            }),
+            ..config(0, false)
+        })
+        .unwrap();
+        clock.advance(3000);
+        clock.advance(4000);
+        game.record_lane_break("", 3, "finish a");
+        game.record_lane_break("", 7, "finish b");
+        let result = game.result().unwrap();
+        assert_eq!(result.head_to_head.unwrap().winner, None);
+        assert!(!result.success);
+    }
+
+    #[test]
+    fn modes_are_read_from_the_config() {
+        let config: GameConfig = serde_json::from_str(
+            r#"{"mode": {"kind": "stealth", "windowSeconds": 30}, "countdownSeconds": 0}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.mode,
+            GameMode::Stealth {
+                window_seconds: 30.0
+            }
+        );
+        assert_eq!(GameConfig::default().mode, GameMode::TimeAttack);
+    }
+
+    #[test]
+    fn abort_during_countdown() {
+        let (clock, mut game) = game();
+        assert!(game.abort(None).is_err());
+        game.start(config(0, false)).unwrap();
+        clock.advance(1000);
+        game.abort(Some("player left".to_string())).unwrap();
+        let result = game.result().unwrap();
+        assert!(result.aborted && !result.success);
+        assert_eq!(result.abort_reason.as_deref(), Some("player left"));
+        assert_eq!(result.elapsed_ms, 0);
+        // A new game can start after an abort.
+        game.start(config(0, false)).unwrap();
+        assert_eq!(game.state().phase, GamePhase::Countdown);
+    }
+}
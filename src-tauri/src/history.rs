@@ -0,0 +1,756 @@
+// History of every game played, including failed and aborted ones, for the
+// end-of-day overview. Saved runs only hold the games someone put on the
+// leaderboard; this records each game as it ends, in the history table of
+// the scores database, which get_run_history and the stats query.
+
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tauri::{Emitter, Listener, Manager};
+
+use crate::game::{GameManager, GameOverReason, GamePhase, GameResult, HeadToHeadResult, Hit};
+use crate::pipeline::unix_time_ms;
+use crate::players;
+use crate::rating::{self, RatingSettings};
+use crate::scores::{self, Run, ScoreBook, ScoreFile, TimeRange};
+use crate::serial_log::days_from_civil;
+
+// History page size when the caller doesn't choose one.
+const DEFAULT_PAGE_SIZE: usize = 100;
+const DAY_MS: u64 = 86_400_000;
+const HOUR_MS: u64 = 3_600_000;
+// Most buckets get_usage_stats returns, a year of hours.
+const MAX_BUCKETS: usize = 366 * 24;
+
+#[derive(Clone, Copy, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Outcome {
+    Completed,
+    Failed,
+    Aborted,
+}
+
+// One ended game.
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GameRecord {
+    pub id: u64,
+    pub player: Option<String>,
+    pub mode: String,
+    pub outcome: Outcome,
+    #[serde(default)]
+    pub practice: bool,
+    pub game_over_reason: Option<GameOverReason>,
+    pub abort_reason: Option<String>,
+    // Unix times the game started running and ended; the start leaves out
+    // the countdown, and pauses lie in between.
+    pub started_ms: u64,
+    pub ended_ms: u64,
+    pub elapsed_ms: u64,
+    pub hits: u32,
+    pub timeline: Vec<Hit>,
+    #[serde(default)]
+    pub head_to_head: Option<HeadToHeadSide>,
+    // Id the game manager gave the game, which restarts with the app, and
+    // the run saved from the game, if any.
+    #[serde(default)]
+    pub game_id: Option<u64>,
+    #[serde(default)]
+    pub run_id: Option<u64>,
+}
+
+// The lane of a head-to-head game a record is of, and how it went.
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HeadToHeadSide {
+    pub lane: String,
+    pub opponent: Option<String>,
+    // False for a loss and a draw.
+    pub won: bool,
+    pub draw: bool,
+}
+
+// Filter of get_run_history; unset fields match everything.
+#[derive(Clone, Debug, Default, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct HistoryFilter {
+    // Range of the end times.
+    #[serde(flatten)]
+    pub range: TimeRange,
+    pub player: Option<String>,
+    pub mode: Option<String>,
+    pub outcome: Option<Outcome>,
+    // Tag of the run saved from the game.
+    pub tag: Option<String>,
+    pub limit: Option<usize>,
+    pub offset: Option<usize>,
+}
+
+impl HistoryFilter {
+    // The condition on history rows the filter stands for, with its
+    // parameters.
+    fn condition(&self) -> (String, Vec<Value>) {
+        let time = |ms: u64| Value::Integer(ms.min(i64::MAX as u64) as i64);
+        let mut conditions = vec!["ended_ms >= ?", "ended_ms < ?"];
+        let mut values = vec![
+            time(self.range.from_ms.unwrap_or(0)),
+            time(self.range.to_ms.unwrap_or(u64::MAX)),
+        ];
+        if let Some(player) = &self.player {
+            conditions.push("player_key = ?");
+            values.push(Value::Text(players::name_key(player)));
+        }
+        if let Some(mode) = &self.mode {
+            conditions.push("mode = ?");
+            values.push(Value::Text(mode.clone()));
+        }
+        if let Some(outcome) = self.outcome {
+            conditions.push("json_extract(data, '$.outcome') = ?");
+            let outcome = match outcome {
+                Outcome::Completed => "completed",
+                Outcome::Failed => "failed",
+                Outcome::Aborted => "aborted",
+            };
+            values.push(Value::Text(outcome.to_string()));
+        }
+        if let Some(tag) = &self.tag {
+            conditions.push(
+                "json_extract(data, '$.runId') IN (SELECT runs.id
+                 FROM runs, json_each(runs.data, '$.tags') AS tag WHERE tag.value = ?)",
+            );
+            values.push(Value::Text(scores::normalize_tag(tag)));
+        }
+        (conditions.join(" AND "), values)
+    }
+}
+
+#[derive(Clone, Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryPage {
+    // Records matching the filter, over all pages.
+    pub total: usize,
+    // Newest first.
+    pub records: Vec<GameRecord>,
+}
+
+// Returned by get_daily_summary.
+#[derive(Clone, Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DailySummary {
+    pub date: String,
+    pub runs: usize,
+    pub completed: usize,
+    pub failed: usize,
+    pub aborted: usize,
+    // Average game time of the completed games.
+    pub average_time_ms: Option<u64>,
+    // Local hour (0-23) in which the most games started.
+    pub busiest_hour: Option<u32>,
+    pub total_hits: u64,
+}
+
+// Length of the buckets of get_usage_stats, in local time.
+#[derive(Clone, Copy, Debug, Default, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Bucket {
+    #[default]
+    Hour,
+    Day,
+}
+
+impl Bucket {
+    fn ms(self) -> u64 {
+        match self {
+            Bucket::Hour => HOUR_MS,
+            Bucket::Day => DAY_MS,
+        }
+    }
+}
+
+// The games that started in one bucket.
+#[derive(Clone, Debug, Default, PartialEq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageBucket {
+    // Unix time the bucket starts.
+    pub start_ms: u64,
+    pub runs: usize,
+    pub unique_players: usize,
+    pub average_duration_ms: Option<u64>,
+    pub total_hits: u64,
+}
+
+// Returned by get_usage_stats.
+#[derive(Clone, Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageStats {
+    // Every bucket of the range in order, empty ones included.
+    pub buckets: Vec<UsageBucket>,
+    pub runs: usize,
+    pub unique_players: usize,
+    // Local hour (0-23) in which the most games of the range started.
+    pub busiest_hour: Option<u32>,
+}
+
+// Adds the record of an ended game to the history.
+pub fn record(
+    file: &mut ScoreFile,
+    game_id: u64,
+    result: &GameResult,
+    ended_ms: u64,
+) -> GameRecord {
+    let outcome = if result.aborted {
+        Outcome::Aborted
+    } else if result.success {
+        Outcome::Completed
+    } else {
+        Outcome::Failed
+    };
+    let paused_ms: u64 = result
+        .pauses
+        .iter()
+        .filter(|pause| !pause.during_countdown)
+        .map(|pause| pause.duration_ms)
+        .sum();
+    let record = GameRecord {
+        id: file.next_history_id,
+        player: result.config.player.clone(),
+        mode: result.mode.clone(),
+        outcome,
+        practice: result.practice,
+        game_over_reason: result.game_over_reason,
+        abort_reason: result.abort_reason.clone(),
+        started_ms: ended_ms.saturating_sub(result.elapsed_ms + paused_ms),
+        ended_ms,
+        elapsed_ms: result.elapsed_ms,
+        hits: result.hits,
+        timeline: result.timeline.clone(),
+        head_to_head: None,
+        game_id: Some(game_id),
+        run_id: None,
+    };
+    file.next_history_id += 1;
+    file.history.push(record.clone());
+    record
+}
+
+// Adds a record for each lane of an ended head-to-head game, with its
+// outcome.
+pub fn record_head_to_head(
+    file: &mut ScoreFile,
+    game_id: u64,
+    head_to_head: &HeadToHeadResult,
+    ended_ms: u64,
+) {
+    for lane in &head_to_head.lanes {
+        record(file, game_id, &lane.result, ended_ms);
+        let opponent = head_to_head
+            .lanes
+            .iter()
+            .find(|other| other.lane != lane.lane)
+            .and_then(|other| other.player.clone());
+        if let Some(record) = file.history.last_mut() {
+            record.head_to_head = Some(HeadToHeadSide {
+                lane: lane.lane.clone(),
+                opponent,
+                won: head_to_head.winner.as_ref() == Some(&lane.lane),
+                draw: head_to_head.winner.is_none()
+                    && head_to_head.lanes.iter().any(|lane| lane.result.success),
+            });
+        }
+    }
+}
+
+// Links the record of the game a run was saved from to the run. Game ids
+// restart with the app, so only the latest records are looked at.
+pub fn link_run(file: &mut ScoreFile, game_id: u64, run: &Run) {
+    let key = players::name_key(&run.player);
+    let linked = file.history.iter().rev().take(2).find(|record| {
+        record.game_id == Some(game_id)
+            && record.run_id.is_none()
+            && (record.head_to_head.is_none()
+                || record
+                    .player
+                    .as_ref()
+                    .is_some_and(|player| players::name_key(player) == key))
+    });
+    let id = linked.map(|record| record.id);
+    if let Some(record) = id.and_then(|id| file.history.get_mut(id)) {
+        record.run_id = Some(run.id);
+    }
+}
+
+fn page(db: &Connection, filter: &HistoryFilter) -> Result<HistoryPage, String> {
+    let (condition, mut values) = filter.condition();
+    let total: i64 = db
+        .query_row(
+            &format!("SELECT COUNT(*) FROM history WHERE {}", condition),
+            params_from_iter(&values),
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    let count = |n: usize| Value::Integer(n.min(i64::MAX as usize) as i64);
+    values.push(count(filter.limit.unwrap_or(DEFAULT_PAGE_SIZE)));
+    values.push(count(filter.offset.unwrap_or(0)));
+    let mut statement = db
+        .prepare(&format!(
+            "SELECT data FROM history WHERE {}
+             ORDER BY ended_ms DESC, id DESC LIMIT ? OFFSET ?",
+            condition
+        ))
+        .map_err(|e| e.to_string())?;
+    let records = statement
+        .query_map(params_from_iter(&values), |row| row.get::<_, String>(0))
+        .map_err(|e| e.to_string())?
+        .map(|data| {
+            serde_json::from_str(&data.map_err(|e| e.to_string())?).map_err(|e| e.to_string())
+        })
+        .collect::<Result<Vec<GameRecord>, String>>()?;
+    Ok(HistoryPage {
+        total: total as usize,
+        records,
+    })
+}
+
+// Parses a "YYYY-MM-DD" date into days since the Unix epoch.
+fn parse_date(date: &str) -> Result<i64, String> {
+    let invalid = || format!("{} isn't a date like 2024-05-31", date);
+    let mut parts = date.trim().splitn(3, '-');
+    let mut next = || parts.next().and_then(|part| part.parse::<i64>().ok());
+    let (Some(year), Some(month), Some(day)) = (next(), next(), next()) else {
+        return Err(invalid());
+    };
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return Err(invalid());
+    }
+    Ok(days_from_civil(year, month as u32, day as u32))
+}
+
+// Local hour in which the most of `records` started; the earliest of
+// equally busy hours.
+fn busiest_hour(records: &[&GameRecord], utc_offset_ms: i64) -> Option<u32> {
+    let mut per_hour = [0usize; 24];
+    for record in records {
+        let local_ms = record.started_ms as i64 + utc_offset_ms;
+        per_hour[(local_ms.rem_euclid(DAY_MS as i64) as u64 / HOUR_MS) as usize] += 1;
+    }
+    (0..24)
+        .filter(|&hour| per_hour[hour] > 0)
+        .max_by_key(|&hour| (per_hour[hour], std::cmp::Reverse(hour)))
+        .map(|hour| hour as u32)
+}
+
+// Games by when they started, in buckets of local time `utc_offset_ms`
+// ahead of UTC, grouped by the database over the history table. The range
+// runs from the first game to `now_ms` unless given.
+fn usage(
+    db: &Connection,
+    range: TimeRange,
+    bucket: Bucket,
+    utc_offset_ms: i64,
+    now_ms: u64,
+) -> Result<UsageStats, String> {
+    let size = bucket.ms() as i64;
+    let from_ms = match range.from_ms {
+        Some(from_ms) => from_ms,
+        None => db
+            .query_row("SELECT MIN(started_ms) FROM history", [], |row| {
+                row.get::<_, Option<i64>>(0)
+            })
+            .map_err(|e| e.to_string())?
+            .map_or(now_ms, |ms| ms as u64),
+    };
+    let to_ms = range.to_ms.unwrap_or(now_ms).max(from_ms);
+    // Buckets start on the local hour or midnight.
+    let first_ms =
+        ((from_ms as i64 + utc_offset_ms).div_euclid(size) * size - utc_offset_ms).max(0) as u64;
+    let count = (to_ms - first_ms).div_ceil(bucket.ms()) as usize;
+    if count > MAX_BUCKETS {
+        return Err(format!(
+            "that range has over {} buckets; pick a shorter one or days",
+            MAX_BUCKETS
+        ));
+    }
+    let (from, to) = (from_ms as i64, to_ms.min(i64::MAX as u64) as i64);
+    let mut buckets: Vec<UsageBucket> = (0..count)
+        .map(|i| UsageBucket {
+            start_ms: first_ms + i as u64 * bucket.ms(),
+            ..UsageBucket::default()
+        })
+        .collect();
+    let mut statement = db
+        .prepare_cached(
+            "SELECT (started_ms - ?3) / ?4, COUNT(*), COUNT(DISTINCT player_key),
+                    SUM(elapsed_ms), SUM(hits)
+             FROM history WHERE started_ms >= ?1 AND started_ms < ?2
+             GROUP BY 1",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = statement
+        .query_map(params![from, to, first_ms as i64, size], |row| {
+            Ok((
+                row.get::<_, i64>(0)? as usize,
+                row.get::<_, i64>(1)? as usize,
+                row.get::<_, i64>(2)? as usize,
+                row.get::<_, i64>(3)? as u64,
+                row.get::<_, i64>(4)? as u64,
+            ))
+        })
+        .map_err(|e| e.to_string())?;
+    for row in rows {
+        let (index, runs, unique_players, duration_ms, total_hits) =
+            row.map_err(|e| e.to_string())?;
+        let bucket = &mut buckets[index];
+        bucket.runs = runs;
+        bucket.unique_players = unique_players;
+        bucket.average_duration_ms = Some(duration_ms / runs as u64);
+        bucket.total_hits = total_hits;
+    }
+    let (runs, unique_players) = db
+        .query_row(
+            "SELECT COUNT(*), COUNT(DISTINCT player_key)
+             FROM history WHERE started_ms >= ?1 AND started_ms < ?2",
+            params![from, to],
+            |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?)),
+        )
+        .map_err(|e| e.to_string())?;
+    // The earliest of equally busy local hours.
+    let busiest_hour = db
+        .query_row(
+            "SELECT ((started_ms + ?3) % ?4 + ?4) % ?4 / ?5 AS hour
+             FROM history WHERE started_ms >= ?1 AND started_ms < ?2
+             GROUP BY hour ORDER BY COUNT(*) DESC, hour LIMIT 1",
+            params![from, to, utc_offset_ms, DAY_MS as i64, HOUR_MS as i64],
+            |row| row.get::<_, i64>(0),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+    Ok(UsageStats {
+        buckets,
+        runs: runs as usize,
+        unique_players: unique_players as usize,
+        busiest_hour: busiest_hour.map(|hour| hour as u32),
+    })
+}
+
+// Summary of the games that ended on a local day, `utc_offset_ms` ahead of
+// UTC.
+fn summary(file: &ScoreFile, date: &str, utc_offset_ms: i64) -> Result<DailySummary, String> {
+    let day_start = parse_date(date)? * DAY_MS as i64 - utc_offset_ms;
+    let range = TimeRange {
+        from_ms: Some(day_start.max(0) as u64),
+        to_ms: Some((day_start + DAY_MS as i64).max(0) as u64),
+    };
+    let records: Vec<&GameRecord> = file
+        .history
+        .iter()
+        .filter(|record| range.contains(record.ended_ms))
+        .collect();
+    let count = |outcome| records.iter().filter(|r| r.outcome == outcome).count();
+    let times: Vec<u64> = records
+        .iter()
+        .filter(|record| record.outcome == Outcome::Completed)
+        .map(|record| record.elapsed_ms)
+        .collect();
+    let busiest_hour = busiest_hour(&records, utc_offset_ms);
+    Ok(DailySummary {
+        date: date.trim().to_string(),
+        runs: records.len(),
+        completed: times.len(),
+        failed: count(Outcome::Failed),
+        aborted: count(Outcome::Aborted),
+        average_time_ms: (!times.is_empty())
+            .then(|| times.iter().sum::<u64>() / times.len() as u64),
+        busiest_hour,
+        total_hits: records.iter().map(|record| u64::from(record.hits)).sum(),
+    })
+}
+
+// Part of the game-state-changed payload the history needs.
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StateChange {
+    game_id: u64,
+    phase: GamePhase,
+}
+
+// Payload of history-error, emitted when an ended game couldn't be
+// recorded.
+#[derive(Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct HistoryError {
+    game_id: u64,
+    message: String,
+}
+
+// Records every game of the managed game manager once it has ended.
+pub fn subscribe(app_handle: &tauri::AppHandle) {
+    let handle = app_handle.clone();
+    let recorded_game = AtomicU64::new(0);
+    app_handle.listen_any("game-state-changed", move |event| {
+        let Ok(change) = serde_json::from_str::<StateChange>(event.payload()) else {
+            return;
+        };
+        if !matches!(change.phase, GamePhase::Finished | GamePhase::Aborted)
+            || recorded_game.swap(change.game_id, Ordering::Relaxed) == change.game_id
+        {
+            return;
+        }
+        let result = match handle.state::<Arc<Mutex<GameManager>>>().lock() {
+            Ok(game) => game.result(),
+            Err(_) => return,
+        };
+        let Some(result) = result else {
+            return;
+        };
+        if result.practice && !scores::keep_practice_runs(&handle) {
+            return;
+        }
+        let rating_settings = RatingSettings::load(&handle);
+        let max_name_length = players::max_name_length(&handle);
+        let scores = handle.state::<Mutex<ScoreBook>>();
+        let recorded = scores
+            .lock()
+            .map_err(|e| e.to_string())
+            .and_then(|mut book| {
+                let ended_ms = unix_time_ms();
+                book.update(|file| {
+                    match &result.head_to_head {
+                        Some(head_to_head) => {
+                            record_head_to_head(file, change.game_id, head_to_head, ended_ms);
+                            if !result.practice {
+                                rating::record_head_to_head(
+                                    file,
+                                    head_to_head,
+                                    &rating_settings,
+                                    max_name_length,
+                                    ended_ms,
+                                );
+                            }
+                        }
+                        None => {
+                            record(file, change.game_id, &result, ended_ms);
+                        }
+                    }
+                    Ok(())
+                })
+            });
+        if let Err(message) = recorded {
+            let _ = handle.emit(
+                "history-error",
+                HistoryError {
+                    game_id: change.game_id,
+                    message: format!("failed to record game {}: {}", change.game_id, message),
+                },
+            );
+        }
+    });
+}
+
+// Command to page through the history of ended games, newest first.
+#[tauri::command]
+pub fn get_run_history(
+    filter: Option<HistoryFilter>,
+    scores: tauri::State<Mutex<ScoreBook>>,
+) -> Result<HistoryPage, String> {
+    let book = scores.lock().map_err(|e| e.to_string())?;
+    page(book.db(), &filter.unwrap_or_default())
+}
+
+// Command to summarize a day ("YYYY-MM-DD"). `utc_offset_minutes` is the
+// local time zone's offset, e.g. 120 for UTC+2; the day is UTC without it.
+#[tauri::command]
+pub fn get_daily_summary(
+    date: String,
+    utc_offset_minutes: Option<i64>,
+    scores: tauri::State<Mutex<ScoreBook>>,
+) -> Result<DailySummary, String> {
+    let book = scores.lock().map_err(|e| e.to_string())?;
+    summary(book.file(), &date, utc_offset_minutes.unwrap_or(0) * 60_000)
+}
+
+// Command to count the games played per hour or day of `range`, e.g. for a
+// dashboard. `utc_offset_minutes` is the local time zone's offset, as with
+// get_daily_summary.
+#[tauri::command]
+pub fn get_usage_stats(
+    range: Option<TimeRange>,
+    bucket: Option<Bucket>,
+    utc_offset_minutes: Option<i64>,
+    scores: tauri::State<Mutex<ScoreBook>>,
+) -> Result<UsageStats, String> {
+    let book = scores.lock().map_err(|e| e.to_string())?;
+    usage(
+        book.db(),
+        range.unwrap_or_default(),
+        bucket.unwrap_or_default(),
+        utc_offset_minutes.unwrap_or(0) * 60_000,
+        unix_time_ms(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{Clock, GameConfig};
+
+    struct FixedClock;
+
+    impl Clock for FixedClock {
+        fn now_ms(&self) -> u64 {
+            0
+        }
+    }
+
+    fn result(aborted: bool, success: bool, elapsed_ms: u64) -> GameResult {
+        let mut game = GameManager::new(Arc::new(FixedClock));
+        game.start(GameConfig {
+            countdown_seconds: 0.0,
+            ..GameConfig::default()
+        })
+        .unwrap();
+        game.abort(None).unwrap();
+        GameResult {
+            aborted,
+            success,
+            elapsed_ms,
+            ..game.result().unwrap()
+        }
+    }
+
+    // A book on an in-memory database holding `file`.
+    fn book_of(file: ScoreFile) -> ScoreBook {
+        let connection = Connection::open_in_memory().unwrap();
+        let mut book = ScoreBook::load(connection, std::path::Path::new("missing.json")).unwrap();
+        book.update(|saved| {
+            *saved = file;
+            Ok(())
+        })
+        .unwrap();
+        book
+    }
+
+    #[test]
+    fn summarizes_a_local_day() {
+        let mut file = ScoreFile::new();
+        // 2024-05-31 is day 19874; UTC+2.
+        let day = 19874 * DAY_MS - 2 * HOUR_MS;
+        record(
+            &mut file,
+            0,
+            &result(false, true, 40_000),
+            day + 9 * HOUR_MS,
+        );
+        record(
+            &mut file,
+            0,
+            &result(false, true, 20_000),
+            day + 14 * HOUR_MS,
+        );
+        record(
+            &mut file,
+            0,
+            &result(false, false, 5_000),
+            day + 13 * HOUR_MS + 60_000,
+        );
+        record(&mut file, 0, &result(true, false, 0), day + 25 * HOUR_MS);
+
+        let summary = summary(&file, "2024-05-31", 2 * HOUR_MS as i64).unwrap();
+        assert_eq!(summary.runs, 3);
+        assert_eq!((summary.completed, summary.failed), (2, 1));
+        assert_eq!(summary.average_time_ms, Some(30_000));
+        assert_eq!(summary.busiest_hour, Some(13));
+        assert!(parse_date("2024-13-01").is_err());
+
+        let filter = HistoryFilter {
+            outcome: Some(Outcome::Aborted),
+            ..HistoryFilter::default()
+        };
+        let book = book_of(file);
+        assert_eq!(page(book.db(), &filter).unwrap().total, 1);
+        let newest = page(book.db(), &HistoryFilter::default()).unwrap().records[0].clone();
+        assert_eq!(newest.outcome, Outcome::Aborted);
+    }
+
+    #[test]
+    fn buckets_usage_without_gaps() {
+        let mut file = ScoreFile::new();
+        // 2024-05-31 is day 19874; UTC+2.
+        let day = 19874 * DAY_MS - 2 * HOUR_MS;
+        for (player, started_ms) in [
+            ("Ann", day + 14 * HOUR_MS),
+            ("ann", day + 14 * HOUR_MS + 600_000),
+            ("Ben", day + 16 * HOUR_MS + 60_000),
+        ] {
+            let mut result = result(false, true, 60_000);
+            result.config.player = Some(player.to_string());
+            result.hits = 2;
+            record(&mut file, 0, &result, started_ms + 60_000);
+        }
+        let book = book_of(file);
+        let db = book.db();
+
+        let range = TimeRange {
+            from_ms: Some(day + 14 * HOUR_MS),
+            to_ms: Some(day + 17 * HOUR_MS),
+        };
+        let stats = usage(db, range, Bucket::Hour, 2 * HOUR_MS as i64, 0).unwrap();
+        let runs: Vec<_> = stats.buckets.iter().map(|bucket| bucket.runs).collect();
+        assert_eq!(runs, [2, 0, 1]);
+        assert_eq!(stats.buckets[0].unique_players, 1);
+        assert_eq!(stats.buckets[0].total_hits, 4);
+        assert_eq!(stats.buckets[1].average_duration_ms, None);
+        assert_eq!((stats.runs, stats.unique_players), (3, 2));
+        assert_eq!(stats.busiest_hour, Some(14));
+
+        let days = usage(db, range, Bucket::Day, 2 * HOUR_MS as i64, 0).unwrap();
+        assert_eq!(days.buckets.len(), 1);
+        assert_eq!(days.buckets[0].start_ms, day);
+    }
+
+    #[test]
+    fn filters_by_the_tags_of_saved_runs() {
+        let mut file = ScoreFile::new();
+        for game_id in 0..3 {
+            let mut result = result(false, true, 40_000);
+            result.config.player = Some("Ann".to_string());
+            record(&mut file, game_id, &result, 0);
+        }
+        let run = |id: u64, tags: &[&str]| -> Run {
+            serde_json::from_value(serde_json::json!({
+                "id": id,
+                "player": "Ann",
+                "mode": "classic",
+                "elapsedMs": 40_000,
+                "hits": 0,
+                "penaltyMs": 0,
+                "timestampMs": 0,
+                "tags": tags,
+            }))
+            .unwrap()
+        };
+        for (game_id, run) in [(1, run(7, &["sensor 4 flaky"])), (2, run(8, &["birthday"]))] {
+            link_run(&mut file, game_id, &run);
+            file.runs.push(run);
+        }
+        // Game 0 is no longer among the latest records.
+        link_run(&mut file, 0, &run(9, &[]));
+        let linked: Vec<_> = file.history.iter().map(|record| record.run_id).collect();
+        assert_eq!(linked, [None, Some(7), Some(8)]);
+
+        let filter = |tag: &str| HistoryFilter {
+            tag: Some(tag.to_string()),
+            ..HistoryFilter::default()
+        };
+        let book = book_of(file);
+        let total = |tag: &str| page(book.db(), &filter(tag)).unwrap().total;
+        assert_eq!(total("BIRTHDAY "), 1);
+        assert_eq!(total("sensor 4 flaky"), 1);
+        assert_eq!(total("rain"), 0);
+        let player = HistoryFilter {
+            player: Some(" ANN".to_string()),
+            ..HistoryFilter::default()
+        };
+        assert_eq!(page(book.db(), &player).unwrap().total, 3);
+    }
+}
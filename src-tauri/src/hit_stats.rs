@@ -0,0 +1,166 @@
+// Which lasers catch players: hits per sensor for one game and across the
+// saved runs. Runs saved before their timeline was kept have no per-sensor
+// data and are only counted as such.
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use crate::game::Hit;
+use crate::scores::{Run, ScoreBook, TimeRange};
+
+// Hits of one sensor in one game.
+#[derive(Clone, Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SensorHits {
+    pub sensor: usize,
+    pub name: String,
+    pub count: u32,
+}
+
+// Hits of one sensor across runs.
+#[derive(Clone, Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SensorStats {
+    pub sensor: usize,
+    // Name of the sensor in its latest hit.
+    pub name: String,
+    pub total_hits: u64,
+    pub hits_per_run: f64,
+    // Share of the runs in which the sensor caught the player at least once.
+    pub run_percent: f64,
+}
+
+// Returned by get_sensor_hit_stats.
+#[derive(Clone, Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SensorHitStats {
+    // Runs the statistics are based on.
+    pub runs: usize,
+    // Runs in the range without per-sensor data.
+    pub runs_without_data: usize,
+    // By sensor index.
+    pub sensors: Vec<SensorStats>,
+}
+
+// Hits per sensor in a timeline, most hits first. Grace and voided hits
+// don't count.
+pub fn count_by_sensor(timeline: &[Hit]) -> Vec<SensorHits> {
+    let mut counts: BTreeMap<usize, SensorHits> = BTreeMap::new();
+    for hit in timeline
+        .iter()
+        .filter(|hit| !hit.grace && hit.voided.is_none())
+    {
+        let entry = counts.entry(hit.sensor).or_insert_with(|| SensorHits {
+            sensor: hit.sensor,
+            name: hit.name.clone(),
+            count: 0,
+        });
+        entry.count += 1;
+    }
+    let mut counts: Vec<SensorHits> = counts.into_values().collect();
+    counts.sort_by_key(|hits| std::cmp::Reverse(hits.count));
+    counts
+}
+
+fn stats<'a>(runs: impl Iterator<Item = &'a Run>) -> SensorHitStats {
+    let mut with_data = 0;
+    let mut runs_without_data = 0;
+    // Per sensor: name, total hits and runs caught in.
+    let mut sensors: BTreeMap<usize, (String, u64, u64)> = BTreeMap::new();
+    for run in runs {
+        let Some(timeline) = &run.timeline else {
+            runs_without_data += 1;
+            continue;
+        };
+        with_data += 1;
+        for hits in count_by_sensor(timeline) {
+            let entry = sensors.entry(hits.sensor).or_default();
+            entry.0 = hits.name;
+            entry.1 += u64::from(hits.count);
+            entry.2 += 1;
+        }
+    }
+    let per_run = |value: u64| match with_data {
+        0 => 0.0,
+        runs => value as f64 / runs as f64,
+    };
+    SensorHitStats {
+        runs: with_data,
+        runs_without_data,
+        sensors: sensors
+            .into_iter()
+            .map(|(sensor, (name, total_hits, caught))| SensorStats {
+                sensor,
+                name,
+                total_hits,
+                hits_per_run: per_run(total_hits),
+                run_percent: per_run(caught) * 100.0,
+            })
+            .collect(),
+    }
+}
+
+// Command to fetch the hits per sensor across the runs saved in `range`, or
+// all runs.
+#[tauri::command]
+pub fn get_sensor_hit_stats(
+    range: Option<TimeRange>,
+    scores: tauri::State<Mutex<ScoreBook>>,
+) -> Result<SensorHitStats, String> {
+    let range = range.unwrap_or_default();
+    let book = scores.lock().map_err(|e| e.to_string())?;
+    Ok(stats(
+        book.file()
+            .runs
+            .iter()
+            .filter(|run| range.contains(run.timestamp_ms)),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hit(sensor: usize, grace: bool) -> Hit {
+        Hit {
+            sensor,
+            name: format!("Laser {}", sensor + 1),
+            elapsed_ms: 0,
+            count: 0,
+            grace,
+            fatal: false,
+            penalty_ms: 0,
+            penalized_ms: 0,
+            voided: None,
+        }
+    }
+
+    #[test]
+    fn counts_hits_per_sensor_across_runs() {
+        let timeline = vec![hit(6, false), hit(2, false), hit(6, false), hit(6, true)];
+        let counts = count_by_sensor(&timeline);
+        assert_eq!(counts[0].name, "Laser 7");
+        assert_eq!(counts[0].count, 2);
+        assert_eq!(counts[1].count, 1);
+
+        let mut runs: Vec<Run> = serde_json::from_str(
+            r#"[
+                {"id": 1, "player": "a", "mode": "timeAttack", "elapsedMs": 1, "hits": 3,
+                 "penaltyMs": 0, "timestampMs": 1},
+                {"id": 2, "player": "a", "mode": "timeAttack", "elapsedMs": 1, "hits": 0,
+                 "penaltyMs": 0, "timestampMs": 2, "timeline": []},
+                {"id": 3, "player": "a", "mode": "timeAttack", "elapsedMs": 1, "hits": 0,
+                 "penaltyMs": 0, "timestampMs": 3}
+            ]"#,
+        )
+        .unwrap();
+        runs[2].timeline = Some(timeline);
+        let stats = stats(runs.iter());
+        assert_eq!(stats.runs, 2);
+        assert_eq!(stats.runs_without_data, 1);
+        let sensor = stats.sensors.iter().find(|s| s.sensor == 6).unwrap();
+        assert_eq!(sensor.total_hits, 2);
+        assert_eq!(sensor.hits_per_run, 1.0);
+        assert_eq!(sensor.run_percent, 50.0);
+    }
+}
@@ -0,0 +1,72 @@
+// Switching individual laser channels of the controller on and off. The
+// controller understands "laser:<index>:<on|off>".
+
+use std::sync::{Arc, Mutex};
+
+use crate::{write_line, SensorData, SerialManager};
+
+fn laser_command(index: usize, on: bool) -> String {
+    format!("laser:{}:{}", index, if on { "on" } else { "off" })
+}
+
+// Sends the command for one laser and remembers the state once it was written.
+fn switch_laser(
+    state: &Mutex<SerialManager>,
+    connection_id: Option<&str>,
+    index: usize,
+    on: bool,
+) -> Result<(), String> {
+    write_line(state, connection_id, laser_command(index, on), false)
+        .map_err(|e| format!("failed to switch laser {}: {}", index, e))?;
+
+    let mut manager = state.lock().map_err(|e| e.to_string())?;
+    if manager.laser_states.len() <= index {
+        manager.laser_states.resize(index + 1, None);
+    }
+    manager.laser_states[index] = Some(on);
+    Ok(())
+}
+
+// Command to switch one laser on or off.
+#[tauri::command(async)]
+pub fn set_laser_state(
+    index: usize,
+    on: bool,
+    connection_id: Option<String>,
+    state: tauri::State<Arc<Mutex<SerialManager>>>,
+) -> Result<(), String> {
+    switch_laser(&state, connection_id.as_deref(), index, on)
+}
+
+// Command to switch every laser on or off. The lasers are those reporting
+// sensor values, plus any that were switched before.
+#[tauri::command(async)]
+pub fn set_all_lasers(
+    on: bool,
+    connection_id: Option<String>,
+    state: tauri::State<Arc<Mutex<SerialManager>>>,
+    sensor_data: tauri::State<Arc<Mutex<SensorData>>>,
+) -> Result<(), String> {
+    let sensor_count = sensor_data
+        .lock()
+        .map_err(|e| e.to_string())?
+        .frame
+        .values
+        .len();
+    let known = state.lock().map_err(|e| e.to_string())?.laser_states.len();
+    let count = sensor_count.max(known);
+    if count == 0 {
+        return Err("the number of lasers is not known yet".to_string());
+    }
+    (0..count).try_for_each(|index| switch_laser(&state, connection_id.as_deref(), index, on))
+}
+
+// Command to fetch the last commanded state of every laser; null for lasers
+// that were never switched.
+#[tauri::command]
+pub fn get_laser_states(
+    state: tauri::State<Arc<Mutex<SerialManager>>>,
+) -> Result<Vec<Option<bool>>, String> {
+    let manager = state.lock().map_err(|e| e.to_string())?;
+    Ok(manager.laser_states.clone())
+}
@@ -1,42 +1,361 @@
-use std::io::{BufRead, BufReader};
-use std::sync::mpsc::{channel, Sender};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::sync::mpsc::{channel, sync_channel, Sender, SyncSender, TrySendError};
 use std::sync::{Arc, Mutex};
 use std::thread::{self, JoinHandle};
-use tauri::Emitter;
+use tauri::Manager;
 use tauri_plugin_store::StoreExt;
 
+mod achievements;
+mod calibration;
+mod game;
+mod history;
+mod hit_stats;
+mod lasers;
+mod midi;
+mod mock;
+mod pipeline;
+mod players;
+mod ports;
+mod protocol;
+mod rating;
+mod reader;
+mod rounds;
+mod scores;
+mod scoring;
+mod sensors;
+mod serial;
+mod serial_log;
+mod session;
+mod tcp;
+mod teams;
+mod tournament;
+mod udp;
+mod writer;
+
+use mock::FaultProfile;
+use pipeline::{
+    LineHandler, ResetWaiter, SensorCountCheck, SensorEventMode, SharedPongWaiter,
+    SharedResetWaiter,
+};
+use ports::{
+    find_port_by_serial, open_port, open_port_with_profile, serial_number_of, ConnectionProfile,
+    PortError, PortSettings, PortWatcher,
+};
+use protocol::{parse_separator, Keyword, LineSyntax, Protocol, PING_LINE};
+use reader::report_connection;
+use sensors::{
+    BeamDebounce, DisabledSensors, SensorNames, SensorThresholds, SmoothingSettings, ValidRange,
+    ValueScaling,
+};
+use serial::SerialReader;
+use serial_log::{LogFormat, SerialLog, SharedLog};
+use writer::{WriteRequest, ACK_ATTEMPTS, ACK_TIMEOUT_MS, WRITE_QUEUE_SIZE};
+
+// Version of the laser-sensor-data payload, bumped whenever its shape
+// changes. Version 2 widened values from 16 to 32 bits; version 3 added
+// `disabled`.
+const SENSOR_FRAME_VERSION: u32 = 3;
+
+// One set of sensor values as emitted in laser-sensor-data.
+#[derive(Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SensorFrame {
+    schema_version: u32,
+    // Counts the parsed lines of the connection that sent the values.
+    seq: u64,
+    // Milliseconds since that connection started, from a monotonic clock.
+    timestamp_ms: u64,
+    values: Vec<u32>,
+    // Sensors left out of hit detection, shown greyed out.
+    disabled: Vec<usize>,
+}
+
+impl Default for SensorFrame {
+    fn default() -> Self {
+        Self {
+            schema_version: SENSOR_FRAME_VERSION,
+            seq: 0,
+            timestamp_ms: 0,
+            values: Vec::new(),
+            disabled: Vec::new(),
+        }
+    }
+}
+
 // Store parsed sensor values for use across the application
 #[derive(Clone, serde::Serialize)]
 struct SensorData {
-    values: Vec<u16>,
+    // Latest merged frame; seq and timestamp are those of the last update.
+    frame: SensorFrame,
+    // Latest values per connection, in the order the connections first sent data.
+    // The frame's values are their concatenation, so a second device's sensors
+    // follow the first's.
+    #[serde(skip)]
+    sources: Vec<(String, Vec<u32>)>,
+    // When the values were last updated by any connection.
+    #[serde(skip)]
+    updated_at: Option<std::time::Instant>,
+    // Whether each beam is currently broken, by index among all sensors.
+    broken: Vec<bool>,
 }
 
 // Store parsed sensor values for use across the application
 impl SensorData {
     fn new() -> Self {
-        Self { values: Vec::new() }
+        Self {
+            frame: SensorFrame::default(),
+            sources: Vec::new(),
+            updated_at: None,
+            broken: Vec::new(),
+        }
+    }
+
+    // Replaces the values of one connection and returns the index its values start at.
+    fn update(&mut self, connection_id: &str, frame: &SensorFrame) -> usize {
+        let position = match self.sources.iter().position(|(id, _)| id == connection_id) {
+            Some(position) => position,
+            None => {
+                self.sources.push((connection_id.to_string(), Vec::new()));
+                self.sources.len() - 1
+            }
+        };
+        self.sources[position].1 = frame.values.clone();
+        self.merge();
+        self.frame.seq = frame.seq;
+        self.frame.timestamp_ms = frame.timestamp_ms;
+        self.updated_at = Some(std::time::Instant::now());
+        self.sources[..position].iter().map(|(_, v)| v.len()).sum()
+    }
+
+    // Index the values of a connection start at, or where they would start.
+    fn offset(&self, connection_id: &str) -> usize {
+        self.sources
+            .iter()
+            .take_while(|(id, _)| id != connection_id)
+            .map(|(_, values)| values.len())
+            .sum()
     }
 
-    fn update(&mut self, new_values: Vec<u16>) {
-        self.values = new_values;
+    // Records whether a beam is broken and returns whether that changed.
+    fn set_broken(&mut self, sensor: usize, broken: bool) -> bool {
+        if self.broken.len() <= sensor {
+            self.broken.resize(sensor + 1, false);
+        }
+        let changed = self.broken[sensor] != broken;
+        self.broken[sensor] = broken;
+        changed
+    }
+
+    // Forgets the values of a connection that was stopped.
+    fn remove(&mut self, connection_id: &str) {
+        self.sources.retain(|(id, _)| id != connection_id);
+        self.merge();
+        // Indices of the remaining sensors may have shifted.
+        self.broken.clear();
+    }
+
+    fn merge(&mut self) {
+        self.frame.values = self
+            .sources
+            .iter()
+            .flat_map(|(_, values)| values.iter().copied())
+            .collect();
     }
 }
 
-// A simple manager to hold the serial reading thread and a channel to stop it.
-struct SerialManager {
+// Result of get_sensor_data. NoData means nothing has arrived since connecting,
+// which the UI shows differently from a reading of all zeros.
+#[derive(serde::Serialize)]
+#[serde(tag = "status", rename_all = "camelCase")]
+enum SensorSnapshot {
+    NoData,
+    Data {
+        values: Vec<u32>,
+        seq: u64,
+        // Milliseconds since the values were updated.
+        #[serde(rename = "ageMs")]
+        age_ms: u64,
+    },
+}
+
+// Number of malformed lines kept as samples in ParseErrorStats.
+const PARSE_ERROR_SAMPLES: usize = 5;
+
+// Lines of a connection that could not be parsed.
+#[derive(Clone, Default, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ParseErrorStats {
+    total: u64,
+    // The last few malformed lines, oldest first.
+    recent: std::collections::VecDeque<String>,
+}
+
+impl ParseErrorStats {
+    fn record(&mut self, line: &str) {
+        self.total += 1;
+        if self.recent.len() == PARSE_ERROR_SAMPLES {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(line.to_string());
+    }
+}
+
+// Counters about the active connection, returned by get_serial_stats.
+#[derive(Clone, Default, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SerialStats {
+    // Binary frames rejected because of a CRC mismatch.
+    bad_frames: u64,
+    parse_errors: ParseErrorStats,
+    // Non-ASCII bytes dropped from received lines.
+    dropped_bytes: u64,
+    // Lines missing according to the firmware's "#<counter>:" prefix.
+    dropped_frames: u64,
+    // Values outside the valid range by sensor index.
+    out_of_range: Vec<u64>,
+    // Times the reading thread panicked and was restarted.
+    thread_crashes: u32,
+    // Whether sensor streaming is paused, see pause_serial.
+    paused: bool,
+    // Beam flaps by sensor index, see BeamTracker.
+    flaps: Vec<u64>,
+    // Lines (or binary frames) handled per wakeup of the reading thread, the
+    // latest and the most so far. A growing number means a backlog.
+    lines_per_wakeup: u32,
+    max_lines_per_wakeup: u32,
+}
+
+impl SerialStats {
+    // Stores the flap counts of the sensors starting at `offset`.
+    fn record_flaps(&mut self, offset: usize, flaps: &[u64]) {
+        if self.flaps.len() < offset + flaps.len() {
+            self.flaps.resize(offset + flaps.len(), 0);
+        }
+        self.flaps[offset..offset + flaps.len()].copy_from_slice(flaps);
+    }
+
+    fn record_wakeup(&mut self, lines: u32) {
+        self.lines_per_wakeup = lines;
+        self.max_lines_per_wakeup = self.max_lines_per_wakeup.max(lines);
+    }
+}
+
+// Where the lines of a connection come from.
+#[derive(Clone, Copy, PartialEq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+enum SourceKind {
+    Serial,
+    Mock,
+    Tcp,
+    Udp,
+    Replay,
+    Midi,
+}
+
+// Summary of an open connection, returned by list_connections.
+#[derive(Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ConnectionInfo {
+    connection_id: String,
+    source: SourceKind,
+    port: String,
+    baud_rate: Option<u32>,
+}
+
+// Id used when configure_serial is called without a connection_id.
+const DEFAULT_CONNECTION_ID: &str = "default";
+// Store file shared with the frontend.
+const STORE_FILE: &str = "laser-config.dat";
+// Default debounce period for the start button and buzzer in milliseconds.
+const DEFAULT_DEBOUNCE_MS: u64 = 2000;
+// Default time the start button or buzzer must be quiet before a new press.
+const DEFAULT_QUIET_MS: u64 = 200;
+// Default time without parseable data before serial-stalled is emitted.
+const DEFAULT_STALL_TIMEOUT_MS: u64 = 3000;
+// Default time after opening a port during which received data is discarded.
+const DEFAULT_SETTLE_MS: u64 = 250;
+
+// Debounce periods shared with the reading thread so they can change live.
+// The per-keyword periods are dead times after an accepted event; the quiet
+// period is how long a keyword must not have been seen before it counts
+// again, see KeywordDebouncer. A value of 0 disables either.
+struct DebounceSettings {
+    buzzer_ms: AtomicU64,
+    start_ms: AtomicU64,
+    quiet_ms: AtomicU64,
+}
+
+impl DebounceSettings {
+    fn new() -> Self {
+        Self {
+            buzzer_ms: AtomicU64::new(DEFAULT_DEBOUNCE_MS),
+            start_ms: AtomicU64::new(DEFAULT_DEBOUNCE_MS),
+            quiet_ms: AtomicU64::new(DEFAULT_QUIET_MS),
+        }
+    }
+
+    fn set(&self, buzzer_ms: u64, start_ms: u64) {
+        self.buzzer_ms.store(buzzer_ms, Ordering::Relaxed);
+        self.start_ms.store(start_ms, Ordering::Relaxed);
+    }
+}
+
+// How long send_serial_command waits for its turn in the queue.
+const WRITE_TIMEOUT_MS: u64 = 2000;
+
+// Write handle to the open port, swapped by the reading thread on reconnect.
+type SharedWriter = Arc<Mutex<Option<Box<dyn serialport::SerialPort>>>>;
+
+// Default number of reopen attempts before giving up on a lost port.
+const DEFAULT_RECONNECT_ATTEMPTS: u32 = 10;
+
+// One open serial port with its reading thread and a channel to stop it.
+struct SerialConnection {
     reading_thread: Option<JoinHandle<()>>,
     stop_sender: Option<Sender<()>>,
+    source: SourceKind,
+    // Port settings of the connection, kept for reconnecting.
+    port: String,
+    settings: Option<PortSettings>,
+    // Outgoing commands are queued and written in order by a writer thread.
+    writer: SharedWriter,
+    write_queue: Option<SyncSender<WriteRequest>>,
+    writing_thread: Option<JoinHandle<()>>,
+    // Passes acknowledged command ids from the reading to the writer thread.
+    ack_sender: Option<Sender<u32>>,
+    // Set while stopping, so queued commands fail instead of being written.
+    closing: Arc<AtomicBool>,
+    stats: Arc<Mutex<SerialStats>>,
+    sensor_count: Arc<SensorCountCheck>,
+    reset_waiter: SharedResetWaiter,
+    pong_waiter: SharedPongWaiter,
+    // Feeds lines into the simulated controller of a mock connection.
+    mock_input: Option<Sender<String>>,
 }
 
-impl SerialManager {
-    fn new() -> Self {
+impl SerialConnection {
+    fn new(port: String, settings: Option<PortSettings>, source: SourceKind) -> Self {
         Self {
             reading_thread: None,
             stop_sender: None,
+            source,
+            port,
+            settings,
+            writer: Arc::new(Mutex::new(None)),
+            write_queue: None,
+            writing_thread: None,
+            ack_sender: None,
+            closing: Arc::new(AtomicBool::new(false)),
+            stats: Arc::new(Mutex::new(SerialStats::default())),
+            sensor_count: Arc::new(SensorCountCheck::new(0, false)),
+            reset_waiter: Arc::new(Mutex::new(None)),
+            pong_waiter: Arc::new(Mutex::new(None)),
+            mock_input: None,
         }
     }
 
-    // Stops any running serial thread.
+    // Stops the serial threads of this connection.
     fn stop(&mut self) {
         if let Some(sender) = self.stop_sender.take() {
             let _ = sender.send(());
@@ -44,6 +363,198 @@ impl SerialManager {
         if let Some(handle) = self.reading_thread.take() {
             let _ = handle.join();
         }
+        // Dropping the queue sender ends the writer thread once the pending
+        // commands have failed.
+        self.closing.store(true, Ordering::Relaxed);
+        self.write_queue = None;
+        if let Some(handle) = self.writing_thread.take() {
+            let _ = handle.join();
+        }
+        if let Ok(mut writer) = self.writer.lock() {
+            *writer = None;
+        }
+    }
+
+    // Starts the writer thread for a freshly opened port.
+    fn start_writer(
+        &mut self,
+        serial_port: &dyn serialport::SerialPort,
+        app_handle: &tauri::AppHandle,
+        connection_id: &str,
+    ) -> Result<(), String> {
+        let write_port = serial_port
+            .try_clone()
+            .map_err(|e| format!("failed to clone port: {}", e))?;
+        *self.writer.lock().map_err(|e| e.to_string())? = Some(write_port);
+
+        let (queue_tx, queue_rx) = sync_channel::<WriteRequest>(WRITE_QUEUE_SIZE);
+        let (ack_tx, ack_rx) = channel();
+        let handle = writer::spawn_writer(
+            Arc::clone(&self.writer),
+            queue_rx,
+            ack_rx,
+            Arc::clone(&self.closing),
+            app_handle.clone(),
+            connection_id.to_string(),
+        );
+
+        self.write_queue = Some(queue_tx);
+        self.writing_thread = Some(handle);
+        self.ack_sender = Some(ack_tx);
+        Ok(())
+    }
+}
+
+// Settings shared by the manager with every reading thread, so commands can
+// change them without reconnecting.
+#[derive(Clone)]
+struct LiveSettings {
+    debounce: Arc<DebounceSettings>,
+    // When set, every received line is also emitted verbatim as serial-raw-line.
+    raw_monitor: Arc<AtomicBool>,
+    // Log file all received lines are appended to while logging is on.
+    traffic_log: SharedLog,
+    // Session file received lines are recorded to for replay_session.
+    session_recording: SharedLog,
+    sensor_names: Arc<SensorNames>,
+    valid_range: Arc<ValidRange>,
+    scaling: Arc<ValueScaling>,
+    thresholds: Arc<SensorThresholds>,
+    disabled_sensors: Arc<DisabledSensors>,
+    beam_debounce: Arc<BeamDebounce>,
+    smoothing: Arc<SmoothingSettings>,
+    // Maximum number of laser-sensor-data events per second; 0 means no limit.
+    sensor_event_rate_hz: Arc<AtomicU32>,
+    sensor_event_mode: Arc<SensorEventMode>,
+    syntax: Arc<std::sync::RwLock<LineSyntax>>,
+    // While set, lines are still read but only keywords are acted on.
+    paused: Arc<AtomicBool>,
+    // Faults the mock controller injects.
+    mock_faults: Arc<std::sync::RwLock<FaultProfile>>,
+}
+
+impl LiveSettings {
+    fn new() -> Self {
+        Self {
+            debounce: Arc::new(DebounceSettings::new()),
+            raw_monitor: Arc::new(AtomicBool::new(false)),
+            traffic_log: Arc::new(Mutex::new(None)),
+            session_recording: Arc::new(Mutex::new(None)),
+            sensor_names: Arc::new(SensorNames::new()),
+            valid_range: Arc::new(ValidRange::new()),
+            scaling: Arc::new(ValueScaling::new()),
+            thresholds: Arc::new(SensorThresholds::new()),
+            disabled_sensors: Arc::new(DisabledSensors::new()),
+            beam_debounce: Arc::new(BeamDebounce::new()),
+            smoothing: Arc::new(SmoothingSettings::new()),
+            sensor_event_rate_hz: Arc::new(AtomicU32::new(0)),
+            sensor_event_mode: Arc::new(SensorEventMode::new()),
+            syntax: Arc::new(std::sync::RwLock::new(LineSyntax::default())),
+            paused: Arc::new(AtomicBool::new(false)),
+            mock_faults: Arc::new(std::sync::RwLock::new(FaultProfile::default())),
+        }
+    }
+}
+
+// A simple manager to hold the serial connections, keyed by connection id.
+struct SerialManager {
+    connections: BTreeMap<String, SerialConnection>,
+    live: LiveSettings,
+    // Last on/off state commanded for each laser; None until one was sent.
+    laser_states: Vec<Option<bool>>,
+}
+
+impl SerialManager {
+    fn new() -> Self {
+        Self {
+            connections: BTreeMap::new(),
+            live: LiveSettings::new(),
+            laser_states: Vec::new(),
+        }
+    }
+
+    // Stops and removes one connection, returning whether it existed.
+    fn stop_connection(&mut self, connection_id: &str) -> bool {
+        match self.connections.remove(connection_id) {
+            Some(mut connection) => {
+                connection.stop();
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Stops all running serial threads.
+    fn stop(&mut self) {
+        for connection in self.connections.values_mut() {
+            connection.stop();
+        }
+        self.connections.clear();
+    }
+
+    // Looks up a connection by id. Without an id the only open connection is
+    // used, falling back to the default id when several are open.
+    fn connection(&self, connection_id: Option<&str>) -> Result<&SerialConnection, String> {
+        let connection = match connection_id {
+            Some(id) => self.connections.get(id),
+            None if self.connections.len() == 1 => self.connections.values().next(),
+            None => self.connections.get(DEFAULT_CONNECTION_ID),
+        };
+        connection.ok_or_else(|| "serial port is not connected".to_string())
+    }
+
+    // Creates the line handler for a new connection, sharing the live settings.
+    fn line_handler(
+        &self,
+        app_handle: &tauri::AppHandle,
+        connection_id: &str,
+        connection: &SerialConnection,
+        sensor_data: &Arc<Mutex<SensorData>>,
+        stall_timeout_ms: u64,
+    ) -> LineHandler {
+        LineHandler::new(
+            app_handle.clone(),
+            connection_id.to_string(),
+            connection,
+            Arc::clone(sensor_data),
+            self.live.clone(),
+            stall_timeout_ms,
+        )
+    }
+}
+
+// Reads a numeric setting stored by the backend in the config store.
+fn read_store_u64(app_handle: &tauri::AppHandle, key: &str) -> Option<u64> {
+    app_handle.store(STORE_FILE).ok()?.get(key)?.as_u64()
+}
+
+// Reads a text setting stored by the backend in the config store.
+fn read_store_string(app_handle: &tauri::AppHandle, key: &str) -> Option<String> {
+    Some(
+        app_handle
+            .store(STORE_FILE)
+            .ok()?
+            .get(key)?
+            .as_str()?
+            .to_string(),
+    )
+}
+
+// Loads the configurable parts of the text protocol, defaulting what wasn't saved.
+fn load_line_syntax(app_handle: &tauri::AppHandle) -> LineSyntax {
+    let default = LineSyntax::default();
+    let keywords = app_handle
+        .store(STORE_FILE)
+        .ok()
+        .and_then(|store| store.get("arduinoSettings.serialKeywords"))
+        .and_then(|value| serde_json::from_value(value).ok());
+    LineSyntax {
+        keywords: keywords.unwrap_or(default.keywords),
+        hit_prefix: read_store_string(app_handle, "arduinoSettings.hitPrefix")
+            .unwrap_or(default.hit_prefix),
+        clear_prefix: read_store_string(app_handle, "arduinoSettings.clearPrefix")
+            .unwrap_or(default.clear_prefix),
+        separator: default.separator,
     }
 }
 
@@ -55,12 +566,27 @@ fn list_ports() -> Result<Vec<String>, String> {
         .map(|ports| ports.into_iter().map(|p| p.port_name).collect())
 }
 
+// Command to check that a port can be opened. See
+// ports::check_connection_detailed for a check that also looks at the data.
 #[tauri::command]
-fn check_connection(port: String, baud_rate: u32) -> Result<bool, String> {
-    let port_result = serialport::new(port.clone(), baud_rate)
-        .timeout(std::time::Duration::from_millis(1000))
-        .open();
-    match port_result {
+fn check_connection(
+    port: String,
+    baud_rate: u32,
+    data_bits: Option<String>,
+    parity: Option<String>,
+    stop_bits: Option<String>,
+    flow_control: Option<String>,
+    read_timeout_ms: Option<u64>,
+) -> Result<bool, String> {
+    let settings = PortSettings::parse(
+        baud_rate,
+        data_bits.as_deref(),
+        parity.as_deref(),
+        stop_bits.as_deref(),
+        flow_control.as_deref(),
+    )?
+    .with_read_timeout(read_timeout_ms)?;
+    match open_port(&port, &settings) {
         Ok(_) => Ok(true),
         Err(e) => {
             eprintln!("failed to open port: {}", e);
@@ -69,125 +595,732 @@ fn check_connection(port: String, baud_rate: u32) -> Result<bool, String> {
     }
 }
 
-// Command to configure and start reading from a serial port.
-#[tauri::command]
+// Command to configure and start reading from a serial port. Runs off the
+// main thread since Bluetooth ports can take several seconds to open.
+#[tauri::command(async)]
+#[allow(clippy::too_many_arguments)]
 fn configure_serial(
-    port: String,
+    port: Option<String>,
     baud_rate: u32,
+    max_reconnect_attempts: Option<u32>,
+    protocol: Option<String>,
+    connection_id: Option<String>,
+    stall_timeout_ms: Option<u64>,
+    data_bits: Option<String>,
+    parity: Option<String>,
+    stop_bits: Option<String>,
+    flow_control: Option<String>,
+    expected_sensor_count: Option<usize>,
+    drop_mismatched_lines: Option<bool>,
+    read_timeout_ms: Option<u64>,
+    connection_profile: Option<String>,
+    settle_ms: Option<u64>,
+    fallback_bauds: Option<Vec<u32>>,
+    separator: Option<String>,
+    device_serial: Option<String>,
     app_handle: tauri::AppHandle,
     state: tauri::State<Arc<Mutex<SerialManager>>>,
     sensor_data: tauri::State<Arc<Mutex<SensorData>>>,
-) -> Result<(), String> {
+) -> Result<(), PortError> {
+    // A device serial number finds the port even after its name changed.
+    let port = match (port, device_serial) {
+        (_, Some(device_serial)) => find_port_by_serial(&device_serial)?,
+        (Some(port), None) => port,
+        (None, None) => {
+            return Err("either a port or a device serial number is required"
+                .to_string()
+                .into())
+        }
+    };
+    let protocol = Protocol::parse(protocol.as_deref())?;
+    let separator = parse_separator(separator.as_deref())?;
+    let profile = ConnectionProfile::parse(connection_profile.as_deref(), &port)?;
+    let settings = PortSettings::parse(
+        baud_rate,
+        data_bits.as_deref(),
+        parity.as_deref(),
+        stop_bits.as_deref(),
+        flow_control.as_deref(),
+    )?
+    .with_read_timeout(read_timeout_ms)?;
+    // XON/XOFF bytes can appear inside binary frames and would be swallowed.
+    if protocol == Protocol::Binary && settings.flow_control == serialport::FlowControl::Software {
+        return Err(
+            "software flow control cannot be used with the binary protocol"
+                .to_string()
+                .into(),
+        );
+    }
+    let connection_id = connection_id.unwrap_or_else(|| DEFAULT_CONNECTION_ID.to_string());
+
     // Lock our SerialManager state.
     let mut manager = state.lock().map_err(|e| e.to_string())?;
-    // Stop any existing thread.
-    manager.stop();
+    if let Some((other_id, _)) = manager
+        .connections
+        .iter()
+        .find(|(id, connection)| **id != connection_id && connection.port == port)
+    {
+        return Err(PortError::PortBusy {
+            hint: format!("stop connection {} first", other_id),
+            message: format!("port {} is already used by connection {}", port, other_id),
+            port,
+        });
+    }
+    // Stop any existing thread for this connection, and replace a running mock or replay.
+    manager.stop_connection(&connection_id);
+    let mock_ids: Vec<String> = manager
+        .connections
+        .iter()
+        .filter(|(_, connection)| {
+            matches!(connection.source, SourceKind::Mock | SourceKind::Replay)
+        })
+        .map(|(id, _)| id.clone())
+        .collect();
+    for id in mock_ids {
+        manager.stop_connection(&id);
+        sensor_data.lock().map_err(|e| e.to_string())?.remove(&id);
+    }
 
     // Try opening the serial port.
-    let port_result = serialport::new(port.clone(), baud_rate)
-        .timeout(std::time::Duration::from_millis(1000))
-        .open();
-    let serial_port = port_result.map_err(|e| format!("failed to open port: {}", e))?;
+    let serial_port = open_port_with_profile(&port, &settings, profile)
+        .map_err(|e| PortError::open_failed(&port, &e))?;
+    // Drop frames the OS buffered while nobody was reading the port.
+    serial_port
+        .clear(serialport::ClearBuffer::Input)
+        .map_err(|e| format!("failed to clear the read buffer: {}", e))?;
+    let max_reconnect_attempts = max_reconnect_attempts.unwrap_or(profile.reconnect_attempts());
+    let mut connection =
+        SerialConnection::new(port.clone(), Some(settings.clone()), SourceKind::Serial);
+    connection.start_writer(serial_port.as_ref(), &app_handle, &connection_id)?;
+    // Without an explicit count the first valid line sets the expectation.
+    connection.sensor_count = Arc::new(SensorCountCheck::new(
+        expected_sensor_count.unwrap_or(0),
+        drop_mismatched_lines.unwrap_or(false),
+    ));
+    let writer = Arc::clone(&connection.writer);
+    let stats = Arc::clone(&connection.stats);
+
+    // Load the persisted debounce periods so they survive restarts.
+    let debounce = Arc::clone(&manager.live.debounce);
+    debounce.set(
+        read_store_u64(&app_handle, "arduinoSettings.buzzerDebounceMs")
+            .unwrap_or(DEFAULT_DEBOUNCE_MS),
+        read_store_u64(&app_handle, "arduinoSettings.startDebounceMs")
+            .unwrap_or(DEFAULT_DEBOUNCE_MS),
+    );
+    debounce.quiet_ms.store(
+        read_store_u64(&app_handle, "arduinoSettings.keywordQuietMs").unwrap_or(DEFAULT_QUIET_MS),
+        Ordering::Relaxed,
+    );
+    manager.live.paused.store(false, Ordering::Relaxed);
+    manager
+        .live
+        .syntax
+        .write()
+        .map_err(|e| e.to_string())?
+        .separator = separator;
+    manager.live.valid_range.load(&app_handle);
+    manager.live.scaling.load(&app_handle);
+    manager.live.thresholds.load(&app_handle);
+    manager.live.beam_debounce.load(&app_handle);
+
+    // An explicit stall timeout is remembered for the next connection.
+    let stall_timeout_ms = match stall_timeout_ms {
+        Some(timeout_ms) => {
+            if let Ok(store) = app_handle.store(STORE_FILE) {
+                store.set("arduinoSettings.stallTimeoutMs", timeout_ms);
+            }
+            timeout_ms
+        }
+        None => read_store_u64(&app_handle, "arduinoSettings.stallTimeoutMs")
+            .unwrap_or(profile.stall_timeout_ms()),
+    };
+
+    let mut handler = manager.line_handler(
+        &app_handle,
+        &connection_id,
+        &connection,
+        sensor_data.inner(),
+        stall_timeout_ms,
+    );
+    handler.settle(settle_ms.unwrap_or(DEFAULT_SETTLE_MS));
 
     // Create a channel to signal the thread to stop.
     let (stop_tx, stop_rx) = channel();
 
-    // Clone the app handle so the thread can emit events.
-    let app_handle_clone = app_handle.clone();
+    let reader = SerialReader {
+        port: port.clone(),
+        settings,
+        protocol,
+        profile,
+        max_reconnect_attempts,
+        fallback_bauds: fallback_bauds.unwrap_or_default(),
+        handler,
+        stats,
+        writer,
+        syntax: Arc::clone(&manager.live.syntax),
+        stop_rx,
+    };
+    let handle = thread::spawn(move || reader.supervise(serial_port));
 
-    // Properly clone the inner Arc for each state
-    let sensor_data_clone = Arc::clone(sensor_data.inner());
+    // Save our thread handle and stop sender in the manager.
+    connection.reading_thread = Some(handle);
+    connection.stop_sender = Some(stop_tx);
+    manager.connections.insert(connection_id, connection);
+    report_connection(&app_handle, &port, true);
+    // Remember the device so the next launch can find it by identity.
+    if let Some(device_serial) = serial_number_of(&port) {
+        if let Ok(store) = app_handle.store(STORE_FILE) {
+            store.set("arduinoSettings.deviceSerial", device_serial);
+        }
+    }
 
-    let handle = thread::spawn(move || {
-        let mut reader = BufReader::new(serial_port);
-        let mut last_buzzer_time = std::time::Instant::now();
-        let mut last_start_time = std::time::Instant::now();
-        let mut last_message = String::new();
+    Ok(())
+}
 
-        // Define the debounce period in milliseconds
-        const DEBOUNCE_MS: u128 = 2000; // 2 second
+// Command to change the start/buzzer debounce periods without reconnecting,
+// and optionally the quiet period that filters contact bounce.
+#[tauri::command]
+fn set_debounce_ms(
+    buzzer_ms: u64,
+    start_ms: u64,
+    quiet_ms: Option<u64>,
+    app_handle: tauri::AppHandle,
+    state: tauri::State<Arc<Mutex<SerialManager>>>,
+) -> Result<(), String> {
+    let manager = state.lock().map_err(|e| e.to_string())?;
+    manager.live.debounce.set(buzzer_ms, start_ms);
 
-        loop {
-            // Check if a stop signal was received.
-            if let Ok(_) = stop_rx.try_recv() {
-                break;
-            }
-            let mut line = String::new();
-            // Try reading a line from the serial port.
-            match reader.read_line(&mut line) {
-                Ok(n) if n > 0 => {
-                    let trimmed = line.trim().to_string();
-
-                    // Ignore empty lines or duplicates of the last message
-                    if trimmed.is_empty() || trimmed == last_message {
-                        continue;
-                    }
-                    last_message = trimmed.clone();
-
-                    let now = std::time::Instant::now();
-                    // Special case for "buzzer" message with proper debounce using milliseconds
-                    if trimmed == "buzzer" {
-                        if now.duration_since(last_buzzer_time).as_millis() >= DEBOUNCE_MS {
-                            // println!("Emitting buzzer event (debounced)");
-                            let _ = app_handle_clone.emit("buzzer", true);
-                            last_buzzer_time = now;
-                        } else {
-                            // println!("Skipping buzzer event (debounce period)");
-                        }
-                    } else if trimmed == "start" {
-                        if now.duration_since(last_start_time).as_millis() >= DEBOUNCE_MS {
-                            // println!("Emitting start-button event (debounced)");
-                            let _ = app_handle_clone.emit("start-button", true);
-                            last_start_time = now;
-                        } else {
-                            // println!("Skipping start event (debounce period)");
-                        }
-                    } else {
-                        // Parse comma separated values into integers
-                        let values: Result<Vec<u16>, _> =
-                            trimmed.split(',').map(|s| s.parse::<u16>()).collect();
-
-                        if let Ok(parsed_values) = values {
-                            // Update shared sensor data
-                            if let Ok(mut sensor_state) = sensor_data_clone.lock() {
-                                sensor_state.update(parsed_values.clone());
-                            }
-
-                            // Emit formatted data
-                            let _ = app_handle_clone.emit("laser-sensor-data", parsed_values);
-                        } else {
-                            // Forward parse errors to the frontend.
-                            let _ = app_handle_clone
-                                .emit("serial-error", format!("parse error: {}", trimmed));
-                        }
-                    }
-                }
-                Ok(_) => {
-                    // No data was available; sleep briefly.
-                    thread::sleep(std::time::Duration::from_millis(10));
-                }
-                Err(e) => {
-                    // Forward read errors to the frontend.
-                    // println!("Serial read error: {}", e);
-                    let _ = app_handle_clone.emit("serial-error", format!("read error: {}", e));
-                    thread::sleep(std::time::Duration::from_millis(300));
-                }
-            }
+    // Persist the values so configure_serial picks them up after a restart.
+    let store = app_handle.store(STORE_FILE).map_err(|e| e.to_string())?;
+    store.set("arduinoSettings.buzzerDebounceMs", buzzer_ms);
+    store.set("arduinoSettings.startDebounceMs", start_ms);
+    if let Some(quiet_ms) = quiet_ms {
+        manager
+            .live
+            .debounce
+            .quiet_ms
+            .store(quiet_ms, Ordering::Relaxed);
+        store.set("arduinoSettings.keywordQuietMs", quiet_ms);
+    }
+    Ok(())
+}
+
+// Command to toggle the raw serial monitor without reopening the port.
+#[tauri::command]
+fn set_raw_monitor(
+    enabled: bool,
+    state: tauri::State<Arc<Mutex<SerialManager>>>,
+) -> Result<(), String> {
+    let manager = state.lock().map_err(|e| e.to_string())?;
+    manager.live.raw_monitor.store(enabled, Ordering::Relaxed);
+    Ok(())
+}
+
+// Command to stop acting on sensor data without closing the port, e.g. during
+// score entry. Lines keep being read so buffers don't fill up, and start and
+// buzzer keywords still come through.
+#[tauri::command]
+fn pause_serial(state: tauri::State<Arc<Mutex<SerialManager>>>) -> Result<(), String> {
+    let manager = state.lock().map_err(|e| e.to_string())?;
+    manager.live.paused.store(true, Ordering::Relaxed);
+    Ok(())
+}
+
+// Command to resume sensor data after pause_serial.
+#[tauri::command]
+fn resume_serial(state: tauri::State<Arc<Mutex<SerialManager>>>) -> Result<(), String> {
+    let manager = state.lock().map_err(|e| e.to_string())?;
+    manager.live.paused.store(false, Ordering::Relaxed);
+    Ok(())
+}
+
+// Command to change the prefixes of "hit:<index>" / "clear:<index>" messages,
+// e.g. to "b:" and "c:". An empty prefix disables that message.
+#[tauri::command]
+fn set_event_prefixes(
+    hit_prefix: String,
+    clear_prefix: String,
+    app_handle: tauri::AppHandle,
+    state: tauri::State<Arc<Mutex<SerialManager>>>,
+) -> Result<(), String> {
+    if !hit_prefix.is_empty() && hit_prefix == clear_prefix {
+        return Err("hit and clear prefixes must differ".to_string());
+    }
+    let store = app_handle.store(STORE_FILE).map_err(|e| e.to_string())?;
+    store.set("arduinoSettings.hitPrefix", hit_prefix.clone());
+    store.set("arduinoSettings.clearPrefix", clear_prefix.clone());
+
+    let manager = state.lock().map_err(|e| e.to_string())?;
+    let mut syntax = manager.live.syntax.write().map_err(|e| e.to_string())?;
+    syntax.hit_prefix = hit_prefix;
+    syntax.clear_prefix = clear_prefix;
+    Ok(())
+}
+
+// Command to map received lines to the events they trigger, e.g.
+// {"BTN_START": "start", "BTN_STOP": "buzzer"}. Lines that aren't mapped are
+// parsed as sensor values.
+#[tauri::command]
+fn set_serial_keywords(
+    map: BTreeMap<String, Keyword>,
+    app_handle: tauri::AppHandle,
+    state: tauri::State<Arc<Mutex<SerialManager>>>,
+) -> Result<(), String> {
+    // Lines are trimmed before matching, so keys are too.
+    let keywords: BTreeMap<String, Keyword> = map
+        .into_iter()
+        .map(|(line, keyword)| (line.trim().to_string(), keyword))
+        .collect();
+    if keywords.contains_key("") {
+        return Err("keywords must not be empty".to_string());
+    }
+    let store = app_handle.store(STORE_FILE).map_err(|e| e.to_string())?;
+    store.set(
+        "arduinoSettings.serialKeywords",
+        serde_json::to_value(&keywords).map_err(|e| e.to_string())?,
+    );
+
+    let manager = state.lock().map_err(|e| e.to_string())?;
+    manager
+        .live
+        .syntax
+        .write()
+        .map_err(|e| e.to_string())?
+        .keywords = keywords;
+    Ok(())
+}
+
+// Command to start logging all received lines to a file. Without a path the
+// log goes to a date-stamped file in the app data dir. Returns the path used.
+#[tauri::command]
+fn start_serial_log(
+    path: Option<String>,
+    app_handle: tauri::AppHandle,
+    state: tauri::State<Arc<Mutex<SerialManager>>>,
+) -> Result<String, String> {
+    let path = match path {
+        Some(path) => std::path::PathBuf::from(path),
+        None => app_handle
+            .path()
+            .app_data_dir()
+            .map_err(|e| e.to_string())?
+            .join("logs")
+            .join(serial_log::default_file_name()),
+    };
+    let log = SerialLog::open(&path, LogFormat::Traffic)?;
+
+    let manager = state.lock().map_err(|e| e.to_string())?;
+    *manager.live.traffic_log.lock().map_err(|e| e.to_string())? = Some(log);
+    Ok(path.display().to_string())
+}
+
+// Command to stop logging; buffered lines are written out when the log closes.
+#[tauri::command]
+fn stop_serial_log(state: tauri::State<Arc<Mutex<SerialManager>>>) -> Result<(), String> {
+    let manager = state.lock().map_err(|e| e.to_string())?;
+    manager
+        .live
+        .traffic_log
+        .lock()
+        .map_err(|e| e.to_string())?
+        .take();
+    Ok(())
+}
+
+// Command to emit laser-sensor-data at most `hz` times per second, e.g. for
+// controllers streaming faster than the UI can render. 0 emits every sample.
+// Beam breaks are still detected on every sample.
+#[tauri::command]
+fn set_sensor_event_rate(
+    hz: u32,
+    state: tauri::State<Arc<Mutex<SerialManager>>>,
+) -> Result<(), String> {
+    let manager = state.lock().map_err(|e| e.to_string())?;
+    manager
+        .live
+        .sensor_event_rate_hz
+        .store(hz, Ordering::Relaxed);
+    Ok(())
+}
+
+// Command to choose how sensor values are emitted: "full" sends every frame
+// as laser-sensor-data, "delta" sends only values that moved by more than
+// `deadband` as laser-sensor-delta, plus a full frame every
+// `keyframe_interval_ms`. SensorData always holds every value.
+#[tauri::command]
+fn set_sensor_event_mode(
+    mode: String,
+    deadband: Option<u32>,
+    keyframe_interval_ms: Option<u64>,
+    state: tauri::State<Arc<Mutex<SerialManager>>>,
+) -> Result<(), String> {
+    let delta = match mode.as_str() {
+        "full" => false,
+        "delta" => true,
+        other => return Err(format!("unknown sensor event mode: {}", other)),
+    };
+    if keyframe_interval_ms == Some(0) {
+        return Err("keyframe interval must be greater than 0".to_string());
+    }
+    let manager = state.lock().map_err(|e| e.to_string())?;
+    let event_mode = &manager.live.sensor_event_mode;
+    if let Some(deadband) = deadband {
+        event_mode.deadband.store(deadband, Ordering::Relaxed);
+    }
+    if let Some(interval_ms) = keyframe_interval_ms {
+        event_mode
+            .keyframe_interval_ms
+            .store(interval_ms, Ordering::Relaxed);
+    }
+    event_mode.delta.store(delta, Ordering::Relaxed);
+    // Listeners switching modes start from a full picture.
+    event_mode.keyframe_requested.store(true, Ordering::Relaxed);
+    Ok(())
+}
+
+// Command to make the next sensor event a full laser-sensor-data frame, for
+// listeners that joined while delta mode is on.
+#[tauri::command]
+fn request_sensor_keyframe(state: tauri::State<Arc<Mutex<SerialManager>>>) -> Result<(), String> {
+    let manager = state.lock().map_err(|e| e.to_string())?;
+    manager
+        .live
+        .sensor_event_mode
+        .keyframe_requested
+        .store(true, Ordering::Relaxed);
+    Ok(())
+}
+
+// Grabs the write queue of a connection, only holding the manager lock that long.
+fn write_queue(
+    state: &Mutex<SerialManager>,
+    connection_id: Option<&str>,
+) -> Result<SyncSender<WriteRequest>, String> {
+    let manager = state.lock().map_err(|e| e.to_string())?;
+    manager
+        .connection(connection_id)?
+        .write_queue
+        .clone()
+        .ok_or_else(|| "serial port is not connected".to_string())
+}
+
+// Queues a line for writing to a connection and waits until it was written,
+// and acknowledged by the firmware with `ack`, or failed.
+fn write_line(
+    state: &Mutex<SerialManager>,
+    connection_id: Option<&str>,
+    line: String,
+    ack: bool,
+) -> Result<(), String> {
+    let queue = write_queue(state, connection_id)?;
+    let (reply_tx, reply_rx) = channel();
+    queue
+        .send(WriteRequest {
+            line,
+            ack,
+            reply: Some(reply_tx),
+        })
+        .map_err(|_| "serial port is not connected".to_string())?;
+
+    let mut timeout_ms = WRITE_TIMEOUT_MS;
+    if ack {
+        timeout_ms += ACK_TIMEOUT_MS * ACK_ATTEMPTS as u64;
+    }
+    reply_rx
+        .recv_timeout(std::time::Duration::from_millis(timeout_ms))
+        .map_err(|_| "timed out waiting for the write queue".to_string())?
+}
+
+// Command to write a line to the Arduino. Returns once the line was written,
+// or with `ack` once the firmware acknowledged it; unacknowledged lines are
+// sent up to ACK_ATTEMPTS times.
+#[tauri::command(async)]
+fn send_serial_command(
+    command: String,
+    connection_id: Option<String>,
+    ack: Option<bool>,
+    state: tauri::State<Arc<Mutex<SerialManager>>>,
+) -> Result<(), String> {
+    write_line(
+        &state,
+        connection_id.as_deref(),
+        command,
+        ack.unwrap_or(false),
+    )
+}
+
+// Command to queue a line for the Arduino without waiting for it. Failures
+// are emitted as serial-write-failed.
+#[tauri::command]
+fn queue_serial_command(
+    command: String,
+    connection_id: Option<String>,
+    ack: Option<bool>,
+    state: tauri::State<Arc<Mutex<SerialManager>>>,
+) -> Result<(), String> {
+    write_queue(&state, connection_id.as_deref())?
+        .try_send(WriteRequest {
+            line: command,
+            ack: ack.unwrap_or(false),
+            reply: None,
+        })
+        .map_err(|e| match e {
+            TrySendError::Full(_) => "the write queue is full".to_string(),
+            TrySendError::Disconnected(_) => "serial port is not connected".to_string(),
+        })
+}
+
+// How long DTR is held low to reset the board.
+const RESET_PULSE_MS: u64 = 100;
+// How long reset_controller waits for the firmware to send after the reset.
+const BOOT_TIMEOUT_MS: u64 = 5000;
+
+// Command to reset the controller by pulsing DTR, which reboots most Arduino
+// boards. Returns once the firmware sends its first line after the reset, or
+// the line given as `banner`.
+#[tauri::command(async)]
+fn reset_controller(
+    connection_id: Option<String>,
+    banner: Option<String>,
+    state: tauri::State<Arc<Mutex<SerialManager>>>,
+) -> Result<(), String> {
+    let (sender, receiver) = channel();
+    let reset_waiter = {
+        let manager = state.lock().map_err(|e| e.to_string())?;
+        let connection = manager.connection(connection_id.as_deref())?;
+        if connection.source != SourceKind::Serial {
+            return Err("only serial controllers can be reset".to_string());
         }
-    });
+        let mut writer = connection.writer.lock().map_err(|e| e.to_string())?;
+        let port = writer.as_mut().ok_or("serial port is not connected")?;
+        port.write_data_terminal_ready(false)
+            .map_err(|e| format!("failed to pulse DTR: {}", e))?;
+        thread::sleep(std::time::Duration::from_millis(RESET_PULSE_MS));
+        port.write_data_terminal_ready(true)
+            .map_err(|e| format!("failed to pulse DTR: {}", e))?;
+        // Drop whatever the firmware sent before it rebooted.
+        port.clear(serialport::ClearBuffer::Input)
+            .map_err(|e| format!("failed to clear the read buffer: {}", e))?;
+        *connection.reset_waiter.lock().map_err(|e| e.to_string())? =
+            Some(ResetWaiter::new(sender));
+        Arc::clone(&connection.reset_waiter)
+    };
 
-    // Save our thread handle and stop sender in the manager.
-    manager.reading_thread = Some(handle);
-    manager.stop_sender = Some(stop_tx);
+    // The manager is unlocked while waiting so other commands keep working.
+    let deadline = std::time::Instant::now() + std::time::Duration::from_millis(BOOT_TIMEOUT_MS);
+    let result = loop {
+        let remaining = deadline.saturating_duration_since(std::time::Instant::now());
+        match receiver.recv_timeout(remaining) {
+            Ok(line) if banner.as_deref().is_none_or(|banner| line == banner) => break Ok(()),
+            Ok(_) => {}
+            Err(_) => break Err("controller did not respond after the reset".to_string()),
+        }
+    };
+    if let Ok(mut reset_waiter) = reset_waiter.lock() {
+        *reset_waiter = None;
+    }
+    result
+}
+
+// How long ping_controller waits for each pong.
+const PING_TIMEOUT_MS: u64 = 1000;
+
+// Round-trip times measured by ping_controller. The times are None when no
+// ping was answered.
+#[derive(Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PingResult {
+    sent: u32,
+    lost: u32,
+    min_ms: Option<f64>,
+    avg_ms: Option<f64>,
+    max_ms: Option<f64>,
+}
+
+// Command to measure the round-trip time to the controller by sending `count`
+// ping lines one after another and timing the pong replies.
+#[tauri::command(async)]
+fn ping_controller(
+    count: u32,
+    connection_id: Option<String>,
+    state: tauri::State<Arc<Mutex<SerialManager>>>,
+) -> Result<PingResult, String> {
+    if count == 0 {
+        return Err("count must be greater than 0".to_string());
+    }
+    let (sender, receiver) = channel();
+    let pong_waiter = {
+        let manager = state.lock().map_err(|e| e.to_string())?;
+        let connection = manager.connection(connection_id.as_deref())?;
+        *connection.pong_waiter.lock().map_err(|e| e.to_string())? = Some(sender);
+        Arc::clone(&connection.pong_waiter)
+    };
+
+    let mut round_trips = Vec::new();
+    let mut result = Ok(());
+    for _ in 0..count {
+        // A pong that arrives after its ping timed out must not count for the next one.
+        while receiver.try_recv().is_ok() {}
+        let sent_at = std::time::Instant::now();
+        if let Err(e) = write_line(
+            &state,
+            connection_id.as_deref(),
+            PING_LINE.to_string(),
+            false,
+        ) {
+            result = Err(e);
+            break;
+        }
+        if let Ok(received_at) =
+            receiver.recv_timeout(std::time::Duration::from_millis(PING_TIMEOUT_MS))
+        {
+            round_trips.push(received_at.duration_since(sent_at).as_secs_f64() * 1000.0);
+        }
+    }
+    if let Ok(mut pong_waiter) = pong_waiter.lock() {
+        *pong_waiter = None;
+    }
+    result?;
+
+    Ok(PingResult {
+        sent: count,
+        lost: count - round_trips.len() as u32,
+        min_ms: round_trips.iter().copied().reduce(f64::min),
+        avg_ms: (!round_trips.is_empty())
+            .then(|| round_trips.iter().sum::<f64>() / round_trips.len() as f64),
+        max_ms: round_trips.iter().copied().reduce(f64::max),
+    })
+}
+
+// Command to fetch the counters of a connection.
+#[tauri::command]
+fn get_serial_stats(
+    connection_id: Option<String>,
+    state: tauri::State<Arc<Mutex<SerialManager>>>,
+) -> Result<SerialStats, String> {
+    let manager = state.lock().map_err(|e| e.to_string())?;
+    let connection = manager.connection(connection_id.as_deref())?;
+    let mut stats = connection.stats.lock().map_err(|e| e.to_string())?.clone();
+    stats.paused = manager.live.paused.load(Ordering::Relaxed);
+    Ok(stats)
+}
+
+// Command to set the expected number of sensors of a connection, e.g. after the
+// maze was rewired. Without a count the next valid line sets it.
+#[tauri::command]
+fn reset_expected_sensor_count(
+    connection_id: Option<String>,
+    count: Option<usize>,
+    state: tauri::State<Arc<Mutex<SerialManager>>>,
+) -> Result<(), String> {
+    let manager = state.lock().map_err(|e| e.to_string())?;
+    let connection = manager.connection(connection_id.as_deref())?;
+    connection
+        .sensor_count
+        .expected
+        .store(count.unwrap_or(0), Ordering::Relaxed);
+    Ok(())
+}
+
+// Command to fetch the parse errors of a connection since it connected.
+#[tauri::command]
+fn get_parse_error_stats(
+    connection_id: Option<String>,
+    state: tauri::State<Arc<Mutex<SerialManager>>>,
+) -> Result<ParseErrorStats, String> {
+    let manager = state.lock().map_err(|e| e.to_string())?;
+    let connection = manager.connection(connection_id.as_deref())?;
+    let stats = connection.stats.lock().map_err(|e| e.to_string())?;
+    Ok(stats.parse_errors.clone())
+}
 
+// Command to zero the parse error counters of a connection.
+#[tauri::command]
+fn reset_parse_error_stats(
+    connection_id: Option<String>,
+    state: tauri::State<Arc<Mutex<SerialManager>>>,
+) -> Result<(), String> {
+    let manager = state.lock().map_err(|e| e.to_string())?;
+    let connection = manager.connection(connection_id.as_deref())?;
+    connection
+        .stats
+        .lock()
+        .map_err(|e| e.to_string())?
+        .parse_errors = ParseErrorStats::default();
     Ok(())
 }
 
-// Command to stop the serial reading thread.
+// Command to fetch the latest sensor values, e.g. after the frontend reloaded.
 #[tauri::command]
-fn stop_serial(state: tauri::State<Arc<Mutex<SerialManager>>>) -> Result<(), String> {
+fn get_sensor_data(
+    sensor_data: tauri::State<Arc<Mutex<SensorData>>>,
+) -> Result<SensorSnapshot, String> {
+    let sensor_data = sensor_data.lock().map_err(|e| e.to_string())?;
+    Ok(match sensor_data.updated_at {
+        Some(updated_at) if !sensor_data.frame.values.is_empty() => SensorSnapshot::Data {
+            values: sensor_data.frame.values.clone(),
+            seq: sensor_data.frame.seq,
+            age_ms: updated_at.elapsed().as_millis() as u64,
+        },
+        _ => SensorSnapshot::NoData,
+    })
+}
+
+// Command to list the open serial connections.
+#[tauri::command]
+fn list_connections(
+    state: tauri::State<Arc<Mutex<SerialManager>>>,
+) -> Result<Vec<ConnectionInfo>, String> {
+    let manager = state.lock().map_err(|e| e.to_string())?;
+    Ok(manager
+        .connections
+        .iter()
+        .map(|(id, connection)| ConnectionInfo {
+            connection_id: id.clone(),
+            source: connection.source,
+            port: connection.port.clone(),
+            baud_rate: connection.settings.as_ref().map(|s| s.baud_rate),
+        })
+        .collect())
+}
+
+// Command to stop one serial connection, or all of them without an id.
+#[tauri::command]
+fn stop_serial(
+    connection_id: Option<String>,
+    app_handle: tauri::AppHandle,
+    state: tauri::State<Arc<Mutex<SerialManager>>>,
+    sensor_data: tauri::State<Arc<Mutex<SensorData>>>,
+) -> Result<(), String> {
     let mut manager = state.lock().map_err(|e| e.to_string())?;
-    manager.stop();
+    let stopped_ports: Vec<String> = manager
+        .connections
+        .iter()
+        .filter(|(id, _)| connection_id.as_ref().is_none_or(|wanted| wanted == *id))
+        .map(|(_, connection)| connection.port.clone())
+        .collect();
+    // The reading threads lock the sensor data too, so only touch it once they stopped.
+    match connection_id {
+        Some(id) => {
+            manager.stop_connection(&id);
+            sensor_data.lock().map_err(|e| e.to_string())?.remove(&id);
+        }
+        None => {
+            manager.stop();
+            *sensor_data.lock().map_err(|e| e.to_string())? = SensorData::new();
+        }
+    }
+    for port in stopped_ports {
+        report_connection(&app_handle, &port, false);
+    }
+    // Close the traffic log so its buffered lines reach the disk.
+    manager
+        .live
+        .traffic_log
+        .lock()
+        .map_err(|e| e.to_string())?
+        .take();
     Ok(())
 }
 
@@ -198,6 +1331,12 @@ pub fn run() {
         // Manage the SerialManager and SensorData state
         .manage(Arc::new(Mutex::new(SerialManager::new())))
         .manage(Arc::new(Mutex::new(SensorData::new())))
+        .manage(Mutex::new(PortWatcher::new()))
+        .manage(Arc::new(Mutex::new(game::GameManager::new(Arc::new(
+            game::MonotonicClock::new(),
+        )))))
+        .manage(Mutex::new(midi::MidiListener::new()))
+        .manage(Mutex::new(calibration::GuidedCalibration::new()))
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_store::Builder::default().build())
         // Register our Tauri commands.
@@ -205,14 +1344,177 @@ pub fn run() {
             list_ports,
             configure_serial,
             stop_serial,
-            check_connection
+            check_connection,
+            set_debounce_ms,
+            send_serial_command,
+            queue_serial_command,
+            reset_controller,
+            ping_controller,
+            lasers::set_laser_state,
+            lasers::set_all_lasers,
+            lasers::get_laser_states,
+            get_serial_stats,
+            get_parse_error_stats,
+            reset_parse_error_stats,
+            reset_expected_sensor_count,
+            list_connections,
+            get_sensor_data,
+            set_raw_monitor,
+            pause_serial,
+            resume_serial,
+            set_sensor_event_rate,
+            set_sensor_event_mode,
+            request_sensor_keyframe,
+            set_event_prefixes,
+            set_serial_keywords,
+            start_serial_log,
+            stop_serial_log,
+            sensors::set_sensor_names,
+            sensors::get_sensor_names,
+            sensors::set_valid_range,
+            sensors::set_value_scaling,
+            sensors::set_sensor_thresholds,
+            sensors::set_disabled_sensors,
+            sensors::get_disabled_sensors,
+            sensors::set_beam_debounce,
+            sensors::set_sensor_beam_debounce,
+            sensors::set_smoothing,
+            calibration::calibrate_baseline,
+            calibration::start_calibration,
+            calibration::cancel_calibration,
+            mock::configure_mock_serial,
+            mock::mock_press_start,
+            mock::mock_press_buzzer,
+            mock::set_mock_fault_profile,
+            tcp::configure_tcp_source,
+            udp::configure_udp_source,
+            midi::list_midi_ports,
+            midi::configure_midi_input,
+            midi::stop_midi_input,
+            game::start_game,
+            game::arm_game,
+            game::rematch,
+            game::rematch_with_player,
+            game::discard_result,
+            game::save_penalty_table,
+            game::get_penalty_tables,
+            game::abort_game,
+            game::pause_game,
+            game::resume_game,
+            game::get_game_state,
+            game::get_game_result,
+            scores::save_run,
+            scores::get_leaderboard,
+            scores::set_leaderboard_range,
+            scores::set_keep_practice_runs,
+            scores::get_streak_bonus,
+            hit_stats::get_sensor_hit_stats,
+            history::get_run_history,
+            history::get_daily_summary,
+            scores::set_run_note,
+            scores::add_run_tag,
+            scores::remove_run_tag,
+            scores::list_tags,
+            history::get_usage_stats,
+            scores::set_streak_bonus,
+            scores::get_min_run_seconds,
+            scores::set_min_run_seconds,
+            scoring::get_scoring_config,
+            scoring::set_scoring_config,
+            scoring::preview_score,
+            scores::get_player_runs,
+            rounds::start_round_set,
+            rounds::get_round_set_result,
+            scores::void_run,
+            scores::unvoid_run,
+            scores::confirm_run,
+            scores::delete_run,
+            scores::remove_hit,
+            scores::export_leaderboard,
+            scores::export_runs,
+            scores::import_runs,
+            teams::create_team,
+            teams::update_team,
+            teams::delete_team,
+            teams::list_teams,
+            teams::get_team_standings,
+            players::create_player,
+            players::suggest_player_names,
+            players::get_max_name_length,
+            players::set_max_name_length,
+            players::list_players,
+            players::get_player_stats,
+            players::set_player_handicap,
+            players::set_player_category,
+            players::get_categories,
+            players::add_category,
+            players::rename_category,
+            players::delete_category,
+            players::set_category_handicap,
+            players::get_category_handicaps,
+            players::delete_player,
+            rating::get_rankings,
+            rating::get_rating_history,
+            rating::get_rating_settings,
+            rating::set_rating_settings,
+            achievements::get_achievement_catalog,
+            achievements::set_custom_achievements,
+            achievements::get_achievements,
+            achievements::backfill_achievements,
+            tournament::create_tournament,
+            tournament::get_bracket,
+            tournament::record_match_result,
+            session::start_session_recording,
+            session::stop_session_recording,
+            session::replay_session,
+            ports::list_ports_detailed,
+            ports::list_candidate_ports,
+            ports::check_connection_detailed,
+            ports::detect_baud_rate,
+            ports::start_port_watcher,
+            ports::stop_port_watcher
         ])
         .setup(|app| {
             // set arduinoSettings.isConnected subfield to false on startup
-            app.store("laser-config.dat")?
+            app.store(STORE_FILE)?
                 .set("arduinoSettings.isConnected", false);
+            // Restore the saved sensor names and thresholds.
+            if let Ok(manager) = app.state::<Arc<Mutex<SerialManager>>>().lock() {
+                manager.live.sensor_names.load(app.handle());
+                manager.live.valid_range.load(app.handle());
+                manager.live.scaling.load(app.handle());
+                manager.live.thresholds.load(app.handle());
+                manager.live.disabled_sensors.load(app.handle());
+                manager.live.beam_debounce.load(app.handle());
+                if let Ok(mut syntax) = manager.live.syntax.write() {
+                    *syntax = load_line_syntax(app.handle());
+                }
+            }
+            // Load the saved runs, importing the store's highscores once.
+            app.manage(Mutex::new(scores::ScoreBook::open(app.handle())?));
+            if let (Ok(manager), Ok(mut game)) = (
+                app.state::<Arc<Mutex<SerialManager>>>().lock(),
+                app.state::<Arc<Mutex<game::GameManager>>>().lock(),
+            ) {
+                game.set_disabled_sensors(&manager.live.disabled_sensors.list());
+            }
+            // Record hits and the finish of games from the sensor events.
+            game::subscribe(app.handle());
+            history::subscribe(app.handle());
+            // Report plugged and unplugged ports until the frontend stops the watcher.
+            if let Ok(mut watcher) = app.state::<Mutex<PortWatcher>>().lock() {
+                watcher.start(app.handle().clone());
+            }
             Ok(())
         })
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            // The MIDI device stays open until its listener thread ends.
+            if let tauri::RunEvent::Exit = event {
+                if let Ok(mut listener) = app.state::<Mutex<midi::MidiListener>>().lock() {
+                    listener.stop();
+                }
+            }
+        });
 }
@@ -0,0 +1,240 @@
+// MIDI input as an alternative start button and buzzer, e.g. a footswitch
+// controller at the operator desk. Note-on messages of the configured notes
+// trigger the same debounced start-button and buzzer events as the serial
+// keywords. The listener runs next to any serial connection.
+//
+// Ports are opened with midir, so ALSA, CoreMIDI and WinMM devices all work.
+
+use midir::{Ignore, MidiInput, MidiInputConnection};
+use std::sync::{Arc, Mutex};
+
+use crate::pipeline::LineHandler;
+use crate::protocol::Keyword;
+use crate::{SensorData, SerialConnection, SerialManager, SourceKind};
+
+// Connection id the MIDI events are reported with.
+const MIDI_CONNECTION_ID: &str = "midi";
+// Client name the app shows up with in the MIDI system.
+const CLIENT_NAME: &str = "Lazer Mazer";
+
+// Status byte of a note-on message, without the channel.
+const NOTE_ON: u8 = 0x90;
+// First and last byte of a system exclusive message.
+const SYSEX_START: u8 = 0xF0;
+const SYSEX_END: u8 = 0xF7;
+
+// Turns a MIDI byte stream into the notes that were struck, following
+// running status and skipping system messages.
+struct NoteParser {
+    status: Option<u8>,
+    data: Vec<u8>,
+    in_sysex: bool,
+}
+
+impl NoteParser {
+    fn new() -> Self {
+        Self {
+            status: None,
+            data: Vec::new(),
+            in_sysex: false,
+        }
+    }
+
+    // Returns the note of a completed note-on message with a velocity above 0.
+    fn push(&mut self, byte: u8) -> Option<u8> {
+        match byte {
+            // Real-time messages may appear anywhere, even inside others.
+            0xF8..=0xFF => None,
+            SYSEX_START => {
+                self.in_sysex = true;
+                self.status = None;
+                None
+            }
+            SYSEX_END => {
+                self.in_sysex = false;
+                None
+            }
+            _ if self.in_sysex => None,
+            // Other system common messages cancel running status.
+            0xF1..=0xF6 => {
+                self.status = None;
+                None
+            }
+            0x80..=0xEF => {
+                self.status = Some(byte);
+                self.data.clear();
+                None
+            }
+            data => {
+                let status = self.status?;
+                self.data.push(data);
+                // Program change and channel pressure carry one data byte.
+                let length = if matches!(status & 0xF0, 0xC0 | 0xD0) {
+                    1
+                } else {
+                    2
+                };
+                if self.data.len() < length {
+                    return None;
+                }
+                let message = std::mem::take(&mut self.data);
+                (status & 0xF0 == NOTE_ON && message[1] > 0).then_some(message[0])
+            }
+        }
+    }
+}
+
+// Holds the MIDI connection, if one is open.
+pub struct MidiListener {
+    connection: Option<MidiInputConnection<()>>,
+}
+
+impl MidiListener {
+    pub fn new() -> Self {
+        Self { connection: None }
+    }
+
+    pub fn stop(&mut self) {
+        if let Some(connection) = self.connection.take() {
+            connection.close();
+        }
+    }
+}
+
+fn midi_input() -> Result<MidiInput, String> {
+    let mut input = MidiInput::new(CLIENT_NAME).map_err(|e| e.to_string())?;
+    // Clock and active sensing bytes would only be skipped by the parser.
+    input.ignore(Ignore::All);
+    Ok(input)
+}
+
+// Turns the configured notes of incoming messages into start-button and
+// buzzer events.
+fn handle_message(
+    parser: &mut NoteParser,
+    handler: &mut LineHandler,
+    message: &[u8],
+    start_note: u8,
+    buzzer_note: u8,
+) {
+    for note in message.iter().filter_map(|&byte| parser.push(byte)) {
+        let keyword = if note == start_note {
+            Keyword::Start
+        } else if note == buzzer_note {
+            Keyword::Buzzer
+        } else {
+            continue;
+        };
+        handler.handle_keyword(keyword, &format!("midi note {}", note));
+    }
+}
+
+// Command to list the MIDI input ports a listener can be started on, by name.
+#[tauri::command]
+pub fn list_midi_ports() -> Result<Vec<String>, String> {
+    let input = midi_input()?;
+    Ok(input
+        .ports()
+        .iter()
+        .filter_map(|port| input.port_name(port).ok())
+        .collect())
+}
+
+// Command to start listening for the start and buzzer notes on a MIDI port,
+// replacing any running listener.
+#[tauri::command]
+pub fn configure_midi_input(
+    port_name: String,
+    start_note: u8,
+    buzzer_note: u8,
+    app_handle: tauri::AppHandle,
+    listener: tauri::State<Mutex<MidiListener>>,
+    state: tauri::State<Arc<Mutex<SerialManager>>>,
+    sensor_data: tauri::State<Arc<Mutex<SensorData>>>,
+) -> Result<(), String> {
+    if start_note > 127 || buzzer_note > 127 {
+        return Err("MIDI notes range from 0 to 127".to_string());
+    }
+    if start_note == buzzer_note {
+        return Err("start and buzzer notes must differ".to_string());
+    }
+    let mut listener = listener.lock().map_err(|e| e.to_string())?;
+    listener.stop();
+
+    let input = midi_input()?;
+    let port = input
+        .ports()
+        .into_iter()
+        .find(|port| input.port_name(port).is_ok_and(|name| name == port_name))
+        .ok_or_else(|| format!("no MIDI port named {}", port_name))?;
+    let mut handler = {
+        let manager = state.lock().map_err(|e| e.to_string())?;
+        // The handler only needs a connection for its counters; the MIDI
+        // listener isn't one of the manager's connections.
+        let connection = SerialConnection::new(port_name.clone(), None, SourceKind::Midi);
+        manager.line_handler(
+            &app_handle,
+            MIDI_CONNECTION_ID,
+            &connection,
+            sensor_data.inner(),
+            crate::DEFAULT_STALL_TIMEOUT_MS,
+        )
+    };
+    let mut parser = NoteParser::new();
+    let connection = input
+        .connect(
+            &port,
+            MIDI_CONNECTION_ID,
+            move |_, message, _| {
+                handle_message(&mut parser, &mut handler, message, start_note, buzzer_note)
+            },
+            (),
+        )
+        .map_err(|e| format!("failed to open MIDI port {}: {}", port_name, e))?;
+    listener.connection = Some(connection);
+    Ok(())
+}
+
+// Command to stop the MIDI listener.
+#[tauri::command]
+pub fn stop_midi_input(listener: tauri::State<Mutex<MidiListener>>) -> Result<(), String> {
+    let mut listener = listener.lock().map_err(|e| e.to_string())?;
+    listener.stop();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn notes(bytes: &[u8]) -> Vec<u8> {
+        let mut parser = NoteParser::new();
+        bytes.iter().filter_map(|&byte| parser.push(byte)).collect()
+    }
+
+    #[test]
+    fn follows_running_status() {
+        assert_eq!(notes(&[0x91, 60, 100, 62, 90, 64, 1]), [60, 62, 64]);
+        // A note-off sets the status the next data bytes run under.
+        assert_eq!(notes(&[0x90, 60, 100, 0x80, 60, 0, 61, 0]), [60]);
+        // Program changes take one data byte.
+        assert_eq!(notes(&[0xC0, 5, 0x90, 60, 100]), [60]);
+        // System common messages cancel running status.
+        assert_eq!(notes(&[0x90, 60, 100, 0xF3, 1, 62, 100]), [60]);
+    }
+
+    #[test]
+    fn skips_sysex_and_real_time_bytes() {
+        assert_eq!(notes(&[0xF0, 0x90, 60, 100, 0xF7, 0x90, 62, 100]), [62]);
+        // Running status doesn't survive a SysEx message.
+        assert_eq!(notes(&[0x90, 60, 100, 0xF0, 1, 0xF7, 62, 100]), [60]);
+        // Clock and active sensing bytes may split a message.
+        assert_eq!(notes(&[0x90, 0xF8, 60, 0xFE, 100, 0xFA]), [60]);
+    }
+
+    #[test]
+    fn ignores_note_on_with_velocity_zero() {
+        assert_eq!(notes(&[0x90, 60, 0, 62, 1]), [62]);
+        assert!(notes(&[0x9F, 60, 0]).is_empty());
+    }
+}
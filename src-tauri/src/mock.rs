@@ -0,0 +1,404 @@
+// Simulated controller for running the app without an Arduino attached. The
+// mock produces the same text lines the firmware sends and feeds them through
+// a LineHandler, so the rest of the app can't tell the difference.
+
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError};
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread::{self, JoinHandle};
+use std::time::Instant;
+use tauri::Emitter;
+
+use crate::pipeline::{unix_time_ms, LineHandler};
+use crate::protocol::Keyword;
+use crate::{SensorData, SerialConnection, SerialManager, SourceKind, DEFAULT_CONNECTION_ID};
+
+// Port name reported for the mock connection.
+const MOCK_PORT: &str = "mock";
+// Chance per sensor and tick that a simulated beam break starts.
+const BREAK_CHANCE: f64 = 0.002;
+// Value of a spiking sensor; the top of the Arduino's ADC range.
+const SPIKE_VALUE: u16 = 1023;
+
+// Small xorshift generator; the mock only needs plausible noise.
+struct Rng(u64);
+
+impl Rng {
+    fn seeded() -> Self {
+        let seed = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or(0);
+        Self(seed | 1)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    // Uniform value in 0.0..1.0.
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    // Uniform value in low..high.
+    fn range(&mut self, low: u16, high: u16) -> u16 {
+        low + (self.next_u64() % (high - low) as u64) as u16
+    }
+
+    // True with the given probability.
+    fn chance(&mut self, probability: f64) -> bool {
+        self.next_f64() < probability
+    }
+
+    // Uniform index in 0..len; len must not be 0.
+    fn index(&mut self, len: usize) -> usize {
+        (self.next_u64() % len as u64) as usize
+    }
+}
+
+// Probabilities per tick of the faults the mock injects, to see how the UI
+// copes with what real installations send.
+#[derive(Clone, Copy, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct FaultProfile {
+    // Random garbage in place of part of the line.
+    pub corrupt_line: f64,
+    // The same line sent twice.
+    pub duplicate_line: f64,
+    // One value left out of the line.
+    pub missing_field: f64,
+    // One sensor jumping to the top of its range.
+    pub value_spike: f64,
+    // The controller going quiet for `silence_ms`.
+    pub silence: f64,
+    pub silence_ms: u64,
+    // Many beams breaking briefly at once.
+    pub break_burst: f64,
+}
+
+impl FaultProfile {
+    // Named profiles for set_mock_fault_profile.
+    fn preset(name: &str) -> Option<Self> {
+        match name {
+            "clean" => Some(Self::default()),
+            "flaky-cable" => Some(Self {
+                corrupt_line: 0.02,
+                duplicate_line: 0.01,
+                missing_field: 0.02,
+                silence: 0.002,
+                silence_ms: 3000,
+                ..Self::default()
+            }),
+            "sunlight" => Some(Self {
+                value_spike: 0.05,
+                break_burst: 0.005,
+                ..Self::default()
+            }),
+            _ => None,
+        }
+    }
+}
+
+// Kind of fault reported by the mock-fault-injected event.
+#[derive(Clone, Copy, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+enum Fault {
+    CorruptLine,
+    DuplicateLine,
+    MissingField,
+    ValueSpike,
+    Silence,
+    BreakBurst,
+}
+
+// Payload for the mock-fault-injected event.
+#[derive(Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct FaultInjection {
+    fault: Fault,
+    timestamp_ms: u64,
+}
+
+// One simulated photodiode.
+struct MockSensor {
+    baseline: u16,
+    // Remaining ticks of the current simulated beam break.
+    broken_ticks: u32,
+}
+
+// Generates sensor lines: a steady baseline with noise and occasional dips.
+struct MockController {
+    sensors: Vec<MockSensor>,
+    interval_ms: u64,
+    rng: Rng,
+    // Separator the app currently expects between values.
+    separator: char,
+    // End of the current injected silence.
+    silent_until: Option<Instant>,
+}
+
+impl MockController {
+    fn new(sensor_count: usize, interval_ms: u64, separator: char) -> Self {
+        let mut rng = Rng::seeded();
+        let sensors = (0..sensor_count)
+            .map(|_| MockSensor {
+                baseline: rng.range(700, 900),
+                broken_ticks: 0,
+            })
+            .collect();
+        Self {
+            sensors,
+            interval_ms,
+            rng,
+            separator,
+            silent_until: None,
+        }
+    }
+
+    // Starts a beam break on `sensor` lasting `duration_ms`.
+    fn break_beam(&mut self, sensor: usize, duration_ms: u64) {
+        let interval_ms = self.interval_ms.max(1);
+        self.sensors[sensor].broken_ticks = (duration_ms / interval_ms).max(1) as u32;
+    }
+
+    fn next_values(&mut self) -> Vec<u16> {
+        for sensor in 0..self.sensors.len() {
+            if self.sensors[sensor].broken_ticks == 0 && self.rng.chance(BREAK_CHANCE) {
+                // Breaks last between 200ms and 800ms.
+                let duration_ms = self.rng.range(200, 800) as u64;
+                self.break_beam(sensor, duration_ms);
+            }
+        }
+        self.sensors
+            .iter_mut()
+            .map(|sensor| {
+                if sensor.broken_ticks > 0 {
+                    sensor.broken_ticks -= 1;
+                    self.rng.range(50, 150)
+                } else {
+                    sensor.baseline - 15 + self.rng.range(0, 30)
+                }
+            })
+            .collect()
+    }
+
+    // Lines to send this tick with the profile's faults applied, along with
+    // the faults that were injected. Empty while an injected silence lasts.
+    fn next_lines(&mut self, profile: &FaultProfile, now: Instant) -> (Vec<String>, Vec<Fault>) {
+        let mut faults = Vec::new();
+        if self.silent_until.is_some_and(|until| now < until) {
+            return (Vec::new(), faults);
+        }
+        self.silent_until = None;
+        if self.rng.chance(profile.silence) {
+            self.silent_until = Some(now + std::time::Duration::from_millis(profile.silence_ms));
+            faults.push(Fault::Silence);
+            return (Vec::new(), faults);
+        }
+        if self.rng.chance(profile.break_burst) {
+            // Short breaks on about half the beams at once.
+            for sensor in 0..self.sensors.len() {
+                if self.rng.chance(0.5) {
+                    let duration_ms = self.rng.range(30, 120) as u64;
+                    self.break_beam(sensor, duration_ms);
+                }
+            }
+            faults.push(Fault::BreakBurst);
+        }
+
+        let mut values = self.next_values();
+        if !values.is_empty() && self.rng.chance(profile.value_spike) {
+            let sensor = self.rng.index(values.len());
+            values[sensor] = SPIKE_VALUE;
+            faults.push(Fault::ValueSpike);
+        }
+        if !values.is_empty() && self.rng.chance(profile.missing_field) {
+            let sensor = self.rng.index(values.len());
+            values.remove(sensor);
+            faults.push(Fault::MissingField);
+        }
+        let mut line = values
+            .iter()
+            .map(|value| value.to_string())
+            .collect::<Vec<_>>()
+            .join(&self.separator.to_string());
+        if !line.is_empty() && self.rng.chance(profile.corrupt_line) {
+            // Cut the line somewhere and append line noise.
+            line.truncate(self.rng.index(line.len()));
+            line.push_str("\u{fffd}#~");
+            faults.push(Fault::CorruptLine);
+        }
+        let mut lines = vec![line];
+        if self.rng.chance(profile.duplicate_line) {
+            lines.push(lines[0].clone());
+            faults.push(Fault::DuplicateLine);
+        }
+        (lines, faults)
+    }
+}
+
+// Spawns the mock thread. Lines sent on `input_rx` (e.g. "start") are fed to
+// the handler as if the controller had sent them. Faults from `faults` are
+// injected into the generated lines and reported as mock-fault-injected.
+fn spawn_mock(
+    mut handler: LineHandler,
+    sensor_count: usize,
+    interval_ms: u64,
+    separator: char,
+    faults: Arc<RwLock<FaultProfile>>,
+    stop_rx: Receiver<()>,
+    input_rx: Receiver<String>,
+) -> JoinHandle<()> {
+    thread::spawn(move || {
+        let mut controller = MockController::new(sensor_count, interval_ms, separator);
+        let interval = std::time::Duration::from_millis(interval_ms);
+        while let Err(RecvTimeoutError::Timeout) = stop_rx.recv_timeout(interval) {
+            handler.tick();
+            for line in input_rx.try_iter() {
+                handler.handle_line(&line);
+            }
+            let profile = faults.read().map(|profile| *profile).unwrap_or_default();
+            let (lines, injected) = controller.next_lines(&profile, Instant::now());
+            for fault in injected {
+                let _ = handler.app_handle().emit(
+                    "mock-fault-injected",
+                    FaultInjection {
+                        fault,
+                        timestamp_ms: unix_time_ms(),
+                    },
+                );
+            }
+            for line in lines {
+                handler.handle_line(&line);
+            }
+        }
+    })
+}
+
+// Command to start the simulated controller in place of any open connection.
+#[tauri::command]
+pub fn configure_mock_serial(
+    sensor_count: usize,
+    interval_ms: u64,
+    app_handle: tauri::AppHandle,
+    state: tauri::State<Arc<Mutex<SerialManager>>>,
+    sensor_data: tauri::State<Arc<Mutex<SensorData>>>,
+) -> Result<(), String> {
+    if sensor_count == 0 {
+        return Err("sensor count must be greater than 0".to_string());
+    }
+    if interval_ms == 0 {
+        return Err("interval must be greater than 0".to_string());
+    }
+
+    let mut manager = state.lock().map_err(|e| e.to_string())?;
+    manager.stop();
+    *sensor_data.lock().map_err(|e| e.to_string())? = SensorData::new();
+
+    let mut connection = SerialConnection::new(MOCK_PORT.to_string(), None, SourceKind::Mock);
+    let handler = manager.line_handler(
+        &app_handle,
+        DEFAULT_CONNECTION_ID,
+        &connection,
+        sensor_data.inner(),
+        crate::DEFAULT_STALL_TIMEOUT_MS,
+    );
+    let separator = manager
+        .live
+        .syntax
+        .read()
+        .map_err(|e| e.to_string())?
+        .separator;
+    let (stop_tx, stop_rx) = channel();
+    let (input_tx, input_rx) = channel();
+
+    connection.reading_thread = Some(spawn_mock(
+        handler,
+        sensor_count,
+        interval_ms,
+        separator,
+        Arc::clone(&manager.live.mock_faults),
+        stop_rx,
+        input_rx,
+    ));
+    connection.stop_sender = Some(stop_tx);
+    connection.mock_input = Some(input_tx);
+    manager
+        .connections
+        .insert(DEFAULT_CONNECTION_ID.to_string(), connection);
+    Ok(())
+}
+
+// Command to set the faults the simulated controller injects, either as a
+// profile or by preset name ("clean", "flaky-cable", "sunlight"). Applies to
+// a running mock right away.
+#[tauri::command]
+pub fn set_mock_fault_profile(
+    profile: Option<FaultProfile>,
+    preset: Option<String>,
+    state: tauri::State<Arc<Mutex<SerialManager>>>,
+) -> Result<(), String> {
+    let profile = match (profile, preset) {
+        (Some(profile), None) => profile,
+        (None, Some(preset)) => FaultProfile::preset(&preset)
+            .ok_or_else(|| format!("unknown fault preset: {}", preset))?,
+        _ => return Err("pass either a fault profile or a preset name".to_string()),
+    };
+    let probabilities = [
+        profile.corrupt_line,
+        profile.duplicate_line,
+        profile.missing_field,
+        profile.value_spike,
+        profile.silence,
+        profile.break_burst,
+    ];
+    if probabilities.iter().any(|p| !(0.0..=1.0).contains(p)) {
+        return Err("fault probabilities must be between 0 and 1".to_string());
+    }
+
+    let manager = state.lock().map_err(|e| e.to_string())?;
+    *manager
+        .live
+        .mock_faults
+        .write()
+        .map_err(|e| e.to_string())? = profile;
+    Ok(())
+}
+
+// Sends the line the simulated controller uses for a keyword, following the
+// configured keyword mapping.
+fn press(state: &Mutex<SerialManager>, keyword: Keyword) -> Result<(), String> {
+    let manager = state.lock().map_err(|e| e.to_string())?;
+    let line = manager
+        .live
+        .syntax
+        .read()
+        .map_err(|e| e.to_string())?
+        .keywords
+        .iter()
+        .find(|(_, &mapped)| mapped == keyword)
+        .map(|(line, _)| line.clone())
+        .ok_or("no serial keyword is mapped to this event")?;
+    manager
+        .connections
+        .values()
+        .find_map(|connection| connection.mock_input.as_ref())
+        .ok_or("mock serial is not running")?
+        .send(line)
+        .map_err(|_| "mock serial is not running".to_string())
+}
+
+// Command to simulate a press of the start button.
+#[tauri::command]
+pub fn mock_press_start(state: tauri::State<Arc<Mutex<SerialManager>>>) -> Result<(), String> {
+    press(&state, Keyword::Start)
+}
+
+// Command to simulate a press of the buzzer.
+#[tauri::command]
+pub fn mock_press_buzzer(state: tauri::State<Arc<Mutex<SerialManager>>>) -> Result<(), String> {
+    press(&state, Keyword::Buzzer)
+}
@@ -0,0 +1,971 @@
+// Turns received lines into sensor updates and frontend events. Every data
+// source (serial port, mock) feeds its lines through a LineHandler so the rest
+// of the app can't tell them apart.
+
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use tauri::Emitter;
+
+use crate::protocol::{
+    parse_line, split_sequence, DuplicateFilter, Keyword, KeywordDebouncer, LineSyntax, Message,
+    SequenceTracker, PONG_LINE,
+};
+use crate::sensors::{BeamTracker, RangeGuard, Smoother};
+use crate::writer::ACK_PREFIX;
+use crate::{
+    LiveSettings, SensorData, SensorFrame, SerialConnection, SerialStats, SENSOR_FRAME_VERSION,
+};
+
+// Payload for the serial-stalled and serial-resumed events.
+#[derive(Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct StallStatus {
+    connection_id: String,
+    seconds_since_data: f64,
+}
+
+// Reports a connection that stopped delivering parseable data, once per stall.
+struct StallWatchdog {
+    timeout: std::time::Duration,
+    last_data: std::time::Instant,
+    stalled: bool,
+}
+
+impl StallWatchdog {
+    fn new(timeout_ms: u64) -> Self {
+        Self {
+            timeout: std::time::Duration::from_millis(timeout_ms),
+            last_data: std::time::Instant::now(),
+            stalled: false,
+        }
+    }
+
+    // Called for every successfully parsed line or frame.
+    fn feed(&mut self, app_handle: &tauri::AppHandle, connection_id: &str) {
+        if self.stalled {
+            self.stalled = false;
+            let _ = app_handle.emit(
+                "serial-resumed",
+                StallStatus {
+                    connection_id: connection_id.to_string(),
+                    seconds_since_data: self.last_data.elapsed().as_secs_f64(),
+                },
+            );
+        }
+        self.last_data = std::time::Instant::now();
+    }
+
+    // Called on every pass of the reading loop.
+    fn check(&mut self, app_handle: &tauri::AppHandle, connection_id: &str) {
+        let since_data = self.last_data.elapsed();
+        if !self.stalled && since_data >= self.timeout {
+            self.stalled = true;
+            let _ = app_handle.emit(
+                "serial-stalled",
+                StallStatus {
+                    connection_id: connection_id.to_string(),
+                    seconds_since_data: since_data.as_secs_f64(),
+                },
+            );
+        }
+    }
+
+    // Restarts the countdown without reporting, e.g. after a reconnect.
+    fn reset(&mut self) {
+        self.last_data = std::time::Instant::now();
+        self.stalled = false;
+    }
+}
+
+// Expected number of values per line, shared with the commands so it can be
+// reset when the maze is rewired. An expected count of 0 is learned from the
+// next valid line.
+pub struct SensorCountCheck {
+    pub expected: AtomicUsize,
+    // Whether lines with the wrong count are dropped instead of published.
+    pub drop_mismatched: AtomicBool,
+}
+
+impl SensorCountCheck {
+    pub fn new(expected: usize, drop_mismatched: bool) -> Self {
+        Self {
+            expected: AtomicUsize::new(expected),
+            drop_mismatched: AtomicBool::new(drop_mismatched),
+        }
+    }
+}
+
+// Payload for the sensor-count-mismatch event.
+#[derive(Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SensorCountMismatch {
+    connection_id: String,
+    expected: usize,
+    actual: usize,
+    dropped: bool,
+}
+
+// Payload for the serial-raw-line event.
+#[derive(Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RawLine {
+    connection_id: String,
+    line: String,
+    // Milliseconds since the Unix epoch.
+    timestamp_ms: u64,
+}
+
+// Milliseconds since the Unix epoch, for timestamps shown to the user.
+pub fn unix_time_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+// Minimum time between two serial-parse-errors summaries.
+const PARSE_ERROR_SUMMARY_MS: u64 = 5000;
+
+// Payload for the serial-parse-errors event.
+#[derive(Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ParseErrorSummary {
+    connection_id: String,
+    // Malformed lines since the previous summary.
+    count: u64,
+    // Malformed lines since connecting.
+    total: u64,
+    samples: Vec<String>,
+}
+
+// Payload for the start-button and buzzer events.
+#[derive(Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ButtonEvent {
+    connection_id: String,
+    // The line as received, e.g. "BTN_START".
+    keyword: String,
+    timestamp_ms: u64,
+}
+
+// Highest number of sensors a hit/clear message may address when the
+// expected sensor count isn't known.
+const MAX_EVENT_SENSORS: usize = 64;
+
+// Payload for the laser-broken and laser-restored events.
+#[derive(Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BeamEvent {
+    connection_id: String,
+    sensor: usize,
+    name: String,
+    // The analog value, unless the controller reported the event itself.
+    value: Option<u32>,
+    timestamp_ms: u64,
+}
+
+// Minimum time between sensor-out-of-range events of one sensor.
+const OUT_OF_RANGE_REPORT_MS: u64 = 1000;
+
+// Payload for the sensor-out-of-range event.
+#[derive(Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct OutOfRange {
+    connection_id: String,
+    sensor: usize,
+    name: String,
+    // The value as received, before it was replaced.
+    value: u32,
+}
+
+// Payload for the laser-sensor-raw event.
+#[derive(Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RawSensorValues {
+    connection_id: String,
+    raw: Vec<u32>,
+    smoothed: Vec<u32>,
+}
+
+// Stores freshly parsed values. With several connections the merged values of
+// all devices are returned for emitting. Also returns the index of the first
+// value among the merged values.
+fn store_sensor_values(
+    sensor_data: &Mutex<SensorData>,
+    connection_id: &str,
+    frame: SensorFrame,
+) -> (usize, SensorFrame) {
+    // Update shared sensor data
+    match sensor_data.lock() {
+        Ok(mut sensor_state) => {
+            let offset = sensor_state.update(connection_id, &frame);
+            (offset, sensor_state.frame.clone())
+        }
+        Err(_) => (0, frame),
+    }
+}
+
+// Default change a value needs before delta mode reports it.
+const DEFAULT_DELTA_DEADBAND: u32 = 2;
+// Default time between full frames in delta mode.
+const DEFAULT_KEYFRAME_INTERVAL_MS: u64 = 5000;
+
+// How sensor values are sent to the frontend, shared with the reading
+// threads. In delta mode only values that moved by more than the deadband are
+// emitted as laser-sensor-delta, with a full laser-sensor-data keyframe every
+// keyframe interval so late listeners can catch up.
+pub struct SensorEventMode {
+    pub delta: AtomicBool,
+    pub deadband: AtomicU32,
+    pub keyframe_interval_ms: AtomicU64,
+    // Set by request_sensor_keyframe; the next emit is a full frame.
+    pub keyframe_requested: AtomicBool,
+}
+
+impl SensorEventMode {
+    pub fn new() -> Self {
+        Self {
+            delta: AtomicBool::new(false),
+            deadband: AtomicU32::new(DEFAULT_DELTA_DEADBAND),
+            keyframe_interval_ms: AtomicU64::new(DEFAULT_KEYFRAME_INTERVAL_MS),
+            keyframe_requested: AtomicBool::new(false),
+        }
+    }
+}
+
+// Payload for the laser-sensor-delta event.
+#[derive(Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SensorDelta {
+    schema_version: u32,
+    seq: u64,
+    timestamp_ms: u64,
+    // (index, value) of every value that changed since it was last emitted.
+    changes: Vec<(usize, u32)>,
+}
+
+// Limits how often laser-sensor-data is emitted. Values that arrive too soon
+// are held back, and only the latest of them is emitted once the interval
+// has passed.
+struct EmitLimiter {
+    last_emit: Option<std::time::Instant>,
+    pending: Option<SensorFrame>,
+    // Values as last emitted and when the last full frame went out, for
+    // delta mode.
+    last_sent: Vec<u32>,
+    last_keyframe: Option<std::time::Instant>,
+}
+
+impl EmitLimiter {
+    fn new() -> Self {
+        Self {
+            last_emit: None,
+            pending: None,
+            last_sent: Vec::new(),
+            last_keyframe: None,
+        }
+    }
+
+    // Keeps a frame to be emitted by the next flush.
+    fn hold(&mut self, frame: SensorFrame) {
+        self.pending = Some(frame);
+    }
+
+    // Makes the next emit a full frame, e.g. after a reconnect.
+    fn reset(&mut self) {
+        self.last_keyframe = None;
+    }
+
+    // Whether an emit is allowed at `rate_hz` (0 means unlimited).
+    fn is_due(&self, rate_hz: u32) -> bool {
+        match (rate_hz, self.last_emit) {
+            (0, _) | (_, None) => true,
+            (rate_hz, Some(last_emit)) => {
+                last_emit.elapsed() >= std::time::Duration::from_secs(1) / rate_hz
+            }
+        }
+    }
+
+    fn offer(
+        &mut self,
+        app_handle: &tauri::AppHandle,
+        frame: SensorFrame,
+        rate_hz: u32,
+        mode: &SensorEventMode,
+    ) {
+        self.pending = Some(frame);
+        self.flush(app_handle, rate_hz, mode);
+    }
+
+    // Emits held back values once they are due.
+    fn flush(&mut self, app_handle: &tauri::AppHandle, rate_hz: u32, mode: &SensorEventMode) {
+        if !self.is_due(rate_hz) {
+            return;
+        }
+        let Some(frame) = self.pending.take() else {
+            return;
+        };
+        let now = std::time::Instant::now();
+        self.last_emit = Some(now);
+        if mode.delta.load(Ordering::Relaxed) && !self.keyframe_due(&frame, now, mode) {
+            let deadband = mode.deadband.load(Ordering::Relaxed);
+            let changes: Vec<(usize, u32)> = frame
+                .values
+                .iter()
+                .zip(self.last_sent.iter_mut())
+                .enumerate()
+                .filter(|(_, (&value, last))| value.abs_diff(**last) > deadband)
+                .map(|(index, (&value, last))| {
+                    *last = value;
+                    (index, value)
+                })
+                .collect();
+            if !changes.is_empty() {
+                let _ = app_handle.emit(
+                    "laser-sensor-delta",
+                    SensorDelta {
+                        schema_version: frame.schema_version,
+                        seq: frame.seq,
+                        timestamp_ms: frame.timestamp_ms,
+                        changes,
+                    },
+                );
+            }
+            return;
+        }
+        self.last_sent.clone_from(&frame.values);
+        self.last_keyframe = Some(now);
+        // Emit formatted data
+        let _ = app_handle.emit("laser-sensor-data", frame);
+    }
+
+    // Whether delta mode has to send a full frame now: on request, once the
+    // keyframe interval has passed, or when the number of sensors changed.
+    fn keyframe_due(
+        &self,
+        frame: &SensorFrame,
+        now: std::time::Instant,
+        mode: &SensorEventMode,
+    ) -> bool {
+        let interval =
+            std::time::Duration::from_millis(mode.keyframe_interval_ms.load(Ordering::Relaxed));
+        mode.keyframe_requested.swap(false, Ordering::Relaxed)
+            || frame.values.len() != self.last_sent.len()
+            || self
+                .last_keyframe
+                .is_none_or(|last| now.duration_since(last) >= interval)
+    }
+}
+
+// Number of received bytes over which the share of dropped bytes is judged.
+const CORRUPTION_WINDOW_BYTES: u64 = 2048;
+// Share of dropped bytes above which the baud rate is probably wrong.
+const CORRUPTION_HINT_RATIO: f64 = 0.25;
+
+// Payload for the serial-baud-hint event.
+#[derive(Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BaudHint {
+    connection_id: String,
+    // Share of the received bytes that were dropped.
+    corrupt_ratio: f64,
+    message: String,
+}
+
+// Installed by reset_controller while it waits for the firmware to boot. The
+// handler passes every line on to the command and forgets its keyword state
+// on the first line after the reset.
+pub struct ResetWaiter {
+    sender: Sender<String>,
+    cleared: bool,
+}
+
+impl ResetWaiter {
+    pub fn new(sender: Sender<String>) -> Self {
+        Self {
+            sender,
+            cleared: false,
+        }
+    }
+}
+
+pub type SharedResetWaiter = Arc<Mutex<Option<ResetWaiter>>>;
+
+// Installed by ping_controller; receives the arrival time of every pong.
+pub type SharedPongWaiter = Arc<Mutex<Option<Sender<std::time::Instant>>>>;
+
+// Payload for the frames-dropped event.
+#[derive(Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct FramesDropped {
+    connection_id: String,
+    // Lines missing before the one just received.
+    gap: u16,
+    // Lines missing since the connection was opened.
+    total: u64,
+}
+
+// Per-connection state for turning received lines into events.
+pub struct LineHandler {
+    app_handle: tauri::AppHandle,
+    connection_id: String,
+    sensor_data: Arc<Mutex<SensorData>>,
+    live: LiveSettings,
+    beams: BeamTracker,
+    range_guard: RangeGuard,
+    smoother: Smoother,
+    emit_limiter: EmitLimiter,
+    // Number of lines parsed so far and when the connection started, for the
+    // seq and timestamp of emitted frames.
+    seq: u64,
+    started: std::time::Instant,
+    sensor_count: Arc<SensorCountCheck>,
+    stats: Arc<Mutex<SerialStats>>,
+    // Malformed lines not yet reported, and when the last summary went out.
+    pending_parse_errors: u64,
+    last_parse_summary: Option<std::time::Instant>,
+    // Last reported (expected, actual) pair, so a mismatch is reported once.
+    last_mismatch: Option<(usize, usize)>,
+    watchdog: StallWatchdog,
+    // Debounce state of each keyword.
+    buzzer_debounce: KeywordDebouncer,
+    start_debounce: KeywordDebouncer,
+    duplicates: DuplicateFilter,
+    sequence: SequenceTracker,
+    reset_waiter: SharedResetWaiter,
+    pong_waiter: SharedPongWaiter,
+    ack_sender: Option<Sender<u32>>,
+    // Received and dropped bytes of the current corruption window, and
+    // whether the baud rate hint was already given.
+    window_bytes: (u64, u64),
+    baud_hint_sent: bool,
+    // Lines arriving before this are stale data from the OS buffer.
+    settle_until: Option<std::time::Instant>,
+    // While set, sensor frames are held back and end_batch emits the latest.
+    batching: bool,
+}
+
+impl LineHandler {
+    pub fn new(
+        app_handle: tauri::AppHandle,
+        connection_id: String,
+        connection: &SerialConnection,
+        sensor_data: Arc<Mutex<SensorData>>,
+        live: LiveSettings,
+        stall_timeout_ms: u64,
+    ) -> Self {
+        Self {
+            app_handle,
+            connection_id,
+            sensor_data,
+            live,
+            beams: BeamTracker::new(),
+            range_guard: RangeGuard::new(),
+            smoother: Smoother::new(),
+            emit_limiter: EmitLimiter::new(),
+            seq: 0,
+            started: std::time::Instant::now(),
+            sensor_count: Arc::clone(&connection.sensor_count),
+            stats: Arc::clone(&connection.stats),
+            pending_parse_errors: 0,
+            last_parse_summary: None,
+            last_mismatch: None,
+            watchdog: StallWatchdog::new(stall_timeout_ms),
+            buzzer_debounce: KeywordDebouncer::blocked_until(std::time::Instant::now()),
+            start_debounce: KeywordDebouncer::blocked_until(std::time::Instant::now()),
+            duplicates: DuplicateFilter::new(),
+            sequence: SequenceTracker::new(),
+            reset_waiter: Arc::clone(&connection.reset_waiter),
+            pong_waiter: Arc::clone(&connection.pong_waiter),
+            ack_sender: connection.ack_sender.clone(),
+            window_bytes: (0, 0),
+            baud_hint_sent: false,
+            settle_until: None,
+            batching: false,
+        }
+    }
+
+    // Discards everything received in the next `settle_ms`, for data the
+    // controller queued up before the connection was opened. Keywords are
+    // debounced from the end of the window, so a buffered start or buzzer can
+    // never fire.
+    pub fn settle(&mut self, settle_ms: u64) {
+        let settle_until = std::time::Instant::now() + std::time::Duration::from_millis(settle_ms);
+        self.settle_until = Some(settle_until);
+        self.buzzer_debounce = KeywordDebouncer::blocked_until(settle_until);
+        self.start_debounce = KeywordDebouncer::blocked_until(settle_until);
+    }
+
+    fn settling(&mut self) -> bool {
+        match self.settle_until {
+            Some(until) if std::time::Instant::now() < until => true,
+            Some(_) => {
+                self.settle_until = None;
+                false
+            }
+            None => false,
+        }
+    }
+
+    pub fn app_handle(&self) -> &tauri::AppHandle {
+        &self.app_handle
+    }
+
+    pub fn connection_id(&self) -> &str {
+        &self.connection_id
+    }
+
+    // Called on every pass of a reading loop to notice silent connections and
+    // to flush held back sensor data and the log files while the line is quiet.
+    pub fn tick(&mut self) {
+        self.watchdog.check(&self.app_handle, &self.connection_id);
+        let rate_hz = self.live.sensor_event_rate_hz.load(Ordering::Relaxed);
+        self.emit_limiter
+            .flush(&self.app_handle, rate_hz, &self.live.sensor_event_mode);
+        self.report_parse_errors();
+        for log in [&self.live.traffic_log, &self.live.session_recording] {
+            if let Ok(mut log) = log.lock() {
+                if let Some(log) = log.as_mut() {
+                    log.flush_if_due();
+                }
+            }
+        }
+    }
+
+    // Starts handling a batch of lines received at once; see end_batch.
+    pub fn begin_batch(&mut self) {
+        self.batching = true;
+    }
+
+    // Ends a batch and emits the latest sensor frame it produced.
+    pub fn end_batch(&mut self) {
+        self.batching = false;
+        let rate_hz = self.live.sensor_event_rate_hz.load(Ordering::Relaxed);
+        self.emit_limiter
+            .flush(&self.app_handle, rate_hz, &self.live.sensor_event_mode);
+    }
+
+    // Forgets per-stream state after the source was reopened.
+    pub fn reset(&mut self) {
+        self.watchdog.reset();
+        self.smoother.reset();
+        self.duplicates.reset();
+        self.sequence.reset();
+        self.emit_limiter.reset();
+    }
+
+    // Hands a line to a waiting reset_controller. The first line after a reset
+    // also clears the dedup and debounce state, so the rebooted firmware's
+    // first keywords aren't swallowed.
+    fn notify_reset_waiter(&mut self, line: &str) {
+        let reset_waiter = Arc::clone(&self.reset_waiter);
+        let Ok(mut reset_waiter) = reset_waiter.lock() else {
+            return;
+        };
+        let Some(waiter) = reset_waiter.as_mut() else {
+            return;
+        };
+        if !waiter.cleared {
+            waiter.cleared = true;
+            self.duplicates.reset();
+            self.sequence.reset();
+            self.buzzer_debounce.clear();
+            self.start_debounce.clear();
+        }
+        let _ = waiter.sender.send(line.to_string());
+    }
+
+    // Checks the firmware's line counter and reports lines lost on the way.
+    fn check_sequence(&mut self, counter: u16) {
+        let gap = self.sequence.check(counter);
+        if gap == 0 {
+            return;
+        }
+        let total = match self.stats.lock() {
+            Ok(mut stats) => {
+                stats.dropped_frames += gap as u64;
+                stats.dropped_frames
+            }
+            Err(_) => return,
+        };
+        let _ = self.app_handle.emit(
+            "frames-dropped",
+            FramesDropped {
+                connection_id: self.connection_id.clone(),
+                gap,
+                total,
+            },
+        );
+    }
+
+    // Counts bytes dropped from a received line. Once a window of received
+    // data is mostly garbage, a single serial-baud-hint is emitted.
+    pub fn record_dropped_bytes(&mut self, received: usize, dropped: usize) {
+        if dropped > 0 {
+            if let Ok(mut stats) = self.stats.lock() {
+                stats.dropped_bytes += dropped as u64;
+            }
+        }
+        if self.baud_hint_sent {
+            return;
+        }
+        self.window_bytes.0 += received as u64;
+        self.window_bytes.1 += dropped as u64;
+        let (received, dropped) = self.window_bytes;
+        if received < CORRUPTION_WINDOW_BYTES {
+            return;
+        }
+        self.window_bytes = (0, 0);
+        let corrupt_ratio = dropped as f64 / received as f64;
+        if corrupt_ratio > CORRUPTION_HINT_RATIO {
+            self.baud_hint_sent = true;
+            let _ = self.app_handle.emit(
+                "serial-baud-hint",
+                BaudHint {
+                    connection_id: self.connection_id.clone(),
+                    corrupt_ratio,
+                    message: "most received data is garbled; check the baud rate".to_string(),
+                },
+            );
+        }
+    }
+
+    // Counts a malformed line; it is reported with the next summary.
+    fn record_parse_error(&mut self, line: &str) {
+        if let Ok(mut stats) = self.stats.lock() {
+            stats.parse_errors.record(line);
+        }
+        self.pending_parse_errors += 1;
+        self.report_parse_errors();
+    }
+
+    // Emits a serial-parse-errors summary, at most once per summary interval.
+    fn report_parse_errors(&mut self) {
+        let due = self.last_parse_summary.is_none_or(|last| {
+            last.elapsed() >= std::time::Duration::from_millis(PARSE_ERROR_SUMMARY_MS)
+        });
+        if self.pending_parse_errors == 0 || !due {
+            return;
+        }
+        let Ok(stats) = self.stats.lock() else {
+            return;
+        };
+        let _ = self.app_handle.emit(
+            "serial-parse-errors",
+            ParseErrorSummary {
+                connection_id: self.connection_id.clone(),
+                count: self.pending_parse_errors,
+                total: stats.parse_errors.total,
+                samples: stats.parse_errors.recent.iter().cloned().collect(),
+            },
+        );
+        self.pending_parse_errors = 0;
+        self.last_parse_summary = Some(std::time::Instant::now());
+    }
+
+    // Compares the number of values with the expected sensor count and reports
+    // deviations. Returns whether the values should be published.
+    fn check_sensor_count(&mut self, actual: usize) -> bool {
+        let expected = self.sensor_count.expected.load(Ordering::Relaxed);
+        if expected == 0 {
+            self.sensor_count.expected.store(actual, Ordering::Relaxed);
+            self.last_mismatch = None;
+            return true;
+        }
+        if actual == expected {
+            self.last_mismatch = None;
+            return true;
+        }
+
+        let dropped = self.sensor_count.drop_mismatched.load(Ordering::Relaxed);
+        if self.last_mismatch != Some((expected, actual)) {
+            self.last_mismatch = Some((expected, actual));
+            let _ = self.app_handle.emit(
+                "sensor-count-mismatch",
+                SensorCountMismatch {
+                    connection_id: self.connection_id.clone(),
+                    expected,
+                    actual,
+                    dropped,
+                },
+            );
+        }
+        !dropped
+    }
+
+    // Handles values that were already decoded, e.g. from a binary frame.
+    pub fn handle_values(&mut self, mut values: Vec<u32>) {
+        if self.settling() {
+            return;
+        }
+        self.watchdog.feed(&self.app_handle, &self.connection_id);
+        if self.live.paused.load(Ordering::Relaxed) {
+            return;
+        }
+        self.seq += 1;
+        if !self.check_sensor_count(values.len()) {
+            return;
+        }
+        // Implausible values come from wiring faults and would look like hits.
+        let faults = self.range_guard.check(&mut values, &self.live.valid_range);
+        self.live.scaling.apply(&mut values);
+
+        let smoothing = &self.live.smoothing;
+        let window = smoothing.window.load(Ordering::Relaxed);
+        let values = if window > 0 {
+            let smoothed = self.smoother.smooth(&values, window);
+            if smoothing.emit_raw.load(Ordering::Relaxed) {
+                let _ = self.app_handle.emit(
+                    "laser-sensor-raw",
+                    RawSensorValues {
+                        connection_id: self.connection_id.clone(),
+                        raw: values,
+                        smoothed: smoothed.clone(),
+                    },
+                );
+            }
+            smoothed
+        } else {
+            values
+        };
+
+        // SensorData always gets every sample; the frontend may get fewer.
+        let count = values.len();
+        let frame = SensorFrame {
+            schema_version: SENSOR_FRAME_VERSION,
+            seq: self.seq,
+            timestamp_ms: self.started.elapsed().as_millis() as u64,
+            values,
+            disabled: Vec::new(),
+        };
+        let (offset, mut merged) =
+            store_sensor_values(&self.sensor_data, &self.connection_id, frame);
+        merged.disabled = self.live.disabled_sensors.list();
+        if !faults.is_empty() {
+            self.report_out_of_range(offset, &faults);
+        }
+
+        // Compare this connection's values with the thresholds on every sample.
+        let transitions = self.beams.update(
+            &merged.values[offset..offset + count],
+            offset,
+            &self.live.thresholds,
+            &self.live.beam_debounce,
+            std::time::Instant::now(),
+        );
+        if let Some(flaps) = self.beams.new_flaps() {
+            if let Ok(mut stats) = self.stats.lock() {
+                stats.record_flaps(offset, &flaps);
+            }
+        }
+        if self.batching {
+            self.emit_limiter.hold(merged);
+        } else {
+            let rate_hz = self.live.sensor_event_rate_hz.load(Ordering::Relaxed);
+            self.emit_limiter.offer(
+                &self.app_handle,
+                merged,
+                rate_hz,
+                &self.live.sensor_event_mode,
+            );
+        }
+
+        // Report beams that were just broken or restored.
+        for transition in transitions {
+            if let Ok(mut sensor_state) = self.sensor_data.lock() {
+                sensor_state.set_broken(transition.sensor, transition.broken);
+            }
+            self.report_beam(transition.sensor, transition.broken, Some(transition.value));
+        }
+    }
+
+    // Counts replaced values per sensor and emits sensor-out-of-range for
+    // them, at most once per OUT_OF_RANGE_REPORT_MS and sensor.
+    fn report_out_of_range(&mut self, offset: usize, faults: &[(usize, u32)]) {
+        if let Ok(mut stats) = self.stats.lock() {
+            for &(index, _) in faults {
+                let sensor = offset + index;
+                if stats.out_of_range.len() <= sensor {
+                    stats.out_of_range.resize(sensor + 1, 0);
+                }
+                stats.out_of_range[sensor] += 1;
+            }
+        }
+        let now = std::time::Instant::now();
+        let interval = std::time::Duration::from_millis(OUT_OF_RANGE_REPORT_MS);
+        for &(index, value) in faults {
+            if !self.range_guard.due_for_report(index, now, interval) {
+                continue;
+            }
+            let sensor = offset + index;
+            let _ = self.app_handle.emit(
+                "sensor-out-of-range",
+                OutOfRange {
+                    connection_id: self.connection_id.clone(),
+                    sensor,
+                    name: self.live.sensor_names.resolve(sensor),
+                    value,
+                },
+            );
+        }
+    }
+
+    // Emits laser-broken or laser-restored for a sensor (index among all
+    // sensors), unless it is disabled.
+    fn report_beam(&self, sensor: usize, broken: bool, value: Option<u32>) {
+        if self.live.disabled_sensors.contains(sensor) {
+            return;
+        }
+        let event = if broken {
+            "laser-broken"
+        } else {
+            "laser-restored"
+        };
+        let _ = self.app_handle.emit(
+            event,
+            BeamEvent {
+                connection_id: self.connection_id.clone(),
+                sensor,
+                name: self.live.sensor_names.resolve(sensor),
+                value,
+                timestamp_ms: unix_time_ms(),
+            },
+        );
+    }
+
+    // Handles a hit or clear message from a controller that detects beam breaks
+    // itself. `index` counts the sensors of this connection.
+    fn handle_beam_event(&mut self, line: &str, index: usize, broken: bool) {
+        let expected = self.sensor_count.expected.load(Ordering::Relaxed);
+        let limit = if expected > 0 {
+            expected
+        } else {
+            MAX_EVENT_SENSORS
+        };
+        if index >= limit {
+            self.record_parse_error(line);
+            return;
+        }
+        self.watchdog.feed(&self.app_handle, &self.connection_id);
+
+        let changed = match self.sensor_data.lock() {
+            Ok(mut sensor_state) => {
+                let sensor = sensor_state.offset(&self.connection_id) + index;
+                sensor_state.set_broken(sensor, broken).then_some(sensor)
+            }
+            Err(_) => None,
+        };
+        if let Some(sensor) = changed {
+            self.report_beam(sensor, broken, None);
+        }
+    }
+
+    // Handles one received text line, including its line terminator.
+    pub fn handle_line(&mut self, line: &str) {
+        let raw_line = line.trim_end_matches(['\r', '\n']);
+        if self.live.raw_monitor.load(Ordering::Relaxed) {
+            let _ = self.app_handle.emit(
+                "serial-raw-line",
+                RawLine {
+                    connection_id: self.connection_id.clone(),
+                    line: raw_line.to_string(),
+                    timestamp_ms: unix_time_ms(),
+                },
+            );
+        }
+        for log in [&self.live.traffic_log, &self.live.session_recording] {
+            if let Ok(mut log) = log.lock() {
+                if let Some(log) = log.as_mut() {
+                    log.write_line(&self.connection_id, raw_line);
+                }
+            }
+        }
+
+        let trimmed = line.trim();
+
+        // Ignore empty lines and stale data
+        if trimmed.is_empty() || self.settling() {
+            return;
+        }
+        let (counter, trimmed) = split_sequence(trimmed);
+        if let Some(counter) = counter {
+            self.check_sequence(counter);
+        }
+        self.notify_reset_waiter(trimmed);
+        // Ping replies never reach the parser, even when they come too late.
+        if trimmed == PONG_LINE {
+            if let Ok(pong_waiter) = self.pong_waiter.lock() {
+                if let Some(sender) = pong_waiter.as_ref() {
+                    let _ = sender.send(std::time::Instant::now());
+                }
+            }
+            return;
+        }
+        // Acknowledgements of written commands go to the writer thread.
+        if let Some(id) = trimmed.strip_prefix(ACK_PREFIX) {
+            if let (Some(sender), Ok(id)) = (&self.ack_sender, id.parse()) {
+                let _ = sender.send(id);
+            }
+            return;
+        }
+
+        let message = match self.live.syntax.read() {
+            Ok(syntax) => parse_line(trimmed, &syntax),
+            Err(_) => parse_line(trimmed, &LineSyntax::default()),
+        };
+        let Some(message) = message else {
+            // Parse errors are summarized; the raw monitor still shows each line.
+            self.record_parse_error(trimmed);
+            return;
+        };
+        // Ignore repeated keywords; data lines always go through.
+        if !self.duplicates.accept(trimmed, &message) {
+            return;
+        }
+
+        match message {
+            Message::Keyword(keyword) => self.handle_keyword(keyword, trimmed),
+            Message::Values(values) => self.handle_values(values),
+            // Only keywords come through while paused.
+            _ if self.live.paused.load(Ordering::Relaxed) => {}
+            Message::Hit(index) => self.handle_beam_event(trimmed, index, true),
+            Message::Clear(index) => self.handle_beam_event(trimmed, index, false),
+        }
+    }
+
+    // Emits the start-button or buzzer event for a keyword, debounced. `line`
+    // is what triggered it, e.g. the received text.
+    pub fn handle_keyword(&mut self, keyword: Keyword, line: &str) {
+        self.watchdog.feed(&self.app_handle, &self.connection_id);
+        let now = std::time::Instant::now();
+        let debounce = &self.live.debounce;
+        let quiet_ms = debounce.quiet_ms.load(Ordering::Relaxed);
+        let (event, dead_time_ms, debouncer) = match keyword {
+            Keyword::Buzzer => (
+                "buzzer",
+                debounce.buzzer_ms.load(Ordering::Relaxed),
+                &mut self.buzzer_debounce,
+            ),
+            Keyword::Start => (
+                "start-button",
+                debounce.start_ms.load(Ordering::Relaxed),
+                &mut self.start_debounce,
+            ),
+        };
+        // Keywords are debounced using milliseconds
+        if debouncer.accept(now, quiet_ms, dead_time_ms) {
+            // println!("Emitting {} event (debounced)", event);
+            let _ = self.app_handle.emit(
+                event,
+                ButtonEvent {
+                    connection_id: self.connection_id.clone(),
+                    keyword: line.to_string(),
+                    timestamp_ms: unix_time_ms(),
+                },
+            );
+        } else {
+            // println!("Skipping {} event (debounce period)", event);
+        }
+    }
+}
@@ -0,0 +1,773 @@
+// Player profiles, so regulars can follow their personal best. Every saved
+// run belongs to a profile; names that differ only in case or spacing map
+// to the same one once confirmed, and names one typo away from a profile
+// need confirming too.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Mutex;
+use tauri_plugin_store::StoreExt;
+
+use crate::pipeline::unix_time_ms;
+use crate::scores::{Run, ScoreBook, ScoreFile};
+use crate::STORE_FILE;
+
+// Name runs of deleted players are kept under.
+const ANONYMOUS: &str = "Anonymous";
+
+const MAX_NAME_LENGTH_KEY: &str = "players.maxNameLength";
+const DEFAULT_MAX_NAME_LENGTH: usize = 24;
+const NAME_LENGTH_LIMIT: usize = 64;
+
+// Categories players and runs can be put in, e.g. "Kids" and "Adults", in
+// the order the scoreboard lists them.
+const CATEGORIES_KEY: &str = "players.categories";
+
+// Evens out a leaderboard between e.g. kids and adults: a timed score is
+// multiplied by `time_multiplier`, then `bonus_seconds` are taken off.
+#[derive(Clone, Copy, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Handicap {
+    pub time_multiplier: f64,
+    pub bonus_seconds: f64,
+}
+
+impl Default for Handicap {
+    fn default() -> Self {
+        Self {
+            time_multiplier: 1.0,
+            bonus_seconds: 0.0,
+        }
+    }
+}
+
+impl Handicap {
+    fn check(&self) -> Result<(), String> {
+        if !(self.time_multiplier > 0.0 && self.time_multiplier <= 10.0) {
+            return Err("the time multiplier must be above 0 and at most 10".to_string());
+        }
+        if !(self.bonus_seconds >= 0.0 && self.bonus_seconds.is_finite()) {
+            return Err("the bonus can't be negative".to_string());
+        }
+        Ok(())
+    }
+
+    // Adjusts a time in milliseconds.
+    pub fn apply(&self, time_ms: u64) -> u64 {
+        let adjusted = time_ms as f64 * self.time_multiplier - self.bonus_seconds * 1000.0;
+        adjusted.max(0.0).round() as u64
+    }
+}
+
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Player {
+    pub id: u64,
+    pub name: String,
+    pub created_ms: u64,
+    // Group such as "kids" whose handicap applies unless the player has one.
+    #[serde(default)]
+    pub category: Option<String>,
+    #[serde(default)]
+    pub handicap: Option<Handicap>,
+}
+
+// Returned by get_player_stats. Times leave out stealth and reverse runs,
+// which aren't scored by time, relay runs, which are shared, and failed or
+// voided runs; the history keeps them.
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlayerStats {
+    pub player: Player,
+    pub best_time_ms: Option<u64>,
+    pub average_time_ms: Option<u64>,
+    pub total_runs: usize,
+    pub total_hits: u64,
+    // All runs of the player, oldest first.
+    pub history: Vec<Run>,
+}
+
+// Why a name was refused when saving, so the UI can tell the cases apart.
+#[derive(Clone, Debug, PartialEq, serde::Serialize)]
+#[serde(
+    tag = "kind",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum NameError {
+    Empty,
+    TooLong { max_length: usize },
+    // Only symbols, e.g. a row of emoji.
+    NoLetters,
+    // Matches a profile except for case; confirm to save under that profile.
+    SameName { name: String, existing: String },
+    // One typo away from a profile; confirm to create a new one.
+    Similar { name: String, existing: String },
+    // Any other failure while saving.
+    Other { message: String },
+}
+
+impl fmt::Display for NameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "the player needs a name"),
+            Self::TooLong { max_length } => {
+                write!(f, "the name can have at most {} characters", max_length)
+            }
+            Self::NoLetters => write!(f, "the name needs at least one letter or digit"),
+            Self::SameName { name, existing } => write!(
+                f,
+                "{} matches the existing player {}; confirm to save under that player",
+                name, existing
+            ),
+            Self::Similar { name, existing } => write!(
+                f,
+                "{} is close to the existing player {}; confirm to create a new player",
+                name, existing
+            ),
+            Self::Other { message } => write!(f, "{}", message),
+        }
+    }
+}
+
+impl From<String> for NameError {
+    fn from(message: String) -> Self {
+        Self::Other { message }
+    }
+}
+
+impl From<NameError> for String {
+    fn from(error: NameError) -> Self {
+        error.to_string()
+    }
+}
+
+// Characters that don't show but would make two names differ.
+fn is_invisible(c: char) -> bool {
+    c.is_control() || matches!(c, '\u{200B}'..='\u{200D}' | '\u{2060}' | '\u{FEFF}')
+}
+
+// A name with invisible characters and surrounding and repeated spaces
+// removed.
+pub fn normalize_name(name: &str) -> String {
+    let visible: String = name.chars().filter(|&c| !is_invisible(c)).collect();
+    visible.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+// Key under which names count as the same player.
+pub fn name_key(name: &str) -> String {
+    normalize_name(name).to_lowercase()
+}
+
+// Whether two keys are one inserted, removed or replaced character apart.
+fn one_edit_apart(a: &str, b: &str) -> bool {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let (short, long) = if a.len() <= b.len() { (a, b) } else { (b, a) };
+    if long.len() - short.len() > 1 {
+        return false;
+    }
+    let prefix = short.iter().zip(&long).take_while(|(x, y)| x == y).count();
+    if prefix == short.len() {
+        return true;
+    }
+    if short.len() == long.len() {
+        short[prefix + 1..] == long[prefix + 1..]
+    } else {
+        short[prefix..] == long[prefix + 1..]
+    }
+}
+
+pub fn find<'a>(file: &'a ScoreFile, name: &str) -> Option<&'a Player> {
+    let key = name_key(name);
+    file.players
+        .iter()
+        .find(|player| name_key(&player.name) == key)
+}
+
+fn create(file: &mut ScoreFile, name: &str) -> Player {
+    let player = Player {
+        id: file.next_player_id,
+        name: normalize_name(name),
+        created_ms: unix_time_ms(),
+        category: None,
+        handicap: None,
+    };
+    file.next_player_id += 1;
+    file.players.push(player.clone());
+    player
+}
+
+// Checks a name a run is about to be saved under, returning the profile it
+// belongs to if there is one. Existing profiles are always accepted; a new
+// name must fit `max_length` and have a letter or digit. A name that only
+// differs in case from a profile, or looks like a typo of one, is refused
+// unless `confirmed` is set.
+pub fn check_name(
+    file: &ScoreFile,
+    name: &str,
+    confirmed: bool,
+    max_length: usize,
+) -> Result<Option<Player>, NameError> {
+    let name = normalize_name(name);
+    if name.is_empty() {
+        return Err(NameError::Empty);
+    }
+    if let Some(player) = find(file, &name) {
+        if player.name != name && !confirmed {
+            return Err(NameError::SameName {
+                name,
+                existing: player.name.clone(),
+            });
+        }
+        return Ok(Some(player.clone()));
+    }
+    if name.chars().count() > max_length {
+        return Err(NameError::TooLong { max_length });
+    }
+    if !name.chars().any(char::is_alphanumeric) {
+        return Err(NameError::NoLetters);
+    }
+    let key = name_key(&name);
+    if let Some(similar) = file
+        .players
+        .iter()
+        .find(|player| one_edit_apart(&name_key(&player.name), &key))
+    {
+        if !confirmed {
+            return Err(NameError::Similar {
+                name,
+                existing: similar.name.clone(),
+            });
+        }
+    }
+    Ok(None)
+}
+
+// The profile a run saved under `name` belongs to, created if needed; see
+// check_name.
+pub fn resolve(
+    file: &mut ScoreFile,
+    name: &str,
+    confirmed: bool,
+    max_length: usize,
+) -> Result<Player, NameError> {
+    match check_name(file, name, confirmed, max_length)? {
+        Some(player) => Ok(player),
+        None => Ok(create(file, name)),
+    }
+}
+
+// The longest name a new profile may have.
+pub fn max_name_length(app_handle: &tauri::AppHandle) -> usize {
+    app_handle
+        .store(STORE_FILE)
+        .ok()
+        .and_then(|store| store.get(MAX_NAME_LENGTH_KEY))
+        .and_then(|value| value.as_u64())
+        .map_or(DEFAULT_MAX_NAME_LENGTH, |length| length as usize)
+}
+
+// Names of profiles starting with `prefix`, ignoring case and spacing, the
+// players with the most runs first.
+fn suggestions(file: &ScoreFile, prefix: &str, limit: usize) -> Vec<String> {
+    let prefix = name_key(prefix);
+    let mut matches: Vec<(usize, &Player)> = file
+        .players
+        .iter()
+        .filter(|player| name_key(&player.name).starts_with(&prefix))
+        .map(|player| {
+            let runs = file
+                .runs
+                .iter()
+                .filter(|run| run.player_id == Some(player.id))
+                .count();
+            (runs, player)
+        })
+        .collect();
+    matches.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.name.cmp(&b.1.name)));
+    matches
+        .into_iter()
+        .take(limit)
+        .map(|(_, player)| player.name.clone())
+        .collect()
+}
+
+// The handicap runs of a player are saved with: their own, else their
+// category's.
+pub fn handicap_for(file: &ScoreFile, player: &Player) -> Option<Handicap> {
+    player.handicap.or_else(|| {
+        player
+            .category
+            .as_ref()
+            .and_then(|category| file.category_handicaps.get(category).copied())
+    })
+}
+
+// Gives every run without a profile one, e.g. after an upgrade or import.
+pub fn link_runs(file: &mut ScoreFile) {
+    let unlinked: Vec<(u64, String)> = file
+        .runs
+        .iter()
+        .filter(|run| run.player_id.is_none() && run.player != ANONYMOUS)
+        .map(|run| (run.id, run.player.clone()))
+        .collect();
+    for (id, name) in unlinked {
+        let player = match find(file, &name) {
+            Some(player) => player.clone(),
+            None => create(file, &name),
+        };
+        if let Some(run) = file.runs.get_mut(id) {
+            run.player_id = Some(player.id);
+        }
+    }
+}
+
+fn stats(file: &ScoreFile, player: &Player) -> PlayerStats {
+    let mut history: Vec<Run> = file
+        .runs
+        .iter()
+        .filter(|run| run.player_id == Some(player.id))
+        .cloned()
+        .collect();
+    history.sort_by_key(|run| (run.timestamp_ms, run.id));
+    let times: Vec<u64> = history
+        .iter()
+        .filter(|run| run.is_timed() && run.ranks() && run.relay_id.is_none())
+        .map(|run| run.elapsed_ms)
+        .collect();
+    PlayerStats {
+        player: player.clone(),
+        best_time_ms: times.iter().min().copied(),
+        average_time_ms: (!times.is_empty())
+            .then(|| times.iter().sum::<u64>() / times.len() as u64),
+        total_runs: history.len(),
+        total_hits: history.iter().map(|run| u64::from(run.hits)).sum(),
+        history,
+    }
+}
+
+// Command to create a player profile ahead of their first run.
+#[tauri::command]
+pub fn create_player(
+    name: String,
+    confirmed: Option<bool>,
+    app_handle: tauri::AppHandle,
+    scores: tauri::State<Mutex<ScoreBook>>,
+) -> Result<Player, NameError> {
+    let max_length = max_name_length(&app_handle);
+    let mut book = scores.lock().map_err(|e| e.to_string())?;
+    if let Some(player) = find(book.file(), &name) {
+        return Err(format!("the player {} already exists", player.name).into());
+    }
+    check_name(book.file(), &name, confirmed.unwrap_or(false), max_length)?;
+    Ok(book.update(|file| Ok(create(file, &name)))?)
+}
+
+// Command to suggest existing player names while one is typed.
+#[tauri::command]
+pub fn suggest_player_names(
+    prefix: String,
+    limit: Option<usize>,
+    scores: tauri::State<Mutex<ScoreBook>>,
+) -> Result<Vec<String>, String> {
+    let book = scores.lock().map_err(|e| e.to_string())?;
+    Ok(suggestions(book.file(), &prefix, limit.unwrap_or(5)))
+}
+
+// Command to fetch the longest name a new player may have.
+#[tauri::command]
+pub fn get_max_name_length(app_handle: tauri::AppHandle) -> usize {
+    max_name_length(&app_handle)
+}
+
+// Command to change the longest name a new player may have. Existing
+// profiles keep their names.
+#[tauri::command]
+pub fn set_max_name_length(length: usize, app_handle: tauri::AppHandle) -> Result<(), String> {
+    if !(1..=NAME_LENGTH_LIMIT).contains(&length) {
+        return Err(format!(
+            "the maximum name length must be between 1 and {}",
+            NAME_LENGTH_LIMIT
+        ));
+    }
+    let store = app_handle.store(STORE_FILE).map_err(|e| e.to_string())?;
+    store.set(MAX_NAME_LENGTH_KEY, length);
+    Ok(())
+}
+
+// Command to list all player profiles.
+#[tauri::command]
+pub fn list_players(scores: tauri::State<Mutex<ScoreBook>>) -> Result<Vec<Player>, String> {
+    let book = scores.lock().map_err(|e| e.to_string())?;
+    Ok(book.file().players.clone())
+}
+
+fn find_mut<'a>(file: &'a mut ScoreFile, name: &str) -> Result<&'a mut Player, String> {
+    let key = name_key(name);
+    file.players
+        .iter_mut()
+        .find(|player| name_key(&player.name) == key)
+        .ok_or_else(|| format!("no player named {}", name))
+}
+
+// Command to set or clear the handicap of a player. Saved runs keep the
+// handicap they were played with.
+#[tauri::command]
+pub fn set_player_handicap(
+    name: String,
+    handicap: Option<Handicap>,
+    scores: tauri::State<Mutex<ScoreBook>>,
+) -> Result<Player, String> {
+    if let Some(handicap) = &handicap {
+        handicap.check()?;
+    }
+    let mut book = scores.lock().map_err(|e| e.to_string())?;
+    book.update(|file| {
+        let player = find_mut(file, &name)?;
+        player.handicap = handicap;
+        Ok(player.clone())
+    })
+}
+
+// Command to put a player into a category, or none.
+#[tauri::command]
+pub fn set_player_category(
+    name: String,
+    category: Option<String>,
+    app_handle: tauri::AppHandle,
+    scores: tauri::State<Mutex<ScoreBook>>,
+) -> Result<Player, String> {
+    let category = category
+        .map(|category| check_category(&app_handle, &category))
+        .transpose()?;
+    let mut book = scores.lock().map_err(|e| e.to_string())?;
+    book.update(|file| {
+        let player = find_mut(file, &name)?;
+        player.category = category;
+        Ok(player.clone())
+    })
+}
+
+// Command to set or clear the handicap of a category.
+#[tauri::command]
+pub fn set_category_handicap(
+    category: String,
+    handicap: Option<Handicap>,
+    scores: tauri::State<Mutex<ScoreBook>>,
+) -> Result<(), String> {
+    if let Some(handicap) = &handicap {
+        handicap.check()?;
+    }
+    let category = normalize_name(&category);
+    if category.is_empty() {
+        return Err("the category needs a name".to_string());
+    }
+    let mut book = scores.lock().map_err(|e| e.to_string())?;
+    book.update(|file| {
+        match handicap {
+            Some(handicap) => file.category_handicaps.insert(category, handicap),
+            None => file.category_handicaps.remove(&category),
+        };
+        Ok(())
+    })
+}
+
+// Command to fetch the handicap of every category.
+#[tauri::command]
+pub fn get_category_handicaps(
+    scores: tauri::State<Mutex<ScoreBook>>,
+) -> Result<BTreeMap<String, Handicap>, String> {
+    let book = scores.lock().map_err(|e| e.to_string())?;
+    Ok(book.file().category_handicaps.clone())
+}
+
+pub fn categories(app_handle: &tauri::AppHandle) -> Vec<String> {
+    app_handle
+        .store(STORE_FILE)
+        .ok()
+        .and_then(|store| store.get(CATEGORIES_KEY))
+        .and_then(|value| serde_json::from_value(value).ok())
+        .unwrap_or_default()
+}
+
+fn save_categories(app_handle: &tauri::AppHandle, categories: &[String]) -> Result<(), String> {
+    let store = app_handle.store(STORE_FILE).map_err(|e| e.to_string())?;
+    store.set(
+        CATEGORIES_KEY,
+        serde_json::to_value(categories).map_err(|e| e.to_string())?,
+    );
+    Ok(())
+}
+
+// The configured spelling of `category`. Until categories are configured
+// any name is taken.
+pub fn check_category(app_handle: &tauri::AppHandle, category: &str) -> Result<String, String> {
+    let category = normalize_name(category);
+    if category.is_empty() {
+        return Err("the category needs a name".to_string());
+    }
+    let categories = categories(app_handle);
+    if categories.is_empty() {
+        return Ok(category);
+    }
+    categories
+        .into_iter()
+        .find(|c| name_key(c) == name_key(&category))
+        .ok_or_else(|| format!("there is no category {}", category))
+}
+
+// Moves the players and runs of category `from` to `to`, or out of any
+// category. The handicap of `from` goes along unless `to` has its own.
+// Returns the number of runs moved.
+pub fn reassign_category(file: &mut ScoreFile, from: &str, to: Option<&str>) -> usize {
+    let key = name_key(from);
+    let in_from =
+        |category: &Option<String>| category.as_deref().is_some_and(|c| name_key(c) == key);
+    for player in file.players.iter_mut().filter(|p| in_from(&p.category)) {
+        player.category = to.map(str::to_string);
+    }
+    let mut moved = 0;
+    for run in file.runs.iter_mut_where(|run| in_from(&run.category)) {
+        run.category = to.map(str::to_string);
+        moved += 1;
+    }
+    let handicaps: Vec<String> = file
+        .category_handicaps
+        .keys()
+        .filter(|c| name_key(c) == key)
+        .cloned()
+        .collect();
+    for category in handicaps {
+        let handicap = file.category_handicaps.remove(&category);
+        if let (Some(to), Some(handicap)) = (to, handicap) {
+            file.category_handicaps
+                .entry(to.to_string())
+                .or_insert(handicap);
+        }
+    }
+    moved
+}
+
+// Command to fetch the configured categories.
+#[tauri::command]
+pub fn get_categories(app_handle: tauri::AppHandle) -> Vec<String> {
+    categories(&app_handle)
+}
+
+// Command to add a category at the end of the list. Returns the list.
+#[tauri::command]
+pub fn add_category(name: String, app_handle: tauri::AppHandle) -> Result<Vec<String>, String> {
+    let name = normalize_name(&name);
+    if name.is_empty() {
+        return Err("the category needs a name".to_string());
+    }
+    let mut categories = categories(&app_handle);
+    if categories.iter().any(|c| name_key(c) == name_key(&name)) {
+        return Err(format!("there already is a category {}", name));
+    }
+    categories.push(name);
+    save_categories(&app_handle, &categories)?;
+    Ok(categories)
+}
+
+// Command to rename a category along with its players, runs and handicap.
+// Renaming it to another category merges the two. Returns the list.
+#[tauri::command]
+pub fn rename_category(
+    name: String,
+    new_name: String,
+    app_handle: tauri::AppHandle,
+    scores: tauri::State<Mutex<ScoreBook>>,
+) -> Result<Vec<String>, String> {
+    let name = check_category(&app_handle, &name)?;
+    let new_name = normalize_name(&new_name);
+    if new_name.is_empty() {
+        return Err("the category needs a name".to_string());
+    }
+    let mut categories = categories(&app_handle);
+    // Keeps the spelling of a category merged into.
+    let new_name = categories
+        .iter()
+        .find(|c| name_key(c) == name_key(&new_name) && name_key(c) != name_key(&name))
+        .cloned()
+        .unwrap_or(new_name);
+    if new_name == name {
+        return Ok(categories);
+    }
+    let merged = categories.contains(&new_name);
+    let mut book = scores.lock().map_err(|e| e.to_string())?;
+    book.update(|file| {
+        reassign_category(file, &name, Some(&new_name));
+        Ok(())
+    })?;
+    drop(book);
+    if let Some(at) = categories.iter().position(|c| *c == name) {
+        if merged {
+            categories.remove(at);
+        } else {
+            categories[at] = new_name;
+        }
+        save_categories(&app_handle, &categories)?;
+    }
+    Ok(categories)
+}
+
+// Command to delete a category. Its players and runs move to `reassign_to`,
+// or out of any category. Returns the list.
+#[tauri::command]
+pub fn delete_category(
+    name: String,
+    reassign_to: Option<String>,
+    app_handle: tauri::AppHandle,
+    scores: tauri::State<Mutex<ScoreBook>>,
+) -> Result<Vec<String>, String> {
+    let name = check_category(&app_handle, &name)?;
+    let reassign_to = reassign_to
+        .map(|to| check_category(&app_handle, &to))
+        .transpose()?;
+    if reassign_to
+        .as_deref()
+        .is_some_and(|to| name_key(to) == name_key(&name))
+    {
+        return Err("a category can't be reassigned to itself".to_string());
+    }
+    let mut book = scores.lock().map_err(|e| e.to_string())?;
+    book.update(|file| {
+        reassign_category(file, &name, reassign_to.as_deref());
+        Ok(())
+    })?;
+    drop(book);
+    let mut categories = categories(&app_handle);
+    categories.retain(|c| *c != name);
+    save_categories(&app_handle, &categories)?;
+    Ok(categories)
+}
+
+// Command to fetch the statistics and run history of a player.
+#[tauri::command]
+pub fn get_player_stats(
+    name: String,
+    scores: tauri::State<Mutex<ScoreBook>>,
+) -> Result<PlayerStats, String> {
+    let book = scores.lock().map_err(|e| e.to_string())?;
+    let file = book.file();
+    let player = find(file, &name).ok_or_else(|| format!("no player named {}", name))?;
+    Ok(stats(file, player))
+}
+
+// Command to delete a player profile. With `cascade` their runs are deleted
+// too; otherwise the runs stay on the leaderboards as anonymous runs.
+#[tauri::command]
+pub fn delete_player(
+    name: String,
+    cascade: bool,
+    scores: tauri::State<Mutex<ScoreBook>>,
+) -> Result<(), String> {
+    let mut book = scores.lock().map_err(|e| e.to_string())?;
+    book.update(|file| {
+        let id = find(file, &name)
+            .ok_or_else(|| format!("no player named {}", name))?
+            .id;
+        file.players.retain(|player| player.id != id);
+        if cascade {
+            file.runs.retain(|run| run.player_id != Some(id));
+        } else {
+            for run in file.runs.iter_mut_where(|run| run.player_id == Some(id)) {
+                run.player_id = None;
+                run.player = ANONYMOUS.to_string();
+            }
+        }
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_names_ignoring_case_and_spacing() {
+        assert_eq!(name_key("  Anna   Lena "), "anna lena");
+        assert!(one_edit_apart("anna", "ana"));
+        assert!(one_edit_apart("anna", "anne"));
+        assert!(!one_edit_apart("anna", "ben"));
+        assert!(!one_edit_apart("anna", "annabel"));
+    }
+
+    #[test]
+    fn validates_new_names() {
+        let mut file = ScoreFile::new();
+        assert_eq!(normalize_name(" Anna\u{200B}  Lena\t"), "Anna Lena");
+        assert_eq!(
+            check_name(&file, " \u{FEFF} ", false, 10).unwrap_err(),
+            NameError::Empty
+        );
+        assert_eq!(
+            check_name(&file, "Maximilian Mustermann", false, 10).unwrap_err(),
+            NameError::TooLong { max_length: 10 }
+        );
+        assert_eq!(
+            check_name(&file, "🔥🔥 !", false, 10).unwrap_err(),
+            NameError::NoLetters
+        );
+        let anna = resolve(&mut file, "  Anna   Lena ", false, 10).unwrap();
+        assert_eq!(anna.name, "Anna Lena");
+        // Existing profiles stay usable after the limit is lowered.
+        assert_eq!(
+            resolve(&mut file, "Anna Lena", false, 3).unwrap().id,
+            anna.id
+        );
+    }
+
+    #[test]
+    fn merges_case_variants_once_confirmed() {
+        let mut file = ScoreFile::new();
+        let anna = resolve(&mut file, "Anna", false, 24).unwrap();
+        assert_eq!(
+            resolve(&mut file, "ANNA", false, 24).unwrap_err(),
+            NameError::SameName {
+                name: "ANNA".to_string(),
+                existing: "Anna".to_string(),
+            }
+        );
+        assert_eq!(resolve(&mut file, " ANNA ", true, 24).unwrap().id, anna.id);
+        assert!(matches!(
+            resolve(&mut file, "Anne", false, 24),
+            Err(NameError::Similar { .. })
+        ));
+        assert_eq!(file.players.len(), 1);
+    }
+
+    #[test]
+    fn suggests_names_by_prefix() {
+        let mut file = ScoreFile::new();
+        for name in ["Anna", "Annabel", "Ben"] {
+            resolve(&mut file, name, true, 24).unwrap();
+        }
+        let run = serde_json::json!({
+            "id": 1, "player": "Annabel", "mode": "timeAttack", "elapsedMs": 30_000,
+            "hits": 0, "penaltyMs": 0, "timestampMs": 0, "playerId": file.players[1].id
+        });
+        file.runs.push(serde_json::from_value(run).unwrap());
+        assert_eq!(suggestions(&file, " ann", 5), ["Annabel", "Anna"]);
+        assert_eq!(suggestions(&file, "ANN", 1), ["Annabel"]);
+        assert!(suggestions(&file, "x", 5).is_empty());
+    }
+
+    #[test]
+    fn handicaps_adjust_times() {
+        let kids = Handicap {
+            time_multiplier: 0.8,
+            bonus_seconds: 5.0,
+        };
+        assert_eq!(kids.apply(60_000), 43_000);
+        assert_eq!(kids.apply(4_000), 0);
+        assert!(Handicap {
+            time_multiplier: 0.0,
+            ..kids
+        }
+        .check()
+        .is_err());
+    }
+}
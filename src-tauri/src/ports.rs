@@ -0,0 +1,799 @@
+// Serial port enumeration and the hot-plug watcher.
+
+use std::io::{BufRead, BufReader, Read};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread::{self, JoinHandle};
+use tauri::{Emitter, Manager};
+use tauri_plugin_store::StoreExt;
+
+use crate::protocol::{is_valid_line, BinaryDecoder, LineSplitter, LineSyntax, Protocol};
+use crate::reader::{MAX_CONSECUTIVE_READ_ERRORS, RECONNECT_INTERVAL_MS};
+use crate::{
+    SensorData, SerialManager, DEFAULT_RECONNECT_ATTEMPTS, DEFAULT_STALL_TIMEOUT_MS, STORE_FILE,
+};
+
+// How often the watcher looks for added or removed ports.
+const WATCH_INTERVAL_MS: u64 = 1000;
+// Baud rates tried by detect_baud_rate, most common first.
+const PROBE_BAUD_RATES: [u32; 5] = [9600, 19200, 57600, 115200, 250000];
+// How long each baud rate is listened to while probing.
+const PROBE_DURATION_MS: u64 = 500;
+// How long a baud rate may go without valid data before a fallback is tried.
+const FALLBACK_PROBE_MS: u64 = 2000;
+// Delay between open attempts while a port is still coming up.
+const OPEN_RETRY_INTERVAL_MS: u64 = 500;
+// How long check_connection_detailed listens for data by default.
+const CHECK_WINDOW_MS: u64 = 1500;
+// USB vendor ids of boards and USB-serial chips laser controllers use:
+// Arduino (two ids), WCH CH340, Silicon Labs CP210x and FTDI.
+const CONTROLLER_VENDOR_IDS: [u16; 5] = [0x2341, 0x2a03, 0x1a86, 0x10c4, 0x0403];
+// Store key of extra VID/PID pairs of custom controller boards.
+const CONTROLLER_USB_IDS_KEY: &str = "arduinoSettings.controllerUsbIds";
+
+// What kind of device a port belongs to.
+#[derive(Clone, Copy, PartialEq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PortType {
+    Usb,
+    Bluetooth,
+    Pci,
+    Unknown,
+}
+
+// A serial port together with its USB details, if it has any.
+#[derive(Clone, PartialEq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PortInfo {
+    pub name: String,
+    pub port_type: PortType,
+    pub vid: Option<u16>,
+    pub pid: Option<u16>,
+    pub manufacturer: Option<String>,
+    pub product: Option<String>,
+    pub serial_number: Option<String>,
+}
+
+impl From<serialport::SerialPortInfo> for PortInfo {
+    fn from(info: serialport::SerialPortInfo) -> Self {
+        match info.port_type {
+            serialport::SerialPortType::UsbPort(usb) => Self {
+                name: info.port_name,
+                port_type: PortType::Usb,
+                vid: Some(usb.vid),
+                pid: Some(usb.pid),
+                manufacturer: usb.manufacturer,
+                product: usb.product,
+                serial_number: usb.serial_number,
+            },
+            other => Self {
+                name: info.port_name,
+                port_type: match other {
+                    serialport::SerialPortType::BluetoothPort => PortType::Bluetooth,
+                    serialport::SerialPortType::PciPort => PortType::Pci,
+                    _ => PortType::Unknown,
+                },
+                vid: None,
+                pid: None,
+                manufacturer: None,
+                product: None,
+                serial_number: None,
+            },
+        }
+    }
+}
+
+// Default read timeout of an open port.
+pub const DEFAULT_READ_TIMEOUT_MS: u64 = 1000;
+
+// Line settings used to open a port. Defaults to 8N1 without flow control.
+#[derive(Clone)]
+pub struct PortSettings {
+    pub baud_rate: u32,
+    // How long a read waits for data; also how often the reading loop checks
+    // for a stop signal while the line is quiet.
+    pub read_timeout_ms: u64,
+    pub data_bits: serialport::DataBits,
+    pub parity: serialport::Parity,
+    pub stop_bits: serialport::StopBits,
+    pub flow_control: serialport::FlowControl,
+}
+
+impl PortSettings {
+    // Builds the settings from the optional string parameters of a command.
+    pub fn parse(
+        baud_rate: u32,
+        data_bits: Option<&str>,
+        parity: Option<&str>,
+        stop_bits: Option<&str>,
+        flow_control: Option<&str>,
+    ) -> Result<Self, String> {
+        let data_bits = match data_bits.unwrap_or("8") {
+            "5" => serialport::DataBits::Five,
+            "6" => serialport::DataBits::Six,
+            "7" => serialport::DataBits::Seven,
+            "8" => serialport::DataBits::Eight,
+            other => return Err(format!("invalid data bits: {} (expected 5-8)", other)),
+        };
+        let parity = match parity.unwrap_or("none") {
+            "none" => serialport::Parity::None,
+            "odd" => serialport::Parity::Odd,
+            "even" => serialport::Parity::Even,
+            other => {
+                return Err(format!(
+                    "invalid parity: {} (expected none, odd or even)",
+                    other
+                ))
+            }
+        };
+        let stop_bits = match stop_bits.unwrap_or("1") {
+            "1" => serialport::StopBits::One,
+            "2" => serialport::StopBits::Two,
+            other => return Err(format!("invalid stop bits: {} (expected 1 or 2)", other)),
+        };
+        let flow_control = match flow_control.unwrap_or("none") {
+            "none" => serialport::FlowControl::None,
+            "software" => serialport::FlowControl::Software,
+            "hardware" => serialport::FlowControl::Hardware,
+            other => {
+                return Err(format!(
+                    "invalid flow control: {} (expected none, software or hardware)",
+                    other
+                ))
+            }
+        };
+
+        if baud_rate == 0 {
+            return Err("baud rate must be greater than 0".to_string());
+        }
+        // UARTs only support 1.5 stop bits with 5 data bits, which serialport can't express.
+        if data_bits == serialport::DataBits::Five && stop_bits == serialport::StopBits::Two {
+            return Err("2 stop bits are not supported with 5 data bits".to_string());
+        }
+
+        Ok(Self {
+            baud_rate,
+            read_timeout_ms: DEFAULT_READ_TIMEOUT_MS,
+            data_bits,
+            parity,
+            stop_bits,
+            flow_control,
+        })
+    }
+
+    // Sets the read timeout, keeping the default without one.
+    pub fn with_read_timeout(mut self, read_timeout_ms: Option<u64>) -> Result<Self, String> {
+        if let Some(read_timeout_ms) = read_timeout_ms {
+            if read_timeout_ms == 0 {
+                return Err("read timeout must be greater than 0".to_string());
+            }
+            self.read_timeout_ms = read_timeout_ms;
+        }
+        Ok(self)
+    }
+
+    // The default 8N1 settings at the given baud rate.
+    pub fn with_baud_rate(baud_rate: u32) -> Self {
+        Self {
+            baud_rate,
+            read_timeout_ms: DEFAULT_READ_TIMEOUT_MS,
+            data_bits: serialport::DataBits::Eight,
+            parity: serialport::Parity::None,
+            stop_bits: serialport::StopBits::One,
+            flow_control: serialport::FlowControl::None,
+        }
+    }
+}
+
+// Timing of a serial connection. Bluetooth serial ports take seconds to come
+// up after pairing and drop out briefly now and then, so they are given more
+// time before a read or open failure counts.
+#[derive(Clone, Copy, PartialEq)]
+pub enum ConnectionProfile {
+    Usb,
+    Bluetooth,
+}
+
+impl ConnectionProfile {
+    // Parses the connection_profile parameter. Without one the profile follows
+    // the type of the port.
+    pub fn parse(profile: Option<&str>, port: &str) -> Result<Self, String> {
+        match profile {
+            Some("usb") => Ok(Self::Usb),
+            Some("bluetooth") => Ok(Self::Bluetooth),
+            Some(other) => Err(format!(
+                "invalid connection profile: {} (expected usb or bluetooth)",
+                other
+            )),
+            None => {
+                let is_bluetooth = available_ports()
+                    .unwrap_or_default()
+                    .iter()
+                    .any(|info| info.name == port && info.port_type == PortType::Bluetooth);
+                Ok(if is_bluetooth {
+                    Self::Bluetooth
+                } else {
+                    Self::Usb
+                })
+            }
+        }
+    }
+
+    // How long failed opens are retried before giving up.
+    pub fn open_timeout_ms(self) -> u64 {
+        match self {
+            Self::Usb => 0,
+            Self::Bluetooth => 10000,
+        }
+    }
+
+    // Failed reads in a row before the connection counts as lost.
+    pub fn max_read_errors(self) -> u32 {
+        match self {
+            Self::Usb => MAX_CONSECUTIVE_READ_ERRORS,
+            Self::Bluetooth => 10,
+        }
+    }
+
+    // Default time without data before serial-stalled is emitted. Gaps of one
+    // to two seconds are normal over Bluetooth.
+    pub fn stall_timeout_ms(self) -> u64 {
+        match self {
+            Self::Usb => DEFAULT_STALL_TIMEOUT_MS,
+            Self::Bluetooth => 6000,
+        }
+    }
+
+    // Delay between reopen attempts after the connection was lost.
+    pub fn reconnect_interval_ms(self) -> u64 {
+        match self {
+            Self::Usb => RECONNECT_INTERVAL_MS,
+            Self::Bluetooth => 5000,
+        }
+    }
+
+    // Default number of reopen attempts; dropouts are common over Bluetooth.
+    pub fn reconnect_attempts(self) -> u32 {
+        match self {
+            Self::Usb => DEFAULT_RECONNECT_ATTEMPTS,
+            Self::Bluetooth => 30,
+        }
+    }
+}
+
+// Opens a serial port, retrying failed attempts for as long as the profile
+// allows.
+pub fn open_port_with_profile(
+    port: &str,
+    settings: &PortSettings,
+    profile: ConnectionProfile,
+) -> serialport::Result<Box<dyn serialport::SerialPort>> {
+    let deadline =
+        std::time::Instant::now() + std::time::Duration::from_millis(profile.open_timeout_ms());
+    loop {
+        match open_port(port, settings) {
+            Ok(serial_port) => return Ok(serial_port),
+            Err(e) if std::time::Instant::now() >= deadline => return Err(e),
+            Err(_) => thread::sleep(std::time::Duration::from_millis(OPEN_RETRY_INTERVAL_MS)),
+        }
+    }
+}
+
+// What keeps a port from being used, judged from the OS error.
+#[derive(Clone, Copy, PartialEq)]
+pub enum PortProblem {
+    // Another program, e.g. the Arduino IDE's serial monitor, has it open.
+    Busy,
+    // The user may not open serial ports at all.
+    PermissionDenied,
+    NotFound,
+    Other,
+}
+
+impl PortProblem {
+    pub fn classify(kind: Option<std::io::ErrorKind>, description: &str) -> Self {
+        let description = description.to_lowercase();
+        match kind {
+            Some(std::io::ErrorKind::ResourceBusy) => Self::Busy,
+            // Windows reports a port another program holds as "Access is denied".
+            _ if description.contains("busy") || description.contains("access is denied") => {
+                Self::Busy
+            }
+            Some(std::io::ErrorKind::PermissionDenied) => Self::PermissionDenied,
+            _ if description.contains("permission denied") => Self::PermissionDenied,
+            Some(std::io::ErrorKind::NotFound) => Self::NotFound,
+            _ => Self::Other,
+        }
+    }
+
+    fn from_serial(error: &serialport::Error) -> Self {
+        match error.kind() {
+            serialport::ErrorKind::Io(kind) => Self::classify(Some(kind), &error.description),
+            serialport::ErrorKind::NoDevice => match Self::classify(None, &error.description) {
+                Self::Other => Self::NotFound,
+                problem => problem,
+            },
+            _ => Self::classify(None, &error.description),
+        }
+    }
+
+    // What the operator can do about it.
+    pub fn hint(self) -> Option<&'static str> {
+        match self {
+            Self::Busy => {
+                Some("close other programs using this port, such as the Arduino IDE serial monitor")
+            }
+            // On Linux serial ports belong to the dialout group.
+            Self::PermissionDenied if cfg!(target_os = "linux") => Some(
+                "add your user to the dialout group (sudo usermod -aG dialout $USER) and log in again",
+            ),
+            Self::PermissionDenied => Some("check that your user may access serial ports"),
+            Self::NotFound => Some("check that the controller is plugged in"),
+            Self::Other => None,
+        }
+    }
+}
+
+// Error returned by configure_serial. Every kind carries a readable message;
+// the port-specific ones also a hint for the operator.
+#[derive(Debug, serde::Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum PortError {
+    PortBusy {
+        port: String,
+        hint: String,
+        message: String,
+    },
+    PermissionDenied {
+        port: String,
+        hint: String,
+        message: String,
+    },
+    NotFound {
+        port: String,
+        hint: String,
+        message: String,
+    },
+    Other {
+        message: String,
+    },
+}
+
+impl PortError {
+    // Classifies an error from opening `port`.
+    pub fn open_failed(port: &str, error: &serialport::Error) -> Self {
+        let problem = PortProblem::from_serial(error);
+        let message = format!("failed to open port {}: {}", port, error);
+        let (port, hint) = (
+            port.to_string(),
+            problem.hint().unwrap_or_default().to_string(),
+        );
+        match problem {
+            PortProblem::Busy => Self::PortBusy {
+                port,
+                hint,
+                message,
+            },
+            PortProblem::PermissionDenied => Self::PermissionDenied {
+                port,
+                hint,
+                message,
+            },
+            PortProblem::NotFound => Self::NotFound {
+                port,
+                hint,
+                message,
+            },
+            PortProblem::Other => Self::Other { message },
+        }
+    }
+}
+
+impl From<String> for PortError {
+    fn from(message: String) -> Self {
+        Self::Other { message }
+    }
+}
+
+// Opens a serial port with the given line settings.
+pub fn open_port(
+    port: &str,
+    settings: &PortSettings,
+) -> serialport::Result<Box<dyn serialport::SerialPort>> {
+    serialport::new(port, settings.baud_rate)
+        .data_bits(settings.data_bits)
+        .parity(settings.parity)
+        .stop_bits(settings.stop_bits)
+        .flow_control(settings.flow_control)
+        .timeout(std::time::Duration::from_millis(settings.read_timeout_ms))
+        .open()
+}
+
+// Lists the available ports with their details.
+pub fn available_ports() -> Result<Vec<PortInfo>, String> {
+    serialport::available_ports()
+        .map_err(|e| e.to_string())
+        .map(|ports| ports.into_iter().map(PortInfo::from).collect())
+}
+
+// Finds the port of the USB device with the given serial number, since port
+// names can change between reboots.
+pub fn find_port_by_serial(device_serial: &str) -> Result<String, String> {
+    let ports = available_ports()?;
+    let matches: Vec<&PortInfo> = ports
+        .iter()
+        .filter(|info| info.serial_number.as_deref() == Some(device_serial))
+        .collect();
+    match matches.as_slice() {
+        [info] => Ok(info.name.clone()),
+        [] => {
+            let candidates: Vec<String> = ports
+                .iter()
+                .filter_map(|info| {
+                    let serial = info.serial_number.as_deref()?;
+                    Some(format!("{} ({})", info.name, serial))
+                })
+                .collect();
+            Err(format!(
+                "no port belongs to the device with serial number {}; devices found: {}",
+                device_serial,
+                if candidates.is_empty() {
+                    "none".to_string()
+                } else {
+                    candidates.join(", ")
+                }
+            ))
+        }
+        several => Err(format!(
+            "several ports belong to the device with serial number {}: {}",
+            device_serial,
+            several
+                .iter()
+                .map(|info| info.name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+    }
+}
+
+// USB serial number of the device behind a port, if it has one.
+pub fn serial_number_of(port: &str) -> Option<String> {
+    available_ports()
+        .ok()?
+        .into_iter()
+        .find(|info| info.name == port)?
+        .serial_number
+}
+
+// Holds the background thread that reports plugged and unplugged ports.
+pub struct PortWatcher {
+    thread: Option<JoinHandle<()>>,
+    stop_sender: Option<Sender<()>>,
+}
+
+impl PortWatcher {
+    pub fn new() -> Self {
+        Self {
+            thread: None,
+            stop_sender: None,
+        }
+    }
+
+    pub fn start(&mut self, app_handle: tauri::AppHandle) {
+        if self.thread.is_some() {
+            return;
+        }
+
+        let (stop_tx, stop_rx) = channel();
+        let handle = thread::spawn(move || {
+            let mut known = available_ports().unwrap_or_default();
+            let interval = std::time::Duration::from_millis(WATCH_INTERVAL_MS);
+            // Wait for the next poll, but exit immediately on a stop signal.
+            while let Err(RecvTimeoutError::Timeout) = stop_rx.recv_timeout(interval) {
+                let Ok(current) = available_ports() else {
+                    continue;
+                };
+                for port in current.iter().filter(|p| !known.contains(p)) {
+                    let _ = app_handle.emit("serial-port-added", port);
+                }
+                for port in known.iter().filter(|p| !current.contains(p)) {
+                    let _ = app_handle.emit("serial-port-removed", port);
+                    stop_connections_on(&app_handle, &port.name);
+                }
+                known = current;
+            }
+        });
+
+        self.thread = Some(handle);
+        self.stop_sender = Some(stop_tx);
+    }
+
+    pub fn stop(&mut self) {
+        if let Some(sender) = self.stop_sender.take() {
+            let _ = sender.send(());
+        }
+        if let Some(handle) = self.thread.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+// Stops any connection that was reading from a port that just disappeared.
+fn stop_connections_on(app_handle: &tauri::AppHandle, port: &str) {
+    let manager = app_handle.state::<Arc<Mutex<SerialManager>>>();
+    let Ok(mut manager) = manager.lock() else {
+        return;
+    };
+    let ids: Vec<String> = manager
+        .connections
+        .iter()
+        .filter(|(_, connection)| connection.port == port)
+        .map(|(id, _)| id.clone())
+        .collect();
+    for id in ids {
+        manager.stop_connection(&id);
+        if let Ok(mut sensor_data) = app_handle.state::<Arc<Mutex<SensorData>>>().lock() {
+            sensor_data.remove(&id);
+        }
+    }
+}
+
+// Command to list available serial ports with their USB details.
+#[tauri::command]
+pub fn list_ports_detailed() -> Result<Vec<PortInfo>, String> {
+    available_ports()
+}
+
+// A port offered by list_candidate_ports.
+#[derive(Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CandidatePort {
+    #[serde(flatten)]
+    info: PortInfo,
+    // Whether the USB ids match a known controller board or chip.
+    likely: bool,
+}
+
+// VID/PID pair of a custom controller board, as saved in the store.
+#[derive(Clone, Copy, PartialEq, serde::Deserialize)]
+struct UsbId {
+    vid: u16,
+    pid: u16,
+}
+
+// Command to list the USB ports that could be a laser controller, known
+// boards and chips first. Virtual ports of VPN and debug tools are left out;
+// list_ports_detailed still returns everything.
+#[tauri::command]
+pub fn list_candidate_ports(app_handle: tauri::AppHandle) -> Result<Vec<CandidatePort>, String> {
+    let custom_ids: Vec<UsbId> = app_handle
+        .store(STORE_FILE)
+        .ok()
+        .and_then(|store| store.get(CONTROLLER_USB_IDS_KEY))
+        .and_then(|value| serde_json::from_value(value).ok())
+        .unwrap_or_default();
+    let mut candidates: Vec<CandidatePort> = available_ports()?
+        .into_iter()
+        .filter_map(|info| {
+            let (vid, pid) = info.vid.zip(info.pid)?;
+            let likely =
+                CONTROLLER_VENDOR_IDS.contains(&vid) || custom_ids.contains(&UsbId { vid, pid });
+            Some(CandidatePort { info, likely })
+        })
+        .collect();
+    candidates.sort_by(|a, b| {
+        b.likely
+            .cmp(&a.likely)
+            .then_with(|| a.info.name.cmp(&b.info.name))
+    });
+    Ok(candidates)
+}
+
+// Result of check_connection_detailed.
+#[derive(Clone, Default, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionCheck {
+    opened: bool,
+    data_arrived: bool,
+    // Whether a sensor line or a known keyword arrived, as opposed to
+    // anything some other device might send.
+    valid_data: bool,
+    error: Option<String>,
+}
+
+// Command to check that a port belongs to a laser controller: opens it,
+// listens for up to `window_ms` and reports what arrived. The port is closed
+// again before returning so configure_serial can open it right away.
+#[tauri::command(async)]
+#[allow(clippy::too_many_arguments)]
+pub fn check_connection_detailed(
+    port: String,
+    baud_rate: u32,
+    data_bits: Option<String>,
+    parity: Option<String>,
+    stop_bits: Option<String>,
+    flow_control: Option<String>,
+    window_ms: Option<u64>,
+    state: tauri::State<Arc<Mutex<SerialManager>>>,
+) -> Result<ConnectionCheck, String> {
+    let syntax = {
+        let manager = state.lock().map_err(|e| e.to_string())?;
+        if manager.connections.values().any(|c| c.port == port) {
+            return Err(format!("port {} is in use by an active connection", port));
+        }
+        let syntax = manager
+            .live
+            .syntax
+            .read()
+            .map_err(|e| e.to_string())?
+            .clone();
+        syntax
+    };
+    let settings = PortSettings::parse(
+        baud_rate,
+        data_bits.as_deref(),
+        parity.as_deref(),
+        stop_bits.as_deref(),
+        flow_control.as_deref(),
+    )?;
+
+    let mut serial_port = match open_port(&port, &settings) {
+        Ok(serial_port) => serial_port,
+        Err(e) => {
+            return Ok(ConnectionCheck {
+                error: Some(e.to_string()),
+                ..Default::default()
+            })
+        }
+    };
+    let mut check = ConnectionCheck {
+        opened: true,
+        ..Default::default()
+    };
+    let _ = serial_port.set_timeout(std::time::Duration::from_millis(100));
+
+    let mut splitter = LineSplitter::new();
+    let mut buffer = [0u8; 256];
+    let window = std::time::Duration::from_millis(window_ms.unwrap_or(CHECK_WINDOW_MS));
+    let deadline = std::time::Instant::now() + window;
+    while !check.valid_data && std::time::Instant::now() < deadline {
+        let n = match serial_port.read(&mut buffer) {
+            Ok(n) => n,
+            Err(e) if e.kind() == std::io::ErrorKind::TimedOut => continue,
+            Err(e) => {
+                check.error = Some(e.to_string());
+                break;
+            }
+        };
+        check.data_arrived |= n > 0;
+        check.valid_data = splitter
+            .push(&buffer[..n])
+            .iter()
+            .any(|line| std::str::from_utf8(line).is_ok_and(|line| is_valid_line(line, &syntax)));
+    }
+    // Close the port explicitly; on Windows a handle still open when the
+    // next connect starts shows up as "access denied".
+    drop(serial_port);
+    Ok(check)
+}
+
+// Listens on a port for a short while and counts the lines that parse.
+fn probe_baud_rate(port: &str, baud_rate: u32) -> usize {
+    let Ok(mut serial_port) = open_port(port, &PortSettings::with_baud_rate(baud_rate)) else {
+        return 0;
+    };
+    let _ = serial_port.set_timeout(std::time::Duration::from_millis(100));
+
+    let mut splitter = LineSplitter::new();
+    let mut buffer = [0u8; 256];
+    let deadline = std::time::Instant::now() + std::time::Duration::from_millis(PROBE_DURATION_MS);
+    let mut valid_lines = 0;
+    while std::time::Instant::now() < deadline {
+        // Timeouts and invalid UTF-8 simply don't score.
+        let Ok(n) = serial_port.read(&mut buffer) else {
+            continue;
+        };
+        valid_lines += splitter
+            .push(&buffer[..n])
+            .iter()
+            .filter(|line| {
+                std::str::from_utf8(line)
+                    .is_ok_and(|line| is_valid_line(line, &LineSyntax::default()))
+            })
+            .count();
+    }
+    valid_lines
+}
+
+// Listens on an open port until a line (or binary frame) parses, for up to
+// FALLBACK_PROBE_MS. Returns None if a stop signal arrives first.
+pub fn receives_valid_data(
+    serial_port: &mut dyn serialport::SerialPort,
+    protocol: Protocol,
+    syntax: &RwLock<LineSyntax>,
+    stop_rx: &Receiver<()>,
+) -> Option<bool> {
+    let mut reader = BufReader::new(serial_port);
+    let mut splitter = LineSplitter::new();
+    let mut decoder = BinaryDecoder::new();
+    let deadline = std::time::Instant::now() + std::time::Duration::from_millis(FALLBACK_PROBE_MS);
+    while std::time::Instant::now() < deadline {
+        if stop_rx.try_recv().is_ok() {
+            return None;
+        }
+        // Timeouts and invalid UTF-8 simply don't count.
+        let valid = match protocol {
+            Protocol::Csv => match reader.fill_buf() {
+                Ok(bytes) if !bytes.is_empty() => {
+                    let n = bytes.len();
+                    let lines = splitter.push(bytes);
+                    reader.consume(n);
+                    lines.iter().any(|line| {
+                        let Ok(line) = std::str::from_utf8(line) else {
+                            return false;
+                        };
+                        syntax
+                            .read()
+                            .is_ok_and(|syntax| is_valid_line(line, &syntax))
+                    })
+                }
+                _ => false,
+            },
+            Protocol::Binary => match reader.fill_buf() {
+                Ok(bytes) if !bytes.is_empty() => {
+                    let n = bytes.len();
+                    let frames = decoder.push(bytes);
+                    reader.consume(n);
+                    !frames.is_empty()
+                }
+                _ => false,
+            },
+        };
+        if valid {
+            return Some(true);
+        }
+    }
+    Some(false)
+}
+
+// Command to find the baud rate the controller on a port is sending at.
+// Runs off the main thread since probing takes a few seconds.
+#[tauri::command(async)]
+pub fn detect_baud_rate(
+    port: String,
+    state: tauri::State<Arc<Mutex<SerialManager>>>,
+) -> Result<u32, String> {
+    {
+        let manager = state.lock().map_err(|e| e.to_string())?;
+        if manager.connections.values().any(|c| c.port == port) {
+            return Err(format!("port {} is in use by an active connection", port));
+        }
+    }
+
+    PROBE_BAUD_RATES
+        .iter()
+        .map(|&baud_rate| (baud_rate, probe_baud_rate(&port, baud_rate)))
+        .filter(|&(_, score)| score > 0)
+        .max_by_key(|&(_, score)| score)
+        .map(|(baud_rate, _)| baud_rate)
+        .ok_or_else(|| format!("no parseable data received on {}", port))
+}
+
+// Command to start reporting serial-port-added / serial-port-removed events.
+#[tauri::command]
+pub fn start_port_watcher(
+    app_handle: tauri::AppHandle,
+    state: tauri::State<Mutex<PortWatcher>>,
+) -> Result<(), String> {
+    let mut watcher = state.lock().map_err(|e| e.to_string())?;
+    watcher.start(app_handle);
+    Ok(())
+}
+
+// Command to stop the port watcher.
+#[tauri::command]
+pub fn stop_port_watcher(state: tauri::State<Mutex<PortWatcher>>) -> Result<(), String> {
+    let mut watcher = state.lock().map_err(|e| e.to_string())?;
+    watcher.stop();
+    Ok(())
+}
@@ -0,0 +1,709 @@
+// Wire formats the Arduino can use to send sensor values.
+
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+// Marks the start of a binary frame.
+const SYNC_BYTE: u8 = 0xAA;
+// Longest line kept while waiting for its terminator, so a stream without
+// line endings can't grow the buffer forever.
+const MAX_LINE_LENGTH: usize = 4096;
+
+// How sensor values are encoded on the serial line.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Protocol {
+    // One comma separated line of values per reading.
+    Csv,
+    // Length-prefixed binary frames, see BinaryDecoder.
+    Binary,
+}
+
+impl Protocol {
+    pub fn parse(name: Option<&str>) -> Result<Self, String> {
+        match name.unwrap_or("csv") {
+            "csv" => Ok(Protocol::Csv),
+            "binary" => Ok(Protocol::Binary),
+            other => Err(format!("unknown protocol: {}", other)),
+        }
+    }
+}
+
+// Line sent by ping_controller and the firmware's reply to it.
+pub const PING_LINE: &str = "ping";
+pub const PONG_LINE: &str = "pong";
+
+// Events a keyword line can trigger.
+#[derive(Clone, Copy, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Keyword {
+    Start,
+    Buzzer,
+}
+
+// Configurable parts of the text protocol.
+#[derive(Clone)]
+pub struct LineSyntax {
+    // Literal lines and the event they trigger, e.g. "BTN_START" -> start.
+    pub keywords: BTreeMap<String, Keyword>,
+    // Prefixes of the event-style messages firmwares with on-board beam
+    // detection send, e.g. "hit:4" and "clear:4". Empty disables them.
+    pub hit_prefix: String,
+    pub clear_prefix: String,
+    // Separator between sensor values; whitespace around values is ignored.
+    pub separator: char,
+}
+
+impl Default for LineSyntax {
+    fn default() -> Self {
+        Self {
+            keywords: BTreeMap::from([
+                ("start".to_string(), Keyword::Start),
+                ("buzzer".to_string(), Keyword::Buzzer),
+            ]),
+            hit_prefix: "hit:".to_string(),
+            clear_prefix: "clear:".to_string(),
+            separator: ',',
+        }
+    }
+}
+
+// Parses the value separator passed to configure_serial: a single character,
+// with a literal \t accepted for a tab. Defaults to a comma.
+pub fn parse_separator(separator: Option<&str>) -> Result<char, String> {
+    let separator = match separator {
+        None => return Ok(','),
+        Some("\\t") => return Ok('\t'),
+        Some(separator) => separator,
+    };
+    let mut chars = separator.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if !c.is_ascii_digit() && c != '-' => Ok(c),
+        _ => Err(format!("invalid value separator: {:?}", separator)),
+    }
+}
+
+// A text line received from the controller.
+#[derive(Debug, PartialEq)]
+pub enum Message {
+    Keyword(Keyword),
+    Values(Vec<u32>),
+    // The beam with the given index was broken.
+    Hit(usize),
+    // The beam with the given index was restored.
+    Clear(usize),
+}
+
+impl Message {
+    fn is_keyword(&self) -> bool {
+        !matches!(self, Message::Values(_))
+    }
+}
+
+// Parses the index after a hit or clear prefix, if the line has the prefix.
+fn parse_event(line: &str, prefix: &str) -> Option<Option<usize>> {
+    if prefix.is_empty() {
+        return None;
+    }
+    let index = line.strip_prefix(prefix)?;
+    Some(index.trim().parse::<usize>().ok())
+}
+
+// Parses a trimmed text line: a known keyword, a hit or clear event or a list
+// of sensor values split by the configured separator. Returns None for
+// anything else.
+pub fn parse_line(line: &str, syntax: &LineSyntax) -> Option<Message> {
+    if let Some(&keyword) = syntax.keywords.get(line) {
+        return Some(Message::Keyword(keyword));
+    }
+    if let Some(index) = parse_event(line, &syntax.hit_prefix) {
+        return index.map(Message::Hit);
+    }
+    if let Some(index) = parse_event(line, &syntax.clear_prefix) {
+        return index.map(Message::Clear);
+    }
+    match line {
+        "" => None,
+        _ => line
+            .split(syntax.separator)
+            .map(|s| s.trim().parse::<u32>())
+            .collect::<Result<Vec<u32>, _>>()
+            .ok()
+            .map(Message::Values),
+    }
+}
+
+// Whether a received text line is something the controller would send.
+pub fn is_valid_line(line: &str, syntax: &LineSyntax) -> bool {
+    let (_, line) = split_sequence(line.trim());
+    parse_line(line, syntax).is_some()
+}
+
+// Gaps at least this large are a restarted or repeated counter rather than
+// lost lines.
+const MAX_SEQUENCE_GAP: u16 = 32768;
+
+// Splits off the optional "#<counter>:" prefix firmwares can put before every
+// line, e.g. "#1234:512,488". Lines without it are returned unchanged.
+pub fn split_sequence(line: &str) -> (Option<u16>, &str) {
+    let prefixed = line
+        .strip_prefix('#')
+        .and_then(|rest| rest.split_once(':'))
+        .and_then(|(counter, rest)| Some((counter.parse().ok()?, rest.trim_start())));
+    match prefixed {
+        Some((counter, rest)) => (Some(counter), rest),
+        None => (None, line),
+    }
+}
+
+// Follows the firmware's line counter, which wraps from 65535 to 0, to count
+// the lines lost between the controller and the app.
+pub struct SequenceTracker {
+    last: Option<u16>,
+}
+
+impl SequenceTracker {
+    pub fn new() -> Self {
+        Self { last: None }
+    }
+
+    // Records a counter and returns how many lines are missing before it.
+    pub fn check(&mut self, counter: u16) -> u16 {
+        let gap = self
+            .last
+            .map_or(0, |last| counter.wrapping_sub(last).wrapping_sub(1));
+        self.last = Some(counter);
+        if gap >= MAX_SEQUENCE_GAP {
+            0
+        } else {
+            gap
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.last = None;
+    }
+}
+
+// Drops immediate repeats of keyword messages, e.g. a button reported twice.
+// Data lines are never dropped: a stable maze legitimately sends the same
+// values over and over.
+pub struct DuplicateFilter {
+    last_keyword: Option<String>,
+}
+
+impl DuplicateFilter {
+    pub fn new() -> Self {
+        Self { last_keyword: None }
+    }
+
+    // Whether the parsed message should be handled. `line` is the trimmed text.
+    pub fn accept(&mut self, line: &str, message: &Message) -> bool {
+        if !message.is_keyword() {
+            self.last_keyword = None;
+            return true;
+        }
+        if self.last_keyword.as_deref() == Some(line) {
+            return false;
+        }
+        self.last_keyword = Some(line.to_string());
+        true
+    }
+
+    pub fn reset(&mut self) {
+        self.last_keyword = None;
+    }
+}
+
+// Debounces one keyword, e.g. the buzzer. An event is accepted once the
+// keyword has been quiet for `quiet_ms`, which swallows the burst a bouncing
+// contact sends, and `dead_time_ms` after the last accepted event. Unlike a
+// plain dead time this lets a genuine second press through as soon as the
+// dead time is over, however close the bounces of the first one were.
+pub struct KeywordDebouncer {
+    last_seen: Option<Instant>,
+    last_accepted: Option<Instant>,
+}
+
+impl KeywordDebouncer {
+    // A debouncer that accepts nothing before `dead_time_ms` after `since`.
+    pub fn blocked_until(since: Instant) -> Self {
+        Self {
+            last_seen: None,
+            last_accepted: Some(since),
+        }
+    }
+
+    // Records the keyword arriving at `now` and returns whether it counts as
+    // a new event.
+    pub fn accept(&mut self, now: Instant, quiet_ms: u64, dead_time_ms: u64) -> bool {
+        let quiet = self.last_seen.is_none_or(|seen| {
+            now.saturating_duration_since(seen) >= Duration::from_millis(quiet_ms)
+        });
+        let dead_time_over = self.last_accepted.is_none_or(|accepted| {
+            now.saturating_duration_since(accepted) >= Duration::from_millis(dead_time_ms)
+        });
+        self.last_seen = Some(now);
+        let accepted = quiet && dead_time_over;
+        if accepted {
+            self.last_accepted = Some(now);
+        }
+        accepted
+    }
+
+    // Lets the next keyword through, e.g. after the firmware rebooted.
+    pub fn clear(&mut self) {
+        self.last_seen = None;
+        self.last_accepted = None;
+    }
+}
+
+// CRC-8 with polynomial 0x07 and initial value 0.
+fn crc8(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0u8, |mut crc, byte| {
+        crc ^= byte;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 {
+                (crc << 1) ^ 0x07
+            } else {
+                crc << 1
+            };
+        }
+        crc
+    })
+}
+
+// Splits a byte stream into lines ending in \n, \r or \r\n. Bytes can be
+// pushed in arbitrary chunks. Empty lines, such as the gap inside a \r\n
+// pair, are skipped.
+pub struct LineSplitter {
+    buffer: Vec<u8>,
+}
+
+impl LineSplitter {
+    pub fn new() -> Self {
+        Self { buffer: Vec::new() }
+    }
+
+    // Appends received bytes and returns every completed line without its
+    // terminator.
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<Vec<u8>> {
+        let mut lines = Vec::new();
+        for &byte in bytes {
+            if byte == b'\n' || byte == b'\r' {
+                if !self.buffer.is_empty() {
+                    lines.push(std::mem::take(&mut self.buffer));
+                }
+            } else {
+                self.buffer.push(byte);
+                if self.buffer.len() >= MAX_LINE_LENGTH {
+                    lines.push(std::mem::take(&mut self.buffer));
+                }
+            }
+        }
+        lines
+    }
+}
+
+// Incremental decoder for binary frames laid out as:
+//   0xAA, count, count * little-endian u16, CRC-8 over count and values
+// Bytes can be pushed in arbitrary chunks. After a bad CRC the decoder drops
+// the sync byte and resynchronizes on the next 0xAA.
+pub struct BinaryDecoder {
+    buffer: Vec<u8>,
+    bad_frames: u64,
+}
+
+impl BinaryDecoder {
+    pub fn new() -> Self {
+        Self {
+            buffer: Vec::new(),
+            bad_frames: 0,
+        }
+    }
+
+    // Number of frames rejected because of a CRC mismatch.
+    pub fn bad_frames(&self) -> u64 {
+        self.bad_frames
+    }
+
+    // Drops any partial frame, e.g. after the port was reopened.
+    pub fn reset(&mut self) {
+        self.buffer.clear();
+    }
+
+    // Appends received bytes and returns the values of every complete frame.
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<Vec<u32>> {
+        self.buffer.extend_from_slice(bytes);
+        let mut frames = Vec::new();
+
+        loop {
+            // Discard everything before the next sync byte.
+            match self.buffer.iter().position(|&b| b == SYNC_BYTE) {
+                Some(start) => {
+                    self.buffer.drain(..start);
+                }
+                None => {
+                    self.buffer.clear();
+                    break;
+                }
+            }
+
+            // Wait for the count byte and the rest of the frame.
+            if self.buffer.len() < 2 {
+                break;
+            }
+            let count = self.buffer[1] as usize;
+            let frame_len = 2 + count * 2 + 1;
+            if self.buffer.len() < frame_len {
+                break;
+            }
+
+            let body = &self.buffer[1..frame_len - 1];
+            if crc8(body) == self.buffer[frame_len - 1] {
+                let values = body[1..]
+                    .chunks_exact(2)
+                    .map(|pair| u16::from_le_bytes([pair[0], pair[1]]) as u32)
+                    .collect();
+                frames.push(values);
+                self.buffer.drain(..frame_len);
+            } else {
+                self.bad_frames += 1;
+                self.buffer.drain(..1);
+            }
+        }
+
+        frames
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Pushes the chunks into a fresh splitter and collects the lines as text.
+    fn split(chunks: &[&[u8]]) -> Vec<String> {
+        let mut splitter = LineSplitter::new();
+        chunks
+            .iter()
+            .flat_map(|chunk| splitter.push(chunk))
+            .map(|line| String::from_utf8(line).unwrap())
+            .collect()
+    }
+
+    // Parses and filters a line the way the line handler does.
+    fn accept(filter: &mut DuplicateFilter, line: &str) -> bool {
+        match parse_line(line, &LineSyntax::default()) {
+            Some(message) => filter.accept(line, &message),
+            None => false,
+        }
+    }
+
+    #[test]
+    fn parses_keywords_and_values() {
+        let syntax = LineSyntax::default();
+        assert_eq!(
+            parse_line("start", &syntax),
+            Some(Message::Keyword(Keyword::Start))
+        );
+        assert_eq!(
+            parse_line("buzzer", &syntax),
+            Some(Message::Keyword(Keyword::Buzzer))
+        );
+        assert_eq!(
+            parse_line("1,2,3", &syntax),
+            Some(Message::Values(vec![1, 2, 3]))
+        );
+        assert_eq!(parse_line("1,x,3", &syntax), None);
+        assert_eq!(parse_line("", &syntax), None);
+    }
+
+    #[test]
+    fn parses_hit_and_clear_events() {
+        let syntax = LineSyntax::default();
+        assert_eq!(parse_line("hit:4", &syntax), Some(Message::Hit(4)));
+        assert_eq!(parse_line("clear:4", &syntax), Some(Message::Clear(4)));
+        assert_eq!(parse_line("hit:x", &syntax), None);
+        assert_eq!(parse_line("hit:", &syntax), None);
+
+        let short = LineSyntax {
+            hit_prefix: "b:".to_string(),
+            clear_prefix: "c:".to_string(),
+            ..LineSyntax::default()
+        };
+        assert_eq!(parse_line("b:2", &short), Some(Message::Hit(2)));
+        assert_eq!(parse_line("c:2", &short), Some(Message::Clear(2)));
+        assert_eq!(parse_line("hit:2", &short), None);
+    }
+
+    #[test]
+    fn parses_mapped_keywords() {
+        let syntax = LineSyntax {
+            keywords: BTreeMap::from([
+                ("BTN_START".to_string(), Keyword::Start),
+                ("BTN_STOP".to_string(), Keyword::Buzzer),
+            ]),
+            ..LineSyntax::default()
+        };
+        assert_eq!(
+            parse_line("BTN_STOP", &syntax),
+            Some(Message::Keyword(Keyword::Buzzer))
+        );
+        // Unmapped words fall through to value parsing.
+        assert_eq!(parse_line("start", &syntax), None);
+        assert_eq!(
+            parse_line("5,6", &syntax),
+            Some(Message::Values(vec![5, 6]))
+        );
+    }
+
+    #[test]
+    fn repeated_data_lines_are_kept() {
+        let mut filter = DuplicateFilter::new();
+        assert!(accept(&mut filter, "800,810"));
+        assert!(accept(&mut filter, "800,810"));
+        assert!(accept(&mut filter, "800,810"));
+    }
+
+    #[test]
+    fn repeated_keywords_are_dropped() {
+        let mut filter = DuplicateFilter::new();
+        assert!(accept(&mut filter, "start"));
+        assert!(!accept(&mut filter, "start"));
+        assert!(accept(&mut filter, "buzzer"));
+        assert!(accept(&mut filter, "start"));
+    }
+
+    #[test]
+    fn data_between_keywords_ends_the_repeat() {
+        let mut filter = DuplicateFilter::new();
+        assert!(accept(&mut filter, "buzzer"));
+        assert!(accept(&mut filter, "800,810"));
+        assert!(accept(&mut filter, "buzzer"));
+    }
+
+    #[test]
+    fn bad_lines_do_not_mask_the_next_valid_line() {
+        let mut filter = DuplicateFilter::new();
+        assert!(accept(&mut filter, "start"));
+        assert!(!accept(&mut filter, "garbage"));
+        assert!(!accept(&mut filter, "start"));
+        assert!(accept(&mut filter, "800,810"));
+        assert!(!accept(&mut filter, "800,x"));
+        assert!(accept(&mut filter, "800,810"));
+    }
+
+    #[test]
+    fn reset_forgets_the_last_keyword() {
+        let mut filter = DuplicateFilter::new();
+        assert!(accept(&mut filter, "start"));
+        filter.reset();
+        assert!(accept(&mut filter, "start"));
+    }
+
+    #[test]
+    fn splits_lf_terminated_lines() {
+        assert_eq!(split(&[b"1,2,3\nstart\n"]), ["1,2,3", "start"]);
+    }
+
+    #[test]
+    fn splits_cr_terminated_lines() {
+        assert_eq!(split(&[b"1,2,3\rbuzzer\r"]), ["1,2,3", "buzzer"]);
+    }
+
+    #[test]
+    fn splits_crlf_terminated_lines_without_empty_segments() {
+        assert_eq!(split(&[b"1,2,3\r\nstart\r\n"]), ["1,2,3", "start"]);
+    }
+
+    #[test]
+    fn splits_mixed_endings_across_chunks() {
+        let chunks: [&[u8]; 5] = [b"1,2", b",3\r", b"\nstart\n4,5", b",6\rbuz", b"zer\r\n"];
+        assert_eq!(split(&chunks), ["1,2,3", "start", "4,5,6", "buzzer"]);
+    }
+
+    #[test]
+    fn keeps_unterminated_line_until_its_ending_arrives() {
+        let mut splitter = LineSplitter::new();
+        assert!(splitter.push(b"1,2,3").is_empty());
+        assert_eq!(splitter.push(b"\r"), [b"1,2,3".to_vec()]);
+    }
+
+    #[test]
+    fn cuts_overlong_lines() {
+        let mut splitter = LineSplitter::new();
+        let lines = splitter.push(&[b'1'; MAX_LINE_LENGTH + 10]);
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0].len(), MAX_LINE_LENGTH);
+    }
+
+    // Parses a line with the default syntax but the given separator.
+    fn values(line: &str, separator: char) -> Option<Message> {
+        let syntax = LineSyntax {
+            separator,
+            ..LineSyntax::default()
+        };
+        parse_line(line, &syntax)
+    }
+
+    #[test]
+    fn parses_values_with_any_separator() {
+        let expected = Some(Message::Values(vec![512, 488, 503]));
+        assert_eq!(values("512,488,503", ','), expected);
+        assert_eq!(values("512;488;503", ';'), expected);
+        assert_eq!(values("512\t488\t503", '\t'), expected);
+        assert_eq!(values("512 488 503", ' '), expected);
+    }
+
+    #[test]
+    fn ignores_whitespace_around_values() {
+        let expected = Some(Message::Values(vec![512, 488, 503]));
+        assert_eq!(values("512 ; 488 ; 503", ';'), expected);
+        assert_eq!(values("512 , 488,503", ','), expected);
+        assert_eq!(values("512\t;\t488 ;503", ';'), expected);
+        assert_eq!(values("512 \t 488\t 503", '\t'), expected);
+    }
+
+    #[test]
+    fn rejects_values_split_by_another_separator() {
+        assert_eq!(values("512;488;503", ','), None);
+        assert_eq!(values("512,488,503", ';'), None);
+        assert_eq!(values("512,488,503", '\t'), None);
+        // An empty field is not a value.
+        assert_eq!(values("512;;503", ';'), None);
+        assert_eq!(values("512 ; ; 503", ';'), None);
+    }
+
+    #[test]
+    fn keywords_are_unaffected_by_the_separator() {
+        assert_eq!(values("start", ';'), Some(Message::Keyword(Keyword::Start)));
+        assert_eq!(values("hit:3", '\t'), Some(Message::Hit(3)));
+    }
+
+    #[test]
+    fn parses_separator_setting() {
+        assert_eq!(parse_separator(None), Ok(','));
+        assert_eq!(parse_separator(Some(";")), Ok(';'));
+        assert_eq!(parse_separator(Some("\\t")), Ok('\t'));
+        assert_eq!(parse_separator(Some("\t")), Ok('\t'));
+        assert!(parse_separator(Some("")).is_err());
+        assert!(parse_separator(Some(",;")).is_err());
+        assert!(parse_separator(Some("5")).is_err());
+        assert!(parse_separator(Some("-")).is_err());
+    }
+
+    #[test]
+    fn splits_sequence_prefix() {
+        assert_eq!(split_sequence("#1234:512,488"), (Some(1234), "512,488"));
+        assert_eq!(split_sequence("#0:start"), (Some(0), "start"));
+        assert_eq!(split_sequence("512,488"), (None, "512,488"));
+        // Not a counter: left for the parser to reject.
+        assert_eq!(split_sequence("#x:512"), (None, "#x:512"));
+        assert_eq!(split_sequence("#70000:512"), (None, "#70000:512"));
+    }
+
+    #[test]
+    fn counts_missing_lines() {
+        let mut tracker = SequenceTracker::new();
+        assert_eq!(tracker.check(10), 0);
+        assert_eq!(tracker.check(11), 0);
+        assert_eq!(tracker.check(15), 3);
+        assert_eq!(tracker.check(16), 0);
+    }
+
+    #[test]
+    fn handles_counter_wraparound() {
+        let mut tracker = SequenceTracker::new();
+        tracker.check(65534);
+        assert_eq!(tracker.check(65535), 0);
+        assert_eq!(tracker.check(0), 0);
+        tracker.check(65533);
+        assert_eq!(tracker.check(2), 4);
+    }
+
+    #[test]
+    fn ignores_restarted_counter() {
+        let mut tracker = SequenceTracker::new();
+        tracker.check(5000);
+        assert_eq!(tracker.check(0), 0);
+        assert_eq!(tracker.check(1), 0);
+        // A repeated line is not a loss of the whole range.
+        assert_eq!(tracker.check(1), 0);
+    }
+
+    // Feeds keywords at the given milliseconds of a simulated clock and
+    // returns the ones accepted.
+    fn debounce(
+        debouncer: &mut KeywordDebouncer,
+        start: Instant,
+        times_ms: &[u64],
+        quiet_ms: u64,
+        dead_time_ms: u64,
+    ) -> Vec<u64> {
+        times_ms
+            .iter()
+            .copied()
+            .filter(|&ms| {
+                debouncer.accept(start + Duration::from_millis(ms), quiet_ms, dead_time_ms)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn swallows_contact_bounce() {
+        let start = Instant::now();
+        let mut debouncer = KeywordDebouncer::blocked_until(start);
+        // Five bounces within 50ms count as one press.
+        assert_eq!(
+            debounce(
+                &mut debouncer,
+                start,
+                &[1000, 1010, 1020, 1035, 1050],
+                200,
+                500
+            ),
+            [1000]
+        );
+    }
+
+    #[test]
+    fn accepts_quick_second_press_after_dead_time() {
+        let start = Instant::now();
+        let mut debouncer = KeywordDebouncer::blocked_until(start);
+        let presses = [2000, 2010, 2020, 2030, 2040, 3900, 3910, 3920];
+        assert_eq!(
+            debounce(&mut debouncer, start, &presses, 200, 1500),
+            [2000, 3900]
+        );
+    }
+
+    #[test]
+    fn dead_time_applies_to_clean_presses() {
+        let start = Instant::now();
+        let mut debouncer = KeywordDebouncer::blocked_until(start);
+        assert_eq!(
+            debounce(&mut debouncer, start, &[3000, 4000, 5100], 200, 2000),
+            [3000, 5100]
+        );
+    }
+
+    #[test]
+    fn continuous_bouncing_is_never_quiet() {
+        let start = Instant::now();
+        let mut debouncer = KeywordDebouncer::blocked_until(start);
+        let chatter: Vec<u64> = (0..50).map(|i| 1000 + i * 100).collect();
+        // Only the first arrives after a quiet period.
+        assert_eq!(debounce(&mut debouncer, start, &chatter, 200, 0), [1000]);
+    }
+
+    #[test]
+    fn blocks_until_the_given_time() {
+        let start = Instant::now();
+        let mut debouncer = KeywordDebouncer::blocked_until(start + Duration::from_millis(500));
+        assert_eq!(
+            debounce(&mut debouncer, start, &[100, 1200, 1600], 200, 1000),
+            [1600]
+        );
+        debouncer.clear();
+        assert!(debouncer.accept(start + Duration::from_millis(1700), 200, 1000));
+    }
+}
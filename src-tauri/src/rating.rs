@@ -0,0 +1,396 @@
+// Elo ratings for the league: players gain rating by beating others in
+// head-to-head games and, if enabled, by passing them on the leaderboard of
+// a session. Every change is kept in the scores file, so progress can be
+// graphed; current ratings follow from the latest change.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tauri_plugin_store::StoreExt;
+
+use crate::game::HeadToHeadResult;
+use crate::players::{self, Player};
+use crate::scores::{LeaderboardQuery, Run, ScoreBook, ScoreFile, TimeRange};
+use crate::STORE_FILE;
+
+// Config store key of the rating settings.
+const RATING_SETTINGS_KEY: &str = "ratings.settings";
+const HOUR_MS: f64 = 3_600_000.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RatingSettings {
+    // Most rating a single match can move.
+    pub k_factor: f64,
+    // Rating of a player before their first match.
+    pub baseline: f64,
+    // Matches a player's rating stays provisional for.
+    pub provisional_games: usize,
+    // Rates a saved run as a win over every player it passes on the
+    // leaderboard of the runs saved within this many hours; None rates
+    // head-to-head games only.
+    pub leaderboard_session_hours: Option<f64>,
+}
+
+impl Default for RatingSettings {
+    fn default() -> Self {
+        Self {
+            k_factor: 32.0,
+            baseline: 1500.0,
+            provisional_games: 10,
+            leaderboard_session_hours: None,
+        }
+    }
+}
+
+impl RatingSettings {
+    fn check(&self) -> Result<(), String> {
+        if !(self.k_factor > 0.0 && self.k_factor <= 100.0) {
+            return Err("the K-factor must be above 0 and at most 100".to_string());
+        }
+        if !(0.0..=10_000.0).contains(&self.baseline) {
+            return Err("the baseline must be between 0 and 10000".to_string());
+        }
+        if self
+            .leaderboard_session_hours
+            .is_some_and(|hours| !(hours > 0.0 && hours <= 168.0))
+        {
+            return Err("a session must last above 0 and at most 168 hours".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn load(app_handle: &tauri::AppHandle) -> Self {
+        app_handle
+            .store(STORE_FILE)
+            .ok()
+            .and_then(|store| store.get(RATING_SETTINGS_KEY))
+            .and_then(|value| serde_json::from_value(value).ok())
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum MatchSource {
+    HeadToHead,
+    Leaderboard,
+}
+
+// The rating of a player after one match.
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RatingChange {
+    pub player_id: u64,
+    pub opponent_id: u64,
+    // 1 for a win, 0.5 for a draw, 0 for a loss.
+    pub outcome: f64,
+    pub rating: f64,
+    pub change: f64,
+    pub source: MatchSource,
+    pub timestamp_ms: u64,
+}
+
+// A player's place in get_rankings.
+#[derive(Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Ranking {
+    pub rank: usize,
+    pub player: Player,
+    pub rating: f64,
+    pub games: usize,
+    pub provisional: bool,
+}
+
+// Current rating and matches played of a player.
+fn rating_of(file: &ScoreFile, player_id: u64, settings: &RatingSettings) -> (f64, usize) {
+    let mut changes = file
+        .ratings
+        .iter()
+        .filter(|change| change.player_id == player_id);
+    let games = changes.clone().count();
+    let rating = changes
+        .next_back()
+        .map_or(settings.baseline, |change| change.rating);
+    (rating, games)
+}
+
+// Chance of a player rated `rating` beating one rated `opponent`.
+fn expected(rating: f64, opponent: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf((opponent - rating) / 400.0))
+}
+
+// Rates a match between two players, `outcome` being the first one's.
+pub fn record_match(
+    file: &mut ScoreFile,
+    players: [u64; 2],
+    outcome: f64,
+    source: MatchSource,
+    settings: &RatingSettings,
+    timestamp_ms: u64,
+) {
+    let ratings = players.map(|id| rating_of(file, id, settings).0);
+    for (i, outcome) in [(0, outcome), (1, 1.0 - outcome)] {
+        let change = settings.k_factor * (outcome - expected(ratings[i], ratings[1 - i]));
+        file.ratings.push(RatingChange {
+            player_id: players[i],
+            opponent_id: players[1 - i],
+            outcome,
+            rating: ratings[i] + change,
+            change,
+            source,
+            timestamp_ms,
+        });
+    }
+}
+
+// Rates an ended head-to-head game between two named players. Games that
+// neither lane finished aren't rated.
+pub fn record_head_to_head(
+    file: &mut ScoreFile,
+    head_to_head: &HeadToHeadResult,
+    settings: &RatingSettings,
+    max_name_length: usize,
+    timestamp_ms: u64,
+) {
+    let [first, second] = head_to_head.lanes.as_slice() else {
+        return;
+    };
+    if !first.result.success && !second.result.success {
+        return;
+    }
+    let (Some(first_name), Some(second_name)) = (&first.player, &second.player) else {
+        return;
+    };
+    let mut ids = [0; 2];
+    for (id, name) in ids.iter_mut().zip([first_name, second_name]) {
+        match players::resolve(file, name, true, max_name_length) {
+            Ok(player) => *id = player.id,
+            Err(_) => return,
+        }
+    }
+    if ids[0] == ids[1] {
+        return;
+    }
+    let outcome = match &head_to_head.winner {
+        Some(winner) if *winner == first.lane => 1.0,
+        Some(_) => 0.0,
+        None => 0.5,
+    };
+    record_match(
+        file,
+        ids,
+        outcome,
+        MatchSource::HeadToHead,
+        settings,
+        timestamp_ms,
+    );
+}
+
+// Players by leaderboard place, each at their best run.
+fn player_order(runs: &[&Run]) -> Vec<u64> {
+    let mut order: Vec<u64> = Vec::new();
+    for id in runs.iter().filter_map(|run| run.player_id) {
+        if !order.contains(&id) {
+            order.push(id);
+        }
+    }
+    order
+}
+
+// Players a just saved run moved its player past on the leaderboard of its
+// mode, counting the runs saved within `session_ms` before it.
+fn overtaken(file: &ScoreFile, run: &Run, session_ms: u64) -> Vec<u64> {
+    let Some(player_id) = run.player_id else {
+        return Vec::new();
+    };
+    let query = LeaderboardQuery {
+        mode: Some(&run.mode),
+        range: TimeRange {
+            from_ms: Some(run.timestamp_ms.saturating_sub(session_ms)),
+            to_ms: None,
+        },
+        ..LeaderboardQuery::default()
+    };
+    let after: Vec<&Run> = file
+        .sorted(&query)
+        .into_iter()
+        .filter(|other| other.relay_id.is_none())
+        .collect();
+    let before: Vec<&Run> = after
+        .iter()
+        .copied()
+        .filter(|other| other.id != run.id)
+        .collect();
+    let before = player_order(&before);
+    let after = player_order(&after);
+    let ahead = |order: &[u64]| -> Vec<u64> {
+        order
+            .iter()
+            .take_while(|&&id| id != player_id)
+            .copied()
+            .collect()
+    };
+    let ahead_after = ahead(&after);
+    ahead(&before)
+        .into_iter()
+        .filter(|id| !ahead_after.contains(id))
+        .collect()
+}
+
+// Rates a saved run as a win over every player it passed on the session's
+// leaderboard, if enabled.
+pub fn record_leaderboard_run(file: &mut ScoreFile, run: &Run, settings: &RatingSettings) {
+    let Some(hours) = settings.leaderboard_session_hours else {
+        return;
+    };
+    if !run.ranks() || run.relay_id.is_some() {
+        return;
+    }
+    let Some(player_id) = run.player_id else {
+        return;
+    };
+    for loser in overtaken(file, run, (hours * HOUR_MS) as u64) {
+        record_match(
+            file,
+            [player_id, loser],
+            1.0,
+            MatchSource::Leaderboard,
+            settings,
+            run.timestamp_ms,
+        );
+    }
+}
+
+fn rankings(file: &ScoreFile, settings: &RatingSettings) -> Vec<Ranking> {
+    let mut games: HashMap<u64, usize> = HashMap::new();
+    for change in &file.ratings {
+        *games.entry(change.player_id).or_default() += 1;
+    }
+    let mut rankings: Vec<Ranking> = file
+        .players
+        .iter()
+        .filter(|player| games.contains_key(&player.id))
+        .map(|player| {
+            let (rating, games) = rating_of(file, player.id, settings);
+            Ranking {
+                rank: 0,
+                player: player.clone(),
+                rating,
+                games,
+                provisional: games < settings.provisional_games,
+            }
+        })
+        .collect();
+    rankings.sort_by(|a, b| b.rating.total_cmp(&a.rating).then(b.games.cmp(&a.games)));
+    for (i, ranking) in rankings.iter_mut().enumerate() {
+        ranking.rank = i + 1;
+    }
+    rankings
+}
+
+// Command to list the rated players, highest rating first.
+#[tauri::command]
+pub fn get_rankings(
+    app_handle: tauri::AppHandle,
+    scores: tauri::State<Mutex<ScoreBook>>,
+) -> Result<Vec<Ranking>, String> {
+    let settings = RatingSettings::load(&app_handle);
+    let book = scores.lock().map_err(|e| e.to_string())?;
+    Ok(rankings(book.file(), &settings))
+}
+
+// Command to fetch the rating changes of a player, oldest first.
+#[tauri::command]
+pub fn get_rating_history(
+    player: String,
+    scores: tauri::State<Mutex<ScoreBook>>,
+) -> Result<Vec<RatingChange>, String> {
+    let book = scores.lock().map_err(|e| e.to_string())?;
+    let file = book.file();
+    let id = players::find(file, &player)
+        .ok_or_else(|| format!("no player named {}", player))?
+        .id;
+    Ok(file
+        .ratings
+        .iter()
+        .filter(|change| change.player_id == id)
+        .cloned()
+        .collect())
+}
+
+// Command to fetch the rating settings.
+#[tauri::command]
+pub fn get_rating_settings(app_handle: tauri::AppHandle) -> RatingSettings {
+    RatingSettings::load(&app_handle)
+}
+
+// Command to change the rating settings; ratings so far stay as they are.
+#[tauri::command]
+pub fn set_rating_settings(
+    settings: RatingSettings,
+    app_handle: tauri::AppHandle,
+) -> Result<(), String> {
+    settings.check()?;
+    let store = app_handle.store(STORE_FILE).map_err(|e| e.to_string())?;
+    store.set(
+        RATING_SETTINGS_KEY,
+        serde_json::to_value(settings).map_err(|e| e.to_string())?,
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(id: u64, player_id: u64, elapsed_ms: u64) -> Run {
+        serde_json::from_value(serde_json::json!({
+            "id": id, "player": "a", "mode": "timeAttack", "elapsedMs": elapsed_ms,
+            "hits": 0, "penaltyMs": 0, "timestampMs": 1_000 + id, "playerId": player_id
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn updates_ratings_by_expected_outcome() {
+        let settings = RatingSettings::default();
+        let mut file = ScoreFile::new();
+        record_match(
+            &mut file,
+            [1, 2],
+            1.0,
+            MatchSource::HeadToHead,
+            &settings,
+            0,
+        );
+        assert_eq!(rating_of(&file, 1, &settings), (1516.0, 1));
+        assert_eq!(rating_of(&file, 2, &settings), (1484.0, 1));
+        // Beating a weaker player gains less.
+        record_match(
+            &mut file,
+            [1, 2],
+            1.0,
+            MatchSource::HeadToHead,
+            &settings,
+            1,
+        );
+        let (rating, games) = rating_of(&file, 1, &settings);
+        assert!(rating > 1516.0 && rating < 1532.0);
+        assert_eq!(games, 2);
+    }
+
+    #[test]
+    fn rates_leaderboard_overtakes() {
+        let settings = RatingSettings {
+            leaderboard_session_hours: Some(1.0),
+            ..RatingSettings::default()
+        };
+        let mut file = ScoreFile::new();
+        file.runs = vec![run(1, 1, 30_000), run(2, 2, 40_000), run(3, 3, 50_000)].into();
+        file.runs.push(run(4, 3, 35_000));
+        let new_run = file.runs[3].clone();
+        assert_eq!(overtaken(&file, &new_run, 3_600_000), [2]);
+        record_leaderboard_run(&mut file, &new_run, &settings);
+        assert_eq!(rating_of(&file, 3, &settings).0, 1516.0);
+    }
+}
@@ -0,0 +1,325 @@
+// Reading loop shared by every stream source (serial port, TCP socket). The
+// source only has to provide a BufRead; reconnecting is left to the caller.
+
+use std::io::BufRead;
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::sync::Mutex;
+use std::thread;
+use tauri::Emitter;
+use tauri_plugin_store::StoreExt;
+
+use crate::pipeline::LineHandler;
+use crate::ports::PortProblem;
+use crate::protocol::{BinaryDecoder, LineSplitter, Protocol};
+use crate::{SerialStats, STORE_FILE};
+
+// Number of consecutive read errors before the source is considered lost.
+pub const MAX_CONSECUTIVE_READ_ERRORS: u32 = 3;
+// Delay between reopen attempts.
+pub const RECONNECT_INTERVAL_MS: u64 = 2000;
+// Size of the read buffer; large enough to take in a backlog of lines at once.
+pub const READ_BUFFER_SIZE: usize = 64 * 1024;
+// First delay after a read error; doubled for each repeat of the same error.
+const ERROR_BACKOFF_MS: u64 = 300;
+// Upper bound for the delay between reads after repeated errors.
+const MAX_ERROR_BACKOFF_MS: u64 = 5000;
+
+// Why the reading loop returned.
+pub enum ReadExit {
+    // A stop signal arrived.
+    Stopped,
+    // The source failed repeatedly or reached end of stream.
+    Lost,
+    // The device was removed; reopening it is pointless.
+    Gone,
+}
+
+// Rough cause of a read error, reported to the UI.
+#[derive(Clone, Copy, PartialEq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+enum ReadErrorKind {
+    Timeout,
+    PortBusy,
+    PermissionDenied,
+    DeviceRemoved,
+    Other,
+}
+
+impl ReadErrorKind {
+    fn classify(kind: std::io::ErrorKind) -> Self {
+        match kind {
+            std::io::ErrorKind::TimedOut | std::io::ErrorKind::WouldBlock => Self::Timeout,
+            std::io::ErrorKind::ResourceBusy => Self::PortBusy,
+            std::io::ErrorKind::PermissionDenied => Self::PermissionDenied,
+            std::io::ErrorKind::NotFound
+            | std::io::ErrorKind::BrokenPipe
+            | std::io::ErrorKind::NotConnected => Self::DeviceRemoved,
+            _ => Self::Other,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::Timeout => "timeout",
+            Self::PortBusy => "port busy",
+            Self::PermissionDenied => "permission denied",
+            Self::DeviceRemoved => "device removed",
+            Self::Other => "i/o error",
+        }
+    }
+
+    // Same hints configure_serial gives when opening the port fails.
+    fn hint(self) -> Option<&'static str> {
+        match self {
+            Self::PortBusy => PortProblem::Busy.hint(),
+            Self::PermissionDenied => PortProblem::PermissionDenied.hint(),
+            _ => None,
+        }
+    }
+}
+
+// Payload for the serial-read-error event.
+#[derive(Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ReadError {
+    connection_id: String,
+    kind: ReadErrorKind,
+    message: String,
+    // How many times in a row this kind of error occurred.
+    repeated: u32,
+    hint: Option<&'static str>,
+}
+
+// Tracks repeated read errors so they are reported on a decaying schedule:
+// on the first error of a kind, then after 2, 4, 8... repeats.
+struct ErrorThrottle {
+    last_kind: Option<ReadErrorKind>,
+    repeated: u32,
+}
+
+impl ErrorThrottle {
+    fn new() -> Self {
+        Self {
+            last_kind: None,
+            repeated: 0,
+        }
+    }
+
+    // Records an error and returns whether it should be reported.
+    fn record(&mut self, kind: ReadErrorKind) -> bool {
+        if self.last_kind == Some(kind) {
+            self.repeated += 1;
+        } else {
+            self.last_kind = Some(kind);
+            self.repeated = 1;
+        }
+        self.repeated.is_power_of_two()
+    }
+
+    // Delay before the next read, doubling with every repeat.
+    fn backoff(&self) -> std::time::Duration {
+        let exponent = self.repeated.saturating_sub(1).min(16);
+        let delay_ms = ERROR_BACKOFF_MS.saturating_mul(1 << exponent);
+        std::time::Duration::from_millis(delay_ms.min(MAX_ERROR_BACKOFF_MS))
+    }
+
+    fn clear(&mut self) {
+        self.last_kind = None;
+        self.repeated = 0;
+    }
+}
+
+// Payload for the serial-reconnecting event.
+#[derive(Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ReconnectStatus {
+    connection_id: String,
+    port: String,
+    attempt: u32,
+    max_attempts: u32,
+}
+
+// Emits serial-connected or serial-disconnected for a port and mirrors the
+// state in the store, so the UI has a single source of truth.
+pub fn report_connection(app_handle: &tauri::AppHandle, port: &str, connected: bool) {
+    let event = if connected {
+        "serial-connected"
+    } else {
+        "serial-disconnected"
+    };
+    let _ = app_handle.emit(event, port);
+    if let Ok(store) = app_handle.store(STORE_FILE) {
+        store.set("arduinoSettings.isConnected", connected);
+    }
+}
+
+// Reports a source that is gone for good and clears the connected flag.
+pub fn mark_disconnected(handler: &LineHandler, port: &str) {
+    report_connection(handler.app_handle(), port, false);
+}
+
+// Reads lines (or binary frames) from `reader` into the handler until a stop
+// signal arrives or the source is lost after `max_errors` failed reads in a
+// row. When `reconnected_port` is set, a serial-reconnected event is emitted as
+// soon as the first data arrives.
+#[allow(clippy::too_many_arguments)]
+pub fn read_stream<R: BufRead>(
+    reader: &mut R,
+    protocol: Protocol,
+    decoder: &mut BinaryDecoder,
+    handler: &mut LineHandler,
+    stats: &Mutex<SerialStats>,
+    stop_rx: &Receiver<()>,
+    max_errors: u32,
+    mut reconnected_port: Option<&str>,
+) -> ReadExit {
+    let mut consecutive_errors = 0;
+    let mut throttle = ErrorThrottle::new();
+    let mut splitter = LineSplitter::new();
+
+    loop {
+        // Check if a stop signal was received.
+        if stop_rx.try_recv().is_ok() {
+            return ReadExit::Stopped;
+        }
+        handler.tick();
+
+        // Drain every complete line (or binary frame) buffered so far in one
+        // go. Keywords and beam changes fire for each line as usual, but only
+        // the latest sensor frame of the batch is emitted.
+        let mut processed: u32 = 0;
+        handler.begin_batch();
+        let read_result = match protocol {
+            Protocol::Csv => reader
+                .fill_buf()
+                .map(|bytes| (bytes.len(), splitter.push(bytes)))
+                .map(|(n, lines)| {
+                    reader.consume(n);
+                    for mut line in lines {
+                        // The protocol is plain ASCII. Anything else is line
+                        // noise or a wrong baud rate; drop it so one corrupted
+                        // byte doesn't cost the rest of the line.
+                        let received = line.len();
+                        line.retain(|byte| byte.is_ascii());
+                        handler.record_dropped_bytes(received, received - line.len());
+                        handler.handle_line(&String::from_utf8_lossy(&line));
+                        processed += 1;
+                    }
+                    n
+                }),
+            Protocol::Binary => reader.fill_buf().map(|bytes| {
+                let n = bytes.len();
+                for values in decoder.push(bytes) {
+                    handler.handle_values(values);
+                    processed += 1;
+                }
+                n
+            }),
+        };
+        handler.end_batch();
+        if processed > 0 {
+            if let Ok(mut stats) = stats.lock() {
+                stats.record_wakeup(processed);
+            }
+        }
+        match read_result {
+            Ok(n) if n > 0 => {
+                consecutive_errors = 0;
+                throttle.clear();
+                if let Some(port) = reconnected_port.take() {
+                    let _ = handler.app_handle().emit("serial-reconnected", port);
+                    report_connection(handler.app_handle(), port, true);
+                }
+
+                if protocol == Protocol::Binary {
+                    reader.consume(n);
+                    // Count bad frames instead of reporting each one as an error.
+                    if let Ok(mut stats) = stats.lock() {
+                        stats.bad_frames = decoder.bad_frames();
+                    }
+                }
+            }
+            Ok(_) => {
+                // End of stream; a closed socket or a vanished device.
+                consecutive_errors += 1;
+                if consecutive_errors >= max_errors {
+                    return ReadExit::Lost;
+                }
+                thread::sleep(std::time::Duration::from_millis(10));
+            }
+            // No data within the read timeout: the device is quiet, which is
+            // the stall watchdog's business rather than an error. The watchdog
+            // measures time since the last data, so short read timeouts only
+            // make the loop (and the stop signal check) run more often.
+            Err(e) if ReadErrorKind::classify(e.kind()) == ReadErrorKind::Timeout => {}
+            Err(e) => {
+                let kind = ReadErrorKind::classify(e.kind());
+                // Forward read errors to the frontend, but not every repeat.
+                // println!("Serial read error: {}", e);
+                if throttle.record(kind) {
+                    let _ = handler.app_handle().emit(
+                        "serial-error",
+                        format!("read error ({}): {}", kind.label(), e),
+                    );
+                    let _ = handler.app_handle().emit(
+                        "serial-read-error",
+                        ReadError {
+                            connection_id: handler.connection_id().to_string(),
+                            kind,
+                            message: e.to_string(),
+                            repeated: throttle.repeated,
+                            hint: kind.hint(),
+                        },
+                    );
+                }
+
+                if kind == ReadErrorKind::DeviceRemoved {
+                    return ReadExit::Gone;
+                }
+                // Anything else repeated several times in a row means the source is lost.
+                consecutive_errors += 1;
+                if consecutive_errors >= max_errors {
+                    return ReadExit::Lost;
+                }
+                thread::sleep(throttle.backoff());
+            }
+        }
+    }
+}
+
+// Tries to reopen a lost source every `interval_ms`. Returns the reopened
+// source, or None if a stop signal arrived or all attempts failed (in which
+// case serial-disconnected has been emitted).
+pub fn reconnect<T>(
+    handler: &LineHandler,
+    port: &str,
+    max_attempts: u32,
+    interval_ms: u64,
+    stop_rx: &Receiver<()>,
+    mut open: impl FnMut() -> Option<T>,
+) -> Option<T> {
+    for attempt in 1..=max_attempts {
+        let _ = handler.app_handle().emit(
+            "serial-reconnecting",
+            ReconnectStatus {
+                connection_id: handler.connection_id().to_string(),
+                port: port.to_string(),
+                attempt,
+                max_attempts,
+            },
+        );
+
+        // Wait before retrying, but wake up immediately on a stop signal.
+        match stop_rx.recv_timeout(std::time::Duration::from_millis(interval_ms)) {
+            Err(RecvTimeoutError::Timeout) => {}
+            _ => return None,
+        }
+
+        if let Some(source) = open() {
+            return Some(source);
+        }
+    }
+
+    mark_disconnected(handler, port);
+    None
+}
@@ -0,0 +1,235 @@
+// Round sets for league nights: a player gets a fixed number of attempts and
+// is ranked by an aggregate of them. While a set is open, every run saved
+// for its player counts as the next attempt.
+
+use std::sync::Mutex;
+use tauri::Emitter;
+
+use crate::pipeline::unix_time_ms;
+use crate::players;
+use crate::scores::{Run, ScoreBook, ScoreFile};
+
+// How the attempts of a set make up its score.
+#[derive(Clone, Copy, Debug, Default, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RoundAggregation {
+    // The best final score.
+    #[default]
+    Best,
+    // The average final score.
+    Average,
+    // The average without the worst attempt.
+    DropWorst,
+}
+
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RoundSet {
+    pub id: u64,
+    pub player: String,
+    pub player_id: u64,
+    pub attempts: u32,
+    pub aggregation: RoundAggregation,
+    // Runs saved for the set, in order.
+    pub run_ids: Vec<u64>,
+    pub created_ms: u64,
+    // Aggregate score, once the last attempt is saved.
+    pub score: Option<u64>,
+}
+
+impl RoundSet {
+    fn is_open(&self) -> bool {
+        (self.run_ids.len() as u32) < self.attempts
+    }
+}
+
+// Returned by get_round_set_result.
+#[derive(Clone, Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RoundSetResult {
+    pub set: RoundSet,
+    pub complete: bool,
+    // Aggregate of the attempts so far.
+    pub score: Option<u64>,
+    // The attempts still saved, in order.
+    pub runs: Vec<Run>,
+}
+
+// Payload of round-set-progress.
+#[derive(Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RoundSetProgress {
+    set_id: u64,
+    player: String,
+    // Attempts saved so far, e.g. 2 for "Attempt 2 of 3".
+    attempt: u32,
+    attempts: u32,
+    score: Option<u64>,
+}
+
+// Aggregates the final scores of the attempts. Failed and voided attempts
+// have no score; they count as the worst attempt for dropping.
+fn aggregate(aggregation: RoundAggregation, runs: &[&Run]) -> Option<u64> {
+    let mut scores: Vec<Option<u64>> = runs
+        .iter()
+        .map(|run| run.ranks().then(|| run.final_score()))
+        .collect();
+    if aggregation == RoundAggregation::DropWorst && scores.len() > 1 {
+        let worst = scores
+            .iter()
+            .position(Option::is_none)
+            .or_else(|| (0..scores.len()).max_by_key(|&i| scores[i]))?;
+        scores.remove(worst);
+    }
+    let scores: Vec<u64> = scores.into_iter().flatten().collect();
+    match aggregation {
+        _ if scores.is_empty() => None,
+        RoundAggregation::Best => scores.iter().min().copied(),
+        RoundAggregation::Average | RoundAggregation::DropWorst => {
+            Some(scores.iter().sum::<u64>() / scores.len() as u64)
+        }
+    }
+}
+
+fn result(file: &ScoreFile, set: &RoundSet) -> RoundSetResult {
+    let runs: Vec<&Run> = set
+        .run_ids
+        .iter()
+        .filter_map(|id| file.runs.iter().find(|run| run.id == *id))
+        .collect();
+    RoundSetResult {
+        set: set.clone(),
+        complete: !set.is_open(),
+        score: aggregate(set.aggregation, &runs),
+        runs: runs.into_iter().cloned().collect(),
+    }
+}
+
+// Counts a newly saved run as the next attempt of its player's open set.
+// Practice runs aren't attempts.
+pub fn add_run(file: &mut ScoreFile, run: &Run) -> Option<RoundSetProgress> {
+    if run.practice {
+        return None;
+    }
+    let index = file
+        .round_sets
+        .iter()
+        .position(|set| set.is_open() && Some(set.player_id) == run.player_id)?;
+    file.round_sets[index].run_ids.push(run.id);
+    let set = &file.round_sets[index];
+    let score = result(file, set).score;
+    let progress = RoundSetProgress {
+        set_id: set.id,
+        player: set.player.clone(),
+        attempt: set.run_ids.len() as u32,
+        attempts: set.attempts,
+        score,
+    };
+    if !file.round_sets[index].is_open() {
+        file.round_sets[index].score = score;
+    }
+    Some(progress)
+}
+
+pub fn report_progress(app_handle: &tauri::AppHandle, progress: RoundSetProgress) {
+    let _ = app_handle.emit("round-set-progress", progress);
+}
+
+// Command to open a set of `attempts` runs for a player.
+#[tauri::command]
+pub fn start_round_set(
+    player: String,
+    attempts: u32,
+    aggregation: Option<RoundAggregation>,
+    confirm_new_player: Option<bool>,
+    app_handle: tauri::AppHandle,
+    scores: tauri::State<Mutex<ScoreBook>>,
+) -> Result<RoundSet, String> {
+    if attempts == 0 {
+        return Err("a round set needs at least one attempt".to_string());
+    }
+    let max_length = players::max_name_length(&app_handle);
+    let mut book = scores.lock().map_err(|e| e.to_string())?;
+    book.update(|file| {
+        let player = players::resolve(
+            file,
+            &player,
+            confirm_new_player.unwrap_or(false),
+            max_length,
+        )?;
+        if file
+            .round_sets
+            .iter()
+            .any(|set| set.is_open() && set.player_id == player.id)
+        {
+            return Err(format!(
+                "{} already has a round set in progress",
+                player.name
+            ));
+        }
+        let set = RoundSet {
+            id: file.next_round_set_id,
+            player: player.name,
+            player_id: player.id,
+            attempts,
+            aggregation: aggregation.unwrap_or_default(),
+            run_ids: Vec::new(),
+            created_ms: unix_time_ms(),
+            score: None,
+        };
+        file.next_round_set_id += 1;
+        file.round_sets.push(set.clone());
+        Ok(set)
+    })
+}
+
+// Command to fetch a round set with its attempts; the latest one without an
+// id.
+#[tauri::command]
+pub fn get_round_set_result(
+    id: Option<u64>,
+    scores: tauri::State<Mutex<ScoreBook>>,
+) -> Result<RoundSetResult, String> {
+    let book = scores.lock().map_err(|e| e.to_string())?;
+    let file = book.file();
+    let set = match id {
+        Some(id) => file.round_sets.iter().find(|set| set.id == id),
+        None => file.round_sets.last(),
+    }
+    .ok_or_else(|| "no such round set".to_string())?;
+    Ok(result(file, set))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn runs(scores: &[(u64, bool)]) -> Vec<Run> {
+        scores
+            .iter()
+            .enumerate()
+            .map(|(i, &(elapsed_ms, failed))| {
+                serde_json::from_value(serde_json::json!({
+                    "id": i, "player": "a", "mode": "timeAttack", "elapsedMs": elapsed_ms,
+                    "hits": 0, "penaltyMs": 0, "timestampMs": i, "failed": failed
+                }))
+                .unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn aggregates_attempts() {
+        let runs = runs(&[(30_000, false), (20_000, false), (40_000, false)]);
+        let all: Vec<&Run> = runs.iter().collect();
+        assert_eq!(aggregate(RoundAggregation::Best, &all), Some(20_000));
+        assert_eq!(aggregate(RoundAggregation::Average, &all), Some(30_000));
+        assert_eq!(aggregate(RoundAggregation::DropWorst, &all), Some(25_000));
+
+        let runs = self::runs(&[(30_000, false), (5_000, true)]);
+        let all: Vec<&Run> = runs.iter().collect();
+        assert_eq!(aggregate(RoundAggregation::Best, &all), Some(30_000));
+        assert_eq!(aggregate(RoundAggregation::DropWorst, &all), Some(30_000));
+        assert_eq!(aggregate(RoundAggregation::Average, &all[1..]), None);
+    }
+}
@@ -0,0 +1,3018 @@
+// Saved game runs and the leaderboards built from them. Runs are kept in a
+// SQLite database in the app data dir rather than in the config store, which
+// the frontend rewrites as a whole on every settings change. Each change
+// writes the rows it touched in one transaction, so an interrupted write
+// leaves the previous version intact.
+
+use rusqlite::types::Value;
+use rusqlite::{params, Connection};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tauri::{Emitter, Manager};
+use tauri_plugin_store::StoreExt;
+
+use crate::achievements::{self, Unlock};
+use crate::game::{self, GameManager, GameResult, Hit, Pace, RelayLeg, Split};
+use crate::history::{self, GameRecord};
+use crate::pipeline::unix_time_ms;
+use crate::players::{self, Handicap, NameError, Player};
+use crate::rating::{self, RatingChange, RatingSettings};
+use crate::rounds::{self, RoundSet};
+use crate::scoring::{self, ScoreInput, ScoringConfig};
+use crate::serial_log::{civil_from_days, days_from_civil, stamped_file_name};
+use crate::teams::{self, Team};
+use crate::tournament::Tournament;
+use crate::STORE_FILE;
+
+// Database the runs are kept in, inside the app data dir.
+const SCORES_DB: &str = "scores.db";
+// File they were kept in before, moved into the database once.
+const SCORES_FILE: &str = "scores.json";
+// Version of the file layout; older files are migrated when loaded.
+const SCORES_VERSION: u32 = 9;
+// Version of the database tables, kept as its user_version.
+const DB_VERSION: i64 = 1;
+// Runs and history records have tables of their own, with the columns
+// queries filter on. The rest share `items`: the small collections, which
+// are compared with their saved rows on every save, and the ratings and
+// unlocks, which are only ever appended to.
+const COUNTERS: &str = "counters";
+const RATINGS: &str = "ratings";
+const UNLOCKS: &str = "unlocks";
+const KINDS: [&str; 5] = ["teams", "players", "tournaments", "roundSets", COUNTERS];
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS runs (
+        id INTEGER PRIMARY KEY,
+        player TEXT NOT NULL,
+        mode TEXT NOT NULL,
+        elapsed_ms INTEGER NOT NULL,
+        hits INTEGER NOT NULL,
+        penalty_ms INTEGER NOT NULL,
+        timestamp_ms INTEGER NOT NULL,
+        data TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS runs_timestamp ON runs (timestamp_ms);
+    CREATE TABLE IF NOT EXISTS history (
+        id INTEGER PRIMARY KEY,
+        player_key TEXT,
+        mode TEXT NOT NULL,
+        started_ms INTEGER NOT NULL,
+        ended_ms INTEGER NOT NULL,
+        elapsed_ms INTEGER NOT NULL,
+        hits INTEGER NOT NULL,
+        data TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS history_started ON history (started_ms);
+    CREATE INDEX IF NOT EXISTS history_ended ON history (ended_ms);
+    CREATE TABLE IF NOT EXISTS items (
+        kind TEXT NOT NULL,
+        key INTEGER NOT NULL,
+        data TEXT NOT NULL,
+        PRIMARY KEY (kind, key)
+    );
+";
+// Mode of runs saved without one, and of imported highscores.
+pub const DEFAULT_MODE: &str = "timeAttack";
+// Leaderboard page size when the caller doesn't choose one.
+const DEFAULT_PAGE_SIZE: usize = 50;
+// Config store key of the streak bonus settings.
+const STREAK_BONUS_KEY: &str = "scoring.streakBonus";
+// Config store key of whether practice games are stored.
+const KEEP_PRACTICE_KEY: &str = "scoring.keepPracticeRuns";
+// Config store key of the shortest plausible run, in seconds.
+const MIN_RUN_SECONDS_KEY: &str = "scoring.minRunSeconds";
+const DEFAULT_MIN_RUN_SECONDS: f64 = 3.0;
+// Config store key of the range the scoreboard shows.
+const LEADERBOARD_RANGE_KEY: &str = "leaderboard.range";
+const DAY_MS: i64 = 86_400_000;
+const MAX_NOTE_LENGTH: usize = 1000;
+const MAX_TAG_LENGTH: usize = 32;
+
+// One saved run.
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Run {
+    pub id: u64,
+    pub player: String,
+    pub mode: String,
+    pub elapsed_ms: u64,
+    pub hits: u32,
+    pub penalty_ms: u64,
+    // Score of a reverse run, from the game result.
+    #[serde(default)]
+    pub points: Option<u64>,
+    // Ended in a game over; such runs stay off the leaderboards by default.
+    #[serde(default)]
+    pub failed: bool,
+    // Set while an operator has voided the run, e.g. for a broken sensor.
+    #[serde(default)]
+    pub void: Option<Void>,
+    // Looks impossible, for `suspect_reasons`; kept off the leaderboards
+    // until an operator confirms the run. The reasons stay after that.
+    #[serde(default)]
+    pub suspect: bool,
+    #[serde(default)]
+    pub suspect_reasons: Vec<SuspectReason>,
+    // Warm-up run; never ranked.
+    #[serde(default)]
+    pub practice: bool,
+    // Unix time the run was saved.
+    pub timestamp_ms: u64,
+    // Game settings the run was played with.
+    #[serde(default)]
+    pub config: serde_json::Value,
+    #[serde(default)]
+    pub team_id: Option<u64>,
+    // Profile of the player; None for runs of deleted players.
+    #[serde(default)]
+    pub player_id: Option<u64>,
+    #[serde(default)]
+    pub tournament_match: Option<MatchRef>,
+    // Beam breaks of the run; None for runs saved before timelines were
+    // kept.
+    #[serde(default)]
+    pub timeline: Option<Vec<Hit>>,
+    // Checkpoint split times, in checkpoint order.
+    #[serde(default)]
+    pub splits: Vec<Split>,
+    // Handicap in effect when the run was saved, and the score it gave.
+    #[serde(default)]
+    pub handicap: Option<Handicap>,
+    #[serde(default)]
+    pub adjusted_score: Option<u64>,
+    // Clean runs in a row of the player, ending with this one; 0 if the run
+    // had hits.
+    #[serde(default)]
+    pub streak: u32,
+    // Time taken off for the streak, included in the final score.
+    #[serde(default)]
+    pub streak_bonus_ms: u64,
+    // Legs of a relay run, which is saved once for every player of the
+    // relay; `relay_id` is the id of the first player's run.
+    #[serde(default)]
+    pub legs: Vec<RelayLeg>,
+    #[serde(default)]
+    pub relay_id: Option<u64>,
+    // Scoring config a timed run was scored with, and the final score it
+    // gave; None for runs saved before scoring was configurable.
+    #[serde(default)]
+    pub scoring: Option<ScoringConfig>,
+    #[serde(default)]
+    pub score_ms: Option<u64>,
+    // Sensors that were disabled during the run, so its maze was shorter.
+    #[serde(default)]
+    pub disabled_sensors: Vec<usize>,
+    // Finished past the time limit of the game.
+    #[serde(default)]
+    pub overtime: bool,
+    // Category the run is ranked in, besides the overall leaderboard.
+    #[serde(default)]
+    pub category: Option<String>,
+    // Operator annotations, e.g. "sensor 4 flaky"; tags are lowercase.
+    #[serde(default)]
+    pub note: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+// Why a run looks impossible.
+#[derive(Clone, Copy, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SuspectReason {
+    // Finished faster than the shortest plausible run.
+    TooFast,
+    // No sensor data arrived for over half the run.
+    SensorSilence,
+}
+
+// Payload of suspect-run.
+#[derive(Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SuspectRun {
+    run_id: u64,
+    player: String,
+    mode: String,
+    elapsed_ms: u64,
+    reasons: Vec<SuspectReason>,
+}
+
+// Why and when a run was voided.
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Void {
+    pub reason: String,
+    pub voided_ms: u64,
+}
+
+// Payload of leaderboard-changed: the run that changed, if one did, and
+// the range the scoreboard shows.
+#[derive(Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct LeaderboardChanged {
+    run_id: Option<u64>,
+    mode: Option<String>,
+    range: LeaderboardRange,
+}
+
+// A tag in use and the number of runs carrying it.
+#[derive(Clone, Debug, PartialEq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TagCount {
+    pub tag: String,
+    pub count: usize,
+}
+
+// The tournament match a run was played for.
+#[derive(Clone, Copy, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MatchRef {
+    pub tournament_id: u64,
+    pub match_id: u64,
+}
+
+// Mode whose runs are ranked by hits rather than time.
+pub const STEALTH_MODE: &str = "stealth";
+// Mode whose runs are ranked by points, highest first, then by time.
+pub const REVERSE_MODE: &str = "reverse";
+
+impl Run {
+    // Whether the run counts for rankings and statistics: it is no practice
+    // run, didn't fail, wasn't voided and isn't suspect.
+    pub fn ranks(&self) -> bool {
+        !self.practice && !self.failed && self.void.is_none() && !self.suspect
+    }
+
+    // Whether the run is scored by its time.
+    pub fn is_timed(&self) -> bool {
+        self.mode != STEALTH_MODE && self.mode != REVERSE_MODE
+    }
+
+    // Hits for stealth runs, points for reverse runs, otherwise the time
+    // scored by the run's scoring config; lower is better, except for
+    // reverse runs.
+    pub fn final_score(&self) -> u64 {
+        if self.mode == REVERSE_MODE {
+            self.points.unwrap_or(u64::from(self.hits))
+        } else if self.mode == STEALTH_MODE {
+            u64::from(self.hits)
+        } else {
+            self.score_ms.unwrap_or_else(|| {
+                (self.elapsed_ms + self.penalty_ms).saturating_sub(self.streak_bonus_ms)
+            })
+        }
+    }
+
+    // Scores a timed run with `scoring`, then applies the handicap.
+    fn score(&mut self, scoring: Option<ScoringConfig>) {
+        if let Some(config) = scoring.filter(|_| self.is_timed()) {
+            let input = ScoreInput {
+                elapsed_ms: self.elapsed_ms,
+                hits: self.hits,
+                penalty_ms: self.penalty_ms,
+                checkpoints: self.splits.len(),
+                streak: self.streak,
+            };
+            let score = scoring::compute_score(&input, &config);
+            self.streak_bonus_ms = score.streak_bonus_ms;
+            self.score_ms = Some(score.final_ms);
+            self.scoring = Some(config);
+        }
+        // Handicaps only change times; stealth and reverse scores count
+        // beams.
+        self.adjusted_score = Some(match &self.handicap {
+            Some(handicap) if self.is_timed() => handicap.apply(self.final_score()),
+            _ => self.final_score(),
+        });
+    }
+
+    // The final score after the run's handicap.
+    pub fn adjusted(&self) -> u64 {
+        self.adjusted_score.unwrap_or_else(|| self.final_score())
+    }
+
+    // The score a leaderboard ranks by.
+    fn ranking_score(&self, adjusted: bool) -> u64 {
+        if adjusted {
+            self.adjusted()
+        } else {
+            self.final_score()
+        }
+    }
+
+    // Completed runs come first, then those in overtime, then those that
+    // didn't finish.
+    fn completion(&self) -> u8 {
+        if self.failed {
+            2
+        } else if self.overtime {
+            1
+        } else {
+            0
+        }
+    }
+
+    // Leaderboard order: by completion, then reverse runs by most points and
+    // the shorter time; other runs by the lower score.
+    fn ranking_cmp(&self, other: &Run, adjusted: bool) -> std::cmp::Ordering {
+        let completion = self.completion().cmp(&other.completion());
+        if completion.is_ne() {
+            completion
+        } else if self.mode == REVERSE_MODE && other.mode == REVERSE_MODE {
+            other
+                .final_score()
+                .cmp(&self.final_score())
+                .then(self.elapsed_ms.cmp(&other.elapsed_ms))
+        } else {
+            self.ranking_score(adjusted)
+                .cmp(&other.ranking_score(adjusted))
+        }
+    }
+
+    // The final score as exported, in seconds for timed modes.
+    fn final_score_text(&self) -> String {
+        if self.is_timed() {
+            seconds(self.final_score())
+        } else {
+            self.final_score().to_string()
+        }
+    }
+}
+
+// A run as passed to save_run.
+#[derive(Clone, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NewRun {
+    pub player: String,
+    pub mode: Option<String>,
+    pub elapsed_ms: u64,
+    pub hits: u32,
+    #[serde(default)]
+    pub penalty_ms: u64,
+    #[serde(default)]
+    pub points: Option<u64>,
+    #[serde(default)]
+    pub failed: bool,
+    #[serde(default)]
+    pub practice: bool,
+    #[serde(default)]
+    pub config: serde_json::Value,
+    // Team the run counts for; defaults to the team listing the player.
+    #[serde(default)]
+    pub team_id: Option<u64>,
+    #[serde(default)]
+    pub tournament_match: Option<MatchRef>,
+    #[serde(default)]
+    pub timeline: Option<Vec<Hit>>,
+    #[serde(default)]
+    pub splits: Vec<Split>,
+    // Game time without sensor data, from the game result.
+    #[serde(default)]
+    pub silent_ms: u64,
+    // Sensors disabled during the game, from the game result.
+    #[serde(default)]
+    pub disabled_sensors: Vec<usize>,
+    #[serde(default)]
+    pub overtime: bool,
+    // Legs of a relay game; the run is saved for each of their players.
+    #[serde(default)]
+    pub legs: Vec<RelayLeg>,
+    // Confirms a new player whose name is close to an existing one, or
+    // saving under a player whose name differs only in case.
+    #[serde(default)]
+    pub confirm_new_player: bool,
+    // Game the run was played in. Its result replaces the times, hits and
+    // timeline sent along, and saving it allows a rematch.
+    #[serde(default)]
+    pub game_id: Option<u64>,
+    // Category to rank the run in; defaults to the player's.
+    #[serde(default)]
+    pub category: Option<String>,
+    #[serde(skip)]
+    pub player_id: Option<u64>,
+    #[serde(skip)]
+    pub relay_id: Option<u64>,
+    #[serde(skip)]
+    pub handicap: Option<Handicap>,
+    #[serde(skip)]
+    pub scoring: Option<ScoringConfig>,
+    // Shortest plausible run; 0 accepts any.
+    #[serde(skip)]
+    pub min_run_ms: u64,
+}
+
+impl NewRun {
+    // Takes what the game clock measured from the result of the game the
+    // run was played in, rather than the frontend's numbers.
+    fn take_game_result(&mut self, result: GameResult) {
+        self.points = (result.mode == REVERSE_MODE).then_some(result.score);
+        self.mode = Some(result.mode);
+        self.elapsed_ms = result.elapsed_ms;
+        self.hits = result.hits;
+        self.penalty_ms = result.penalty_ms;
+        self.failed = result.failed;
+        self.practice = result.practice;
+        self.timeline = Some(result.timeline);
+        self.splits = result.splits;
+        self.silent_ms = result.silent_ms;
+        self.disabled_sensors = result.disabled_sensors;
+        self.overtime = result.overtime;
+        self.legs = result.legs;
+    }
+}
+
+// Rewards clean runs in a row: each streak level takes `percent_per_level`
+// off the time left after the previous levels, up to `max_level` levels.
+#[derive(Clone, Copy, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct StreakBonus {
+    pub percent_per_level: f64,
+    pub max_level: u32,
+}
+
+impl Default for StreakBonus {
+    fn default() -> Self {
+        Self {
+            percent_per_level: 2.0,
+            max_level: 5,
+        }
+    }
+}
+
+impl StreakBonus {
+    pub fn check(&self) -> Result<(), String> {
+        if !(0.0..100.0).contains(&self.percent_per_level) {
+            return Err("the bonus per level must be at least 0% and below 100%".to_string());
+        }
+        Ok(())
+    }
+
+    // Time taken off a run of `time_ms` at a streak of `streak` clean runs.
+    pub fn bonus_ms(&self, time_ms: u64, streak: u32) -> u64 {
+        let level = streak.min(self.max_level) as i32;
+        let kept = (1.0 - self.percent_per_level / 100.0).powi(level);
+        (time_ms as f64 * (1.0 - kept)).round() as u64
+    }
+
+    pub fn load(app_handle: &tauri::AppHandle) -> Self {
+        app_handle
+            .store(STORE_FILE)
+            .ok()
+            .and_then(|store| store.get(STREAK_BONUS_KEY))
+            .and_then(|value| serde_json::from_value(value).ok())
+            .unwrap_or_default()
+    }
+}
+
+// Span of save times, in Unix milliseconds; open ends are unbounded.
+#[derive(Clone, Copy, Debug, Default, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimeRange {
+    pub from_ms: Option<u64>,
+    // Exclusive.
+    pub to_ms: Option<u64>,
+}
+
+impl TimeRange {
+    pub fn contains(&self, timestamp_ms: u64) -> bool {
+        self.from_ms.is_none_or(|from| timestamp_ms >= from)
+            && self.to_ms.is_none_or(|to| timestamp_ms < to)
+    }
+}
+
+// Span of save times a leaderboard covers, in the local calendar.
+#[derive(Clone, Copy, Debug, Default, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(
+    tag = "kind",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum LeaderboardRange {
+    #[default]
+    AllTime,
+    Today,
+    // Since Monday.
+    ThisWeek,
+    ThisMonth,
+    Custom {
+        from_ms: Option<u64>,
+        // Exclusive.
+        to_ms: Option<u64>,
+    },
+}
+
+impl LeaderboardRange {
+    // The save times in the range at `now_ms`, for local time
+    // `utc_offset_ms` ahead of UTC. One offset places every run, so a
+    // daylight saving change can move where a range starts by an hour but
+    // never moves a run to another day.
+    fn resolve(&self, now_ms: u64, utc_offset_ms: i64) -> TimeRange {
+        let today = (now_ms as i64 + utc_offset_ms).div_euclid(DAY_MS);
+        let first_day = match *self {
+            LeaderboardRange::AllTime => return TimeRange::default(),
+            LeaderboardRange::Custom { from_ms, to_ms } => return TimeRange { from_ms, to_ms },
+            LeaderboardRange::Today => today,
+            // Day 0 was a Thursday.
+            LeaderboardRange::ThisWeek => today - (today + 3).rem_euclid(7),
+            LeaderboardRange::ThisMonth => {
+                let (year, month, _) = civil_from_days(today);
+                days_from_civil(year, month, 1)
+            }
+        };
+        TimeRange {
+            from_ms: Some((first_day * DAY_MS - utc_offset_ms).max(0) as u64),
+            to_ms: None,
+        }
+    }
+
+    fn load(app_handle: &tauri::AppHandle) -> Self {
+        app_handle
+            .store(STORE_FILE)
+            .ok()
+            .and_then(|store| store.get(LEADERBOARD_RANGE_KEY))
+            .and_then(|value| serde_json::from_value(value).ok())
+            .unwrap_or_default()
+    }
+}
+
+// Which runs a leaderboard holds and what it ranks them by.
+#[derive(Clone, Copy, Default)]
+pub struct LeaderboardQuery<'a> {
+    // Runs of one mode, or of all.
+    pub mode: Option<&'a str>,
+    // Rank by the handicap-adjusted score.
+    pub adjusted: bool,
+    // Include runs that ended in a game over.
+    pub include_failed: bool,
+    // Save times of the runs.
+    pub range: TimeRange,
+    // Runs of one category, or of all.
+    pub category: Option<&'a str>,
+}
+
+impl LeaderboardQuery<'_> {
+    fn matches(&self, run: &Run) -> bool {
+        self.mode.is_none_or(|mode| run.mode == mode)
+            && self.range.contains(run.timestamp_ms)
+            && !run.practice
+            && run.void.is_none()
+            && !run.suspect
+            && (self.include_failed || !run.failed)
+            && self.category.is_none_or(|category| {
+                run.category
+                    .as_deref()
+                    .is_some_and(|c| players::name_key(c) == players::name_key(category))
+            })
+            // A relay is listed once, by its first player's run.
+            && run.relay_id.is_none_or(|id| id == run.id)
+    }
+}
+
+// The runs of a query in leaderboard order.
+fn sorted<'a>(runs: &'a [Run], query: &LeaderboardQuery) -> Vec<&'a Run> {
+    let mut runs: Vec<&Run> = runs.iter().filter(|run| query.matches(run)).collect();
+    runs.sort_by(|a, b| {
+        a.ranking_cmp(b, query.adjusted)
+            .then((a.timestamp_ms, a.id).cmp(&(b.timestamp_ms, b.id)))
+    });
+    runs
+}
+
+fn leaderboard_page(sorted: Vec<&Run>, limit: usize, offset: usize) -> LeaderboardPage {
+    LeaderboardPage {
+        total: sorted.len(),
+        runs: sorted
+            .into_iter()
+            .enumerate()
+            .skip(offset)
+            .take(limit)
+            .map(|(i, run)| RankedRun {
+                rank: i + 1,
+                final_score: run.final_score(),
+                adjusted_score: run.adjusted(),
+                run: run.clone(),
+            })
+            .collect(),
+        range: LeaderboardRange::AllTime,
+    }
+}
+
+// A run with its leaderboard position.
+#[derive(Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RankedRun {
+    pub rank: usize,
+    pub final_score: u64,
+    pub adjusted_score: u64,
+    #[serde(flatten)]
+    pub run: Run,
+}
+
+// One page of a leaderboard.
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LeaderboardPage {
+    // Runs on the whole leaderboard.
+    pub total: usize,
+    pub runs: Vec<RankedRun>,
+    pub range: LeaderboardRange,
+}
+
+// Everything the scores database holds, in the layout of the scores.json it
+// replaced.
+#[derive(Clone, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScoreFile {
+    version: u32,
+    next_id: u64,
+    // Set once the highscores of the config store were copied over.
+    legacy_imported: bool,
+    pub runs: Rows<Run>,
+    // Added in version 2.
+    #[serde(default)]
+    pub teams: Vec<Team>,
+    #[serde(default)]
+    pub next_team_id: u64,
+    // Added in version 3.
+    #[serde(default)]
+    pub players: Vec<Player>,
+    #[serde(default)]
+    pub next_player_id: u64,
+    // Added in version 4.
+    #[serde(default)]
+    pub tournaments: Vec<Tournament>,
+    #[serde(default)]
+    pub next_tournament_id: u64,
+    // Added in version 5.
+    #[serde(default)]
+    pub category_handicaps: BTreeMap<String, Handicap>,
+    // Added in version 6.
+    #[serde(default)]
+    pub history: Rows<GameRecord>,
+    #[serde(default)]
+    pub next_history_id: u64,
+    // Added in version 7.
+    #[serde(default)]
+    pub round_sets: Vec<RoundSet>,
+    #[serde(default)]
+    pub next_round_set_id: u64,
+    // Added in version 8.
+    #[serde(default)]
+    pub ratings: Vec<RatingChange>,
+    // Added in version 9.
+    #[serde(default)]
+    pub unlocks: Vec<Unlock>,
+}
+
+impl ScoreFile {
+    pub fn new() -> Self {
+        Self {
+            version: SCORES_VERSION,
+            next_id: 1,
+            legacy_imported: false,
+            runs: Rows::default(),
+            teams: Vec::new(),
+            next_team_id: 1,
+            players: Vec::new(),
+            next_player_id: 1,
+            tournaments: Vec::new(),
+            next_tournament_id: 1,
+            category_handicaps: BTreeMap::new(),
+            history: Rows::default(),
+            next_history_id: 1,
+            round_sets: Vec::new(),
+            next_round_set_id: 1,
+            ratings: Vec::new(),
+            unlocks: Vec::new(),
+        }
+    }
+
+    // Brings a file written by an older version up to date.
+    fn migrate(&mut self) {
+        self.version = SCORES_VERSION;
+        self.next_id = self
+            .next_id
+            .max(self.runs.iter().map(|run| run.id + 1).max().unwrap_or(1));
+        self.next_team_id = self
+            .next_team_id
+            .max(self.teams.iter().map(|team| team.id + 1).max().unwrap_or(1));
+        self.next_player_id = self.next_player_id.max(
+            self.players
+                .iter()
+                .map(|player| player.id + 1)
+                .max()
+                .unwrap_or(1),
+        );
+        self.next_tournament_id = self.next_tournament_id.max(
+            self.tournaments
+                .iter()
+                .map(|tournament| tournament.id + 1)
+                .max()
+                .unwrap_or(1),
+        );
+        self.next_history_id = self.next_history_id.max(
+            self.history
+                .iter()
+                .map(|record| record.id + 1)
+                .max()
+                .unwrap_or(1),
+        );
+        self.next_round_set_id = self.next_round_set_id.max(
+            self.round_sets
+                .iter()
+                .map(|set| set.id + 1)
+                .max()
+                .unwrap_or(1),
+        );
+        players::link_runs(self);
+    }
+
+    // Checks that a run linked to a tournament match was played by one of
+    // the match's players.
+    fn check_match(&self, player: &str, link: MatchRef) -> Result<(), String> {
+        let played = self
+            .tournaments
+            .iter()
+            .find(|tournament| tournament.id == link.tournament_id)
+            .and_then(|tournament| tournament.find_match(link.match_id))
+            .ok_or_else(|| {
+                format!(
+                    "no match {} in tournament {}",
+                    link.match_id, link.tournament_id
+                )
+            })?;
+        if !played
+            .players
+            .iter()
+            .flatten()
+            .any(|p| players::name_key(p) == players::name_key(player))
+        {
+            return Err(format!(
+                "{} doesn't play in match {}",
+                player, link.match_id
+            ));
+        }
+        Ok(())
+    }
+
+    // The team a new run counts for: the requested one, or else the first
+    // team listing the player.
+    fn team_for(&self, player: &str, team_id: Option<u64>) -> Result<Option<u64>, String> {
+        match team_id {
+            Some(id) if self.teams.iter().any(|team| team.id == id) => Ok(Some(id)),
+            Some(id) => Err(format!("no team with id {}", id)),
+            None => Ok(self
+                .teams
+                .iter()
+                .find(|team| team.has_member(player))
+                .map(|team| team.id)),
+        }
+    }
+
+    fn insert(&mut self, new_run: NewRun, timestamp_ms: u64) -> Run {
+        let mut run = Run {
+            id: self.next_id,
+            player: new_run.player.trim().to_string(),
+            mode: new_run.mode.unwrap_or_else(|| DEFAULT_MODE.to_string()),
+            elapsed_ms: new_run.elapsed_ms,
+            hits: new_run.hits,
+            penalty_ms: new_run.penalty_ms,
+            points: new_run.points,
+            failed: new_run.failed,
+            void: None,
+            suspect: false,
+            suspect_reasons: Vec::new(),
+            practice: new_run.practice,
+            timestamp_ms,
+            config: new_run.config,
+            team_id: new_run.team_id,
+            player_id: new_run.player_id,
+            tournament_match: new_run.tournament_match,
+            timeline: new_run.timeline,
+            splits: new_run.splits,
+            handicap: new_run.handicap,
+            adjusted_score: None,
+            streak: 0,
+            streak_bonus_ms: 0,
+            relay_id: (!new_run.legs.is_empty()).then(|| new_run.relay_id.unwrap_or(self.next_id)),
+            legs: new_run.legs,
+            scoring: None,
+            score_ms: None,
+            disabled_sensors: new_run.disabled_sensors,
+            overtime: new_run.overtime,
+            category: new_run.category,
+            note: None,
+            tags: Vec::new(),
+        };
+        if !run.practice {
+            if !run.failed && run.elapsed_ms < new_run.min_run_ms {
+                run.suspect_reasons.push(SuspectReason::TooFast);
+            }
+            if new_run.silent_ms * 2 > run.elapsed_ms {
+                run.suspect_reasons.push(SuspectReason::SensorSilence);
+            }
+            run.suspect = !run.suspect_reasons.is_empty();
+        }
+        // Reverse runs are meant to break beams, so they have no streak.
+        if run.hits == 0 && !run.practice && run.mode != REVERSE_MODE {
+            run.streak = self.streak_of(run.player_id) + 1;
+        }
+        run.score(new_run.scoring);
+        self.next_id += 1;
+        self.runs.push(run.clone());
+        run
+    }
+
+    // The clean-run streak of a player as of their latest run; practice and
+    // reverse runs neither extend nor break it.
+    fn streak_of(&self, player_id: Option<u64>) -> u32 {
+        player_id
+            .and_then(|id| {
+                self.runs
+                    .iter()
+                    .filter(|run| {
+                        run.player_id == Some(id) && !run.practice && run.mode != REVERSE_MODE
+                    })
+                    .max_by_key(|run| (run.timestamp_ms, run.id))
+            })
+            .map_or(0, |run| run.streak)
+    }
+
+    // The runs of a query in leaderboard order: by final score, or by the
+    // handicap-adjusted one, earlier runs first on ties.
+    pub fn sorted(&self, query: &LeaderboardQuery) -> Vec<&Run> {
+        sorted(&self.runs, query)
+    }
+
+    // One page of a leaderboard; only the runs on it are copied.
+    fn leaderboard(
+        &self,
+        query: &LeaderboardQuery,
+        limit: usize,
+        offset: usize,
+    ) -> LeaderboardPage {
+        leaderboard_page(self.sorted(query), limit, offset)
+    }
+
+    // Runs of a player, newest first. Names match ignoring case and spacing.
+    fn player_runs(&self, name: &str) -> Vec<Run> {
+        let key = players::name_key(name);
+        let mut runs: Vec<Run> = self
+            .runs
+            .iter()
+            .filter(|run| players::name_key(&run.player) == key)
+            .cloned()
+            .collect();
+        runs.sort_by_key(|run| std::cmp::Reverse((run.timestamp_ms, run.id)));
+        runs
+    }
+
+    // What a game of a player in a mode is paced against: the player's best
+    // ranked run and the record of the mode. Relay runs take no part.
+    pub fn pace(&self, player: Option<&str>, mode: &str) -> Pace {
+        let ranked = self
+            .runs
+            .iter()
+            .filter(|run| run.mode == mode && run.ranks() && run.relay_id.is_none());
+        let record_ms = ranked.clone().map(|run| run.final_score()).min();
+        let best = player.and_then(|player| {
+            let key = players::name_key(player);
+            ranked
+                .filter(|run| players::name_key(&run.player) == key)
+                .min_by_key(|run| (run.final_score(), run.timestamp_ms, run.id))
+        });
+        Pace {
+            splits: best
+                .map(|run| {
+                    run.splits
+                        .iter()
+                        .filter_map(|split| Some((split.name.clone(), split.elapsed_ms?)))
+                        .collect()
+                })
+                .unwrap_or_default(),
+            finish_ms: best.map(|run| run.elapsed_ms),
+            best_ms: best.map(|run| run.final_score()),
+            record_ms,
+        }
+    }
+
+    fn delete(&mut self, id: u64) -> bool {
+        let count = self.runs.len();
+        self.runs.retain(|run| run.id != id);
+        self.runs.len() != count
+    }
+
+    // Adds runs exported from another scores file, skipping those saved
+    // already. They get new ids; their players are found again by name, and
+    // links to teams and tournaments of the other file are dropped.
+    fn import_runs(&mut self, runs: Vec<Run>) -> usize {
+        let mut ids = HashMap::new();
+        let mut imported = 0;
+        for mut run in runs {
+            let key = players::name_key(&run.player);
+            if self.runs.iter().any(|saved| {
+                saved.timestamp_ms == run.timestamp_ms
+                    && saved.elapsed_ms == run.elapsed_ms
+                    && saved.mode == run.mode
+                    && players::name_key(&saved.player) == key
+            }) {
+                continue;
+            }
+            ids.insert(run.id, self.next_id);
+            run.id = self.next_id;
+            self.next_id += 1;
+            run.relay_id = run
+                .relay_id
+                .map(|id| ids.get(&id).copied().unwrap_or(run.id));
+            run.player_id = None;
+            run.team_id = None;
+            run.tournament_match = None;
+            self.runs.push(run);
+            imported += 1;
+        }
+        players::link_runs(self);
+        imported
+    }
+
+    // Adds the highscores the frontend kept in the config store. Their ids
+    // are the Unix time they were saved at.
+    fn import_legacy(&mut self, highscores: &[serde_json::Value]) -> usize {
+        let mut imported = 0;
+        for score in highscores {
+            let (Some(player), Some(time)) = (score["name"].as_str(), score["time"].as_u64())
+            else {
+                continue;
+            };
+            let timestamp_ms = score["id"]
+                .as_str()
+                .and_then(|id| id.parse().ok())
+                .unwrap_or(0);
+            let config = serde_json::json!({
+                "maxAllowedTouches": score["maxAllowedTouches"],
+                "reactivateLasers": score["reactivationEnabled"],
+                "reactivationTimeSeconds": score["reactivationTimeSeconds"],
+            });
+            self.insert(
+                NewRun {
+                    player: player.to_string(),
+                    mode: None,
+                    elapsed_ms: time,
+                    hits: score["touchedLasers"].as_u64().unwrap_or(0) as u32,
+                    penalty_ms: 0,
+                    points: None,
+                    failed: false,
+                    practice: false,
+                    config,
+                    team_id: None,
+                    tournament_match: None,
+                    timeline: None,
+                    splits: Vec::new(),
+                    silent_ms: 0,
+                    disabled_sensors: Vec::new(),
+                    overtime: false,
+                    legs: Vec::new(),
+                    confirm_new_player: true,
+                    game_id: None,
+                    category: None,
+                    player_id: None,
+                    relay_id: None,
+                    handicap: None,
+                    scoring: None,
+                    min_run_ms: 0,
+                },
+                timestamp_ms,
+            );
+            imported += 1;
+        }
+        players::link_runs(self);
+        self.legacy_imported = true;
+        imported
+    }
+}
+
+// Items with a row of their own in the database.
+pub trait Keyed {
+    fn key(&self) -> u64;
+}
+
+impl Keyed for Run {
+    fn key(&self) -> u64 {
+        self.id
+    }
+}
+
+impl Keyed for GameRecord {
+    fn key(&self) -> u64 {
+        self.id
+    }
+}
+
+// Runs or history records, in key order. Reads go through the slice;
+// changes go through methods that note the keys they touch, so a save
+// writes only those rows.
+#[derive(Clone, Debug)]
+pub struct Rows<T> {
+    items: Vec<T>,
+    touched: BTreeSet<u64>,
+}
+
+impl<T> Default for Rows<T> {
+    fn default() -> Self {
+        Self {
+            items: Vec::new(),
+            touched: BTreeSet::new(),
+        }
+    }
+}
+
+impl<T> std::ops::Deref for Rows<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        &self.items
+    }
+}
+
+// Items that aren't saved yet, e.g. read from a scores.json.
+impl<T: Keyed> From<Vec<T>> for Rows<T> {
+    fn from(items: Vec<T>) -> Self {
+        Self {
+            touched: items.iter().map(Keyed::key).collect(),
+            items,
+        }
+    }
+}
+
+impl<T: serde::Serialize> serde::Serialize for Rows<T> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.items.serialize(serializer)
+    }
+}
+
+impl<'de, T: serde::Deserialize<'de> + Keyed> serde::Deserialize<'de> for Rows<T> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Vec::deserialize(deserializer).map(Rows::from)
+    }
+}
+
+impl<T: Keyed> Rows<T> {
+    fn position(&self, key: u64) -> Option<usize> {
+        self.items
+            .binary_search_by_key(&key, Keyed::key)
+            .ok()
+            .or_else(|| self.items.iter().position(|item| item.key() == key))
+    }
+
+    pub fn get_mut(&mut self, key: u64) -> Option<&mut T> {
+        let i = self.position(key)?;
+        self.touched.insert(key);
+        Some(&mut self.items[i])
+    }
+
+    pub fn last_mut(&mut self) -> Option<&mut T> {
+        let item = self.items.last_mut()?;
+        self.touched.insert(item.key());
+        Some(item)
+    }
+
+    pub fn push(&mut self, item: T) {
+        self.touched.insert(item.key());
+        self.items.push(item);
+    }
+
+    pub fn retain(&mut self, mut keep: impl FnMut(&T) -> bool) {
+        let touched = &mut self.touched;
+        self.items.retain(|item| {
+            keep(item) || {
+                touched.insert(item.key());
+                false
+            }
+        });
+    }
+
+    // The items matching `filter`, to change.
+    pub fn iter_mut_where<'a>(
+        &'a mut self,
+        mut filter: impl FnMut(&T) -> bool + 'a,
+    ) -> impl Iterator<Item = &'a mut T> + 'a {
+        let touched = &mut self.touched;
+        self.items
+            .iter_mut()
+            .filter(move |item| filter(item))
+            .inspect(move |item| {
+                touched.insert(item.key());
+            })
+    }
+
+    // Sets the item of `key` as saved, or removes it for None.
+    fn put(&mut self, key: u64, item: Option<T>) {
+        match (self.position(key), item) {
+            (Some(i), Some(item)) => self.items[i] = item,
+            (Some(i), None) => {
+                self.items.remove(i);
+            }
+            (None, Some(item)) => {
+                let i = self.items.partition_point(|other| other.key() < key);
+                self.items.insert(i, item);
+            }
+            (None, None) => {}
+        }
+    }
+}
+
+// Counters and settings of the scores file, kept in one row.
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Counters {
+    version: u32,
+    next_id: u64,
+    legacy_imported: bool,
+    next_team_id: u64,
+    next_player_id: u64,
+    next_tournament_id: u64,
+    next_history_id: u64,
+    next_round_set_id: u64,
+    category_handicaps: BTreeMap<String, Handicap>,
+}
+
+// A row of the `items` table.
+struct Item {
+    kind: &'static str,
+    key: u64,
+    data: String,
+}
+
+fn integer(n: u64) -> Value {
+    Value::Integer(n.min(i64::MAX as u64) as i64)
+}
+
+fn to_json(item: &impl serde::Serialize) -> Result<String, String> {
+    serde_json::to_string(item).map_err(|e| e.to_string())
+}
+
+fn from_json<T: serde::de::DeserializeOwned>(data: &str) -> Result<T, String> {
+    serde_json::from_str(data).map_err(|e| e.to_string())
+}
+
+fn push_items<'a, T: serde::Serialize + 'a>(
+    items: &mut Vec<Item>,
+    kind: &'static str,
+    keyed: impl Iterator<Item = (u64, &'a T)>,
+) -> Result<(), String> {
+    for (key, item) in keyed {
+        items.push(Item {
+            kind,
+            key,
+            data: to_json(item)?,
+        });
+    }
+    Ok(())
+}
+
+// The rows of the small collections of a scores file.
+fn small_items(file: &ScoreFile) -> Result<Vec<Item>, String> {
+    let mut items = Vec::new();
+    push_items(&mut items, "teams", file.teams.iter().map(|t| (t.id, t)))?;
+    push_items(
+        &mut items,
+        "players",
+        file.players.iter().map(|p| (p.id, p)),
+    )?;
+    push_items(
+        &mut items,
+        "tournaments",
+        file.tournaments.iter().map(|t| (t.id, t)),
+    )?;
+    push_items(
+        &mut items,
+        "roundSets",
+        file.round_sets.iter().map(|s| (s.id, s)),
+    )?;
+    let counters = Counters {
+        version: file.version,
+        next_id: file.next_id,
+        legacy_imported: file.legacy_imported,
+        next_team_id: file.next_team_id,
+        next_player_id: file.next_player_id,
+        next_tournament_id: file.next_tournament_id,
+        next_history_id: file.next_history_id,
+        next_round_set_id: file.next_round_set_id,
+        category_handicaps: file.category_handicaps.clone(),
+    };
+    push_items(&mut items, COUNTERS, std::iter::once((0, &counters)))?;
+    Ok(items)
+}
+
+fn write_run(db: &Connection, run: &Run) -> Result<(), String> {
+    db.execute(
+        "INSERT OR REPLACE INTO runs
+         (id, player, mode, elapsed_ms, hits, penalty_ms, timestamp_ms, data)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            integer(run.id),
+            run.player,
+            run.mode,
+            integer(run.elapsed_ms),
+            run.hits,
+            integer(run.penalty_ms),
+            integer(run.timestamp_ms),
+            to_json(run)?,
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+fn write_record(db: &Connection, record: &GameRecord) -> Result<(), String> {
+    db.execute(
+        "INSERT OR REPLACE INTO history
+         (id, player_key, mode, started_ms, ended_ms, elapsed_ms, hits, data)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            integer(record.id),
+            record.player.as_deref().map(players::name_key),
+            record.mode,
+            integer(record.started_ms),
+            integer(record.ended_ms),
+            integer(record.elapsed_ms),
+            record.hits,
+            to_json(record)?,
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+fn write_item(db: &Connection, item: &Item) -> rusqlite::Result<usize> {
+    db.execute(
+        "INSERT OR REPLACE INTO items (kind, key, data) VALUES (?1, ?2, ?3)",
+        params![item.kind, integer(item.key), item.data],
+    )
+}
+
+// Writes the touched runs or history records, deleting those that are gone.
+fn write_touched<T: Keyed>(
+    db: &Connection,
+    rows: &Rows<T>,
+    table: &str,
+    write: impl Fn(&Connection, &T) -> Result<(), String>,
+) -> Result<(), String> {
+    for &key in &rows.touched {
+        match rows.position(key) {
+            Some(i) => write(db, &rows.items[i])?,
+            None => {
+                db.execute(
+                    &format!("DELETE FROM {} WHERE id = ?1", table),
+                    [integer(key)],
+                )
+                .map_err(|e| e.to_string())?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// Writes the items of a log appended to since `saved` of them were written.
+fn write_appended<T: serde::Serialize>(
+    db: &Connection,
+    kind: &'static str,
+    log: &[T],
+    saved: usize,
+) -> Result<(), String> {
+    for (key, entry) in log.iter().enumerate().skip(saved) {
+        let item = Item {
+            kind,
+            key: key as u64,
+            data: to_json(entry)?,
+        };
+        write_item(db, &item).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+// Reads the data column of a table or of a kind of items, in key order.
+fn read_data<T: serde::de::DeserializeOwned>(
+    db: &Connection,
+    sql: &str,
+    params: impl rusqlite::Params,
+) -> Result<Vec<T>, String> {
+    let mut statement = db.prepare(sql).map_err(|e| e.to_string())?;
+    let rows = statement
+        .query_map(params, |row| row.get::<_, String>(0))
+        .map_err(|e| e.to_string())?;
+    rows.map(|data| from_json(&data.map_err(|e| e.to_string())?))
+        .collect()
+}
+
+// Puts the touched runs or history records back as saved.
+fn restore_touched<T: Keyed + serde::de::DeserializeOwned>(
+    db: &Connection,
+    rows: &mut Rows<T>,
+    table: &str,
+) -> Result<(), String> {
+    let sql = format!("SELECT data FROM {} WHERE id = ?1", table);
+    for key in std::mem::take(&mut rows.touched) {
+        let item = read_data(db, &sql, [integer(key)])?.pop();
+        rows.put(key, item);
+    }
+    Ok(())
+}
+
+// The database of a ScoreBook. Keeps the rows of the small collections as
+// last written, which tell what a change touched, and how many ratings and
+// unlocks were written.
+struct ScoreDb {
+    connection: Connection,
+    saved: BTreeMap<(&'static str, u64), String>,
+    saved_ratings: usize,
+    saved_unlocks: usize,
+}
+
+impl ScoreDb {
+    // Creates or migrates the tables and reads the small collections.
+    fn open(connection: Connection) -> Result<Self, String> {
+        let version: i64 = connection
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .map_err(|e| e.to_string())?;
+        if version < DB_VERSION {
+            connection
+                .execute_batch(SCHEMA)
+                .and_then(|_| connection.pragma_update(None, "user_version", DB_VERSION))
+                .map_err(|e| e.to_string())?;
+        }
+        let mut saved = BTreeMap::new();
+        for kind in KINDS {
+            let mut statement = connection
+                .prepare("SELECT key, data FROM items WHERE kind = ?1")
+                .map_err(|e| e.to_string())?;
+            let rows = statement
+                .query_map([kind], |row| Ok((row.get::<_, i64>(0)?, row.get(1)?)))
+                .map_err(|e| e.to_string())?;
+            for row in rows {
+                let (key, data) = row.map_err(|e| e.to_string())?;
+                saved.insert((kind, key as u64), data);
+            }
+        }
+        Ok(Self {
+            connection,
+            saved,
+            saved_ratings: 0,
+            saved_unlocks: 0,
+        })
+    }
+
+    // The scores file the database holds; None for a new database.
+    fn read(&mut self) -> Result<Option<ScoreFile>, String> {
+        if !self.saved.contains_key(&(COUNTERS, 0)) {
+            return Ok(None);
+        }
+        let db = &self.connection;
+        let mut file = ScoreFile::new();
+        for kind in KINDS {
+            self.restore_kind(&mut file, kind)?;
+        }
+        file.runs.items = read_data(db, "SELECT data FROM runs ORDER BY id", [])?;
+        file.history.items = read_data(db, "SELECT data FROM history ORDER BY id", [])?;
+        let log = "SELECT data FROM items WHERE kind = ?1 ORDER BY key";
+        file.ratings = read_data(db, log, [RATINGS])?;
+        file.unlocks = read_data(db, log, [UNLOCKS])?;
+        self.saved_ratings = file.ratings.len();
+        self.saved_unlocks = file.unlocks.len();
+        Ok(Some(file))
+    }
+
+    fn saved_items(&self, kind: &'static str) -> impl Iterator<Item = (u64, &String)> {
+        self.saved
+            .range((kind, 0)..=(kind, u64::MAX))
+            .map(|((_, key), data)| (*key, data))
+    }
+
+    fn items<T: serde::de::DeserializeOwned>(&self, kind: &'static str) -> Result<Vec<T>, String> {
+        self.saved_items(kind)
+            .map(|(_, data)| from_json(data))
+            .collect()
+    }
+
+    // Sets one small collection of the file to its saved rows.
+    fn restore_kind(&self, file: &mut ScoreFile, kind: &'static str) -> Result<(), String> {
+        match kind {
+            "teams" => file.teams = self.items(kind)?,
+            "players" => file.players = self.items(kind)?,
+            "tournaments" => file.tournaments = self.items(kind)?,
+            "roundSets" => file.round_sets = self.items(kind)?,
+            _ => {
+                if let Some(counters) = self.items::<Counters>(kind)?.pop() {
+                    file.version = counters.version;
+                    file.next_id = counters.next_id;
+                    file.legacy_imported = counters.legacy_imported;
+                    file.next_team_id = counters.next_team_id;
+                    file.next_player_id = counters.next_player_id;
+                    file.next_tournament_id = counters.next_tournament_id;
+                    file.next_history_id = counters.next_history_id;
+                    file.next_round_set_id = counters.next_round_set_id;
+                    file.category_handicaps = counters.category_handicaps;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Writes what changed since the last save in one transaction: the
+    // touched runs and history records, the appended ratings and unlocks,
+    // and the rows of the small collections that differ.
+    fn save(&mut self, file: &mut ScoreFile) -> Result<(), String> {
+        let items = small_items(file)?;
+        let keys: BTreeSet<(&str, u64)> = items.iter().map(|item| (item.kind, item.key)).collect();
+        let changed: Vec<Item> = items
+            .into_iter()
+            .filter(|item| self.saved.get(&(item.kind, item.key)) != Some(&item.data))
+            .collect();
+        let removed: Vec<(&'static str, u64)> = self
+            .saved
+            .keys()
+            .filter(|key| !keys.contains(key))
+            .copied()
+            .collect();
+        let transaction = self.connection.transaction().map_err(|e| e.to_string())?;
+        write_touched(&transaction, &file.runs, "runs", write_run)?;
+        write_touched(&transaction, &file.history, "history", write_record)?;
+        write_appended(&transaction, RATINGS, &file.ratings, self.saved_ratings)?;
+        write_appended(&transaction, UNLOCKS, &file.unlocks, self.saved_unlocks)?;
+        for item in &changed {
+            write_item(&transaction, item).map_err(|e| e.to_string())?;
+        }
+        for &(kind, key) in &removed {
+            transaction
+                .execute(
+                    "DELETE FROM items WHERE kind = ?1 AND key = ?2",
+                    params![kind, integer(key)],
+                )
+                .map_err(|e| e.to_string())?;
+        }
+        transaction.commit().map_err(|e| e.to_string())?;
+        for item in changed {
+            self.saved.insert((item.kind, item.key), item.data);
+        }
+        for key in removed {
+            self.saved.remove(&key);
+        }
+        file.runs.touched.clear();
+        file.history.touched.clear();
+        self.saved_ratings = file.ratings.len();
+        self.saved_unlocks = file.unlocks.len();
+        Ok(())
+    }
+
+    // Undoes a failed change: reads the touched runs and history records
+    // back, drops appended ratings and unlocks, and puts back the small
+    // collections that differ from their saved rows.
+    fn restore(&self, file: &mut ScoreFile) -> Result<(), String> {
+        restore_touched(&self.connection, &mut file.runs, "runs")?;
+        restore_touched(&self.connection, &mut file.history, "history")?;
+        file.ratings.truncate(self.saved_ratings);
+        file.unlocks.truncate(self.saved_unlocks);
+        let items = small_items(file)?;
+        for kind in KINDS {
+            let current = items
+                .iter()
+                .filter(|item| item.kind == kind)
+                .map(|item| (item.key, &item.data));
+            if !current.eq(self.saved_items(kind)) {
+                self.restore_kind(file, kind)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// Reads the scores file the runs were kept in before the database.
+fn read_scores_file(path: &Path) -> Result<Option<ScoreFile>, String> {
+    match std::fs::read(path) {
+        Ok(bytes) => match serde_json::from_slice::<ScoreFile>(&bytes) {
+            Ok(file) => Ok(Some(file)),
+            // Keep an unreadable file around for manual recovery.
+            Err(_) => {
+                let _ = std::fs::rename(path, path.with_extension("json.corrupt"));
+                Ok(None)
+            }
+        },
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(format!("failed to read {}: {}", path.display(), e)),
+    }
+}
+
+// The saved runs together with the database they live in.
+pub struct ScoreBook {
+    db: ScoreDb,
+    file: ScoreFile,
+}
+
+impl ScoreBook {
+    // Loads the runs from the app data dir, migrating older files and
+    // importing the config store's highscores the first time.
+    pub fn open(app_handle: &tauri::AppHandle) -> Result<Self, String> {
+        let dir = app_handle
+            .path()
+            .app_data_dir()
+            .map_err(|e| e.to_string())?;
+        std::fs::create_dir_all(&dir)
+            .map_err(|e| format!("failed to create {}: {}", dir.display(), e))?;
+        let path = dir.join(SCORES_DB);
+        let connection = Connection::open(&path)
+            .map_err(|e| format!("failed to open {}: {}", path.display(), e))?;
+        let mut book = Self::load(connection, &dir.join(SCORES_FILE))?;
+        if !book.file.legacy_imported {
+            let highscores = app_handle
+                .store(STORE_FILE)
+                .ok()
+                .and_then(|store| store.get("laserConfig"))
+                .and_then(|config| config["highscores"].as_array().cloned())
+                .unwrap_or_default();
+            book.update(|file| {
+                file.import_legacy(&highscores);
+                Ok(())
+            })?;
+        }
+        Ok(book)
+    }
+
+    // Reads the runs from the database, creating its tables the first time
+    // and moving in the scores file at `json_path` that held them before.
+    pub fn load(connection: Connection, json_path: &Path) -> Result<Self, String> {
+        let mut db = ScoreDb::open(connection)?;
+        let (mut file, imported) = match db.read()? {
+            Some(file) => (file, false),
+            None => match read_scores_file(json_path)? {
+                Some(file) => (file, true),
+                None => (ScoreFile::new(), false),
+            },
+        };
+        file.migrate();
+        let mut book = Self { db, file };
+        book.db.save(&mut book.file)?;
+        if imported {
+            let _ = std::fs::rename(json_path, json_path.with_extension("json.imported"));
+        }
+        Ok(book)
+    }
+
+    pub fn file(&self) -> &ScoreFile {
+        &self.file
+    }
+
+    pub fn db(&self) -> &Connection {
+        &self.db.connection
+    }
+
+    // Applies a change and saves the rows it touched. If either fails, the
+    // runs stay as they were.
+    pub fn update<T>(
+        &mut self,
+        change: impl FnOnce(&mut ScoreFile) -> Result<T, String>,
+    ) -> Result<T, String> {
+        let result =
+            change(&mut self.file).and_then(|value| self.db.save(&mut self.file).map(|_| value));
+        if result.is_err() {
+            // Rows that were written once always read back.
+            let _ = self.db.restore(&mut self.file);
+        }
+        result
+    }
+
+    // One page of a leaderboard. Leaderboards of a time range read only the
+    // runs saved in it, by the timestamp index.
+    fn leaderboard(
+        &self,
+        query: &LeaderboardQuery,
+        limit: usize,
+        offset: usize,
+    ) -> Result<LeaderboardPage, String> {
+        if query.range == TimeRange::default() {
+            return Ok(self.file.leaderboard(query, limit, offset));
+        }
+        let mut statement = self
+            .db()
+            .prepare_cached("SELECT data FROM runs WHERE timestamp_ms >= ?1 AND timestamp_ms < ?2")
+            .map_err(|e| e.to_string())?;
+        let runs = statement
+            .query_map(
+                [
+                    integer(query.range.from_ms.unwrap_or(0)),
+                    integer(query.range.to_ms.unwrap_or(u64::MAX)),
+                ],
+                |row| row.get::<_, String>(0),
+            )
+            .map_err(|e| e.to_string())?
+            .map(|data| {
+                serde_json::from_str(&data.map_err(|e| e.to_string())?).map_err(|e| e.to_string())
+            })
+            .collect::<Result<Vec<Run>, String>>()?;
+        Ok(leaderboard_page(sorted(&runs, query), limit, offset))
+    }
+}
+
+// Quotes a CSV field if it contains a separator, quote or line break.
+fn csv_field(text: &str) -> std::borrow::Cow<'_, str> {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\"")).into()
+    } else {
+        text.into()
+    }
+}
+
+// Milliseconds as seconds with three decimals, e.g. 41.200.
+fn seconds(ms: u64) -> String {
+    format!("{}.{:03}", ms / 1000, ms % 1000)
+}
+
+// Unix time in milliseconds as "YYYY-MM-DD HH:MM:SS" (UTC).
+fn utc_date(timestamp_ms: u64) -> String {
+    let secs = timestamp_ms / 1000;
+    let (year, month, day) = civil_from_days((secs / 86400) as i64);
+    let time = secs % 86400;
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        time / 3600,
+        time / 60 % 60,
+        time % 60
+    )
+}
+
+// Writes the leaderboard of a mode (or all runs) as CSV, one row at a time.
+fn write_leaderboard_csv(
+    out: &mut impl Write,
+    file: &ScoreFile,
+    mode: Option<&str>,
+) -> std::io::Result<()> {
+    writeln!(
+        out,
+        "Rank,Player,Mode,Time (s),Hits,Penalty (s),Final score,Date (UTC),Note,Tags"
+    )?;
+    let query = LeaderboardQuery {
+        mode,
+        ..LeaderboardQuery::default()
+    };
+    for (i, run) in file.sorted(&query).into_iter().enumerate() {
+        writeln!(
+            out,
+            "{},{},{},{},{},{},{},{},{},{}",
+            i + 1,
+            csv_field(&run.player),
+            csv_field(&run.mode),
+            seconds(run.elapsed_ms),
+            run.hits,
+            seconds(run.penalty_ms),
+            run.final_score_text(),
+            utc_date(run.timestamp_ms),
+            csv_field(run.note.as_deref().unwrap_or("")),
+            csv_field(&run.tags.join("; "))
+        )?;
+    }
+    out.flush()
+}
+
+fn write_runs_json(out: &mut impl Write, file: &ScoreFile) -> std::io::Result<()> {
+    serde_json::to_writer_pretty(&mut *out, &file.runs)?;
+    out.flush()
+}
+
+pub fn normalize_tag(tag: &str) -> String {
+    players::name_key(tag)
+}
+
+fn check_tag(tag: &str) -> Result<String, String> {
+    let tag = normalize_tag(tag);
+    if tag.is_empty() {
+        return Err("a tag can't be empty".to_string());
+    }
+    if tag.chars().count() > MAX_TAG_LENGTH {
+        return Err(format!("tags are at most {} characters", MAX_TAG_LENGTH));
+    }
+    Ok(tag)
+}
+
+// The tags of saved runs, the most used first.
+fn tags(file: &ScoreFile) -> Vec<TagCount> {
+    let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+    for tag in file.runs.iter().flat_map(|run| &run.tags) {
+        *counts.entry(tag).or_default() += 1;
+    }
+    let mut tags: Vec<TagCount> = counts
+        .into_iter()
+        .map(|(tag, count)| TagCount {
+            tag: tag.to_string(),
+            count,
+        })
+        .collect();
+    tags.sort_by_key(|tag| std::cmp::Reverse(tag.count));
+    tags
+}
+
+// The players a run is saved for: those of its relay legs in running
+// order, else its player.
+fn run_players(result: &NewRun) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for name in result.legs.iter().map(|leg| &leg.player) {
+        if !names
+            .iter()
+            .any(|n| players::name_key(n) == players::name_key(name))
+        {
+            names.push(name.clone());
+        }
+    }
+    if names.is_empty() {
+        names.push(result.player.clone());
+    }
+    names
+}
+
+// Command to save a finished run. Returns it with its id and timestamp, or
+// null for a practice run when those are discarded. A relay run is saved for
+// every player of the relay and the first player's run returned; it takes
+// no handicaps and counts for no round set.
+#[tauri::command]
+pub fn save_run(
+    mut result: NewRun,
+    app_handle: tauri::AppHandle,
+    scores: tauri::State<Mutex<ScoreBook>>,
+) -> Result<Option<Run>, NameError> {
+    if let Some(game_id) = result.game_id {
+        let game_result = app_handle
+            .state::<Arc<Mutex<GameManager>>>()
+            .lock()
+            .map_err(|e| e.to_string())?
+            .result_of(game_id, &result.player)?;
+        result.take_game_result(game_result);
+    }
+    if result.practice && !keep_practice_runs(&app_handle) {
+        settle_game(&app_handle, result.game_id);
+        return Ok(None);
+    }
+    let relay = !result.legs.is_empty();
+    if relay && result.tournament_match.is_some() {
+        return Err("a relay can't be played for a tournament match"
+            .to_string()
+            .into());
+    }
+    let mode = result.mode.as_deref().unwrap_or(DEFAULT_MODE);
+    result.scoring = Some(ScoringConfig::load(&app_handle, mode));
+    result.min_run_ms = (min_run_seconds(&app_handle) * 1000.0).round() as u64;
+    let max_length = players::max_name_length(&app_handle);
+    let rating_settings = RatingSettings::load(&app_handle);
+    let catalog = achievements::catalog(&app_handle);
+    result.category = result
+        .category
+        .as_deref()
+        .map(|category| players::check_category(&app_handle, category))
+        .transpose()?;
+    let mut book = scores.lock().map_err(|e| e.to_string())?;
+    for name in run_players(&result) {
+        players::check_name(book.file(), &name, result.confirm_new_player, max_length)?;
+    }
+    let (runs, progress, unlocks) = book.update(|file| {
+        let timestamp_ms = unix_time_ms();
+        let mut runs: Vec<Run> = Vec::new();
+        let mut progress = None;
+        let mut unlocks = Vec::new();
+        for name in run_players(&result) {
+            let mut new_run = result.clone();
+            let player = players::resolve(file, &name, result.confirm_new_player, max_length)?;
+            new_run.handicap = (!relay)
+                .then(|| players::handicap_for(file, &player))
+                .flatten();
+            new_run.category = result.category.clone().or(player.category);
+            new_run.player = player.name;
+            new_run.player_id = Some(player.id);
+            new_run.relay_id = runs.first().map(|run| run.id);
+            new_run.team_id = file.team_for(&new_run.player, new_run.team_id)?;
+            if let Some(link) = new_run.tournament_match {
+                file.check_match(&new_run.player, link)?;
+            }
+            let run = file.insert(new_run, timestamp_ms);
+            if let Some(game_id) = result.game_id {
+                history::link_run(file, game_id, &run);
+            }
+            if !relay {
+                progress = rounds::add_run(file, &run);
+                rating::record_leaderboard_run(file, &run, &rating_settings);
+            }
+            unlocks.extend(achievements::evaluate(file, &run, &catalog));
+            runs.push(run);
+        }
+        Ok((runs, progress, unlocks))
+    })?;
+    for run in &runs {
+        let _ = app_handle.emit("run-saved", run);
+        teams::report_run(&app_handle, run);
+    }
+    if let Some(run) = runs.first().filter(|run| run.suspect) {
+        let _ = app_handle.emit(
+            "suspect-run",
+            SuspectRun {
+                run_id: run.id,
+                player: run.player.clone(),
+                mode: run.mode.clone(),
+                elapsed_ms: run.elapsed_ms,
+                reasons: run.suspect_reasons.clone(),
+            },
+        );
+    }
+    for unlocked in achievements::announcements(book.file(), &unlocks, &catalog) {
+        let _ = app_handle.emit("achievement-unlocked", unlocked);
+    }
+    drop(book);
+    if let Some(progress) = progress {
+        rounds::report_progress(&app_handle, progress);
+    }
+    settle_game(&app_handle, result.game_id);
+    Ok(runs.into_iter().next())
+}
+
+// Lets the game a run was saved from be played again with rematch.
+fn settle_game(app_handle: &tauri::AppHandle, game_id: Option<u64>) {
+    let Some(game_id) = game_id else {
+        return;
+    };
+    if let Ok(mut game) = app_handle.state::<Arc<Mutex<GameManager>>>().lock() {
+        game.settle_result(game_id);
+    }
+}
+
+// Whether practice games are stored, as runs and in the history.
+pub fn keep_practice_runs(app_handle: &tauri::AppHandle) -> bool {
+    app_handle
+        .store(STORE_FILE)
+        .ok()
+        .and_then(|store| store.get(KEEP_PRACTICE_KEY))
+        .and_then(|value| value.as_bool())
+        .unwrap_or(true)
+}
+
+// Command to choose whether practice games are stored or discarded.
+#[tauri::command]
+pub fn set_keep_practice_runs(keep: bool, app_handle: tauri::AppHandle) -> Result<(), String> {
+    let store = app_handle.store(STORE_FILE).map_err(|e| e.to_string())?;
+    store.set(KEEP_PRACTICE_KEY, keep);
+    Ok(())
+}
+
+// Shortest run time that is plausible; faster finishes are saved as
+// suspect.
+fn min_run_seconds(app_handle: &tauri::AppHandle) -> f64 {
+    app_handle
+        .store(STORE_FILE)
+        .ok()
+        .and_then(|store| store.get(MIN_RUN_SECONDS_KEY))
+        .and_then(|value| value.as_f64())
+        .unwrap_or(DEFAULT_MIN_RUN_SECONDS)
+}
+
+// Command to fetch the shortest plausible run time.
+#[tauri::command]
+pub fn get_min_run_seconds(app_handle: tauri::AppHandle) -> f64 {
+    min_run_seconds(&app_handle)
+}
+
+// Command to change the shortest plausible run time; 0 accepts any.
+#[tauri::command]
+pub fn set_min_run_seconds(seconds: f64, app_handle: tauri::AppHandle) -> Result<(), String> {
+    if !(0.0..=3600.0).contains(&seconds) {
+        return Err("the shortest run must be between 0 and 3600 seconds".to_string());
+    }
+    let store = app_handle.store(STORE_FILE).map_err(|e| e.to_string())?;
+    store.set(MIN_RUN_SECONDS_KEY, seconds);
+    Ok(())
+}
+
+// Command to fetch the streak bonus settings.
+#[tauri::command]
+pub fn get_streak_bonus(app_handle: tauri::AppHandle) -> StreakBonus {
+    StreakBonus::load(&app_handle)
+}
+
+// Command to change the streak bonus of the modes without a scoring config
+// of their own; applies to runs saved from now on.
+#[tauri::command]
+pub fn set_streak_bonus(bonus: StreakBonus, app_handle: tauri::AppHandle) -> Result<(), String> {
+    bonus.check()?;
+    let store = app_handle.store(STORE_FILE).map_err(|e| e.to_string())?;
+    store.set(
+        STREAK_BONUS_KEY,
+        serde_json::to_value(bonus).map_err(|e| e.to_string())?,
+    );
+    Ok(())
+}
+
+// Command to fetch one page of the leaderboard of a mode, or of all runs.
+// Failed runs are left out unless `include_failed` is set. `range` limits
+// the runs by when they were saved, in local time `utc_offset_minutes` ahead
+// of UTC, and defaults to the range set for the scoreboard. With a
+// `category` only the runs of that category are ranked.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub fn get_leaderboard(
+    mode: Option<String>,
+    adjusted: Option<bool>,
+    include_failed: Option<bool>,
+    range: Option<LeaderboardRange>,
+    utc_offset_minutes: Option<i64>,
+    category: Option<String>,
+    limit: Option<usize>,
+    offset: Option<usize>,
+    app_handle: tauri::AppHandle,
+    scores: tauri::State<Mutex<ScoreBook>>,
+) -> Result<LeaderboardPage, String> {
+    let range = range.unwrap_or_else(|| LeaderboardRange::load(&app_handle));
+    let book = scores.lock().map_err(|e| e.to_string())?;
+    let query = LeaderboardQuery {
+        mode: mode.as_deref(),
+        adjusted: adjusted.unwrap_or(false),
+        include_failed: include_failed.unwrap_or(false),
+        range: range.resolve(unix_time_ms(), utc_offset_minutes.unwrap_or(0) * 60_000),
+        category: category.as_deref(),
+    };
+    let mut page = book.leaderboard(
+        &query,
+        limit.unwrap_or(DEFAULT_PAGE_SIZE),
+        offset.unwrap_or(0),
+    )?;
+    page.range = range;
+    Ok(page)
+}
+
+// Command to choose the range the scoreboard shows, used by get_leaderboard
+// when none is given.
+#[tauri::command]
+pub fn set_leaderboard_range(
+    range: LeaderboardRange,
+    app_handle: tauri::AppHandle,
+) -> Result<(), String> {
+    let store = app_handle.store(STORE_FILE).map_err(|e| e.to_string())?;
+    store.set(
+        LEADERBOARD_RANGE_KEY,
+        serde_json::to_value(range).map_err(|e| e.to_string())?,
+    );
+    let _ = app_handle.emit(
+        "leaderboard-changed",
+        LeaderboardChanged {
+            run_id: None,
+            mode: None,
+            range,
+        },
+    );
+    Ok(())
+}
+
+// Command to fetch all runs of a player, newest first.
+#[tauri::command]
+pub fn get_player_runs(
+    name: String,
+    scores: tauri::State<Mutex<ScoreBook>>,
+) -> Result<Vec<Run>, String> {
+    let book = scores.lock().map_err(|e| e.to_string())?;
+    Ok(book.file.player_runs(&name))
+}
+
+fn report_change(app_handle: &tauri::AppHandle, run: &Run) {
+    let _ = app_handle.emit(
+        "leaderboard-changed",
+        LeaderboardChanged {
+            run_id: Some(run.id),
+            mode: Some(run.mode.clone()),
+            range: LeaderboardRange::load(app_handle),
+        },
+    );
+}
+
+fn find_run(file: &mut ScoreFile, id: u64) -> Result<&mut Run, String> {
+    file.runs
+        .get_mut(id)
+        .ok_or_else(|| format!("no run with id {}", id))
+}
+
+// Voids hit `index` of a saved run and rescores it. Returns the voided
+// hit, and whether the run failed because of it; it stays failed.
+fn void_run_hit(run: &mut Run, index: usize, reason: &str) -> Result<(Hit, bool), String> {
+    if run.mode == REVERSE_MODE {
+        return Err("collected beams can't be removed".to_string());
+    }
+    if run.relay_id.is_some() {
+        return Err("hits of a relay can't be removed".to_string());
+    }
+    let timeline = run
+        .timeline
+        .as_mut()
+        .ok_or_else(|| format!("run {} was saved without its hits", run.id))?;
+    let last = game::void_hit(timeline, index, reason)?;
+    let hit = timeline[index].clone();
+    run.hits = timeline.iter().filter(|hit| hit.counts()).count() as u32;
+    run.penalty_ms = run.penalty_ms.saturating_sub(hit.penalty_ms);
+    run.score(run.scoring);
+    Ok((hit, last && run.failed))
+}
+
+// Payload of game-hit-voided, also returned by remove_hit.
+#[derive(Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HitVoided {
+    // The saved run, or None for the current game.
+    pub run_id: Option<u64>,
+    pub game_id: Option<u64>,
+    pub index: usize,
+    pub hit: Hit,
+    // Hits and penalties after the correction.
+    pub hits: u32,
+    pub penalty_ms: u64,
+    // Set when the game ended in a game over because of the hit; it isn't
+    // resumed.
+    pub warning: Option<String>,
+}
+
+// Command to void hit `index` of the timeline of saved run `run_id`, or of
+// the current game with None. The hit stays in the timeline with the
+// reason; the hits, penalties and score are recalculated.
+#[tauri::command]
+pub fn remove_hit(
+    run_id: Option<u64>,
+    index: usize,
+    reason: String,
+    app_handle: tauri::AppHandle,
+    game: tauri::State<Arc<Mutex<GameManager>>>,
+    scores: tauri::State<Mutex<ScoreBook>>,
+) -> Result<HitVoided, String> {
+    let reason = reason.trim().to_string();
+    if reason.is_empty() {
+        return Err("removing a hit needs a reason".to_string());
+    }
+    let warning = |ended: bool| {
+        ended.then(|| "the game ended because of this hit and stays over".to_string())
+    };
+    let voided = match run_id {
+        Some(id) => {
+            let mut book = scores.lock().map_err(|e| e.to_string())?;
+            let (voided, run) = book.update(|file| {
+                let run = find_run(file, id)?;
+                let (hit, ended) = void_run_hit(run, index, &reason)?;
+                let voided = HitVoided {
+                    run_id: Some(id),
+                    game_id: None,
+                    index,
+                    hit,
+                    hits: run.hits,
+                    penalty_ms: run.penalty_ms,
+                    warning: warning(ended),
+                };
+                Ok((voided, run.clone()))
+            })?;
+            drop(book);
+            report_change(&app_handle, &run);
+            voided
+        }
+        None => {
+            let (voided, state) = {
+                let mut game = game.lock().map_err(|e| e.to_string())?;
+                let (hit, ended) = game.void_hit(index, &reason)?;
+                let state = game.state();
+                let voided = HitVoided {
+                    run_id: None,
+                    game_id: Some(state.game_id),
+                    index,
+                    hit,
+                    hits: state.hits,
+                    penalty_ms: state.penalty_ms,
+                    warning: warning(ended),
+                };
+                (voided, state)
+            };
+            game::emit_state(&app_handle, state);
+            voided
+        }
+    };
+    let _ = app_handle.emit("game-hit-voided", voided.clone());
+    Ok(voided)
+}
+
+// Command to void a run: it stays saved, with the reason, but leaves the
+// leaderboards and team standings.
+#[tauri::command]
+pub fn void_run(
+    id: u64,
+    reason: String,
+    app_handle: tauri::AppHandle,
+    scores: tauri::State<Mutex<ScoreBook>>,
+) -> Result<Run, String> {
+    let reason = reason.trim().to_string();
+    if reason.is_empty() {
+        return Err("voiding a run needs a reason".to_string());
+    }
+    let mut book = scores.lock().map_err(|e| e.to_string())?;
+    let run = book.update(|file| {
+        let run = find_run(file, id)?;
+        if run.void.is_some() {
+            return Err(format!("run {} is already void", id));
+        }
+        run.void = Some(Void {
+            reason,
+            voided_ms: unix_time_ms(),
+        });
+        Ok(run.clone())
+    })?;
+    report_change(&app_handle, &run);
+    Ok(run)
+}
+
+// Command to confirm a suspect run after checking it, which puts it on the
+// leaderboards.
+#[tauri::command]
+pub fn confirm_run(
+    id: u64,
+    app_handle: tauri::AppHandle,
+    scores: tauri::State<Mutex<ScoreBook>>,
+) -> Result<Run, String> {
+    let mut book = scores.lock().map_err(|e| e.to_string())?;
+    let run = book.update(|file| {
+        let run = find_run(file, id)?;
+        if !run.suspect {
+            return Err(format!("run {} isn't suspect", id));
+        }
+        run.suspect = false;
+        Ok(run.clone())
+    })?;
+    report_change(&app_handle, &run);
+    Ok(run)
+}
+
+// Command to put a voided run back on the leaderboards.
+#[tauri::command]
+pub fn unvoid_run(
+    id: u64,
+    app_handle: tauri::AppHandle,
+    scores: tauri::State<Mutex<ScoreBook>>,
+) -> Result<Run, String> {
+    let mut book = scores.lock().map_err(|e| e.to_string())?;
+    let run = book.update(|file| {
+        let run = find_run(file, id)?;
+        if run.void.take().is_none() {
+            return Err(format!("run {} isn't void", id));
+        }
+        Ok(run.clone())
+    })?;
+    report_change(&app_handle, &run);
+    Ok(run)
+}
+
+// Command to delete a saved run.
+#[tauri::command]
+pub fn delete_run(id: u64, scores: tauri::State<Mutex<ScoreBook>>) -> Result<(), String> {
+    let mut book = scores.lock().map_err(|e| e.to_string())?;
+    book.update(|file| {
+        if !file.delete(id) {
+            return Err(format!("no run with id {}", id));
+        }
+        Ok(())
+    })
+}
+
+// Command to set the note of a saved run, or clear it with an empty one.
+#[tauri::command]
+pub fn set_run_note(
+    id: u64,
+    note: Option<String>,
+    scores: tauri::State<Mutex<ScoreBook>>,
+) -> Result<Run, String> {
+    let note = note
+        .map(|note| note.trim().to_string())
+        .filter(|note| !note.is_empty());
+    if note
+        .as_ref()
+        .is_some_and(|note| note.chars().count() > MAX_NOTE_LENGTH)
+    {
+        return Err(format!("notes are at most {} characters", MAX_NOTE_LENGTH));
+    }
+    let mut book = scores.lock().map_err(|e| e.to_string())?;
+    book.update(|file| {
+        let run = find_run(file, id)?;
+        run.note = note;
+        Ok(run.clone())
+    })
+}
+
+// Command to tag a saved run; tags are trimmed and lowercased.
+#[tauri::command]
+pub fn add_run_tag(
+    id: u64,
+    tag: String,
+    scores: tauri::State<Mutex<ScoreBook>>,
+) -> Result<Run, String> {
+    let tag = check_tag(&tag)?;
+    let mut book = scores.lock().map_err(|e| e.to_string())?;
+    book.update(|file| {
+        let run = find_run(file, id)?;
+        if !run.tags.contains(&tag) {
+            run.tags.push(tag);
+        }
+        Ok(run.clone())
+    })
+}
+
+#[tauri::command]
+pub fn remove_run_tag(
+    id: u64,
+    tag: String,
+    scores: tauri::State<Mutex<ScoreBook>>,
+) -> Result<Run, String> {
+    let tag = normalize_tag(&tag);
+    let mut book = scores.lock().map_err(|e| e.to_string())?;
+    book.update(|file| {
+        let run = find_run(file, id)?;
+        run.tags.retain(|t| *t != tag);
+        Ok(run.clone())
+    })
+}
+
+// Command to fetch the tags in use with their counts, for autocompletion.
+#[tauri::command]
+pub fn list_tags(scores: tauri::State<Mutex<ScoreBook>>) -> Result<Vec<TagCount>, String> {
+    let book = scores.lock().map_err(|e| e.to_string())?;
+    Ok(tags(&book.file))
+}
+
+// Command to export the leaderboard of a mode, or of all runs, as a CSV
+// file. Without a path it goes to a date-stamped file in the user's
+// documents folder. Returns the path written.
+#[tauri::command(async)]
+pub fn export_leaderboard(
+    path: Option<String>,
+    mode: Option<String>,
+    app_handle: tauri::AppHandle,
+    scores: tauri::State<Mutex<ScoreBook>>,
+) -> Result<String, String> {
+    let path = match path {
+        Some(path) => PathBuf::from(path),
+        None => app_handle
+            .path()
+            .document_dir()
+            .map_err(|e| e.to_string())?
+            .join(stamped_file_name("leaderboard", "csv")),
+    };
+    let file =
+        File::create(&path).map_err(|e| format!("failed to create {}: {}", path.display(), e))?;
+    let book = scores.lock().map_err(|e| e.to_string())?;
+    write_leaderboard_csv(&mut BufWriter::new(file), &book.file, mode.as_deref())
+        .map_err(|e| format!("failed to write {}: {}", path.display(), e))?;
+    Ok(path.display().to_string())
+}
+
+// Command to export every saved run, notes and tags included, as a JSON
+// file for import_runs on another PC. Without a path it goes to the
+// documents folder, like export_leaderboard. Returns the path written.
+#[tauri::command(async)]
+pub fn export_runs(
+    path: Option<String>,
+    app_handle: tauri::AppHandle,
+    scores: tauri::State<Mutex<ScoreBook>>,
+) -> Result<String, String> {
+    let path = match path {
+        Some(path) => PathBuf::from(path),
+        None => app_handle
+            .path()
+            .document_dir()
+            .map_err(|e| e.to_string())?
+            .join(stamped_file_name("runs", "json")),
+    };
+    let file =
+        File::create(&path).map_err(|e| format!("failed to create {}: {}", path.display(), e))?;
+    let book = scores.lock().map_err(|e| e.to_string())?;
+    write_runs_json(&mut BufWriter::new(file), &book.file)
+        .map_err(|e| format!("failed to write {}: {}", path.display(), e))?;
+    Ok(path.display().to_string())
+}
+
+// Command to import the runs of a file written by export_runs. Returns the
+// number imported; runs saved already are skipped.
+#[tauri::command(async)]
+pub fn import_runs(
+    path: String,
+    app_handle: tauri::AppHandle,
+    scores: tauri::State<Mutex<ScoreBook>>,
+) -> Result<usize, String> {
+    let file = File::open(&path).map_err(|e| format!("failed to open {}: {}", path, e))?;
+    let runs: Vec<Run> = serde_json::from_reader(BufReader::new(file))
+        .map_err(|e| format!("failed to read {}: {}", path, e))?;
+    let mut book = scores.lock().map_err(|e| e.to_string())?;
+    let imported = book.update(|file| Ok(file.import_runs(runs)))?;
+    drop(book);
+    if imported > 0 {
+        let _ = app_handle.emit(
+            "leaderboard-changed",
+            LeaderboardChanged {
+                run_id: None,
+                mode: None,
+                range: LeaderboardRange::load(&app_handle),
+            },
+        );
+    }
+    Ok(imported)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_run(player: &str, mode: &str, elapsed_ms: u64, penalty_ms: u64) -> NewRun {
+        NewRun {
+            player: player.to_string(),
+            mode: Some(mode.to_string()),
+            elapsed_ms,
+            hits: 0,
+            penalty_ms,
+            points: None,
+            failed: false,
+            practice: false,
+            config: serde_json::Value::Null,
+            team_id: None,
+            tournament_match: None,
+            timeline: None,
+            splits: Vec::new(),
+            silent_ms: 0,
+            disabled_sensors: Vec::new(),
+            overtime: false,
+            legs: Vec::new(),
+            confirm_new_player: false,
+            game_id: None,
+            category: None,
+            player_id: None,
+            relay_id: None,
+            handicap: None,
+            scoring: None,
+            min_run_ms: 0,
+        }
+    }
+
+    #[test]
+    fn ranks_categories_apart_and_keeps_them_on_rename() {
+        let mut file = ScoreFile::new();
+        let kid = |player, elapsed_ms| NewRun {
+            category: Some("Kids".to_string()),
+            ..new_run(player, DEFAULT_MODE, elapsed_ms, 0)
+        };
+        file.insert(kid("Ann", 30_000), 1);
+        file.insert(new_run("Ben", DEFAULT_MODE, 20_000, 0), 2);
+        file.insert(kid("Cem", 25_000), 3);
+
+        let kids = LeaderboardQuery {
+            category: Some("kids"),
+            ..LeaderboardQuery::default()
+        };
+        let page = file.leaderboard(&kids, 10, 0);
+        let players: Vec<_> = page.runs.iter().map(|r| r.run.player.as_str()).collect();
+        assert_eq!(players, ["Cem", "Ann"]);
+        assert_eq!(
+            file.leaderboard(&LeaderboardQuery::default(), 10, 0).total,
+            3
+        );
+
+        assert_eq!(
+            players::reassign_category(&mut file, "KIDS", Some("Juniors")),
+            2
+        );
+        assert_eq!(file.leaderboard(&kids, 10, 0).total, 0);
+        let juniors = LeaderboardQuery {
+            category: Some("Juniors"),
+            ..LeaderboardQuery::default()
+        };
+        assert_eq!(file.leaderboard(&juniors, 10, 0).total, 2);
+    }
+
+    #[test]
+    fn game_results_replace_the_sent_times() {
+        struct FixedClock;
+        impl crate::game::Clock for FixedClock {
+            fn now_ms(&self) -> u64 {
+                0
+            }
+        }
+        let mut game = GameManager::new(Arc::new(FixedClock));
+        game.start(crate::game::GameConfig {
+            countdown_seconds: 0.0,
+            ..crate::game::GameConfig::default()
+        })
+        .unwrap();
+        assert!(game.finish());
+        let mut sent = NewRun {
+            hits: 3,
+            timeline: None,
+            ..new_run("Ann", DEFAULT_MODE, 1, 9_000)
+        };
+        sent.take_game_result(game.result().unwrap());
+        assert_eq!((sent.elapsed_ms, sent.hits, sent.penalty_ms), (0, 0, 0));
+        assert_eq!(sent.timeline.map(|timeline| timeline.len()), Some(0));
+    }
+
+    #[test]
+    fn voided_hits_stay_in_the_timeline_and_rescore() {
+        let hit = |elapsed_ms, count, penalty_ms| Hit {
+            sensor: 2,
+            name: "Laser 3".to_string(),
+            elapsed_ms,
+            count,
+            grace: false,
+            fatal: false,
+            penalty_ms,
+            penalized_ms: 0,
+            voided: None,
+        };
+        let mut file = ScoreFile::new();
+        let mut run = file.insert(
+            NewRun {
+                hits: 2,
+                failed: true,
+                timeline: Some(vec![hit(1_000, 1, 5_000), hit(2_000, 2, 3_000)]),
+                scoring: Some(ScoringConfig::default()),
+                ..new_run("Ann", DEFAULT_MODE, 30_000, 8_000)
+            },
+            1,
+        );
+        assert_eq!(run.final_score(), 38_000);
+
+        let (voided, ended) = void_run_hit(&mut run, 0, "spectator").unwrap();
+        assert_eq!(voided.voided.as_deref(), Some("spectator"));
+        assert!(!ended);
+        assert_eq!(
+            (run.hits, run.penalty_ms, run.final_score()),
+            (1, 3_000, 33_000)
+        );
+        let timeline = run.timeline.as_ref().unwrap();
+        assert_eq!((timeline[1].count, timeline[1].penalized_ms), (1, 5_000));
+        assert!(void_run_hit(&mut run, 0, "again").is_err());
+
+        // The run failed on its last hit, and stays failed.
+        let (_, ended) = void_run_hit(&mut run, 1, "spectator").unwrap();
+        assert!(ended && run.failed);
+        assert_eq!(run.hits, 0);
+    }
+
+    #[test]
+    fn ranks_by_final_score_per_mode() {
+        let mut file = ScoreFile::new();
+        file.insert(new_run("Ann", DEFAULT_MODE, 30_000, 0), 1);
+        file.insert(new_run("Ben", DEFAULT_MODE, 20_000, 15_000), 2);
+        file.insert(new_run("Cem", DEFAULT_MODE, 25_000, 0), 3);
+        file.insert(new_run("Ann", STEALTH_MODE, 1_000, 0), 4);
+
+        let timed = LeaderboardQuery {
+            mode: Some(DEFAULT_MODE),
+            ..LeaderboardQuery::default()
+        };
+        let page = file.leaderboard(&timed, 2, 1);
+        assert_eq!(page.total, 3);
+        let players: Vec<_> = page.runs.iter().map(|r| r.run.player.as_str()).collect();
+        assert_eq!(players, ["Ann", "Ben"]);
+        assert_eq!(page.runs[0].rank, 2);
+        assert_eq!(page.runs[1].final_score, 35_000);
+
+        assert_eq!(
+            file.leaderboard(&LeaderboardQuery::default(), 10, 0).total,
+            4
+        );
+        assert_eq!(file.player_runs(" ann ")[0].mode, "stealth");
+        assert!(file.delete(2));
+        assert!(!file.delete(2));
+
+        file.insert(
+            NewRun {
+                failed: true,
+                ..new_run("Dana", DEFAULT_MODE, 5_000, 0)
+            },
+            5,
+        );
+        assert_eq!(file.leaderboard(&timed, 10, 0).runs[0].run.player, "Cem");
+        let with_failed = LeaderboardQuery {
+            include_failed: true,
+            ..timed
+        };
+        // Runs that didn't finish rank below the others, however fast.
+        let players: Vec<_> = file
+            .leaderboard(&with_failed, 10, 0)
+            .runs
+            .into_iter()
+            .map(|r| r.run.player)
+            .collect();
+        assert_eq!(players.last().unwrap(), "Dana");
+
+        let id = file.runs[0].id;
+        file.runs.get_mut(id).unwrap().void = Some(Void {
+            reason: "helped".to_string(),
+            voided_ms: 6,
+        });
+        assert_eq!(file.leaderboard(&with_failed, 10, 0).total, 2);
+        file.insert(
+            NewRun {
+                practice: true,
+                ..new_run("Eve", DEFAULT_MODE, 1_000, 0)
+            },
+            7,
+        );
+        assert_eq!(file.leaderboard(&with_failed, 10, 0).total, 2);
+        assert_eq!(file.player_runs("ann").len(), 2);
+
+        // Overtime runs rank between completed runs and unfinished ones.
+        file.insert(
+            NewRun {
+                overtime: true,
+                ..new_run("Finn", DEFAULT_MODE, 2_000, 0)
+            },
+            8,
+        );
+        let players: Vec<_> = file
+            .leaderboard(&with_failed, 10, 0)
+            .runs
+            .into_iter()
+            .map(|r| r.run.player)
+            .collect();
+        assert_eq!(players[players.len() - 2..], ["Finn", "Dana"]);
+    }
+
+    #[test]
+    fn resolves_leaderboard_ranges_in_local_time() {
+        // Wednesday 2024-05-29 00:30 in UTC+2, still Tuesday in UTC.
+        let day = days_from_civil(2024, 5, 29);
+        let offset = 2 * 3_600_000;
+        let now = (day * DAY_MS - offset) as u64 + 1_800_000;
+        let start = |range: LeaderboardRange| range.resolve(now, offset).from_ms.unwrap();
+        assert_eq!(
+            start(LeaderboardRange::Today),
+            (day * DAY_MS - offset) as u64
+        );
+        assert_eq!(
+            start(LeaderboardRange::ThisWeek),
+            ((day - 2) * DAY_MS - offset) as u64
+        );
+        assert_eq!(
+            start(LeaderboardRange::ThisMonth),
+            ((day - 28) * DAY_MS - offset) as u64
+        );
+        assert_eq!(LeaderboardRange::AllTime.resolve(now, offset).from_ms, None);
+
+        let mut file = ScoreFile::new();
+        file.insert(new_run("Ann", DEFAULT_MODE, 10_000, 0), now - 3_600_000);
+        file.insert(new_run("Ben", DEFAULT_MODE, 20_000, 0), now);
+        let today = LeaderboardQuery {
+            range: LeaderboardRange::Today.resolve(now, offset),
+            ..LeaderboardQuery::default()
+        };
+        assert_eq!(file.leaderboard(&today, 10, 0).runs[0].run.player, "Ben");
+    }
+
+    #[test]
+    fn keeps_suspect_runs_off_the_leaderboard() {
+        let mut file = ScoreFile::new();
+        let too_fast = file.insert(
+            NewRun {
+                min_run_ms: 3_000,
+                ..new_run("Ann", DEFAULT_MODE, 800, 0)
+            },
+            1,
+        );
+        assert_eq!(too_fast.suspect_reasons, [SuspectReason::TooFast]);
+        let unplugged = file.insert(
+            NewRun {
+                silent_ms: 20_000,
+                ..new_run("Ben", DEFAULT_MODE, 30_000, 0)
+            },
+            2,
+        );
+        assert_eq!(unplugged.suspect_reasons, [SuspectReason::SensorSilence]);
+        file.insert(new_run("Cem", DEFAULT_MODE, 40_000, 0), 3);
+        let query = LeaderboardQuery::default();
+        assert_eq!(file.leaderboard(&query, 10, 0).runs[0].run.player, "Cem");
+
+        find_run(&mut file, too_fast.id).unwrap().suspect = false;
+        assert_eq!(file.leaderboard(&query, 10, 0).runs[0].run.player, "Ann");
+    }
+
+    #[test]
+    fn ranks_reverse_runs_by_most_points_then_time() {
+        let mut file = ScoreFile::new();
+        let reverse_run = |player, elapsed_ms, points| NewRun {
+            points: Some(points),
+            hits: 5,
+            ..new_run(player, REVERSE_MODE, elapsed_ms, 0)
+        };
+        file.insert(reverse_run("Ann", 60_000, 5), 1);
+        file.insert(reverse_run("Ben", 40_000, 25), 2);
+        file.insert(reverse_run("Cem", 50_000, 5), 3);
+
+        let reverse = LeaderboardQuery {
+            mode: Some(REVERSE_MODE),
+            ..LeaderboardQuery::default()
+        };
+        let players: Vec<_> = file
+            .leaderboard(&reverse, usize::MAX, 0)
+            .runs
+            .into_iter()
+            .map(|r| (r.run.player, r.final_score))
+            .collect();
+        assert_eq!(
+            players,
+            [
+                ("Ben".to_string(), 25),
+                ("Cem".to_string(), 5),
+                ("Ann".to_string(), 5)
+            ]
+        );
+        assert!(!file.runs[0].is_timed());
+        assert_eq!(file.runs[0].final_score_text(), "5");
+    }
+
+    #[test]
+    fn lists_relays_once() {
+        let mut file = ScoreFile::new();
+        let leg = |leg, player: &str| RelayLeg {
+            leg,
+            player: player.to_string(),
+            elapsed_ms: 10_000,
+            split_ms: leg as u64 * 10_000,
+            hits: 0,
+            penalty_ms: 0,
+        };
+        let relay = NewRun {
+            legs: vec![leg(1, "Ann"), leg(2, "ben"), leg(3, "Ann ")],
+            ..new_run("Ann", DEFAULT_MODE, 30_000, 0)
+        };
+        assert_eq!(run_players(&relay), ["Ann", "ben"]);
+        let first = file.insert(relay.clone(), 1);
+        let second = file.insert(
+            NewRun {
+                player: "ben".to_string(),
+                relay_id: Some(first.id),
+                ..relay
+            },
+            1,
+        );
+        assert_eq!(first.relay_id, Some(first.id));
+        assert_eq!(second.relay_id, Some(first.id));
+        file.insert(new_run("Cem", DEFAULT_MODE, 40_000, 0), 2);
+
+        let ranked = file.leaderboard(&LeaderboardQuery::default(), 10, 0).runs;
+        let players: Vec<_> = ranked.iter().map(|r| r.run.player.as_str()).collect();
+        assert_eq!(players, ["Ann", "Cem"]);
+        assert_eq!(file.player_runs("ben").len(), 1);
+    }
+
+    #[test]
+    fn ranks_by_the_handicap_saved_with_the_run() {
+        let mut file = ScoreFile::new();
+        let kids = Handicap {
+            time_multiplier: 0.5,
+            bonus_seconds: 0.0,
+        };
+        file.insert(new_run("Adult", DEFAULT_MODE, 30_000, 0), 1);
+        file.insert(
+            NewRun {
+                handicap: Some(kids),
+                ..new_run("Kid", DEFAULT_MODE, 50_000, 0)
+            },
+            2,
+        );
+        file.insert(
+            NewRun {
+                handicap: Some(kids),
+                ..new_run("Kid", STEALTH_MODE, 1_000, 0)
+            },
+            3,
+        );
+
+        let mut query = LeaderboardQuery {
+            mode: Some(DEFAULT_MODE),
+            ..LeaderboardQuery::default()
+        };
+        let raw = file.leaderboard(&query, 10, 0);
+        assert_eq!(raw.runs[0].run.player, "Adult");
+        query.adjusted = true;
+        let adjusted = file.leaderboard(&query, 10, 0);
+        assert_eq!(adjusted.runs[0].run.player, "Kid");
+        assert_eq!(adjusted.runs[0].adjusted_score, 25_000);
+        assert_eq!(adjusted.runs[0].final_score, 50_000);
+        assert_eq!(file.runs[2].adjusted(), file.runs[2].final_score());
+    }
+
+    #[test]
+    fn clean_runs_build_a_streak_bonus() {
+        let mut file = ScoreFile::new();
+        let bonus = StreakBonus {
+            percent_per_level: 10.0,
+            max_level: 2,
+        };
+        let mut save = |hits: u32, timestamp_ms: u64| {
+            file.insert(
+                NewRun {
+                    hits,
+                    player_id: Some(1),
+                    scoring: Some(ScoringConfig {
+                        streak_bonus: bonus,
+                        ..ScoringConfig::default()
+                    }),
+                    ..new_run("Ann", DEFAULT_MODE, 10_000, 0)
+                },
+                timestamp_ms,
+            )
+        };
+        let runs: Vec<Run> = [0, 0, 0, 2, 0]
+            .iter()
+            .zip(1..)
+            .map(|(&hits, timestamp_ms)| save(hits, timestamp_ms))
+            .collect();
+        let streaks: Vec<u32> = runs.iter().map(|run| run.streak).collect();
+        assert_eq!(streaks, [1, 2, 3, 0, 1]);
+        let scores: Vec<u64> = runs.iter().map(Run::final_score).collect();
+        assert_eq!(scores, [9_000, 8_100, 8_100, 10_000, 9_000]);
+    }
+
+    #[test]
+    fn aggregates_team_runs() {
+        let mut file = ScoreFile::new();
+        file.teams = vec![
+            Team {
+                id: 1,
+                name: "Red".to_string(),
+                members: vec!["Ann".to_string(), "Ben".to_string()],
+            },
+            Team {
+                id: 2,
+                name: "Blue".to_string(),
+                members: vec!["Cem".to_string()],
+            },
+            Team {
+                id: 3,
+                name: "Green".to_string(),
+                members: Vec::new(),
+            },
+        ];
+        for (player, elapsed_ms) in [("Ann", 30_000), ("ann", 20_000), ("Ben", 40_000)] {
+            let team_id = file.team_for(player, None).unwrap();
+            file.insert(
+                NewRun {
+                    team_id,
+                    ..new_run(player, DEFAULT_MODE, elapsed_ms, 0)
+                },
+                0,
+            );
+        }
+        file.insert(
+            NewRun {
+                team_id: file.team_for("Dana", Some(2)).unwrap(),
+                ..new_run("Dana", DEFAULT_MODE, 50_000, 0)
+            },
+            0,
+        );
+        assert!(file.team_for("Dana", Some(9)).is_err());
+
+        let best_sum = teams::standings(&file.teams, &file.runs, None, Default::default());
+        assert_eq!(best_sum[0].team.name, "Blue");
+        assert_eq!(best_sum[1].score, Some(60_000));
+        assert_eq!(best_sum[1].players, 2);
+        assert_eq!((best_sum[2].rank, best_sum[2].score), (3, None));
+
+        let average = teams::standings(
+            &file.teams,
+            &file.runs,
+            Some(DEFAULT_MODE),
+            teams::Aggregation::Average,
+        );
+        assert_eq!(average[0].team.name, "Red");
+        assert_eq!(average[0].score, Some(30_000));
+    }
+
+    #[test]
+    fn saves_runs_to_player_profiles() {
+        let mut file = ScoreFile::new();
+        let anna = players::resolve(&mut file, "Anna", false, 24).unwrap();
+        assert_eq!(
+            players::resolve(&mut file, " Anna ", false, 24).unwrap().id,
+            anna.id
+        );
+        assert!(players::resolve(&mut file, "Ana", false, 24).is_err());
+        assert_ne!(
+            players::resolve(&mut file, "Ana", true, 24).unwrap().id,
+            anna.id
+        );
+        assert!(players::resolve(&mut file, "  ", true, 24).is_err());
+
+        file.insert(new_run("Old Name", DEFAULT_MODE, 1, 0), 0);
+        file.migrate();
+        assert_eq!(file.players.len(), 3);
+        assert!(file.runs[0].player_id.is_some());
+    }
+
+    #[test]
+    fn exports_escaped_csv_rows() {
+        let mut file = ScoreFile::new();
+        file.insert(
+            new_run("Ann \"the Ace\", Jr.", DEFAULT_MODE, 41_200, 5_000),
+            0,
+        );
+        file.insert(new_run("Ben", DEFAULT_MODE, 50_000, 0), 86_400_000);
+        let mut out = Vec::new();
+        write_leaderboard_csv(&mut out, &file, None).unwrap();
+        let csv = String::from_utf8(out).unwrap();
+        let rows: Vec<&str> = csv.lines().collect();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(
+            rows[1],
+            "1,\"Ann \"\"the Ace\"\", Jr.\",timeAttack,41.200,0,5.000,46.200,1970-01-01 00:00:00,,"
+        );
+        assert!(rows[2].ends_with(",1970-01-02 00:00:00,,"));
+    }
+
+    #[test]
+    fn imports_config_store_highscores() {
+        let mut file = ScoreFile::new();
+        let highscores = serde_json::json!([
+            {
+                "id": "1710000000000",
+                "name": "Ann",
+                "time": 41_200,
+                "date": "2024-03-09T16:00:00.000Z",
+                "touchedLasers": 2,
+                "maxAllowedTouches": 3,
+                "reactivationEnabled": false,
+                "reactivationTimeSeconds": 5
+            },
+            { "name": "broken" }
+        ]);
+        assert_eq!(file.import_legacy(highscores.as_array().unwrap()), 1);
+        let run = &file.runs[0];
+        assert_eq!(run.timestamp_ms, 1_710_000_000_000);
+        assert_eq!((run.elapsed_ms, run.hits), (41_200, 2));
+        assert_eq!(run.mode, DEFAULT_MODE);
+        assert_eq!(run.config["maxAllowedTouches"], 3);
+        assert_eq!(file.players[0].name, "Ann");
+        assert_eq!(run.player_id, Some(file.players[0].id));
+        assert!(file.legacy_imported);
+    }
+
+    #[test]
+    fn counts_tags() {
+        let mut file = ScoreFile::new();
+        let ids: Vec<u64> = (0..3)
+            .map(|i| file.insert(new_run("Ann", "classic", 40_000 + i, 0), 0).id)
+            .collect();
+        let tag = check_tag("  Sensor 4  Flaky ").unwrap();
+        assert_eq!(tag, "sensor 4 flaky");
+        assert!(check_tag(" \t").is_err());
+        for id in [ids[0], ids[2]] {
+            find_run(&mut file, id).unwrap().tags.push(tag.clone());
+        }
+        find_run(&mut file, ids[1])
+            .unwrap()
+            .tags
+            .push("birthday".to_string());
+        let counts: Vec<_> = tags(&file).into_iter().map(|t| (t.tag, t.count)).collect();
+        assert_eq!(
+            counts,
+            [
+                ("sensor 4 flaky".to_string(), 2),
+                ("birthday".to_string(), 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn exported_runs_import_with_notes_and_tags() {
+        let mut file = ScoreFile::new();
+        let id = file.insert(new_run("Ann", "classic", 40_000, 0), 1_000).id;
+        let run = find_run(&mut file, id).unwrap();
+        run.note = Some("sensor 4 flaky, reset after".to_string());
+        run.tags = vec!["birthday".to_string(), "school".to_string()];
+
+        let mut csv = Vec::new();
+        write_leaderboard_csv(&mut csv, &file, None).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert!(csv.ends_with(",\"sensor 4 flaky, reset after\",birthday; school\n"));
+
+        let mut json = Vec::new();
+        write_runs_json(&mut json, &file).unwrap();
+        let runs: Vec<Run> = serde_json::from_slice(&json).unwrap();
+        let mut other = ScoreFile::new();
+        other.insert(new_run("Ben", "classic", 50_000, 0), 500);
+        assert_eq!(other.import_runs(runs.clone()), 1);
+        assert_eq!(other.import_runs(runs), 0);
+        let imported = other.runs.last().unwrap();
+        assert_ne!(imported.id, other.runs[0].id);
+        assert_eq!(
+            imported.note.as_deref(),
+            Some("sensor 4 flaky, reset after")
+        );
+        assert_eq!(imported.tags, ["birthday", "school"]);
+        assert!(imported.player_id.is_some());
+    }
+
+    fn book() -> ScoreBook {
+        let connection = Connection::open_in_memory().unwrap();
+        ScoreBook::load(connection, Path::new("missing.json")).unwrap()
+    }
+
+    #[test]
+    fn stores_runs_in_the_database() {
+        let mut book = book();
+        let id = book
+            .update(|file| {
+                Ok(file
+                    .insert(new_run("Ann", DEFAULT_MODE, 40_000, 0), 1_000)
+                    .id)
+            })
+            .unwrap();
+        book.update(|file| {
+            file.insert(new_run("Ben", DEFAULT_MODE, 30_000, 0), 2_000);
+            Ok(())
+        })
+        .unwrap();
+        // A save writes only the runs the change touched.
+        let hits = |book: &ScoreBook| -> i64 {
+            book.db()
+                .query_row("SELECT hits FROM runs WHERE id = ?1", [id as i64], |row| {
+                    row.get(0)
+                })
+                .unwrap()
+        };
+        book.db()
+            .execute("UPDATE runs SET hits = 99 WHERE id = ?1", [id as i64])
+            .unwrap();
+        book.update(|file| {
+            find_run(file, id + 1)?.note = Some("fast".to_string());
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(hits(&book), 99);
+
+        let failed: Result<(), String> = book.update(|file| {
+            find_run(file, id)?.note = Some("lost".to_string());
+            file.delete(id + 1);
+            Err("rejected".to_string())
+        });
+        assert!(failed.is_err());
+        assert_eq!(book.file().runs.len(), 2);
+        assert_eq!(book.file().runs[0].note, None);
+
+        book.update(|file| {
+            file.delete(id + 1);
+            find_run(file, id)?.tags.push("kept".to_string());
+            Ok(())
+        })
+        .unwrap();
+        let ScoreBook { db, .. } = book;
+        let book = ScoreBook::load(db.connection, Path::new("missing.json")).unwrap();
+        assert_eq!(book.file().runs.len(), 1);
+        assert_eq!(book.file().runs[0].tags, ["kept"]);
+        assert_eq!(book.file().next_id, id + 2);
+        let count: i64 = book
+            .db()
+            .query_row("SELECT COUNT(*) FROM runs", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn ranks_a_time_range_from_the_database() {
+        let mut book = book();
+        book.update(|file| {
+            file.insert(new_run("Ann", DEFAULT_MODE, 40_000, 0), 1_000);
+            file.insert(new_run("Ben", DEFAULT_MODE, 50_000, 0), 2_000);
+            file.insert(new_run("Cem", DEFAULT_MODE, 45_000, 0), 3_000);
+            Ok(())
+        })
+        .unwrap();
+        let query = LeaderboardQuery {
+            range: TimeRange {
+                from_ms: Some(2_000),
+                to_ms: None,
+            },
+            ..LeaderboardQuery::default()
+        };
+        let page = book.leaderboard(&query, 10, 0).unwrap();
+        let players: Vec<_> = page.runs.iter().map(|r| r.run.player.as_str()).collect();
+        assert_eq!(players, ["Cem", "Ben"]);
+        assert_eq!(page.total, book.file().leaderboard(&query, 10, 0).total);
+    }
+
+    #[test]
+    fn moves_the_scores_file_into_the_database() {
+        let path = std::env::temp_dir().join(format!("lazer-mazer-{}.json", std::process::id()));
+        let mut file = ScoreFile::new();
+        file.insert(new_run("Ann", DEFAULT_MODE, 40_000, 0), 1_000);
+        std::fs::write(&path, serde_json::to_vec(&file).unwrap()).unwrap();
+
+        let book = ScoreBook::load(Connection::open_in_memory().unwrap(), &path).unwrap();
+        assert_eq!(book.file().runs[0].player, "Ann");
+        assert!(!path.exists());
+        let imported = path.with_extension("json.imported");
+        assert!(imported.exists());
+        let _ = std::fs::remove_file(imported);
+    }
+}
@@ -0,0 +1,223 @@
+// Scoring formulas of the timed modes. A run's final score is its time
+// times `time_weight`, plus the penalty table's penalties and the hit
+// penalties, minus the checkpoint and streak bonuses. Every run keeps the
+// config it was scored with, so old scores stay explainable.
+
+use std::collections::BTreeMap;
+use tauri_plugin_store::StoreExt;
+
+use crate::scores::{StreakBonus, REVERSE_MODE, STEALTH_MODE};
+use crate::STORE_FILE;
+
+// Config store key of the scoring configs, by mode.
+const SCORING_KEY: &str = "scoring.modes";
+
+#[derive(Clone, Copy, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ScoringConfig {
+    // Factor on the raw time.
+    pub time_weight: f64,
+    // Seconds added per hit, on top of the penalty table.
+    pub hit_penalty_seconds: f64,
+    // Share of the raw time added per hit, in percent.
+    pub hit_penalty_percent: f64,
+    // Seconds taken off per checkpoint reached.
+    pub checkpoint_bonus_seconds: f64,
+    pub streak_bonus: StreakBonus,
+}
+
+impl Default for ScoringConfig {
+    fn default() -> Self {
+        Self {
+            time_weight: 1.0,
+            hit_penalty_seconds: 0.0,
+            hit_penalty_percent: 0.0,
+            checkpoint_bonus_seconds: 0.0,
+            streak_bonus: StreakBonus::default(),
+        }
+    }
+}
+
+impl ScoringConfig {
+    fn check(&self) -> Result<(), String> {
+        if !(self.time_weight > 0.0 && self.time_weight <= 10.0) {
+            return Err("the time weight must be above 0 and at most 10".to_string());
+        }
+        if !(0.0..=3600.0).contains(&self.hit_penalty_seconds) {
+            return Err("the hit penalty must be between 0 and 3600 seconds".to_string());
+        }
+        if !(0.0..=100.0).contains(&self.hit_penalty_percent) {
+            return Err("the hit penalty must be between 0% and 100%".to_string());
+        }
+        if !(0.0..=3600.0).contains(&self.checkpoint_bonus_seconds) {
+            return Err("the checkpoint bonus must be between 0 and 3600 seconds".to_string());
+        }
+        self.streak_bonus.check()
+    }
+
+    // The config of a mode; modes without one of their own score plain time
+    // with the general streak bonus.
+    pub fn load(app_handle: &tauri::AppHandle, mode: &str) -> Self {
+        configs(app_handle).remove(mode).unwrap_or_else(|| Self {
+            streak_bonus: StreakBonus::load(app_handle),
+            ..Self::default()
+        })
+    }
+}
+
+// What a score is computed from.
+#[derive(Clone, Copy, Debug, Default, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ScoreInput {
+    pub elapsed_ms: u64,
+    pub hits: u32,
+    // Penalties of the penalty table.
+    pub penalty_ms: u64,
+    pub checkpoints: usize,
+    // Clean runs in a row, ending with this one.
+    pub streak: u32,
+}
+
+// A computed score and its parts, in milliseconds.
+#[derive(Clone, Copy, Debug, PartialEq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Score {
+    pub time_ms: u64,
+    pub penalty_ms: u64,
+    pub checkpoint_bonus_ms: u64,
+    pub streak_bonus_ms: u64,
+    pub final_ms: u64,
+}
+
+pub fn compute_score(input: &ScoreInput, config: &ScoringConfig) -> Score {
+    let elapsed = input.elapsed_ms as f64;
+    let per_hit =
+        config.hit_penalty_seconds * 1000.0 + elapsed * config.hit_penalty_percent / 100.0;
+    let time_ms = (elapsed * config.time_weight).round() as u64;
+    let penalty_ms = input.penalty_ms + (f64::from(input.hits) * per_hit).round() as u64;
+    let checkpoint_bonus_ms =
+        (input.checkpoints as f64 * config.checkpoint_bonus_seconds * 1000.0).round() as u64;
+    let before_streak = (time_ms + penalty_ms).saturating_sub(checkpoint_bonus_ms);
+    let streak_bonus_ms = config.streak_bonus.bonus_ms(before_streak, input.streak);
+    Score {
+        time_ms,
+        penalty_ms,
+        checkpoint_bonus_ms,
+        streak_bonus_ms,
+        final_ms: before_streak - streak_bonus_ms,
+    }
+}
+
+fn configs(app_handle: &tauri::AppHandle) -> BTreeMap<String, ScoringConfig> {
+    app_handle
+        .store(STORE_FILE)
+        .ok()
+        .and_then(|store| store.get(SCORING_KEY))
+        .and_then(|value| serde_json::from_value(value).ok())
+        .unwrap_or_default()
+}
+
+// Command to fetch the scoring config of a mode.
+#[tauri::command]
+pub fn get_scoring_config(mode: String, app_handle: tauri::AppHandle) -> ScoringConfig {
+    ScoringConfig::load(&app_handle, &mode)
+}
+
+// Command to set the scoring config of a mode, or with None go back to the
+// defaults; applies to runs saved from now on.
+#[tauri::command]
+pub fn set_scoring_config(
+    mode: String,
+    config: Option<ScoringConfig>,
+    app_handle: tauri::AppHandle,
+) -> Result<(), String> {
+    if mode == STEALTH_MODE || mode == REVERSE_MODE {
+        return Err(format!("{} runs aren't scored by time", mode));
+    }
+    let mut configs = configs(&app_handle);
+    match config {
+        Some(config) => {
+            config.check()?;
+            configs.insert(mode, config);
+        }
+        None => {
+            configs.remove(&mode);
+        }
+    }
+    let store = app_handle.store(STORE_FILE).map_err(|e| e.to_string())?;
+    store.set(
+        SCORING_KEY,
+        serde_json::to_value(configs).map_err(|e| e.to_string())?,
+    );
+    Ok(())
+}
+
+// Command to score a hypothetical run, with the saved config of `mode` or
+// with `config` while it is being tuned.
+#[tauri::command]
+pub fn preview_score(
+    mode: String,
+    hypothetical: ScoreInput,
+    config: Option<ScoringConfig>,
+    app_handle: tauri::AppHandle,
+) -> Result<Score, String> {
+    let config = match config {
+        Some(config) => {
+            config.check()?;
+            config
+        }
+        None => ScoringConfig::load(&app_handle, &mode),
+    };
+    Ok(compute_score(&hypothetical, &config))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults_score_time_and_penalties() {
+        let input = ScoreInput {
+            elapsed_ms: 60_000,
+            hits: 3,
+            penalty_ms: 15_000,
+            checkpoints: 2,
+            streak: 0,
+        };
+        assert_eq!(
+            compute_score(&input, &ScoringConfig::default()).final_ms,
+            75_000
+        );
+    }
+
+    #[test]
+    fn applies_the_weights() {
+        let config = ScoringConfig {
+            time_weight: 0.5,
+            hit_penalty_seconds: 2.0,
+            hit_penalty_percent: 10.0,
+            checkpoint_bonus_seconds: 1.5,
+            streak_bonus: StreakBonus {
+                percent_per_level: 10.0,
+                max_level: 1,
+            },
+        };
+        let input = ScoreInput {
+            elapsed_ms: 60_000,
+            hits: 3,
+            penalty_ms: 1_000,
+            checkpoints: 2,
+            streak: 4,
+        };
+        assert_eq!(
+            compute_score(&input, &config),
+            Score {
+                time_ms: 30_000,
+                penalty_ms: 1_000 + 3 * 8_000,
+                checkpoint_bonus_ms: 3_000,
+                streak_bonus_ms: 5_200,
+                final_ms: 46_800,
+            }
+        );
+    }
+}
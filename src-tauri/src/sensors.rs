@@ -0,0 +1,722 @@
+// Per-sensor settings: the names lasers are labelled with in the maze, the
+// range of plausible values, the scaling applied to them, the thresholds below
+// which a beam counts as broken, how long a beam state has to hold, and
+// optional smoothing.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use tauri_plugin_store::StoreExt;
+
+use crate::game::GameManager;
+use crate::{SensorData, SerialManager, STORE_FILE};
+
+// Store key of the sensor names.
+const NAMES_KEY: &str = "arduinoSettings.sensorNames";
+// Store key of the beam-break thresholds.
+const THRESHOLDS_KEY: &str = "arduinoSettings.sensorThresholds";
+// Store key of the range of valid sensor values.
+const VALID_RANGE_KEY: &str = "arduinoSettings.validRange";
+// Store key of the value scale factor and offset.
+const VALUE_SCALING_KEY: &str = "arduinoSettings.valueScaling";
+// Store key of the sensors left out of hit detection.
+const DISABLED_KEY: &str = "arduinoSettings.disabledSensors";
+// Store key of the beam debounce times.
+const BEAM_DEBOUNCE_KEY: &str = "arduinoSettings.beamDebounce";
+// Default time after a break during which the beam can't be restored.
+const DEFAULT_BEAM_HOLD_MS: u64 = 150;
+// Default time a beam has to stay above its threshold to count as restored.
+const DEFAULT_BEAM_CLEAR_MS: u64 = 100;
+
+// Reads a list saved under `key` in the config store.
+fn load_list<T: serde::de::DeserializeOwned>(
+    app_handle: &tauri::AppHandle,
+    key: &str,
+) -> Option<Vec<T>> {
+    let value = app_handle.store(STORE_FILE).ok()?.get(key)?;
+    serde_json::from_value(value).ok()
+}
+
+// Friendly names of the sensors by index, shared with the reading threads.
+pub struct SensorNames(RwLock<Vec<String>>);
+
+impl SensorNames {
+    pub fn new() -> Self {
+        Self(RwLock::new(Vec::new()))
+    }
+
+    // Loads the names saved by set_sensor_names.
+    pub fn load(&self, app_handle: &tauri::AppHandle) {
+        let names = load_list(app_handle, NAMES_KEY);
+        if let (Some(names), Ok(mut current)) = (names, self.0.write()) {
+            *current = names;
+        }
+    }
+
+    // Name of the sensor at `index`; unnamed sensors are "Sensor N", counting from 1.
+    pub fn resolve(&self, index: usize) -> String {
+        self.0
+            .read()
+            .ok()
+            .and_then(|names| names.get(index).cloned())
+            .filter(|name| !name.trim().is_empty())
+            .unwrap_or_else(|| format!("Sensor {}", index + 1))
+    }
+}
+
+// Per-sensor thresholds, shared with the reading threads. A sensor without a
+// threshold (or with 0) is never reported as broken.
+pub struct SensorThresholds(RwLock<Vec<u32>>);
+
+impl SensorThresholds {
+    pub fn new() -> Self {
+        Self(RwLock::new(Vec::new()))
+    }
+
+    // Loads the thresholds saved by set_sensor_thresholds.
+    pub fn load(&self, app_handle: &tauri::AppHandle) {
+        let thresholds = load_list(app_handle, THRESHOLDS_KEY);
+        if let (Some(thresholds), Ok(mut current)) = (thresholds, self.0.write()) {
+            *current = thresholds;
+        }
+    }
+
+    // Replaces the thresholds and saves them in the store.
+    pub fn save(&self, app_handle: &tauri::AppHandle, thresholds: Vec<u32>) -> Result<(), String> {
+        let store = app_handle.store(STORE_FILE).map_err(|e| e.to_string())?;
+        store.set(THRESHOLDS_KEY, thresholds.clone());
+        *self.0.write().map_err(|e| e.to_string())? = thresholds;
+        Ok(())
+    }
+
+    pub fn get(&self, index: usize) -> Option<u32> {
+        let thresholds = self.0.read().ok()?;
+        thresholds
+            .get(index)
+            .copied()
+            .filter(|&threshold| threshold > 0)
+    }
+}
+
+// Sensors left out of hit detection, e.g. a dead laser whose sensor floats,
+// shared with the reading threads. Their values are still reported.
+pub struct DisabledSensors(RwLock<Vec<usize>>);
+
+impl DisabledSensors {
+    pub fn new() -> Self {
+        Self(RwLock::new(Vec::new()))
+    }
+
+    // Loads the sensors saved by set_disabled_sensors.
+    pub fn load(&self, app_handle: &tauri::AppHandle) {
+        let disabled = load_list(app_handle, DISABLED_KEY);
+        if let (Some(disabled), Ok(mut current)) = (disabled, self.0.write()) {
+            *current = disabled;
+        }
+    }
+
+    // Replaces the disabled sensors and saves them in the store.
+    pub fn save(
+        &self,
+        app_handle: &tauri::AppHandle,
+        mut disabled: Vec<usize>,
+    ) -> Result<(), String> {
+        disabled.sort_unstable();
+        disabled.dedup();
+        let store = app_handle.store(STORE_FILE).map_err(|e| e.to_string())?;
+        store.set(DISABLED_KEY, disabled.clone());
+        *self.0.write().map_err(|e| e.to_string())? = disabled;
+        Ok(())
+    }
+
+    pub fn contains(&self, index: usize) -> bool {
+        self.0
+            .read()
+            .is_ok_and(|disabled| disabled.contains(&index))
+    }
+
+    pub fn list(&self) -> Vec<usize> {
+        self.0
+            .read()
+            .map(|disabled| disabled.clone())
+            .unwrap_or_default()
+    }
+}
+
+// Range of values the sensors can legitimately report, shared with the
+// reading threads. Anything outside points at a wiring fault.
+pub struct ValidRange {
+    min: AtomicU32,
+    max: AtomicU32,
+}
+
+#[derive(Clone, Copy, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StoredRange {
+    min_valid: u32,
+    max_valid: u32,
+}
+
+impl ValidRange {
+    pub fn new() -> Self {
+        Self {
+            min: AtomicU32::new(0),
+            max: AtomicU32::new(u32::MAX),
+        }
+    }
+
+    // Loads the range saved by set_valid_range.
+    pub fn load(&self, app_handle: &tauri::AppHandle) {
+        let stored: Option<StoredRange> = app_handle
+            .store(STORE_FILE)
+            .ok()
+            .and_then(|store| store.get(VALID_RANGE_KEY))
+            .and_then(|value| serde_json::from_value(value).ok());
+        if let Some(stored) = stored {
+            self.min.store(stored.min_valid, Ordering::Relaxed);
+            self.max.store(stored.max_valid, Ordering::Relaxed);
+        }
+    }
+
+    pub fn bounds(&self) -> (u32, u32) {
+        (
+            self.min.load(Ordering::Relaxed),
+            self.max.load(Ordering::Relaxed),
+        )
+    }
+}
+
+// Scale factor and offset applied to received values before they are compared
+// with the thresholds, so readings of wide external ADCs keep calibration
+// values in a sane range.
+#[derive(Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Scaling {
+    pub scale: f64,
+    pub offset: f64,
+}
+
+impl Scaling {
+    // Leaves values untouched; the default, so 10-bit firmware needs no setup.
+    const IDENTITY: Self = Self {
+        scale: 1.0,
+        offset: 0.0,
+    };
+}
+
+// The scaling shared with the reading threads.
+pub struct ValueScaling(RwLock<Scaling>);
+
+impl ValueScaling {
+    pub fn new() -> Self {
+        Self(RwLock::new(Scaling::IDENTITY))
+    }
+
+    // Loads the scaling saved by set_value_scaling.
+    pub fn load(&self, app_handle: &tauri::AppHandle) {
+        let stored: Option<Scaling> = app_handle
+            .store(STORE_FILE)
+            .ok()
+            .and_then(|store| store.get(VALUE_SCALING_KEY))
+            .and_then(|value| serde_json::from_value(value).ok());
+        if let (Some(stored), Ok(mut current)) = (stored, self.0.write()) {
+            *current = stored;
+        }
+    }
+
+    // Scales the values in place. Results are rounded and clamped to the
+    // range of a value.
+    pub fn apply(&self, values: &mut [u32]) {
+        let Ok(scaling) = self.0.read() else {
+            return;
+        };
+        if *scaling == Scaling::IDENTITY {
+            return;
+        }
+        for value in values.iter_mut() {
+            let scaled = (*value as f64 * scaling.scale + scaling.offset).round();
+            *value = scaled.clamp(0.0, u32::MAX as f64) as u32;
+        }
+    }
+}
+
+// Replaces out-of-range values of one connection with the last good value of
+// the same sensor, or clamps them when there is none yet.
+pub struct RangeGuard {
+    last_good: Vec<Option<u32>>,
+    last_report: Vec<Option<std::time::Instant>>,
+}
+
+impl RangeGuard {
+    pub fn new() -> Self {
+        Self {
+            last_good: Vec::new(),
+            last_report: Vec::new(),
+        }
+    }
+
+    // Fixes the values in place and returns the index and raw value of every
+    // replaced one.
+    pub fn check(&mut self, values: &mut [u32], range: &ValidRange) -> Vec<(usize, u32)> {
+        let (min, max) = range.bounds();
+        self.last_good.resize(values.len(), None);
+        let mut faults = Vec::new();
+        for (i, value) in values.iter_mut().enumerate() {
+            if (min..=max).contains(value) {
+                self.last_good[i] = Some(*value);
+            } else {
+                faults.push((i, *value));
+                *value = self.last_good[i].unwrap_or((*value).clamp(min, max));
+            }
+        }
+        faults
+    }
+
+    // Whether a fault of the sensor at `index` should be reported now, at
+    // most once per `interval`.
+    pub fn due_for_report(
+        &mut self,
+        index: usize,
+        now: std::time::Instant,
+        interval: std::time::Duration,
+    ) -> bool {
+        if self.last_report.len() <= index {
+            self.last_report.resize(index + 1, None);
+        }
+        let due = self.last_report[index].is_none_or(|last| now.duration_since(last) >= interval);
+        if due {
+            self.last_report[index] = Some(now);
+        }
+        due
+    }
+}
+
+// Debounce times of one sensor.
+#[derive(Clone, Copy, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BeamTiming {
+    // Time after a break during which further transitions are ignored.
+    pub hold_ms: u64,
+    // Time the value has to stay above the threshold before laser-restored.
+    pub clear_ms: u64,
+}
+
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BeamDebounceConfig {
+    global: BeamTiming,
+    // Sensors that don't use the global times, by index.
+    overrides: BTreeMap<usize, BeamTiming>,
+}
+
+// Beam debounce times shared with the reading threads.
+pub struct BeamDebounce(RwLock<BeamDebounceConfig>);
+
+impl BeamDebounce {
+    pub fn new() -> Self {
+        Self(RwLock::new(BeamDebounceConfig {
+            global: BeamTiming {
+                hold_ms: DEFAULT_BEAM_HOLD_MS,
+                clear_ms: DEFAULT_BEAM_CLEAR_MS,
+            },
+            overrides: BTreeMap::new(),
+        }))
+    }
+
+    // Loads the times saved by set_beam_debounce and set_sensor_beam_debounce.
+    pub fn load(&self, app_handle: &tauri::AppHandle) {
+        let config = app_handle
+            .store(STORE_FILE)
+            .ok()
+            .and_then(|store| store.get(BEAM_DEBOUNCE_KEY))
+            .and_then(|value| serde_json::from_value(value).ok());
+        if let (Some(config), Ok(mut current)) = (config, self.0.write()) {
+            *current = config;
+        }
+    }
+
+    // Changes the times and saves them in the store.
+    fn update(
+        &self,
+        app_handle: &tauri::AppHandle,
+        change: impl FnOnce(&mut BeamDebounceConfig),
+    ) -> Result<(), String> {
+        let mut config = self.0.write().map_err(|e| e.to_string())?;
+        change(&mut config);
+        let store = app_handle.store(STORE_FILE).map_err(|e| e.to_string())?;
+        store.set(
+            BEAM_DEBOUNCE_KEY,
+            serde_json::to_value(&*config).map_err(|e| e.to_string())?,
+        );
+        Ok(())
+    }
+
+    pub fn get(&self, index: usize) -> BeamTiming {
+        match self.0.read() {
+            Ok(config) => config
+                .overrides
+                .get(&index)
+                .copied()
+                .unwrap_or(config.global),
+            Err(_) => BeamTiming {
+                hold_ms: DEFAULT_BEAM_HOLD_MS,
+                clear_ms: DEFAULT_BEAM_CLEAR_MS,
+            },
+        }
+    }
+}
+
+// A change of a beam between broken and unbroken.
+pub struct BeamTransition {
+    // Index of the sensor in the merged values of all connections.
+    pub sensor: usize,
+    pub broken: bool,
+    pub value: u32,
+}
+
+// Debounced state of one beam.
+#[derive(Clone, Default)]
+struct BeamState {
+    broken: bool,
+    broken_at: Option<std::time::Instant>,
+    // Since when a broken beam's value has been above the threshold.
+    above_since: Option<std::time::Instant>,
+    // Times the value crossed back below the threshold before the beam
+    // counted as restored; high counts point at badly aligned beams.
+    flaps: u64,
+}
+
+// Broken/unbroken state of the sensors of one connection.
+pub struct BeamTracker {
+    beams: Vec<BeamState>,
+    flaps_changed: bool,
+}
+
+impl BeamTracker {
+    pub fn new() -> Self {
+        Self {
+            beams: Vec::new(),
+            flaps_changed: false,
+        }
+    }
+
+    // Compares fresh values with the thresholds and returns the beams that
+    // changed state. `offset` is the index of the first value among all sensors.
+    // A break is reported right away; the restore only once the hold time has
+    // passed and the value stayed above the threshold for the clear time.
+    pub fn update(
+        &mut self,
+        values: &[u32],
+        offset: usize,
+        thresholds: &SensorThresholds,
+        debounce: &BeamDebounce,
+        now: std::time::Instant,
+    ) -> Vec<BeamTransition> {
+        self.beams.resize(values.len(), BeamState::default());
+        let mut transitions = Vec::new();
+        for (i, (&value, beam)) in values.iter().zip(self.beams.iter_mut()).enumerate() {
+            let below = thresholds
+                .get(offset + i)
+                .is_some_and(|threshold| value < threshold);
+            let broken = match (beam.broken, below) {
+                (false, false) => false,
+                (false, true) => {
+                    beam.broken_at = Some(now);
+                    true
+                }
+                (true, true) => {
+                    if beam.above_since.take().is_some() {
+                        beam.flaps += 1;
+                        self.flaps_changed = true;
+                    }
+                    true
+                }
+                (true, false) => {
+                    let timing = debounce.get(offset + i);
+                    let above_since = *beam.above_since.get_or_insert(now);
+                    let held = beam.broken_at.is_none_or(|broken_at| {
+                        now.duration_since(broken_at).as_millis() >= timing.hold_ms as u128
+                    });
+                    let cleared =
+                        now.duration_since(above_since).as_millis() >= timing.clear_ms as u128;
+                    !(held && cleared)
+                }
+            };
+            if broken != beam.broken {
+                beam.broken = broken;
+                beam.above_since = None;
+                transitions.push(BeamTransition {
+                    sensor: offset + i,
+                    broken,
+                    value,
+                });
+            }
+        }
+        transitions
+    }
+
+    // Flap counts by sensor of this connection, if they changed since the
+    // last call.
+    pub fn new_flaps(&mut self) -> Option<Vec<u64>> {
+        if !std::mem::take(&mut self.flaps_changed) {
+            return None;
+        }
+        Some(self.beams.iter().map(|beam| beam.flaps).collect())
+    }
+}
+
+// Smoothing settings shared with the reading threads.
+pub struct SmoothingSettings {
+    // Number of samples averaged per sensor; 0 disables smoothing.
+    pub window: AtomicUsize,
+    // Also emit the raw values next to the smoothed ones, for tuning the window.
+    pub emit_raw: AtomicBool,
+}
+
+impl SmoothingSettings {
+    pub fn new() -> Self {
+        Self {
+            window: AtomicUsize::new(0),
+            emit_raw: AtomicBool::new(false),
+        }
+    }
+}
+
+// Moving average over the last samples of each sensor of one connection.
+pub struct Smoother {
+    window: usize,
+    history: Vec<VecDeque<u32>>,
+}
+
+impl Smoother {
+    pub fn new() -> Self {
+        Self {
+            window: 0,
+            history: Vec::new(),
+        }
+    }
+
+    // Forgets the collected samples, e.g. after a reconnect.
+    pub fn reset(&mut self) {
+        self.history.clear();
+    }
+
+    // Adds the values to the window and returns the averages. The window
+    // starts over when its size or the number of sensors changes.
+    pub fn smooth(&mut self, values: &[u32], window: usize) -> Vec<u32> {
+        if window != self.window || values.len() != self.history.len() {
+            self.window = window;
+            self.history = vec![VecDeque::with_capacity(window); values.len()];
+        }
+        values
+            .iter()
+            .zip(self.history.iter_mut())
+            .map(|(&value, samples)| {
+                if samples.len() == window {
+                    samples.pop_front();
+                }
+                samples.push_back(value);
+                let sum: u64 = samples.iter().map(|&v| v as u64).sum();
+                (sum / samples.len() as u64) as u32
+            })
+            .collect()
+    }
+}
+
+// Command to name the sensors by index. The names are saved in the store.
+#[tauri::command]
+pub fn set_sensor_names(
+    names: Vec<String>,
+    app_handle: tauri::AppHandle,
+    state: tauri::State<Arc<Mutex<SerialManager>>>,
+) -> Result<(), String> {
+    let names: Vec<String> = names.iter().map(|name| name.trim().to_string()).collect();
+    let store = app_handle.store(STORE_FILE).map_err(|e| e.to_string())?;
+    store.set(NAMES_KEY, names.clone());
+
+    let manager = state.lock().map_err(|e| e.to_string())?;
+    *manager
+        .live
+        .sensor_names
+        .0
+        .write()
+        .map_err(|e| e.to_string())? = names;
+    Ok(())
+}
+
+// Command to fetch the resolved name of every configured or reporting sensor.
+#[tauri::command]
+pub fn get_sensor_names(
+    state: tauri::State<Arc<Mutex<SerialManager>>>,
+    sensor_data: tauri::State<Arc<Mutex<SensorData>>>,
+) -> Result<Vec<String>, String> {
+    let sensor_count = sensor_data
+        .lock()
+        .map_err(|e| e.to_string())?
+        .frame
+        .values
+        .len();
+    let manager = state.lock().map_err(|e| e.to_string())?;
+    let names = &manager.live.sensor_names;
+    let count = names
+        .0
+        .read()
+        .map_err(|e| e.to_string())?
+        .len()
+        .max(sensor_count);
+    Ok((0..count).map(|index| names.resolve(index)).collect())
+}
+
+// Command to set the beam-break threshold of every sensor by index. The
+// thresholds are saved in the store and apply immediately.
+#[tauri::command]
+pub fn set_sensor_thresholds(
+    thresholds: Vec<u32>,
+    app_handle: tauri::AppHandle,
+    state: tauri::State<Arc<Mutex<SerialManager>>>,
+) -> Result<(), String> {
+    let manager = state.lock().map_err(|e| e.to_string())?;
+    manager.live.thresholds.save(&app_handle, thresholds)
+}
+
+// Command to leave sensors out of hit detection by index, e.g. one whose
+// laser died. They never report broken beams, also in the game in progress.
+// The list is saved in the store and applies immediately.
+#[tauri::command]
+pub fn set_disabled_sensors(
+    indices: Vec<usize>,
+    app_handle: tauri::AppHandle,
+    state: tauri::State<Arc<Mutex<SerialManager>>>,
+    game: tauri::State<Arc<Mutex<GameManager>>>,
+) -> Result<(), String> {
+    let disabled = {
+        let manager = state.lock().map_err(|e| e.to_string())?;
+        manager.live.disabled_sensors.save(&app_handle, indices)?;
+        manager.live.disabled_sensors.list()
+    };
+    game.lock()
+        .map_err(|e| e.to_string())?
+        .set_disabled_sensors(&disabled);
+    Ok(())
+}
+
+// Command to fetch the sensors left out of hit detection.
+#[tauri::command]
+pub fn get_disabled_sensors(
+    state: tauri::State<Arc<Mutex<SerialManager>>>,
+) -> Result<Vec<usize>, String> {
+    let manager = state.lock().map_err(|e| e.to_string())?;
+    Ok(manager.live.disabled_sensors.list())
+}
+
+// Command to set the range of values the sensors can legitimately report.
+// Values outside are replaced and reported as sensor-out-of-range. The range
+// is saved in the store and applies immediately.
+#[tauri::command]
+pub fn set_valid_range(
+    min_valid: u32,
+    max_valid: u32,
+    app_handle: tauri::AppHandle,
+    state: tauri::State<Arc<Mutex<SerialManager>>>,
+) -> Result<(), String> {
+    if min_valid > max_valid {
+        return Err("min_valid must not be greater than max_valid".to_string());
+    }
+    let store = app_handle.store(STORE_FILE).map_err(|e| e.to_string())?;
+    let stored = StoredRange {
+        min_valid,
+        max_valid,
+    };
+    store.set(
+        VALID_RANGE_KEY,
+        serde_json::to_value(stored).map_err(|e| e.to_string())?,
+    );
+
+    let manager = state.lock().map_err(|e| e.to_string())?;
+    let range = &manager.live.valid_range;
+    range.min.store(min_valid, Ordering::Relaxed);
+    range.max.store(max_valid, Ordering::Relaxed);
+    Ok(())
+}
+
+// Command to set the scale factor and offset applied to every received value
+// before the thresholds are checked. The scaling is saved in the store and
+// applies immediately; a scale of 1 and an offset of 0 turn it off.
+#[tauri::command]
+pub fn set_value_scaling(
+    scale: f64,
+    offset: f64,
+    app_handle: tauri::AppHandle,
+    state: tauri::State<Arc<Mutex<SerialManager>>>,
+) -> Result<(), String> {
+    if !(scale > 0.0 && scale.is_finite() && offset.is_finite()) {
+        return Err("scale must be greater than 0 and offset finite".to_string());
+    }
+    let scaling = Scaling { scale, offset };
+    let store = app_handle.store(STORE_FILE).map_err(|e| e.to_string())?;
+    store.set(
+        VALUE_SCALING_KEY,
+        serde_json::to_value(scaling).map_err(|e| e.to_string())?,
+    );
+
+    let manager = state.lock().map_err(|e| e.to_string())?;
+    *manager.live.scaling.0.write().map_err(|e| e.to_string())? = scaling;
+    Ok(())
+}
+
+// Command to set the beam debounce times used by sensors without an override.
+// The times are saved in the store and apply immediately.
+#[tauri::command]
+pub fn set_beam_debounce(
+    hold_ms: u64,
+    clear_ms: u64,
+    app_handle: tauri::AppHandle,
+    state: tauri::State<Arc<Mutex<SerialManager>>>,
+) -> Result<(), String> {
+    let manager = state.lock().map_err(|e| e.to_string())?;
+    manager.live.beam_debounce.update(&app_handle, |config| {
+        config.global = BeamTiming { hold_ms, clear_ms };
+    })
+}
+
+// Command to override the beam debounce times of one sensor. A time left out
+// is taken from the global times; leaving out both removes the override.
+#[tauri::command]
+pub fn set_sensor_beam_debounce(
+    sensor: usize,
+    hold_ms: Option<u64>,
+    clear_ms: Option<u64>,
+    app_handle: tauri::AppHandle,
+    state: tauri::State<Arc<Mutex<SerialManager>>>,
+) -> Result<(), String> {
+    let manager = state.lock().map_err(|e| e.to_string())?;
+    manager.live.beam_debounce.update(&app_handle, |config| {
+        if hold_ms.is_none() && clear_ms.is_none() {
+            config.overrides.remove(&sensor);
+            return;
+        }
+        let timing = BeamTiming {
+            hold_ms: hold_ms.unwrap_or(config.global.hold_ms),
+            clear_ms: clear_ms.unwrap_or(config.global.clear_ms),
+        };
+        config.overrides.insert(sensor, timing);
+    })
+}
+
+// Command to average every sensor over the last `window` samples before
+// threshold comparison. 0 disables smoothing; `emit_raw` additionally emits
+// the raw values as laser-sensor-raw.
+#[tauri::command]
+pub fn set_smoothing(
+    window: usize,
+    emit_raw: Option<bool>,
+    state: tauri::State<Arc<Mutex<SerialManager>>>,
+) -> Result<(), String> {
+    let manager = state.lock().map_err(|e| e.to_string())?;
+    let smoothing = &manager.live.smoothing;
+    smoothing.window.store(window, Ordering::Relaxed);
+    smoothing
+        .emit_raw
+        .store(emit_raw.unwrap_or(false), Ordering::Relaxed);
+    Ok(())
+}
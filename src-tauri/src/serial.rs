@@ -0,0 +1,257 @@
+// Reading thread of a serial port connection. The thread is supervised: if
+// the reader panics, the port is reopened and reading starts over, up to
+// MAX_THREAD_RESTARTS times.
+
+use std::io::BufReader;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::sync::{Arc, Mutex, RwLock};
+use tauri::Emitter;
+use tauri_plugin_store::StoreExt;
+
+use crate::pipeline::LineHandler;
+use crate::ports::{open_port, receives_valid_data, ConnectionProfile, PortSettings};
+use crate::protocol::{BinaryDecoder, LineSyntax, Protocol};
+use crate::reader::{mark_disconnected, read_stream, reconnect, ReadExit, READ_BUFFER_SIZE};
+use crate::{SerialStats, SharedWriter, STORE_FILE};
+
+// Number of times a crashed reader is restarted before giving up.
+const MAX_THREAD_RESTARTS: u32 = 3;
+// Delay before a crashed reader is restarted.
+const RESTART_DELAY_MS: u64 = 1000;
+
+// Payload for the baud-switched event.
+#[derive(Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BaudSwitch {
+    connection_id: String,
+    port: String,
+    baud_rate: u32,
+}
+
+// Payload for the serial-thread-crashed event.
+#[derive(Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ThreadCrash {
+    connection_id: String,
+    port: String,
+    message: String,
+    // Crashes of this connection so far.
+    crashes: u32,
+    will_restart: bool,
+}
+
+// Everything the reading thread of a serial connection works with.
+pub struct SerialReader {
+    pub port: String,
+    pub settings: PortSettings,
+    pub protocol: Protocol,
+    pub profile: ConnectionProfile,
+    pub max_reconnect_attempts: u32,
+    // Baud rates tried in turn when the configured one yields no valid data.
+    pub fallback_bauds: Vec<u32>,
+    pub handler: LineHandler,
+    pub stats: Arc<Mutex<SerialStats>>,
+    pub writer: SharedWriter,
+    pub syntax: Arc<RwLock<LineSyntax>>,
+    pub stop_rx: Receiver<()>,
+}
+
+impl SerialReader {
+    // Reads from the port until stopped, restarting the reader after a panic.
+    // A clean return (stop signal, port gone, reconnecting given up) ends the
+    // thread without a restart.
+    pub fn supervise(mut self, serial_port: Box<dyn serialport::SerialPort>) {
+        let mut serial_port = Some(serial_port);
+        let mut crashes = 0;
+        while let Some(current_port) = serial_port.take() {
+            let Err(panic) = catch_unwind(AssertUnwindSafe(|| self.run(current_port))) else {
+                break;
+            };
+            crashes += 1;
+            let will_restart = crashes <= MAX_THREAD_RESTARTS;
+            if let Ok(mut stats) = self.stats.lock() {
+                stats.thread_crashes += 1;
+            }
+            let _ = self.handler.app_handle().emit(
+                "serial-thread-crashed",
+                ThreadCrash {
+                    connection_id: self.handler.connection_id().to_string(),
+                    port: self.port.clone(),
+                    message: panic_message(panic.as_ref()),
+                    crashes,
+                    will_restart,
+                },
+            );
+            if !will_restart {
+                mark_disconnected(&self.handler, &self.port);
+                break;
+            }
+
+            // Wait before restarting, but leave right away on a stop signal.
+            let delay = std::time::Duration::from_millis(RESTART_DELAY_MS);
+            if !matches!(
+                self.stop_rx.recv_timeout(delay),
+                Err(RecvTimeoutError::Timeout)
+            ) {
+                break;
+            }
+            // The old port handle went down with the reader; close the
+            // writer's one too before reopening.
+            self.set_writer(None);
+            match open_port(&self.port, &self.settings) {
+                Ok(reopened) => {
+                    self.set_writer(Some(reopened.as_ref()));
+                    self.handler.reset();
+                    serial_port = Some(reopened);
+                }
+                Err(e) => {
+                    let _ = self.handler.app_handle().emit(
+                        "serial-error",
+                        format!("failed to reopen {} after a crash: {}", self.port, e),
+                    );
+                    mark_disconnected(&self.handler, &self.port);
+                }
+            }
+        }
+    }
+
+    // Points the writer thread at a port, or at nothing.
+    fn set_writer(&self, serial_port: Option<&dyn serialport::SerialPort>) {
+        if let Ok(mut guard) = self.writer.lock() {
+            *guard = serial_port.and_then(|serial_port| serial_port.try_clone().ok());
+        }
+    }
+
+    // Finds a working baud rate if fallbacks are configured, then reads until
+    // a stop signal arrives or the port is lost for good.
+    fn run(&mut self, mut serial_port: Box<dyn serialport::SerialPort>) {
+        if !self.fallback_bauds.is_empty() {
+            // Writes wait until a working rate is found.
+            self.set_writer(None);
+            let Some(working_port) = self.find_baud_rate(serial_port) else {
+                return;
+            };
+            serial_port = working_port;
+            self.set_writer(Some(serial_port.as_ref()));
+            // A restarted reader goes straight to the rate that worked.
+            self.fallback_bauds.clear();
+        }
+
+        let mut reader = BufReader::with_capacity(READ_BUFFER_SIZE, serial_port);
+        let mut decoder = BinaryDecoder::new();
+        let mut reconnected_port = None;
+
+        loop {
+            match read_stream(
+                &mut reader,
+                self.protocol,
+                &mut decoder,
+                &mut self.handler,
+                &self.stats,
+                &self.stop_rx,
+                self.profile.max_read_errors(),
+                reconnected_port,
+            ) {
+                ReadExit::Stopped => break,
+                ReadExit::Gone => {
+                    mark_disconnected(&self.handler, &self.port);
+                    break;
+                }
+                ReadExit::Lost => {}
+            }
+            let reopened = reconnect(
+                &self.handler,
+                &self.port,
+                self.max_reconnect_attempts,
+                self.profile.reconnect_interval_ms(),
+                &self.stop_rx,
+                || open_port(&self.port, &self.settings).ok(),
+            );
+            let Some(new_port) = reopened else {
+                break;
+            };
+            // Point the writer thread at the reopened port as well.
+            self.set_writer(Some(new_port.as_ref()));
+            reader = BufReader::with_capacity(READ_BUFFER_SIZE, new_port);
+            decoder.reset();
+            self.handler.reset();
+            reconnected_port = Some(self.port.as_str());
+        }
+    }
+
+    // Listens at the configured baud rate and, if nothing valid arrives,
+    // reopens the port at each fallback rate in turn. Returns the port that
+    // produced valid data, or None if stopped or every rate failed.
+    fn find_baud_rate(
+        &mut self,
+        mut serial_port: Box<dyn serialport::SerialPort>,
+    ) -> Option<Box<dyn serialport::SerialPort>> {
+        let primary_baud_rate = self.settings.baud_rate;
+        let mut rates = self.fallback_bauds.iter();
+        loop {
+            match receives_valid_data(
+                serial_port.as_mut(),
+                self.protocol,
+                &self.syntax,
+                &self.stop_rx,
+            ) {
+                None => return None,
+                Some(true) => break,
+                Some(false) => {}
+            }
+            // Close the port before reopening it at the next rate.
+            drop(serial_port);
+            let reopened = rates.by_ref().find_map(|&baud_rate| {
+                self.settings.baud_rate = baud_rate;
+                open_port(&self.port, &self.settings).ok()
+            });
+            let Some(reopened) = reopened else {
+                let _ = self.handler.app_handle().emit(
+                    "serial-error",
+                    format!(
+                        "no valid data from {} at {} baud or any fallback rate",
+                        self.port, primary_baud_rate
+                    ),
+                );
+                mark_disconnected(&self.handler, &self.port);
+                return None;
+            };
+            serial_port = reopened;
+        }
+
+        if self.settings.baud_rate != primary_baud_rate {
+            let _ = self.handler.app_handle().emit(
+                "baud-switched",
+                BaudSwitch {
+                    connection_id: self.handler.connection_id().to_string(),
+                    port: self.port.clone(),
+                    baud_rate: self.settings.baud_rate,
+                },
+            );
+            // Remember the rate in the frontend's config, which the next
+            // launch connects with.
+            if let Ok(store) = self.handler.app_handle().store(STORE_FILE) {
+                if let Some(mut config) = store.get("laserConfig") {
+                    if let Some(settings) = config
+                        .get_mut("arduinoSettings")
+                        .and_then(|settings| settings.as_object_mut())
+                    {
+                        settings.insert("baudRate".to_string(), self.settings.baud_rate.into());
+                        store.set("laserConfig", config);
+                    }
+                }
+            }
+        }
+        Some(serial_port)
+    }
+}
+
+// Text of a panic payload; panics usually carry a &str or a String.
+fn panic_message(panic: &(dyn std::any::Any + Send)) -> String {
+    panic
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| panic.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string())
+}
@@ -0,0 +1,137 @@
+// Optional log of everything received from the controllers, for debugging
+// odd hit detections after a session. The same writer records sessions for
+// replay_session.
+
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use crate::pipeline::unix_time_ms;
+
+// How often buffered lines are written to disk.
+const FLUSH_INTERVAL_MS: u64 = 1000;
+
+// Log shared by all reading threads; None while logging is off.
+pub type SharedLog = Arc<Mutex<Option<SerialLog>>>;
+
+// How lines are written to the file.
+#[derive(Clone, Copy, PartialEq)]
+pub enum LogFormat {
+    // "<unix ms> [<connection id>] <line>", appended to an existing file.
+    Traffic,
+    // "<ms since start>\t<line>", replacing an existing file. Read back by replay_session.
+    Session,
+}
+
+// An open log file. Buffered lines are flushed when the log is dropped.
+pub struct SerialLog {
+    writer: BufWriter<File>,
+    format: LogFormat,
+    started: std::time::Instant,
+    last_flush: std::time::Instant,
+}
+
+impl SerialLog {
+    // Opens the log file, creating its directory if needed.
+    pub fn open(path: &Path, format: LogFormat) -> Result<Self, String> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| format!("failed to create {}: {}", dir.display(), e))?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(format == LogFormat::Traffic)
+            .write(true)
+            .truncate(format == LogFormat::Session)
+            .open(path)
+            .map_err(|e| format!("failed to open {}: {}", path.display(), e))?;
+        let mut writer = BufWriter::new(file);
+        if format == LogFormat::Session {
+            let _ = writeln!(writer, "# session recording: <ms since start>\t<line>");
+        }
+        Ok(Self {
+            writer,
+            format,
+            started: std::time::Instant::now(),
+            last_flush: std::time::Instant::now(),
+        })
+    }
+
+    // Appends one received line with a millisecond timestamp.
+    pub fn write_line(&mut self, connection_id: &str, line: &str) {
+        let _ = match self.format {
+            LogFormat::Traffic => writeln!(
+                self.writer,
+                "{} [{}] {}",
+                unix_time_ms(),
+                connection_id,
+                line
+            ),
+            LogFormat::Session => writeln!(
+                self.writer,
+                "{}\t{}",
+                self.started.elapsed().as_millis(),
+                line
+            ),
+        };
+        self.flush_if_due();
+    }
+
+    // Writes buffered lines to disk at most once per flush interval.
+    pub fn flush_if_due(&mut self) {
+        if self.last_flush.elapsed().as_millis() as u64 >= FLUSH_INTERVAL_MS {
+            let _ = self.writer.flush();
+            self.last_flush = std::time::Instant::now();
+        }
+    }
+}
+
+// Default log file name, e.g. serial-2025-03-14-201502.log (UTC).
+pub fn default_file_name() -> String {
+    stamped_file_name("serial", "log")
+}
+
+// File name with the current UTC date and time, e.g.
+// serial-2025-03-14-201502.log for ("serial", "log").
+pub fn stamped_file_name(prefix: &str, extension: &str) -> String {
+    let secs = unix_time_ms() / 1000;
+    let (year, month, day) = civil_from_days((secs / 86400) as i64);
+    let time = secs % 86400;
+    format!(
+        "{}-{:04}-{:02}-{:02}-{:02}{:02}{:02}.{}",
+        prefix,
+        year,
+        month,
+        day,
+        time / 3600,
+        time / 60 % 60,
+        time % 60,
+        extension
+    )
+}
+
+// Converts days since the Unix epoch into a (year, month, day) date.
+pub fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+// Converts a (year, month, day) date into days since the Unix epoch.
+pub fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = year - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = i64::from((month + 9) % 12);
+    let doy = (153 * mp + 2) / 5 + i64::from(day) - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
@@ -0,0 +1,139 @@
+// Records the lines received during a session and replays them later through
+// the same pipeline, for reproducing hit detection problems at the desk.
+
+use std::path::Path;
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use tauri::Emitter;
+
+use crate::pipeline::LineHandler;
+use crate::serial_log::{LogFormat, SerialLog};
+use crate::{
+    SensorData, SerialConnection, SerialManager, SourceKind, DEFAULT_CONNECTION_ID,
+    DEFAULT_STALL_TIMEOUT_MS,
+};
+
+// Reads a recording: one "<ms since start>\t<line>" entry per line. Blank
+// lines and lines starting with '#' are skipped.
+fn read_recording(path: &Path) -> Result<Vec<(u64, String)>, String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.starts_with('#'))
+        .map(|(number, line)| {
+            let (time, text) = line
+                .split_once('\t')
+                .ok_or_else(|| format!("line {}: missing tab after the timestamp", number + 1))?;
+            let time_ms = time
+                .trim()
+                .parse::<u64>()
+                .map_err(|_| format!("line {}: invalid timestamp {}", number + 1, time))?;
+            Ok((time_ms, text.to_string()))
+        })
+        .collect()
+}
+
+// Spawns the replay thread, which feeds the entries to the handler at their
+// recorded times divided by `speed`.
+fn spawn_replay(
+    mut handler: LineHandler,
+    entries: Vec<(u64, String)>,
+    speed: f32,
+    stop_rx: Receiver<()>,
+) -> JoinHandle<()> {
+    thread::spawn(move || {
+        let started = std::time::Instant::now();
+        for (time_ms, line) in entries {
+            let due = started
+                + std::time::Duration::from_secs_f64(time_ms as f64 / 1000.0 / speed as f64);
+            // Wait for the entry, but exit immediately on a stop signal.
+            let wait = due.saturating_duration_since(std::time::Instant::now());
+            match stop_rx.recv_timeout(wait) {
+                Err(RecvTimeoutError::Timeout) => {}
+                _ => return,
+            }
+            handler.tick();
+            handler.handle_line(&line);
+        }
+        let _ = handler.app_handle().emit("replay-finished", ());
+    })
+}
+
+// Command to record every received line with its time since the recording started.
+#[tauri::command]
+pub fn start_session_recording(
+    path: String,
+    state: tauri::State<Arc<Mutex<SerialManager>>>,
+) -> Result<(), String> {
+    let recording = SerialLog::open(Path::new(&path), LogFormat::Session)?;
+    let manager = state.lock().map_err(|e| e.to_string())?;
+    *manager
+        .live
+        .session_recording
+        .lock()
+        .map_err(|e| e.to_string())? = Some(recording);
+    Ok(())
+}
+
+// Command to stop recording and write the remaining lines to the file.
+#[tauri::command]
+pub fn stop_session_recording(
+    state: tauri::State<Arc<Mutex<SerialManager>>>,
+) -> Result<(), String> {
+    let manager = state.lock().map_err(|e| e.to_string())?;
+    manager
+        .live
+        .session_recording
+        .lock()
+        .map_err(|e| e.to_string())?
+        .take();
+    Ok(())
+}
+
+// Command to replay a recorded session at `speed` times the recorded pace.
+// The replay stands in for the controller until it ends or stop_serial is called.
+#[tauri::command]
+pub fn replay_session(
+    path: String,
+    speed: f32,
+    app_handle: tauri::AppHandle,
+    state: tauri::State<Arc<Mutex<SerialManager>>>,
+    sensor_data: tauri::State<Arc<Mutex<SensorData>>>,
+) -> Result<(), String> {
+    if !(speed > 0.0 && speed.is_finite()) {
+        return Err("speed must be greater than 0".to_string());
+    }
+    let entries = read_recording(Path::new(&path))?;
+
+    let mut manager = state.lock().map_err(|e| e.to_string())?;
+    // Replayed lines would mix with live data from a real device.
+    if manager.connections.values().any(|connection| {
+        matches!(
+            connection.source,
+            SourceKind::Serial | SourceKind::Tcp | SourceKind::Udp
+        )
+    }) {
+        return Err("stop the connected device before replaying a session".to_string());
+    }
+    manager.stop();
+    *sensor_data.lock().map_err(|e| e.to_string())? = SensorData::new();
+
+    let mut connection = SerialConnection::new(path, None, SourceKind::Replay);
+    let handler = manager.line_handler(
+        &app_handle,
+        DEFAULT_CONNECTION_ID,
+        &connection,
+        sensor_data.inner(),
+        DEFAULT_STALL_TIMEOUT_MS,
+    );
+    let (stop_tx, stop_rx) = channel();
+    connection.reading_thread = Some(spawn_replay(handler, entries, speed, stop_rx));
+    connection.stop_sender = Some(stop_tx);
+    manager
+        .connections
+        .insert(DEFAULT_CONNECTION_ID.to_string(), connection);
+    Ok(())
+}
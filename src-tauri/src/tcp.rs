@@ -0,0 +1,126 @@
+// Network source for controllers that send their lines over TCP, e.g. an
+// ESP32 on WiFi. Uses the same reading loop and pipeline as the serial port.
+
+use std::io::BufReader;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::mpsc::channel;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::protocol::{BinaryDecoder, Protocol};
+use crate::reader::{
+    mark_disconnected, read_stream, reconnect, ReadExit, MAX_CONSECUTIVE_READ_ERRORS,
+    READ_BUFFER_SIZE, RECONNECT_INTERVAL_MS,
+};
+use crate::{
+    SensorData, SerialConnection, SerialManager, SourceKind, DEFAULT_CONNECTION_ID,
+    DEFAULT_RECONNECT_ATTEMPTS, DEFAULT_STALL_TIMEOUT_MS,
+};
+
+// How long to wait for the controller to accept a connection.
+const CONNECT_TIMEOUT_MS: u64 = 3000;
+// Read timeout so the reading loop can notice stop signals.
+const READ_TIMEOUT_MS: u64 = 1000;
+
+// Connects to the controller, trying every address the host resolves to.
+fn connect(address: &str) -> Result<TcpStream, String> {
+    let addresses = address
+        .to_socket_addrs()
+        .map_err(|e| format!("failed to resolve {}: {}", address, e))?;
+
+    let mut last_error = format!("{} did not resolve to any address", address);
+    for socket_address in addresses {
+        match TcpStream::connect_timeout(
+            &socket_address,
+            std::time::Duration::from_millis(CONNECT_TIMEOUT_MS),
+        ) {
+            Ok(stream) => {
+                stream
+                    .set_read_timeout(Some(std::time::Duration::from_millis(READ_TIMEOUT_MS)))
+                    .map_err(|e| e.to_string())?;
+                return Ok(stream);
+            }
+            Err(e) => last_error = format!("failed to connect to {}: {}", address, e),
+        }
+    }
+    Err(last_error)
+}
+
+// Command to read sensor lines from a TCP controller instead of a serial port.
+// Runs off the main thread since connecting can take a few seconds.
+#[tauri::command(async)]
+pub fn configure_tcp_source(
+    host: String,
+    port: u16,
+    connection_id: Option<String>,
+    max_reconnect_attempts: Option<u32>,
+    app_handle: tauri::AppHandle,
+    state: tauri::State<Arc<Mutex<SerialManager>>>,
+    sensor_data: tauri::State<Arc<Mutex<SensorData>>>,
+) -> Result<(), String> {
+    let connection_id = connection_id.unwrap_or_else(|| DEFAULT_CONNECTION_ID.to_string());
+    let max_reconnect_attempts = max_reconnect_attempts.unwrap_or(DEFAULT_RECONNECT_ATTEMPTS);
+    let address = format!("{}:{}", host, port);
+
+    let mut manager = state.lock().map_err(|e| e.to_string())?;
+    manager.stop_connection(&connection_id);
+
+    // Connect failures are reported as the command result.
+    let stream = connect(&address)?;
+
+    let mut connection = SerialConnection::new(address.clone(), None, SourceKind::Tcp);
+    let mut handler = manager.line_handler(
+        &app_handle,
+        &connection_id,
+        &connection,
+        sensor_data.inner(),
+        DEFAULT_STALL_TIMEOUT_MS,
+    );
+    let stats = Arc::clone(&connection.stats);
+    let (stop_tx, stop_rx) = channel();
+
+    let handle = thread::spawn(move || {
+        let mut reader = BufReader::with_capacity(READ_BUFFER_SIZE, stream);
+        let mut decoder = BinaryDecoder::new();
+        let mut reconnected_address = None;
+
+        loop {
+            match read_stream(
+                &mut reader,
+                Protocol::Csv,
+                &mut decoder,
+                &mut handler,
+                &stats,
+                &stop_rx,
+                MAX_CONSECUTIVE_READ_ERRORS,
+                reconnected_address,
+            ) {
+                ReadExit::Stopped => break,
+                ReadExit::Gone => {
+                    mark_disconnected(&handler, &address);
+                    break;
+                }
+                ReadExit::Lost => {}
+            }
+            let reopened = reconnect(
+                &handler,
+                &address,
+                max_reconnect_attempts,
+                RECONNECT_INTERVAL_MS,
+                &stop_rx,
+                || connect(&address).ok(),
+            );
+            let Some(stream) = reopened else {
+                break;
+            };
+            reader = BufReader::with_capacity(READ_BUFFER_SIZE, stream);
+            handler.reset();
+            reconnected_address = Some(address.as_str());
+        }
+    });
+
+    connection.reading_thread = Some(handle);
+    connection.stop_sender = Some(stop_tx);
+    manager.connections.insert(connection_id, connection);
+    Ok(())
+}
@@ -0,0 +1,228 @@
+// Teams for party games, e.g. red team vs blue team. Runs saved for a team
+// add up to a team score; the team definitions live in the scores file.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tauri::Emitter;
+
+use crate::scores::{Run, ScoreBook};
+
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Team {
+    pub id: u64,
+    pub name: String,
+    // Players whose runs go to the team when saved without one.
+    pub members: Vec<String>,
+}
+
+impl Team {
+    pub fn has_member(&self, player: &str) -> bool {
+        self.members
+            .iter()
+            .any(|member| member.trim().eq_ignore_ascii_case(player.trim()))
+    }
+}
+
+// How the runs of a team make up its score.
+#[derive(Clone, Copy, Debug, Default, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Aggregation {
+    // Sum of the best final score of each player.
+    #[default]
+    BestSum,
+    // Average final score of all runs.
+    Average,
+}
+
+// A team's place in get_team_standings; teams without runs come last
+// without a score.
+#[derive(Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TeamStanding {
+    pub rank: usize,
+    pub team: Team,
+    pub score: Option<u64>,
+    pub runs: usize,
+    pub players: usize,
+}
+
+// Payload of team-standings-changed.
+#[derive(Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct StandingsChanged {
+    team_id: u64,
+    mode: String,
+}
+
+// Cleans up a team definition before it is saved.
+fn normalize(name: &str, members: Vec<String>) -> Result<(String, Vec<String>), String> {
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err("the team needs a name".to_string());
+    }
+    let mut unique: Vec<String> = Vec::new();
+    for member in members {
+        let member = member.trim();
+        if !member.is_empty() && !unique.iter().any(|m| m.eq_ignore_ascii_case(member)) {
+            unique.push(member.to_string());
+        }
+    }
+    Ok((name, unique))
+}
+
+fn check_unique_name(teams: &[Team], name: &str, id: Option<u64>) -> Result<(), String> {
+    if teams
+        .iter()
+        .any(|team| Some(team.id) != id && team.name.eq_ignore_ascii_case(name))
+    {
+        return Err(format!("a team named {} already exists", name));
+    }
+    Ok(())
+}
+
+// Ranks the teams by the runs saved for them, of one mode or of all. Failed
+// and voided runs don't count.
+pub fn standings(
+    teams: &[Team],
+    runs: &[Run],
+    mode: Option<&str>,
+    aggregation: Aggregation,
+) -> Vec<TeamStanding> {
+    let mut standings: Vec<TeamStanding> = teams
+        .iter()
+        .map(|team| {
+            let team_runs: Vec<&Run> = runs
+                .iter()
+                .filter(|run| run.team_id == Some(team.id) && run.ranks())
+                .filter(|run| mode.is_none_or(|mode| run.mode == mode))
+                .collect();
+            let mut best: HashMap<String, u64> = HashMap::new();
+            for run in &team_runs {
+                let score = best
+                    .entry(run.player.to_lowercase())
+                    .or_insert(run.final_score());
+                *score = (*score).min(run.final_score());
+            }
+            let score = match aggregation {
+                _ if team_runs.is_empty() => None,
+                Aggregation::BestSum => Some(best.values().sum()),
+                Aggregation::Average => Some(
+                    team_runs.iter().map(|run| run.final_score()).sum::<u64>()
+                        / team_runs.len() as u64,
+                ),
+            };
+            TeamStanding {
+                rank: 0,
+                team: team.clone(),
+                score,
+                runs: team_runs.len(),
+                players: best.len(),
+            }
+        })
+        .collect();
+    standings.sort_by_key(|standing| (standing.score.is_none(), standing.score));
+    for (i, standing) in standings.iter_mut().enumerate() {
+        standing.rank = i + 1;
+    }
+    standings
+}
+
+// Tells the scoreboard that the standings changed through a saved run.
+pub fn report_run(app_handle: &tauri::AppHandle, run: &Run) {
+    if let Some(team_id) = run.team_id {
+        let _ = app_handle.emit(
+            "team-standings-changed",
+            StandingsChanged {
+                team_id,
+                mode: run.mode.clone(),
+            },
+        );
+    }
+}
+
+// Command to create a team.
+#[tauri::command]
+pub fn create_team(
+    name: String,
+    members: Vec<String>,
+    scores: tauri::State<Mutex<ScoreBook>>,
+) -> Result<Team, String> {
+    let (name, members) = normalize(&name, members)?;
+    let mut book = scores.lock().map_err(|e| e.to_string())?;
+    book.update(|file| {
+        check_unique_name(&file.teams, &name, None)?;
+        let team = Team {
+            id: file.next_team_id,
+            name,
+            members,
+        };
+        file.next_team_id += 1;
+        file.teams.push(team.clone());
+        Ok(team)
+    })
+}
+
+// Command to rename a team or change its members.
+#[tauri::command]
+pub fn update_team(
+    id: u64,
+    name: String,
+    members: Vec<String>,
+    scores: tauri::State<Mutex<ScoreBook>>,
+) -> Result<Team, String> {
+    let (name, members) = normalize(&name, members)?;
+    let mut book = scores.lock().map_err(|e| e.to_string())?;
+    book.update(|file| {
+        check_unique_name(&file.teams, &name, Some(id))?;
+        let team = file
+            .teams
+            .iter_mut()
+            .find(|team| team.id == id)
+            .ok_or_else(|| format!("no team with id {}", id))?;
+        team.name = name;
+        team.members = members;
+        Ok(team.clone())
+    })
+}
+
+// Command to delete a team. Its runs stay, without a team.
+#[tauri::command]
+pub fn delete_team(id: u64, scores: tauri::State<Mutex<ScoreBook>>) -> Result<(), String> {
+    let mut book = scores.lock().map_err(|e| e.to_string())?;
+    book.update(|file| {
+        let count = file.teams.len();
+        file.teams.retain(|team| team.id != id);
+        if file.teams.len() == count {
+            return Err(format!("no team with id {}", id));
+        }
+        for run in file.runs.iter_mut_where(|run| run.team_id == Some(id)) {
+            run.team_id = None;
+        }
+        Ok(())
+    })
+}
+
+// Command to list all teams.
+#[tauri::command]
+pub fn list_teams(scores: tauri::State<Mutex<ScoreBook>>) -> Result<Vec<Team>, String> {
+    let book = scores.lock().map_err(|e| e.to_string())?;
+    Ok(book.file().teams.clone())
+}
+
+// Command to rank the teams by their runs of a mode, or of all runs.
+#[tauri::command]
+pub fn get_team_standings(
+    mode: Option<String>,
+    aggregation: Option<Aggregation>,
+    scores: tauri::State<Mutex<ScoreBook>>,
+) -> Result<Vec<TeamStanding>, String> {
+    let book = scores.lock().map_err(|e| e.to_string())?;
+    let file = book.file();
+    Ok(standings(
+        &file.teams,
+        &file.runs,
+        mode.as_deref(),
+        aggregation.unwrap_or_default(),
+    ))
+}
@@ -0,0 +1,322 @@
+// Single-elimination tournaments for company events. The bracket lives in
+// the scores file, so it survives a restart mid-event; runs can be linked to
+// the match they were played for.
+
+use std::sync::Mutex;
+use tauri::Emitter;
+
+use crate::pipeline::unix_time_ms;
+use crate::players::normalize_name;
+use crate::scores::{ScoreBook, ScoreFile};
+
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Match {
+    pub id: u64,
+    // 0 for the first round.
+    pub round: usize,
+    // None while the player isn't known yet, or for a bye.
+    pub players: [Option<String>; 2],
+    pub winner: Option<String>,
+}
+
+impl Match {
+    fn is_ready(&self) -> bool {
+        self.winner.is_none() && self.players.iter().all(Option::is_some)
+    }
+}
+
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Tournament {
+    pub id: u64,
+    pub created_ms: u64,
+    // Matches per round, first round first; the last round is the final.
+    pub rounds: Vec<Vec<Match>>,
+    pub winner: Option<String>,
+}
+
+impl Tournament {
+    fn matches(&self) -> impl Iterator<Item = &Match> {
+        self.rounds.iter().flatten()
+    }
+
+    // The next match to be played: the earliest one with both players known.
+    pub fn next_match(&self) -> Option<&Match> {
+        self.matches().find(|m| m.is_ready())
+    }
+
+    pub fn find_match(&self, match_id: u64) -> Option<&Match> {
+        self.matches().find(|m| m.id == match_id)
+    }
+}
+
+// How players are placed in the bracket.
+#[derive(Clone, Copy, Debug, Default, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Seeding {
+    // In the given order, strongest first; top seeds get the byes.
+    #[default]
+    Ordered,
+    Random,
+}
+
+// Payload of next-match.
+#[derive(Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct NextMatch {
+    tournament_id: u64,
+    match_id: u64,
+    round: usize,
+    players: [String; 2],
+}
+
+// Seed numbers (from 1) in bracket order, so that seed 1 and 2 can only meet
+// in the final, e.g. [1, 4, 2, 3] for four players.
+fn seed_order(size: usize) -> Vec<usize> {
+    let mut order = vec![1];
+    while order.len() < size {
+        let count = order.len() * 2;
+        order = order.iter().flat_map(|&s| [s, count + 1 - s]).collect();
+    }
+    order
+}
+
+// Shuffles the names with a small xorshift generator seeded by the clock;
+// fairness here doesn't need more.
+fn shuffle(names: &mut [String]) {
+    let mut state = unix_time_ms() | 1;
+    for i in (1..names.len()).rev() {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        names.swap(i, (state % (i as u64 + 1)) as usize);
+    }
+}
+
+// Builds the bracket for the seeded names, padding to a power of two with
+// byes that send their opponent straight to the next round.
+fn build_bracket(id: u64, names: Vec<String>) -> Tournament {
+    let size = names.len().next_power_of_two();
+    let mut rounds = Vec::new();
+    let mut next_id = 1;
+    let mut count = size / 2;
+    while count > 0 {
+        let round = rounds.len();
+        rounds.push(
+            (0..count)
+                .map(|_| {
+                    next_id += 1;
+                    Match {
+                        id: next_id - 1,
+                        round,
+                        players: [None, None],
+                        winner: None,
+                    }
+                })
+                .collect::<Vec<_>>(),
+        );
+        count /= 2;
+    }
+    let mut tournament = Tournament {
+        id,
+        created_ms: unix_time_ms(),
+        rounds,
+        winner: None,
+    };
+    let order = seed_order(size);
+    for (slot, seed) in order.iter().enumerate() {
+        tournament.rounds[0][slot / 2].players[slot % 2] = names.get(seed - 1).cloned();
+    }
+    let byes: Vec<(u64, String)> = tournament.rounds[0]
+        .iter()
+        .filter_map(|m| match &m.players {
+            [Some(player), None] | [None, Some(player)] => Some((m.id, player.clone())),
+            _ => None,
+        })
+        .collect();
+    for (match_id, player) in byes {
+        let _ = advance(&mut tournament, match_id, &player);
+    }
+    tournament
+}
+
+// Records the winner of a match and moves them on to the next round.
+fn advance(tournament: &mut Tournament, match_id: u64, winner: &str) -> Result<(), String> {
+    let (round, index) = tournament
+        .rounds
+        .iter()
+        .enumerate()
+        .find_map(|(round, matches)| {
+            matches
+                .iter()
+                .position(|m| m.id == match_id)
+                .map(|index| (round, index))
+        })
+        .ok_or_else(|| format!("no match with id {}", match_id))?;
+    let played = &mut tournament.rounds[round][index];
+    if played.winner.is_some() {
+        return Err("the match already has a winner".to_string());
+    }
+    let winner = played
+        .players
+        .iter()
+        .flatten()
+        .find(|player| player.eq_ignore_ascii_case(winner.trim()))
+        .cloned()
+        .ok_or_else(|| format!("{} doesn't play in this match", winner))?;
+    played.winner = Some(winner.clone());
+    match tournament.rounds.get_mut(round + 1) {
+        Some(next_round) => next_round[index / 2].players[index % 2] = Some(winner),
+        None => tournament.winner = Some(winner),
+    }
+    Ok(())
+}
+
+fn find_mut(file: &mut ScoreFile, id: u64) -> Result<&mut Tournament, String> {
+    file.tournaments
+        .iter_mut()
+        .find(|tournament| tournament.id == id)
+        .ok_or_else(|| format!("no tournament with id {}", id))
+}
+
+// Announces the next match of a tournament, if there is one left.
+fn report_next_match(app_handle: &tauri::AppHandle, tournament: &Tournament) {
+    if let Some(next) = tournament.next_match() {
+        let [Some(first), Some(second)] = next.players.clone() else {
+            return;
+        };
+        let _ = app_handle.emit(
+            "next-match",
+            NextMatch {
+                tournament_id: tournament.id,
+                match_id: next.id,
+                round: next.round,
+                players: [first, second],
+            },
+        );
+    }
+}
+
+// Command to create a tournament from at least two player names.
+#[tauri::command]
+pub fn create_tournament(
+    names: Vec<String>,
+    seeding: Option<Seeding>,
+    app_handle: tauri::AppHandle,
+    scores: tauri::State<Mutex<ScoreBook>>,
+) -> Result<Tournament, String> {
+    let mut players: Vec<String> = Vec::new();
+    for name in names.iter().map(|name| normalize_name(name)) {
+        if name.is_empty() {
+            continue;
+        }
+        if players.iter().any(|p| p.eq_ignore_ascii_case(&name)) {
+            return Err(format!("{} is listed twice", name));
+        }
+        players.push(name);
+    }
+    if players.len() < 2 {
+        return Err("a tournament needs at least two players".to_string());
+    }
+    if seeding.unwrap_or_default() == Seeding::Random {
+        shuffle(&mut players);
+    }
+    let mut book = scores.lock().map_err(|e| e.to_string())?;
+    let tournament = book.update(|file| {
+        let tournament = build_bracket(file.next_tournament_id, players);
+        file.next_tournament_id += 1;
+        file.tournaments.push(tournament.clone());
+        Ok(tournament)
+    })?;
+    report_next_match(&app_handle, &tournament);
+    Ok(tournament)
+}
+
+// Command to fetch the bracket of a tournament.
+#[tauri::command]
+pub fn get_bracket(
+    tournament_id: u64,
+    scores: tauri::State<Mutex<ScoreBook>>,
+) -> Result<Tournament, String> {
+    let book = scores.lock().map_err(|e| e.to_string())?;
+    book.file()
+        .tournaments
+        .iter()
+        .find(|tournament| tournament.id == tournament_id)
+        .cloned()
+        .ok_or_else(|| format!("no tournament with id {}", tournament_id))
+}
+
+// Command to record the winner of a match; announces the next match.
+#[tauri::command]
+pub fn record_match_result(
+    tournament_id: u64,
+    match_id: u64,
+    winner: String,
+    app_handle: tauri::AppHandle,
+    scores: tauri::State<Mutex<ScoreBook>>,
+) -> Result<Tournament, String> {
+    let mut book = scores.lock().map_err(|e| e.to_string())?;
+    let tournament = book.update(|file| {
+        let tournament = find_mut(file, tournament_id)?;
+        advance(tournament, match_id, &winner)?;
+        Ok(tournament.clone())
+    })?;
+    report_next_match(&app_handle, &tournament);
+    Ok(tournament)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(count: usize) -> Vec<String> {
+        (1..=count).map(|i| format!("P{}", i)).collect()
+    }
+
+    #[test]
+    fn orders_seeds_so_favourites_meet_last() {
+        assert_eq!(seed_order(4), [1, 4, 2, 3]);
+        assert_eq!(seed_order(8), [1, 8, 4, 5, 2, 7, 3, 6]);
+    }
+
+    #[test]
+    fn top_seeds_get_the_byes() {
+        let tournament = build_bracket(1, names(5));
+        assert_eq!(tournament.rounds.len(), 3);
+        // P1, P2 and P3 skip the first round.
+        let second_round: Vec<_> = tournament.rounds[1]
+            .iter()
+            .map(|m| m.players.clone())
+            .collect();
+        assert_eq!(second_round[0][0].as_deref(), Some("P1"));
+        assert_eq!(second_round[0][1], None);
+        assert_eq!(second_round[1][0].as_deref(), Some("P2"));
+        assert_eq!(second_round[1][1].as_deref(), Some("P3"));
+        let next = tournament.next_match().unwrap();
+        assert_eq!(next.round, 0);
+        assert_eq!(
+            next.players,
+            [Some("P4".to_string()), Some("P5".to_string())]
+        );
+    }
+
+    #[test]
+    fn winners_advance_to_the_final() {
+        let mut tournament = build_bracket(1, names(3));
+        let first = tournament.next_match().unwrap().id;
+        assert!(advance(&mut tournament, first, "nobody").is_err());
+        advance(&mut tournament, first, "p3").unwrap();
+        assert!(advance(&mut tournament, first, "P2").is_err());
+
+        let final_match = tournament.next_match().unwrap().clone();
+        assert_eq!(
+            final_match.players,
+            [Some("P1".to_string()), Some("P3".to_string())]
+        );
+        advance(&mut tournament, final_match.id, "P3").unwrap();
+        assert_eq!(tournament.winner.as_deref(), Some("P3"));
+        assert!(tournament.next_match().is_none());
+    }
+}
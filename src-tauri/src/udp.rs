@@ -0,0 +1,108 @@
+// Network source for wireless nodes that broadcast their readings as UDP
+// datagrams, one line per packet. Datagrams go through the same pipeline as
+// serial lines; several nodes may send to the same port.
+
+use std::net::UdpSocket;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use tauri::Emitter;
+
+use crate::pipeline::{unix_time_ms, LineHandler};
+use crate::{
+    SensorData, SerialConnection, SerialManager, SourceKind, DEFAULT_CONNECTION_ID,
+    DEFAULT_STALL_TIMEOUT_MS,
+};
+
+// Read timeout so the receiving loop can notice stop signals.
+const READ_TIMEOUT_MS: u64 = 500;
+// Largest datagram accepted; longer ones are truncated by the socket.
+const MAX_DATAGRAM_SIZE: usize = 2048;
+
+// Payload for the udp-datagram debug event.
+#[derive(Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Datagram {
+    connection_id: String,
+    sender: String,
+    line: String,
+    timestamp_ms: u64,
+}
+
+// Receives datagrams until a stop signal arrives. With the raw monitor on,
+// each datagram is also emitted as udp-datagram along with its sender.
+fn spawn_listener(
+    socket: UdpSocket,
+    mut handler: LineHandler,
+    raw_monitor: Arc<AtomicBool>,
+    stop_rx: Receiver<()>,
+) -> JoinHandle<()> {
+    thread::spawn(move || {
+        let mut buffer = [0u8; MAX_DATAGRAM_SIZE];
+        while stop_rx.try_recv().is_err() {
+            handler.tick();
+            // Timeouts only mean no node sent anything.
+            let Ok((n, sender)) = socket.recv_from(&mut buffer) else {
+                continue;
+            };
+            let payload = String::from_utf8_lossy(&buffer[..n]);
+            for line in payload.lines() {
+                if raw_monitor.load(Ordering::Relaxed) {
+                    let _ = handler.app_handle().emit(
+                        "udp-datagram",
+                        Datagram {
+                            connection_id: handler.connection_id().to_string(),
+                            sender: sender.ip().to_string(),
+                            line: line.to_string(),
+                            timestamp_ms: unix_time_ms(),
+                        },
+                    );
+                }
+                handler.handle_line(line);
+            }
+        }
+    })
+}
+
+// Command to read sensor lines from UDP datagrams sent to `bind_port`.
+#[tauri::command]
+pub fn configure_udp_source(
+    bind_port: u16,
+    connection_id: Option<String>,
+    app_handle: tauri::AppHandle,
+    state: tauri::State<Arc<Mutex<SerialManager>>>,
+    sensor_data: tauri::State<Arc<Mutex<SensorData>>>,
+) -> Result<(), String> {
+    let connection_id = connection_id.unwrap_or_else(|| DEFAULT_CONNECTION_ID.to_string());
+    let address = format!("0.0.0.0:{}", bind_port);
+
+    let mut manager = state.lock().map_err(|e| e.to_string())?;
+    manager.stop_connection(&connection_id);
+
+    let socket =
+        UdpSocket::bind(&address).map_err(|e| format!("failed to bind {}: {}", address, e))?;
+    socket
+        .set_read_timeout(Some(std::time::Duration::from_millis(READ_TIMEOUT_MS)))
+        .map_err(|e| e.to_string())?;
+
+    let mut connection = SerialConnection::new(address, None, SourceKind::Udp);
+    let handler = manager.line_handler(
+        &app_handle,
+        &connection_id,
+        &connection,
+        sensor_data.inner(),
+        DEFAULT_STALL_TIMEOUT_MS,
+    );
+    let (stop_tx, stop_rx) = channel();
+
+    connection.reading_thread = Some(spawn_listener(
+        socket,
+        handler,
+        Arc::clone(&manager.live.raw_monitor),
+        stop_rx,
+    ));
+    connection.stop_sender = Some(stop_tx);
+    manager.connections.insert(connection_id, connection);
+    Ok(())
+}
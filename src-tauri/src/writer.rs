@@ -0,0 +1,123 @@
+// Outgoing side of a serial connection: lines are queued and written in order
+// by a writer thread. A line can ask for an acknowledgement, in which case it
+// is sent as "<line>#<id>" and retried until the firmware answers "ok:<id>".
+
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{Receiver, Sender};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use tauri::Emitter;
+
+use crate::SharedWriter;
+
+// Number of commands that may wait in the write queue.
+pub const WRITE_QUEUE_SIZE: usize = 16;
+// Prefix of the firmware's acknowledgement lines.
+pub const ACK_PREFIX: &str = "ok:";
+// How long to wait for an acknowledgement before sending the line again.
+pub const ACK_TIMEOUT_MS: u64 = 500;
+// Number of times an acknowledged line is sent before giving up.
+pub const ACK_ATTEMPTS: u32 = 3;
+
+// A line to write to the port, whether it needs an acknowledgement, and the
+// channel to report the outcome on. Without a channel, failures are emitted
+// as serial-write-failed.
+pub struct WriteRequest {
+    pub line: String,
+    pub ack: bool,
+    pub reply: Option<Sender<Result<(), String>>>,
+}
+
+// Payload for the serial-write-failed event.
+#[derive(Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct WriteFailure {
+    connection_id: String,
+    command: String,
+    error: String,
+}
+
+// Starts the writer thread of a connection. Acknowledged ids arrive on
+// `ack_rx` from the reading thread. Once `closing` is set, queued requests
+// fail without being written.
+pub fn spawn_writer(
+    writer: SharedWriter,
+    queue_rx: Receiver<WriteRequest>,
+    ack_rx: Receiver<u32>,
+    closing: Arc<AtomicBool>,
+    app_handle: tauri::AppHandle,
+    connection_id: String,
+) -> JoinHandle<()> {
+    thread::spawn(move || {
+        let mut next_id: u32 = 0;
+        // Requests are handled one at a time so lines never interleave.
+        for request in queue_rx {
+            let result = if closing.load(Ordering::Relaxed) {
+                Err("serial connection closed".to_string())
+            } else if request.ack {
+                next_id = next_id.wrapping_add(1);
+                write_acknowledged(&writer, &request.line, next_id, &ack_rx, &closing)
+            } else {
+                write(&writer, &request.line)
+            };
+            match request.reply {
+                Some(reply) => {
+                    let _ = reply.send(result);
+                }
+                None => {
+                    if let Err(error) = result {
+                        let _ = app_handle.emit(
+                            "serial-write-failed",
+                            WriteFailure {
+                                connection_id: connection_id.clone(),
+                                command: request.line,
+                                error,
+                            },
+                        );
+                    }
+                }
+            }
+        }
+    })
+}
+
+fn write(writer: &SharedWriter, line: &str) -> Result<(), String> {
+    let mut guard = writer.lock().map_err(|e| e.to_string())?;
+    let port = guard.as_mut().ok_or("serial port is not connected")?;
+    port.write_all(format!("{}\n", line).as_bytes())
+        .and_then(|_| port.flush())
+        .map_err(|e| format!("write error: {}", e))
+}
+
+// Writes a line tagged with `id` until its acknowledgement arrives.
+// Acknowledgements of other ids, e.g. late ones of earlier commands, are
+// ignored.
+fn write_acknowledged(
+    writer: &SharedWriter,
+    line: &str,
+    id: u32,
+    ack_rx: &Receiver<u32>,
+    closing: &AtomicBool,
+) -> Result<(), String> {
+    let tagged = format!("{}#{}", line, id);
+    for _ in 0..ACK_ATTEMPTS {
+        write(writer, &tagged)?;
+        let deadline = std::time::Instant::now() + std::time::Duration::from_millis(ACK_TIMEOUT_MS);
+        loop {
+            let remaining = deadline.saturating_duration_since(std::time::Instant::now());
+            match ack_rx.recv_timeout(remaining) {
+                Ok(acked) if acked == id => return Ok(()),
+                Ok(_) => {}
+                Err(_) => break,
+            }
+        }
+        if closing.load(Ordering::Relaxed) {
+            return Err("serial connection closed".to_string());
+        }
+    }
+    Err(format!(
+        "no acknowledgement after {} attempts",
+        ACK_ATTEMPTS
+    ))
+}
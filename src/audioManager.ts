@@ -1,3 +1,5 @@
+import { listen } from "@tauri-apps/api/event";
+
 // Sound effect types
 export enum SoundEffect {
   GameStart = "GameStart",
@@ -6,10 +8,13 @@ export enum SoundEffect {
   Buzzer = "Buzzer",
   Countdown = "Countdown",
   Click = "Click",
+  Overtime = "Overtime",
 }
 
 export class AudioManager {
   private sounds: Map<SoundEffect, HTMLAudioElement> = new Map();
+  // Effects still playing, so the countdown can be cut short
+  private playing: Set<HTMLAudioElement> = new Set();
   private backgroundMusic: HTMLAudioElement | null = null;
   private musicVolume: number = 0.7;
   private effectVolume: number = 0.7;
@@ -31,6 +36,7 @@ export class AudioManager {
       this.loadSoundEffect(SoundEffect.Buzzer, ["./assets/audio/game_finished.wav"]);
       this.loadSoundEffect(SoundEffect.Countdown, ["./assets/audio/countdown.wav"]);
       this.loadSoundEffect(SoundEffect.Click, ["./assets/audio/click.wav"]);
+      this.loadSoundEffect(SoundEffect.Overtime, ["./assets/audio/overtime.wav"]);
 
       // Set up background music with multiple format options
       this.backgroundMusic = new Audio();
@@ -45,6 +51,27 @@ export class AudioManager {
     } catch (error) {
       console.error("Failed to initialize audio manager:", error);
     }
+
+    // Games run by the backend end on their own when the hit limit is reached
+    listen("game-over", () => {
+      this.playEffect(SoundEffect.GameOver);
+      this.stopBackgroundMusic();
+    }).catch((error) => {
+      console.error("Failed to listen for game over:", error);
+    });
+
+    // The backend times the countdown sounds of its games
+    listen<{ effect: SoundEffect; level: number }>("play-sound", (event) => {
+      // Overtime beeps get faster as the level rises
+      this.playEffect(event.payload.effect, 1 + event.payload.level * 0.1);
+    }).catch((error) => {
+      console.error("Failed to listen for sounds:", error);
+    });
+    listen("stop-sounds", () => {
+      this.stopEffects();
+    }).catch((error) => {
+      console.error("Failed to listen for stopped sounds:", error);
+    });
   }
 
   private loadSoundEffect(effect: SoundEffect, sources: string[]) {
@@ -77,7 +104,7 @@ export class AudioManager {
     });
   }
 
-  playEffect(effect: SoundEffect) {
+  playEffect(effect: SoundEffect, playbackRate: number = 1) {
     if (!this.effectsEnabled || !this.initialized) return;
 
     const sound = this.sounds.get(effect);
@@ -86,6 +113,9 @@ export class AudioManager {
         // Clone the audio to allow multiple simultaneous playback
         const soundToPlay = sound.cloneNode(true) as HTMLAudioElement;
         soundToPlay.volume = this.effectVolume;
+        soundToPlay.playbackRate = playbackRate;
+        this.playing.add(soundToPlay);
+        soundToPlay.addEventListener("ended", () => this.playing.delete(soundToPlay));
 
         // Add specific error handling for this playback
         const playPromise = soundToPlay.play();
@@ -160,8 +190,21 @@ export class AudioManager {
     }
   }
 
+  // Stops the sound effects that are still playing
+  stopEffects() {
+    this.playing.forEach((sound) => {
+      try {
+        sound.pause();
+      } catch (err) {
+        console.error("Error stopping audio:", err);
+      }
+    });
+    this.playing.clear();
+  }
+
   stopAllAudio() {
     this.stopBackgroundMusic();
+    this.stopEffects();
 
     // Stop any playing sound effects
     this.sounds.forEach((sound) => {
@@ -19,6 +19,7 @@ import {
 import { invoke } from "@tauri-apps/api/core";
 import { listen } from "@tauri-apps/api/event";
 import { useLaserConfig } from "../context/LaserConfigContext";
+import { SensorFrame } from "../types/LaserConfig";
 import UsbIcon from "@mui/icons-material/Usb";
 import PowerIcon from "@mui/icons-material/Power";
 import NotificationsActiveIcon from "@mui/icons-material/NotificationsActive";
@@ -51,7 +52,7 @@ export const ArduinoSettings: React.FC = () => {
 
     // Listen for serial data events
     const unlistenSerialData = listen("laser-sensor-data", (event) => {
-      setSerialData(event.payload as number[]);
+      setSerialData((event.payload as SensorFrame).values);
     });
 
     // Listen for serial error events
@@ -11,7 +11,7 @@ import {
   Chip,
 } from "@mui/material";
 import DeleteIcon from "@mui/icons-material/Delete";
-import { LaserConfig } from "../types/LaserConfig";
+import { LaserConfig, SensorFrame } from "../types/LaserConfig";
 import LaserSlider from "./LaserSlider";
 import { listen } from "@tauri-apps/api/event";
 
@@ -36,7 +36,7 @@ export const LaserConfigItem: React.FC<LaserConfigItemProps> = ({
   // Listen for serial data events from Arduino
   useEffect(() => {
     const unlistenSerialData = listen("laser-sensor-data", (event) => {
-      const sensorValues = event.payload as number[];
+      const sensorValues = (event.payload as SensorFrame).values;
       if (sensorValues && sensorValues.length > laser.sensorIndex) {
         // Get the specific sensor value for this laser
         const sensorValue = sensorValues[laser.sensorIndex];
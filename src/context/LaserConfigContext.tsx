@@ -10,6 +10,7 @@ import {
   Highscore,
 } from "../types/LaserConfig";
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 
 interface LaserConfigContextType {
   laserConfig: LaserConfigState;
@@ -88,6 +89,35 @@ export const LaserConfigProvider: React.FC<{ children: React.ReactNode }> = ({ c
     loadConfig();
   }, []);
 
+  // Follow the connection state reported by the backend
+  useEffect(() => {
+    const setConnected = (isConnected: boolean) =>
+      setLaserConfig((config) => ({
+        ...config,
+        arduinoSettings: { ...config.arduinoSettings, isConnected },
+      }));
+
+    const unlistenConnected = listen("serial-connected", () => setConnected(true));
+    const unlistenDisconnected = listen("serial-disconnected", () => setConnected(false));
+    // Keep the baud rate the backend fell back to for the next connection
+    const unlistenBaudSwitched = listen<{ baudRate: number }>("baud-switched", (event) =>
+      setLaserConfig((config) => {
+        const newConfig = {
+          ...config,
+          arduinoSettings: { ...config.arduinoSettings, baudRate: event.payload.baudRate },
+        };
+        saveConfig(newConfig);
+        return newConfig;
+      })
+    );
+
+    return () => {
+      unlistenConnected.then((unlisten) => unlisten());
+      unlistenDisconnected.then((unlisten) => unlisten());
+      unlistenBaudSwitched.then((unlisten) => unlisten());
+    };
+  }, []);
+
   const saveConfig = async (config: LaserConfigState) => {
     try {
       const resolvedStore = await store;
@@ -1,5 +1,6 @@
 import { useState, useEffect, useCallback } from "react";
 import { listen, UnlistenFn } from "@tauri-apps/api/event";
+import { SensorFrame } from "../types/LaserConfig";
 
 export interface DebugMonitorState {
   serialData: number[];
@@ -41,7 +42,7 @@ export const useDebugMonitor = () => {
       const setupListeners = async () => {
         // Listen for serial data
         const unlistenSerialData = await listen("laser-sensor-data", (event) => {
-          const values = event.payload as number[];
+          const values = (event.payload as SensorFrame).values;
           setSerialData(values);
 
           // Add the raw data to messages with timestamp
@@ -3,6 +3,7 @@ import { listen, UnlistenFn } from "@tauri-apps/api/event";
 import { useLaserConfig } from "../context/LaserConfigContext";
 import { audioManager, SoundEffect } from "../audioManager";
 import { Logger } from "../utils/Logger"; // Import the Logger
+import { SensorFrame } from "../types/LaserConfig";
 
 // Add debugging counters
 let listenerSetupCount = 0;
@@ -121,7 +122,7 @@ export const useGameLogic = () => {
             const unlistenSerialData = await listen("laser-sensor-data", (event) => {
               eventHandlerCalls["laser-sensor-data"]++;
 
-              const values = event.payload as number[];
+              const values = (event.payload as SensorFrame).values;
 
               // Check each laser to see if it's been triggered
               laserConfig.lasers.forEach((laser) => {
@@ -71,3 +71,15 @@ export const defaultLaserConfig: LaserConfigState = {
   },
   highscores: [],
 };
+
+// Payload of the laser-sensor-data event
+export interface SensorFrame {
+  // Bumped whenever the payload changes shape; 2 made values 32-bit and 3
+  // added the disabled sensor indexes.
+  schemaVersion: number;
+  seq: number;
+  timestampMs: number;
+  values: number[];
+  // Indexes of the sensors disabled by the operator
+  disabled: number[];
+}